@@ -1,5 +1,5 @@
 use crate::logging;
-use crate::session::build_session;
+use crate::session::{build_session, RunOptions, SessionBuilderConfig};
 use crate::Session;
 use async_trait::async_trait;
 use goose::config::Config;
@@ -37,7 +37,7 @@ impl BenchAgent for BenchSession {
             errors.clear();
         }
 
-        self.session.headless(p).await?;
+        self.session.headless(p, RunOptions::default()).await?;
         Ok(self.session.message_history())
     }
 
@@ -81,13 +81,11 @@ async fn run_eval(
     let requirements = evaluation.required_extensions();
 
     // Create session with error capture
-    let base_session = build_session(
-        None,
-        false,
-        requirements.external,
-        requirements.builtin,
-        false,
-    )
+    let base_session = build_session(SessionBuilderConfig {
+        extensions: requirements.external,
+        builtins: requirements.builtin,
+        ..Default::default()
+    })
     .await;
 
     let bench_session = Arc::new(Mutex::new(BenchSession::new(base_session)));
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use console::style;
+use goose_mcp::Checkpoints;
+
+fn open() -> Result<Checkpoints> {
+    let cwd = std::env::current_dir()?;
+    Checkpoints::open(&cwd)
+}
+
+pub fn handle_checkpoint_list(limit: usize, format: String) -> Result<()> {
+    let checkpoints = open()?.list(limit)?;
+
+    match format.as_str() {
+        "json" => {
+            println!("{}", serde_json::to_string(&checkpoints)?);
+        }
+        _ => {
+            if checkpoints.is_empty() {
+                println!("No checkpoints found");
+                return Ok(());
+            }
+            println!("Available checkpoints:");
+            for checkpoint in checkpoints {
+                let created = chrono::DateTime::from_timestamp(checkpoint.created, 0)
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                    .unwrap_or_else(|| "Unknown".to_string());
+                println!(
+                    "{} - {} - {}",
+                    style(checkpoint.short_id()).cyan(),
+                    created,
+                    checkpoint.message
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn handle_checkpoint_diff(id: String, stat: bool) -> Result<()> {
+    let diff = open()?.diff(&id, stat)?;
+    if diff.trim().is_empty() {
+        println!("No changes since checkpoint {}", id);
+    } else {
+        print!("{}", diff);
+    }
+    Ok(())
+}
+
+pub fn handle_checkpoint_restore(id: String, yes: bool) -> Result<()> {
+    let checkpoints = open()?;
+    let checkpoint = checkpoints.get(&id)?;
+
+    if !yes {
+        let confirmed = cliclack::confirm(format!(
+            "Restore {} to checkpoint {} ({})? The current state will be checkpointed first.",
+            checkpoints.root().display(),
+            checkpoint.short_id(),
+            checkpoint.message
+        ))
+        .initial_value(false)
+        .interact()?;
+        if !confirmed {
+            return Ok(());
+        }
+    }
+
+    checkpoints.restore(&checkpoint.id)?;
+    println!(
+        "Restored working tree to checkpoint {}",
+        style(checkpoint.short_id()).cyan()
+    );
+    Ok(())
+}
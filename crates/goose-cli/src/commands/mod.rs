@@ -1,7 +1,9 @@
 pub mod agent_version;
 pub mod bench;
+pub mod checkpoint;
 pub mod configure;
 pub mod info;
 pub mod mcp;
+pub mod recipe;
 pub mod session;
 pub mod update;
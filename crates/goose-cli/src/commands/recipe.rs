@@ -0,0 +1,65 @@
+use anyhow::Result;
+use goose::recipe::{list_recipes, recipe_dirs};
+use serde_json::json;
+
+pub fn handle_recipe_list(verbose: bool, format: String) -> Result<()> {
+    let dirs = recipe_dirs();
+    let recipes = list_recipes(&dirs);
+
+    match format.as_str() {
+        "json" => {
+            let recipes: Vec<_> = recipes
+                .iter()
+                .map(|file| match &file.recipe {
+                    Ok(recipe) => json!({
+                        "name": file.name,
+                        "path": file.path,
+                        "recipe": recipe,
+                    }),
+                    Err(e) => json!({
+                        "name": file.name,
+                        "path": file.path,
+                        "error": format!("{:#}", e),
+                    }),
+                })
+                .collect();
+            println!("{}", serde_json::to_string(&recipes)?);
+        }
+        _ => {
+            if recipes.is_empty() {
+                println!("No recipes found. Recipes are looked up in:");
+                for dir in &dirs {
+                    println!("  {}", dir.display());
+                }
+                return Ok(());
+            }
+            println!("Available recipes:");
+            for file in &recipes {
+                match &file.recipe {
+                    Ok(recipe) => {
+                        println!("{} - {}", file.name, recipe.description);
+                        if verbose {
+                            println!("    Title: {}", recipe.title);
+                            println!("    Path: {}", file.path.display());
+                            for parameter in &recipe.parameters {
+                                let default = parameter
+                                    .default
+                                    .as_ref()
+                                    .map(|d| format!(" (default: {})", d))
+                                    .unwrap_or_else(|| " (required)".to_string());
+                                println!(
+                                    "    --param {}=...{} {}",
+                                    parameter.key,
+                                    default,
+                                    parameter.description.as_deref().unwrap_or_default()
+                                );
+                            }
+                        }
+                    }
+                    Err(e) => println!("{} - invalid recipe: {:#}", file.name, e),
+                }
+            }
+        }
+    }
+    Ok(())
+}
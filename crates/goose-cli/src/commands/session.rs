@@ -1,5 +1,14 @@
 use anyhow::Result;
+use console::style;
+use goose::session;
 use goose::session::info::{get_session_info, SessionInfo};
+use goose::session::manage::{
+    export_session, fork_session, remove_session, rename_session, validate_session_id,
+    ExportFormat, SessionFilter,
+};
+use goose::session::search::search_sessions;
+use goose::session::Identifier;
+use std::path::PathBuf;
 
 pub fn handle_session_list(verbose: bool, format: String) -> Result<()> {
     let sessions = match get_session_info() {
@@ -36,6 +45,12 @@ pub fn handle_session_list(verbose: bool, format: String) -> Result<()> {
                     if verbose {
                         println!("  {}", output);
                         println!("    Path: {}", path);
+                        if let Some(lineage) = &metadata.forked_from {
+                            println!(
+                                "    Forked from: {} at message {}",
+                                lineage.session_id, lineage.message_index
+                            );
+                        }
                     } else {
                         println!("{}", output);
                     }
@@ -45,3 +60,139 @@ pub fn handle_session_list(verbose: bool, format: String) -> Result<()> {
     }
     Ok(())
 }
+
+/// The path of an existing session by id
+fn session_path(id: &str) -> Result<PathBuf> {
+    validate_session_id(id)?;
+    let path = session::get_path(Identifier::Name(id.to_string()));
+    if !path.exists() {
+        return Err(anyhow::anyhow!("No session named '{}'", id));
+    }
+    Ok(path)
+}
+
+/// Remove the sessions that match, after listing them and asking unless `yes`
+fn remove_sessions(sessions: Vec<(String, PathBuf)>, dry_run: bool, yes: bool) -> Result<()> {
+    if sessions.is_empty() {
+        println!("No sessions match");
+        return Ok(());
+    }
+    for (id, path) in &sessions {
+        let description = session::read_metadata(path)
+            .map(|metadata| metadata.description)
+            .unwrap_or_default();
+        println!("  {} - {}", id, description);
+    }
+    if dry_run {
+        println!("{} sessions would be removed", sessions.len());
+        return Ok(());
+    }
+    if !yes {
+        let confirmed = cliclack::confirm(format!("Remove these {} sessions?", sessions.len()))
+            .initial_value(false)
+            .interact()?;
+        if !confirmed {
+            return Ok(());
+        }
+    }
+    for (id, path) in &sessions {
+        remove_session(path).map_err(|e| anyhow::anyhow!("Failed to remove {}: {}", id, e))?;
+    }
+    println!("Removed {} sessions", style(sessions.len()).cyan());
+    Ok(())
+}
+
+pub fn handle_session_remove(
+    id: Option<String>,
+    regex: Option<String>,
+    dry_run: bool,
+    yes: bool,
+) -> Result<()> {
+    let sessions = match (id, regex) {
+        (Some(id), _) => vec![(id.clone(), session_path(&id)?)],
+        (None, Some(pattern)) => {
+            SessionFilter::new(Some(&pattern), None)?.apply(session::list_sessions()?)
+        }
+        (None, None) => {
+            return Err(anyhow::anyhow!(
+                "Give a session id or a --regex to match sessions by id or description"
+            ))
+        }
+    };
+    remove_sessions(sessions, dry_run, yes)
+}
+
+pub fn handle_session_prune(
+    older_than: String,
+    regex: Option<String>,
+    dry_run: bool,
+    yes: bool,
+) -> Result<()> {
+    let filter = SessionFilter::new(regex.as_deref(), Some(&older_than))?;
+    remove_sessions(filter.apply(session::list_sessions()?), dry_run, yes)
+}
+
+pub fn handle_session_rename(id: String, new_id: String) -> Result<()> {
+    rename_session(&session_path(&id)?, &new_id)?;
+    println!("Renamed session {} to {}", id, style(new_id).cyan());
+    Ok(())
+}
+
+pub fn handle_session_fork(id: String, at: Option<usize>, name: Option<String>) -> Result<()> {
+    let path = session_path(&id)?;
+    let at = match at {
+        Some(at) => at,
+        None => session::read_messages(&path)?.len(),
+    };
+    let (new_id, _) = fork_session(&path, at, name.as_deref())?;
+    println!(
+        "Forked session {} at message {} into {}",
+        id,
+        at,
+        style(&new_id).cyan()
+    );
+    println!("Resume it with: goose session --resume --name {}", new_id);
+    Ok(())
+}
+
+pub fn handle_session_export(id: String, format: String, output: Option<PathBuf>) -> Result<()> {
+    let format: ExportFormat = format.parse()?;
+    let exported = export_session(&session_path(&id)?, &id, format)?;
+    match output {
+        Some(path) => {
+            std::fs::write(&path, exported)?;
+            println!("Exported session {} to {}", id, path.display());
+        }
+        None => print!("{}", exported),
+    }
+    Ok(())
+}
+
+pub fn handle_session_search(query: String, limit: usize, format: String) -> Result<()> {
+    let hits = search_sessions(&query, limit)?;
+    match format.as_str() {
+        "json" => println!("{}", serde_json::to_string(&hits)?),
+        _ => {
+            if hits.is_empty() {
+                println!("No sessions match '{}'", query);
+                return Ok(());
+            }
+            for hit in hits {
+                let description = if hit.description.is_empty() {
+                    "(none)"
+                } else {
+                    &hit.description
+                };
+                println!("{} - {}", style(&hit.session_id).cyan(), description);
+                println!("    {}", hit.working_dir);
+                let location = hit
+                    .message_index
+                    .map(|index| format!("message {}: ", index))
+                    .unwrap_or_default();
+                println!("    {}{}", style(location).dim(), hit.snippet);
+                println!("    {}", style(&hit.resume_command).dim());
+            }
+        }
+    }
+    Ok(())
+}
@@ -2,16 +2,25 @@ use anyhow::Result;
 use clap::{Args, Parser, Subcommand};
 
 use goose::config::Config;
+use goose::recipe::{load_recipe, recipe_dirs};
 
 use goose_cli::commands::agent_version::AgentCommand;
 use goose_cli::commands::bench::{list_selectors, run_benchmark};
+use goose_cli::commands::checkpoint::{
+    handle_checkpoint_diff, handle_checkpoint_list, handle_checkpoint_restore,
+};
 use goose_cli::commands::configure::handle_configure;
 use goose_cli::commands::info::handle_info;
 use goose_cli::commands::mcp::run_server;
-use goose_cli::commands::session::handle_session_list;
+use goose_cli::commands::recipe::handle_recipe_list;
+use goose_cli::commands::session::{
+    handle_session_export, handle_session_fork, handle_session_list, handle_session_prune,
+    handle_session_remove, handle_session_rename, handle_session_search,
+};
 use goose_cli::logging::setup_logging;
 use goose_cli::session;
-use goose_cli::session::build_session;
+use goose_cli::session::{build_session, OutputFormat, RunOptions, SessionBuilderConfig};
+use std::collections::HashMap;
 use std::io::Read;
 use std::path::PathBuf;
 
@@ -69,6 +78,188 @@ enum SessionCommand {
         )]
         format: String,
     },
+
+    #[command(about = "Remove a session, or the sessions matching a regex")]
+    Remove {
+        /// Session id, as shown by `goose session list`
+        #[arg(required_unless_present = "regex", conflicts_with = "regex")]
+        id: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "PATTERN",
+            help = "Remove the sessions whose id or description matches this regex"
+        )]
+        regex: Option<String>,
+
+        #[arg(long, help = "Only list the sessions that would be removed")]
+        dry_run: bool,
+
+        #[arg(short, long, help = "Skip the confirmation prompt")]
+        yes: bool,
+    },
+
+    #[command(about = "Give a session a new id")]
+    Rename {
+        /// Current session id
+        id: String,
+        /// New session id
+        new_id: String,
+    },
+
+    #[command(about = "Copy a session up to a message into a new session")]
+    Fork {
+        /// Session id, as shown by `goose session list`
+        id: String,
+
+        #[arg(
+            long,
+            value_name = "INDEX",
+            help = "Keep the messages before this index, counting from 0 (default: all of them)"
+        )]
+        at: Option<usize>,
+
+        #[arg(
+            short,
+            long,
+            value_name = "NAME",
+            help = "Id for the new session (default: <id>-fork-<n>)"
+        )]
+        name: Option<String>,
+    },
+
+    #[command(about = "Export a session as markdown, json or html")]
+    Export {
+        /// Session id, as shown by `goose session list`
+        id: String,
+
+        #[arg(
+            short,
+            long,
+            help = "Output format (markdown, json, html)",
+            default_value = "markdown"
+        )]
+        format: String,
+
+        #[arg(
+            short,
+            long,
+            value_name = "FILE",
+            help = "Write the export to a file instead of stdout"
+        )]
+        output: Option<PathBuf>,
+    },
+
+    #[command(about = "Remove sessions that have not been used for a while")]
+    Prune {
+        #[arg(
+            long,
+            value_name = "AGE",
+            help = "Remove sessions last used longer ago than this, e.g. 30d, 12h or 2w"
+        )]
+        older_than: String,
+
+        #[arg(
+            long,
+            value_name = "PATTERN",
+            help = "Only remove sessions whose id or description matches this regex"
+        )]
+        regex: Option<String>,
+
+        #[arg(long, help = "Only list the sessions that would be removed")]
+        dry_run: bool,
+
+        #[arg(short, long, help = "Skip the confirmation prompt")]
+        yes: bool,
+    },
+
+    #[command(about = "Search the messages, tool calls and working directories of past sessions")]
+    Search {
+        /// Words to look for
+        #[arg(required = true, num_args = 1..)]
+        query: Vec<String>,
+
+        #[arg(
+            short,
+            long,
+            help = "Maximum number of sessions to show",
+            default_value_t = 10
+        )]
+        limit: usize,
+
+        #[arg(
+            short,
+            long,
+            help = "Output format (text, json)",
+            default_value = "text"
+        )]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum RecipeCommand {
+    #[command(about = "List the recipes in the recipe directories")]
+    List {
+        #[arg(short, long, help = "Show each recipe's path and parameters")]
+        verbose: bool,
+
+        #[arg(
+            short,
+            long,
+            help = "Output format (text, json)",
+            default_value = "text"
+        )]
+        format: String,
+    },
+}
+
+fn parse_param(param: &str) -> Result<(String, String), String> {
+    param
+        .split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .ok_or_else(|| format!("invalid parameter '{}', expected KEY=VALUE", param))
+}
+
+#[derive(Subcommand)]
+enum CheckpointCommand {
+    #[command(about = "List checkpoints of the current repository")]
+    List {
+        #[arg(
+            short,
+            long,
+            help = "Maximum number of checkpoints to list",
+            default_value = "20"
+        )]
+        limit: usize,
+
+        #[arg(
+            short,
+            long,
+            help = "Output format (text, json)",
+            default_value = "text"
+        )]
+        format: String,
+    },
+
+    #[command(about = "Show changes between a checkpoint and the working tree")]
+    Diff {
+        /// Checkpoint id, as shown by `goose checkpoint list`
+        id: String,
+
+        #[arg(long, help = "Only summarize the changed files")]
+        stat: bool,
+    },
+
+    #[command(about = "Restore the working tree to a checkpoint")]
+    Restore {
+        /// Checkpoint id, as shown by `goose checkpoint list`
+        id: String,
+
+        #[arg(short, long, help = "Skip the confirmation prompt")]
+        yes: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -148,7 +339,7 @@ enum Command {
             long,
             value_name = "FILE",
             help = "Path to instruction file containing commands. Use - for stdin.",
-            conflicts_with = "input_text"
+            conflicts_with_all = ["input_text", "recipe"]
         )]
         instructions: Option<String>,
 
@@ -159,10 +350,30 @@ enum Command {
             value_name = "TEXT",
             help = "Input text to provide to Goose directly",
             long_help = "Input text containing commands for Goose. Use this in lieu of the instructions argument.",
-            conflicts_with = "instructions"
+            conflicts_with_all = ["instructions", "recipe"]
         )]
         input_text: Option<String>,
 
+        /// Recipe to run
+        #[arg(
+            long = "recipe",
+            value_name = "RECIPE",
+            help = "Recipe file, or name of a recipe in the recipe directories",
+            long_help = "Run a recipe: a YAML file with a prompt template, its parameters, the extensions, goose mode and model settings to use, and an optional response schema. Give a path, or the name of a recipe listed by 'goose recipe list'."
+        )]
+        recipe: Option<String>,
+
+        /// Parameters for the recipe
+        #[arg(
+            long = "param",
+            value_name = "KEY=VALUE",
+            help = "Set a recipe parameter (can be specified multiple times)",
+            requires = "recipe",
+            value_parser = parse_param,
+            action = clap::ArgAction::Append
+        )]
+        params: Vec<(String, String)>,
+
         /// Continue in interactive mode after processing input
         #[arg(
             short = 's',
@@ -212,6 +423,40 @@ enum Command {
             value_delimiter = ','
         )]
         builtin: Vec<String>,
+
+        /// Output format for non-interactive runs
+        #[arg(
+            long = "output-format",
+            value_name = "FORMAT",
+            value_enum,
+            conflicts_with = "interactive",
+            help = "Print the run as plain text or JSON instead of styled terminal output",
+            long_help = "Print only the final response (text), a single JSON document when the run finishes (json), or one JSON event per line as the run progresses (stream-json). JSON output includes token usage and cost. Tool calls that need approval are declined in json and stream-json runs."
+        )]
+        output_format: Option<OutputFormat>,
+
+        /// Stop the run after using this many tokens
+        #[arg(
+            long = "token-budget",
+            value_name = "TOKENS",
+            conflicts_with = "interactive",
+            help = "Stop the run once it has used this many tokens"
+        )]
+        token_budget: Option<i64>,
+    },
+
+    /// List and inspect recipes
+    #[command(about = "List recipes from the recipe directories")]
+    Recipe {
+        #[command(subcommand)]
+        command: RecipeCommand,
+    },
+
+    /// Manage checkpoints of changes made during sessions
+    #[command(about = "List, diff and restore checkpoints of the working tree")]
+    Checkpoint {
+        #[command(subcommand)]
+        command: CheckpointCommand,
     },
 
     /// List available agent versions
@@ -331,15 +576,54 @@ async fn main() -> Result<()> {
                     handle_session_list(verbose, format)?;
                     return Ok(());
                 }
+                Some(SessionCommand::Remove {
+                    id,
+                    regex,
+                    dry_run,
+                    yes,
+                }) => {
+                    handle_session_remove(id, regex, dry_run, yes)?;
+                    return Ok(());
+                }
+                Some(SessionCommand::Rename { id, new_id }) => {
+                    handle_session_rename(id, new_id)?;
+                    return Ok(());
+                }
+                Some(SessionCommand::Fork { id, at, name }) => {
+                    handle_session_fork(id, at, name)?;
+                    return Ok(());
+                }
+                Some(SessionCommand::Export { id, format, output }) => {
+                    handle_session_export(id, format, output)?;
+                    return Ok(());
+                }
+                Some(SessionCommand::Prune {
+                    older_than,
+                    regex,
+                    dry_run,
+                    yes,
+                }) => {
+                    handle_session_prune(older_than, regex, dry_run, yes)?;
+                    return Ok(());
+                }
+                Some(SessionCommand::Search {
+                    query,
+                    limit,
+                    format,
+                }) => {
+                    handle_session_search(query.join(" "), limit, format)?;
+                    return Ok(());
+                }
                 None => {
                     // Run session command by default
-                    let mut session = build_session(
-                        identifier.map(extract_identifier),
+                    let mut session = build_session(SessionBuilderConfig {
+                        identifier: identifier.map(extract_identifier),
                         resume,
-                        extension,
-                        builtin,
+                        extensions: extension,
+                        builtins: builtin,
                         debug,
-                    )
+                        ..Default::default()
+                    })
                     .await;
                     setup_logging(
                         session.session_file().file_stem().and_then(|s| s.to_str()),
@@ -353,42 +637,69 @@ async fn main() -> Result<()> {
         Some(Command::Run {
             instructions,
             input_text,
+            recipe,
+            params,
             interactive,
             identifier,
             resume,
             debug,
             extension,
             builtin,
+            output_format,
+            token_budget,
         }) => {
-            let contents = match (instructions, input_text) {
-                (Some(file), _) if file == "-" => {
+            let recipe = recipe.map(|recipe| {
+                let (_, recipe) = load_recipe(&recipe, &recipe_dirs()).unwrap_or_else(|err| {
+                    eprintln!("Error: {:#}", err);
+                    std::process::exit(1);
+                });
+                recipe
+            });
+
+            let contents = match (instructions, input_text, &recipe) {
+                (_, _, Some(recipe)) => {
+                    let params: HashMap<String, String> = params.into_iter().collect();
+                    recipe.render_prompt(&params).unwrap_or_else(|err| {
+                        eprintln!("Error: {}", err);
+                        std::process::exit(1);
+                    })
+                }
+                (Some(file), _, None) if file == "-" => {
                     let mut stdin = String::new();
                     std::io::stdin()
                         .read_to_string(&mut stdin)
                         .expect("Failed to read from stdin");
                     stdin
                 }
-                (Some(file), _) => std::fs::read_to_string(&file).unwrap_or_else(|err| {
+                (Some(file), _, None) => std::fs::read_to_string(&file).unwrap_or_else(|err| {
                     eprintln!(
                         "Instruction file not found — did you mean to use goose run --text?\n{}",
                         err
                     );
                     std::process::exit(1);
                 }),
-                (None, Some(text)) => text,
-                (None, None) => {
-                    eprintln!("Error: Must provide either --instructions (-i) or --text (-t). Use -i - for stdin.");
+                (None, Some(text), None) => text,
+                (None, None, None) => {
+                    eprintln!("Error: Must provide either --instructions (-i), --text (-t) or --recipe. Use -i - for stdin.");
                     std::process::exit(1);
                 }
             };
 
-            let mut session = build_session(
-                identifier.map(extract_identifier),
+            let mut session = build_session(SessionBuilderConfig {
+                identifier: identifier.map(extract_identifier),
                 resume,
-                extension,
-                builtin,
+                extensions: extension,
+                builtins: builtin,
+                extension_configs: recipe
+                    .as_ref()
+                    .map(|r| r.extensions.clone())
+                    .unwrap_or_default(),
+                settings: recipe.as_ref().and_then(|r| r.settings.clone()),
+                goose_mode: recipe.as_ref().and_then(|r| r.goose_mode.clone()),
+                additional_system_prompt: recipe.as_ref().and_then(|r| r.system_prompt()),
                 debug,
-            )
+                quiet: output_format.is_some(),
+            })
             .await;
 
             setup_logging(
@@ -399,11 +710,33 @@ async fn main() -> Result<()> {
             if interactive {
                 session.interactive(Some(contents)).await?;
             } else {
-                session.headless(contents).await?;
+                let options = RunOptions {
+                    format: output_format,
+                    token_budget,
+                    recipe,
+                };
+                let code = session.headless(contents, options).await?;
+                if code != 0 {
+                    std::process::exit(code);
+                }
             }
 
             return Ok(());
         }
+        Some(Command::Recipe { command }) => {
+            match command {
+                RecipeCommand::List { verbose, format } => handle_recipe_list(verbose, format)?,
+            }
+            return Ok(());
+        }
+        Some(Command::Checkpoint { command }) => {
+            match command {
+                CheckpointCommand::List { limit, format } => handle_checkpoint_list(limit, format)?,
+                CheckpointCommand::Diff { id, stat } => handle_checkpoint_diff(id, stat)?,
+                CheckpointCommand::Restore { id, yes } => handle_checkpoint_restore(id, yes)?,
+            }
+            return Ok(());
+        }
         Some(Command::Agents(cmd)) => {
             cmd.run()?;
             return Ok(());
@@ -469,7 +802,7 @@ async fn main() -> Result<()> {
                 return Ok(());
             } else {
                 // Run session command by default
-                let mut session = build_session(None, false, vec![], vec![], false).await;
+                let mut session = build_session(SessionBuilderConfig::default()).await;
                 setup_logging(
                     session.session_file().file_stem().and_then(|s| s.to_str()),
                     None,
@@ -1,7 +1,8 @@
 use console::style;
-use goose::agents::extension::ExtensionError;
+use goose::agents::extension::{ExtensionConfig, ExtensionError};
 use goose::agents::AgentFactory;
 use goose::config::{Config, ExtensionManager};
+use goose::recipe::Settings;
 use goose::session;
 use goose::session::Identifier;
 use mcp_client::transport::Error as McpClientError;
@@ -10,24 +11,60 @@ use std::process;
 use super::output;
 use super::Session;
 
-pub async fn build_session(
-    identifier: Option<Identifier>,
-    resume: bool,
-    extensions: Vec<String>,
-    builtins: Vec<String>,
-    debug: bool,
-) -> Session {
+/// How to set up a session
+#[derive(Default)]
+pub struct SessionBuilderConfig {
+    pub identifier: Option<Identifier>,
+    pub resume: bool,
+    /// Stdio extension commands to add, as given to --with-extension
+    pub extensions: Vec<String>,
+    /// Builtin extension names to add, as given to --with-builtin
+    pub builtins: Vec<String>,
+    /// Extensions to add from a recipe
+    pub extension_configs: Vec<ExtensionConfig>,
+    /// Model settings that override the configured ones
+    pub settings: Option<Settings>,
+    /// Goose mode for this process, leaving the configured mode unchanged
+    pub goose_mode: Option<String>,
+    /// Added to the system prompt
+    pub additional_system_prompt: Option<String>,
+    pub debug: bool,
+    /// Skip printing the session info
+    pub quiet: bool,
+}
+
+pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
+    let SessionBuilderConfig {
+        identifier,
+        resume,
+        extensions,
+        builtins,
+        extension_configs,
+        settings,
+        goose_mode,
+        additional_system_prompt,
+        debug,
+        quiet,
+    } = session_config;
+    let settings = settings.unwrap_or_default();
+
     // Load config and get provider/model
     let config = Config::global();
 
-    let provider_name: String = config
-        .get_param("GOOSE_PROVIDER")
-        .expect("No provider configured. Run 'goose configure' first");
-
-    let model: String = config
-        .get_param("GOOSE_MODEL")
-        .expect("No model configured. Run 'goose configure' first");
-    let model_config = goose::model::ModelConfig::new(model.clone());
+    let provider_name: String = settings.goose_provider.clone().unwrap_or_else(|| {
+        config
+            .get_param("GOOSE_PROVIDER")
+            .expect("No provider configured. Run 'goose configure' first")
+    });
+
+    let model: String = settings.goose_model.clone().unwrap_or_else(|| {
+        config
+            .get_param("GOOSE_MODEL")
+            .expect("No model configured. Run 'goose configure' first")
+    });
+    let model_config = goose::model::ModelConfig::new(model.clone())
+        .with_temperature(settings.temperature)
+        .with_max_tokens(settings.max_tokens);
     let provider =
         goose::providers::create(&provider_name, model_config).expect("Failed to create provider");
 
@@ -92,9 +129,11 @@ pub async fn build_session(
 
     // Setup extensions for the agent
     // Extensions need to be added after the session is created because we change directory when resuming a session
+    let mut enabled = Vec::new();
     for extension in ExtensionManager::get_all().expect("should load extensions") {
         if extension.enabled {
             let config = extension.config.clone();
+            enabled.push(config.key());
             agent
                 .add_extension(config.clone())
                 .await
@@ -115,6 +154,26 @@ pub async fn build_session(
         }
     }
 
+    // Add recipe extensions that are not enabled already
+    for extension in extension_configs {
+        if enabled.contains(&extension.key()) {
+            continue;
+        }
+        agent
+            .add_extension(extension.clone())
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to start extension: {}, {}", extension.name(), e);
+                process::exit(1);
+            });
+    }
+
+    // The agent reads the mode from the config on every reply, where the environment
+    // takes precedence over the config file
+    if let Some(mode) = goose_mode {
+        std::env::set_var("GOOSE_MODE", mode);
+    }
+
     // Create new session
     let mut session = Session::new(agent, session_file.clone(), debug);
 
@@ -148,6 +207,12 @@ pub async fn build_session(
         session.agent.override_system_prompt(override_prompt).await;
     }
 
-    output::display_session_info(resume, &provider_name, &model, &session_file);
+    if let Some(prompt) = additional_system_prompt {
+        session.agent.extend_system_prompt(prompt).await;
+    }
+
+    if !quiet {
+        output::display_session_info(resume, &provider_name, &model, &session_file);
+    }
     session
 }
@@ -129,6 +129,7 @@ impl GooseCompleter {
             "/prompts",
             "/prompt",
             "/mode",
+            "/rewind",
         ];
 
         // Find commands that match the prefix
@@ -17,6 +17,8 @@ pub enum InputResult {
     GooseMode(String),
     Plan(PlanCommandOptions),
     EndPlan,
+    /// Remove this many of the latest prompts and everything after them
+    Rewind(usize),
 }
 
 #[derive(Debug)]
@@ -81,6 +83,7 @@ fn handle_slash_command(input: &str) -> Option<InputResult> {
     const CMD_MODE: &str = "/mode ";
     const CMD_PLAN: &str = "/plan";
     const CMD_ENDPLAN: &str = "/endplan";
+    const CMD_REWIND: &str = "/rewind";
 
     match input {
         "/exit" | "/quit" => Some(InputResult::Exit),
@@ -122,6 +125,14 @@ fn handle_slash_command(input: &str) -> Option<InputResult> {
         }
         s if s.starts_with(CMD_PLAN) => parse_plan_command(s[CMD_PLAN.len()..].trim().to_string()),
         s if s == CMD_ENDPLAN => Some(InputResult::EndPlan),
+        s if s == CMD_REWIND => Some(InputResult::Rewind(1)),
+        s if s.starts_with("/rewind ") => match s[CMD_REWIND.len()..].trim().parse() {
+            Ok(count) if count > 0 => Some(InputResult::Rewind(count)),
+            _ => {
+                println!("Usage: /rewind [number of prompts to remove]");
+                Some(InputResult::Retry)
+            }
+        },
         _ => None,
     }
 }
@@ -203,6 +214,8 @@ fn print_help() {
                         The model is used based on $GOOSE_PLANNER_PROVIDER and $GOOSE_PLANNER_MODEL environment variables.
                         If no model is set, the default model is used.
 /endplan - Exit plan mode and return to 'normal' goose mode.
+/rewind [n] - Remove your last n prompts (default 1) and the replies to them from the session.
+              Use 'goose session fork' first to keep a copy of the full session.
 /? or /help - Display this help message
 
 Navigation:
@@ -260,6 +273,18 @@ mod tests {
 
         // Test unknown commands
         assert!(handle_slash_command("/unknown").is_none());
+        assert!(matches!(
+            handle_slash_command("/rewind"),
+            Some(InputResult::Rewind(1))
+        ));
+        assert!(matches!(
+            handle_slash_command("/rewind 3"),
+            Some(InputResult::Rewind(3))
+        ));
+        assert!(matches!(
+            handle_slash_command("/rewind 0"),
+            Some(InputResult::Retry)
+        ));
     }
 
     #[test]
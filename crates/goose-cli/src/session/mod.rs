@@ -3,11 +3,13 @@ mod completion;
 mod input;
 mod output;
 mod prompt;
+mod report;
 mod thinking;
 
-pub use builder::build_session;
+pub use builder::{build_session, SessionBuilderConfig};
 use goose::providers::base::Provider;
 pub use goose::session::Identifier;
+pub use report::{exit_code, OutputFormat, RunOptions};
 
 use anyhow::Result;
 use completion::GooseCompleter;
@@ -16,7 +18,7 @@ use etcetera::AppStrategy;
 use goose::agents::extension::{Envs, ExtensionConfig};
 use goose::agents::{Agent, SessionConfig};
 use goose::config::Config;
-use goose::message::{Message, MessageContent};
+use goose::message::{Message, MessageContent, ToolConfirmationRequest};
 use goose::session;
 use mcp_core::handler::ToolError;
 use mcp_core::prompt::PromptMessage;
@@ -24,11 +26,16 @@ use mcp_core::prompt::PromptMessage;
 use rand::{distributions::Alphanumeric, Rng};
 use serde_json::Value;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Instant;
 use tokio;
 
+const USER_INTERRUPT: &str = "Interrupted by the user to make a correction";
+const TOOL_ERROR: &str = "An uncaught error happened during tool use";
+const BUDGET_STOP: &str = "Stopped because the run used up its token budget";
+
 pub enum RunMode {
     Normal,
     Plan,
@@ -42,6 +49,8 @@ pub struct Session {
     completion_cache: Arc<std::sync::RwLock<CompletionCache>>,
     debug: bool, // New field for debug mode
     run_mode: RunMode,
+    // Set while running non-interactively, see `headless`
+    run: Option<report::RunReporter>,
 }
 
 // Cache structure for completion data
@@ -114,6 +123,7 @@ impl Session {
             completion_cache: Arc::new(std::sync::RwLock::new(CompletionCache::new())),
             debug,
             run_mode: RunMode::Normal,
+            run: None,
         }
     }
 
@@ -240,8 +250,57 @@ impl Session {
         Ok(result.messages)
     }
 
+    /// Checkpoint the working tree before the agent acts on a new user message
+    ///
+    /// Enabled unless GOOSE_CHECKPOINTS is set to false. Does nothing outside of a git repository.
+    /// Files matched by .gooseignore are left out of the checkpoint.
+    async fn checkpoint_turn(&self, message: &str) {
+        let enabled: bool = Config::global()
+            .get_param("GOOSE_CHECKPOINTS")
+            .unwrap_or(true);
+        if !enabled {
+            return;
+        }
+
+        let cwd = match std::env::current_dir() {
+            Ok(cwd) => cwd,
+            Err(_) => return,
+        };
+        let session_id = self
+            .session_file
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let summary: String = message
+            .lines()
+            .next()
+            .unwrap_or("")
+            .chars()
+            .take(60)
+            .collect();
+        let label = format!("[{}] Before: {}", session_id, summary);
+
+        // Snapshotting runs git over the whole working tree, keep it off the async runtime
+        let snapshot = tokio::task::spawn_blocking(move || {
+            let checkpoints = match goose_mcp::Checkpoints::open(&cwd) {
+                Ok(checkpoints) => checkpoints,
+                Err(e) => {
+                    tracing::debug!("Skipping checkpoint: {}", e);
+                    return;
+                }
+            };
+            if let Err(e) = checkpoints.create(&label) {
+                tracing::warn!("Failed to create checkpoint: {}", e);
+            }
+        });
+        if let Err(e) = snapshot.await {
+            tracing::warn!("Failed to create checkpoint: {}", e);
+        }
+    }
+
     /// Process a single message and get the response
     async fn process_message(&mut self, message: String) -> Result<()> {
+        self.checkpoint_turn(&message).await;
         self.messages.push(Message::user().with_text(&message));
         // Get the provider from the agent for description generation
         let provider = self.agent.provider().await;
@@ -313,6 +372,7 @@ impl Session {
                         RunMode::Normal => {
                             save_history(&mut editor);
 
+                            self.checkpoint_turn(&content).await;
                             self.messages.push(Message::user().with_text(&content));
 
                             // Get the provider from the agent for description generation
@@ -422,6 +482,22 @@ impl Session {
                     self.plan_with_reasoner_model(plan_messages, reasoner)
                         .await?;
                 }
+                input::InputResult::Rewind(count) => {
+                    save_history(&mut editor);
+
+                    match self.rewind(count) {
+                        Ok(Some(prompt)) => output::goose_mode_message(&format!(
+                            "Rewound the session to before: {}",
+                            prompt
+                        )),
+                        Ok(None) => output::render_error(&format!(
+                            "The session does not have {} prompts to rewind",
+                            count
+                        )),
+                        Err(e) => output::render_error(&e.to_string()),
+                    }
+                    continue;
+                }
                 input::InputResult::EndPlan => {
                     self.run_mode = RunMode::Normal;
                     output::render_exit_plan_mode();
@@ -564,9 +640,16 @@ impl Session {
         Ok(())
     }
 
-    /// Process a single message and exit
-    pub async fn headless(&mut self, message: String) -> Result<()> {
-        self.process_message(message).await
+    /// Process a single message and exit, returning the exit code for the run
+    pub async fn headless(&mut self, message: String, options: RunOptions) -> Result<i32> {
+        self.run = Some(report::RunReporter::new(options));
+        if let Err(e) = self.process_message(message).await {
+            if let Some(run) = self.run.as_mut() {
+                run.error(e.to_string());
+            }
+        }
+        let run = self.run.take().expect("run reporter was set above");
+        Ok(run.finish(self.session_file.clone()))
     }
 
     async fn process_agent_response(&mut self, interactive: bool) -> Result<()> {
@@ -593,11 +676,7 @@ impl Session {
                             if let Some(MessageContent::ToolConfirmationRequest(confirmation)) = message.content.first() {
                                 output::hide_thinking();
 
-                                // Format the confirmation prompt
-                                let prompt = "Goose would like to call the above tool, do you approve?".to_string();
-
-                                // Get confirmation from user
-                                let confirmed = cliclack::confirm(prompt).initial_value(true).interact()?;
+                                let confirmed = confirm_tool(self.run.as_mut(), confirmation)?;
                                 self.agent.handle_confirmation(confirmation.id.clone(), confirmed).await;
                             }
                             // otherwise we have a model/tool to render
@@ -608,22 +687,36 @@ impl Session {
                                 session::persist_messages(&self.session_file, &self.messages, None).await?;
 
                                 if interactive {output::hide_thinking()};
-                                output::render_message(&message, self.debug);
+                                render_message(self.run.as_mut(), &message, self.debug);
                                 if interactive {output::show_thinking()};
+
+                                if message.role == mcp_core::role::Role::Assistant && record_usage(self.run.as_mut(), &self.session_file) {
+                                    drop(stream);
+                                    if let Err(e) = self.handle_interrupted_messages(BUDGET_STOP).await {
+                                        eprintln!("Error handling interruption: {}", e);
+                                    }
+                                    break;
+                                }
                             }
                         }
                         Some(Err(e)) => {
-                            eprintln!("Error: {}", e);
+                            if let Some(run) = self.run.as_mut() {
+                                run.error(e.to_string());
+                            } else {
+                                eprintln!("Error: {}", e);
+                            }
                             drop(stream);
-                            if let Err(e) = self.handle_interrupted_messages(false).await {
+                            if let Err(e) = self.handle_interrupted_messages(TOOL_ERROR).await {
                                 eprintln!("Error handling interruption: {}", e);
                             }
-                            output::render_error(
-                                "The error above was an exception we were not able to handle.\n\
-                                These errors are often related to connection or authentication\n\
-                                We've removed the conversation up to the most recent user message\n\
-                                - depending on the error you may be able to continue",
-                            );
+                            if self.run.is_none() {
+                                output::render_error(
+                                    "The error above was an exception we were not able to handle.\n\
+                                    These errors are often related to connection or authentication\n\
+                                    We've removed the conversation up to the most recent user message\n\
+                                    - depending on the error you may be able to continue",
+                                );
+                            }
                             break;
                         }
                         None => break,
@@ -631,7 +724,10 @@ impl Session {
                 }
                 _ = tokio::signal::ctrl_c() => {
                     drop(stream);
-                    if let Err(e) = self.handle_interrupted_messages(true).await {
+                    if let Some(run) = self.run.as_mut() {
+                        run.interrupted();
+                    }
+                    if let Err(e) = self.handle_interrupted_messages(USER_INTERRUPT).await {
                         eprintln!("Error handling interruption: {}", e);
                     }
                     break;
@@ -641,7 +737,9 @@ impl Session {
         Ok(())
     }
 
-    async fn handle_interrupted_messages(&mut self, interrupt: bool) -> Result<()> {
+    /// Close off the conversation after the reply stream was dropped, with `notification`
+    /// as the result of any tool calls that did not run
+    async fn handle_interrupted_messages(&mut self, notification: &str) -> Result<()> {
         // First, get any tool requests from the last message if it exists
         let tool_requests = self
             .messages
@@ -669,15 +767,10 @@ impl Session {
                 .and_then(|(_, tool_call)| tool_call.as_ref().ok().map(|tool| tool.name.clone()))
                 .unwrap_or_else(|| "tool".to_string());
 
-            let notification = if interrupt {
-                "Interrupted by the user to make a correction".to_string()
-            } else {
-                "An uncaught error happened during tool use".to_string()
-            };
             for (req_id, _) in &tool_requests {
                 response_message.content.push(MessageContent::tool_response(
                     req_id.clone(),
-                    Err(ToolError::ExecutionError(notification.clone())),
+                    Err(ToolError::ExecutionError(notification.to_string())),
                 ));
             }
             self.messages.push(response_message);
@@ -694,7 +787,11 @@ impl Session {
             // No need for description update here
             session::persist_messages(&self.session_file, &self.messages, None).await?;
 
-            output::render_message(&Message::assistant().with_text(&prompt), self.debug);
+            render_message(
+                self.run.as_mut(),
+                &Message::assistant().with_text(&prompt),
+                self.debug,
+            );
         } else {
             // An interruption occurred outside of a tool request-response.
             if let Some(last_msg) = self.messages.last() {
@@ -709,7 +806,8 @@ impl Session {
                             session::persist_messages(&self.session_file, &self.messages, None)
                                 .await?;
 
-                            output::render_message(
+                            render_message(
+                                self.run.as_mut(),
                                 &Message::assistant().with_text(prompt),
                                 self.debug,
                             );
@@ -718,7 +816,8 @@ impl Session {
                             // A real users message
                             self.messages.pop();
                             let prompt = "Interrupted before the model replied and removed the last message.";
-                            output::render_message(
+                            render_message(
+                                self.run.as_mut(),
                                 &Message::assistant().with_text(prompt),
                                 self.debug,
                             );
@@ -731,6 +830,21 @@ impl Session {
         Ok(())
     }
 
+    /// Remove the last `prompts` prompts and everything after them, returning the earliest one removed
+    pub fn rewind(&mut self, prompts: usize) -> Result<Option<String>> {
+        let indices = session::manage::prompt_indices(&self.messages);
+        let Some(&index) = indices
+            .len()
+            .checked_sub(prompts)
+            .and_then(|i| indices.get(i))
+        else {
+            return Ok(None);
+        };
+        let prompt = self.messages[index].as_concat_text();
+        self.messages = session::manage::rewind_session(&self.session_file, index)?;
+        Ok(Some(prompt))
+    }
+
     pub fn session_file(&self) -> PathBuf {
         self.session_file.clone()
     }
@@ -796,6 +910,49 @@ impl Session {
     }
 }
 
+fn render_message(run: Option<&mut report::RunReporter>, message: &Message, debug: bool) {
+    match run {
+        Some(run) => run.message(message, debug),
+        None => output::render_message(message, debug),
+    }
+}
+
+/// Ask whether a tool call may run. Structured runs and runs without a terminal to ask on
+/// decline instead.
+fn confirm_tool(
+    run: Option<&mut report::RunReporter>,
+    confirmation: &ToolConfirmationRequest,
+) -> Result<bool> {
+    let ask = run
+        .as_ref()
+        .is_none_or(|run| !run.structured() && std::io::stdin().is_terminal());
+    let confirmed = if ask {
+        let prompt = "Goose would like to call the above tool, do you approve?";
+        cliclack::confirm(prompt).initial_value(true).interact()?
+    } else {
+        false
+    };
+    if let (false, Some(run)) = (confirmed, run) {
+        run.tool_declined(confirmation);
+    }
+    Ok(confirmed)
+}
+
+/// Add the usage of the latest provider call to the run, returning whether the run has used up
+/// its token budget
+fn record_usage(run: Option<&mut report::RunReporter>, session_file: &Path) -> bool {
+    let Some(run) = run else {
+        return false;
+    };
+    match session::read_metadata(session_file) {
+        Ok(metadata) => run.record_usage(&metadata),
+        Err(e) => {
+            tracing::warn!("Failed to read session usage: {}", e);
+            false
+        }
+    }
+}
+
 fn get_reasoner() -> Result<Box<dyn Provider + Send + Sync>, anyhow::Error> {
     use goose::model::ModelConfig;
     use goose::providers::create;
@@ -0,0 +1,460 @@
+//! Reporting for non-interactive `goose run`: renders the run in the chosen output format,
+//! tracks token usage and cost, and decides the exit code.
+//!
+//! Cost is computed from `GOOSE_INPUT_TOKEN_COST` and `GOOSE_OUTPUT_TOKEN_COST`, in USD per
+//! million tokens, when either is configured.
+
+use goose::config::Config;
+use goose::message::{Message, MessageContent, ToolConfirmationRequest};
+use goose::recipe::Recipe;
+use goose::session::SessionMetadata;
+use mcp_core::role::Role;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::PathBuf;
+
+use super::output;
+
+/// Exit codes of `goose run`. Invalid arguments exit with 2.
+pub mod exit_code {
+    pub const SUCCESS: i32 = 0;
+    /// The provider or agent failed
+    pub const ERROR: i32 = 1;
+    /// The run used up its token budget
+    pub const BUDGET_EXCEEDED: i32 = 3;
+    /// A tool call needed confirmation and was declined
+    pub const TOOL_DECLINED: i32 = 4;
+    /// Interrupted with ctrl-c
+    pub const INTERRUPTED: i32 = 130;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Only the final response, as plain text
+    Text,
+    /// A single JSON document once the run finishes
+    Json,
+    /// One JSON event per line as the run progresses
+    StreamJson,
+}
+
+/// How to run a non-interactive session
+#[derive(Default)]
+pub struct RunOptions {
+    /// None keeps the usual terminal rendering
+    pub format: Option<OutputFormat>,
+    /// Stop the run once it has used this many tokens
+    pub token_budget: Option<i64>,
+    /// The recipe being run, to check the response against its schema
+    pub recipe: Option<Recipe>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinishReason {
+    Completed,
+    Error,
+    BudgetExceeded,
+    ToolDeclined,
+    Interrupted,
+}
+
+impl FinishReason {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            FinishReason::Completed => exit_code::SUCCESS,
+            FinishReason::Error => exit_code::ERROR,
+            FinishReason::BudgetExceeded => exit_code::BUDGET_EXCEEDED,
+            FinishReason::ToolDeclined => exit_code::TOOL_DECLINED,
+            FinishReason::Interrupted => exit_code::INTERRUPTED,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RunUsage {
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub total_tokens: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RunEvent {
+    Message {
+        role: String,
+        text: String,
+    },
+    ToolCall {
+        id: String,
+        name: String,
+        arguments: Value,
+    },
+    ToolResult {
+        id: String,
+        is_error: bool,
+        output: String,
+    },
+    ToolDeclined {
+        id: String,
+        name: String,
+    },
+    Error {
+        message: String,
+    },
+    Finish(RunSummary),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub reason: FinishReason,
+    pub exit_code: i32,
+    /// The text of the last assistant message
+    pub result: String,
+    /// The parsed response, when the recipe has a response schema
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+    pub usage: RunUsage,
+    /// In USD, when token costs are configured
+    pub cost: Option<f64>,
+    pub session_file: PathBuf,
+    /// Every event of the run, in the json format
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<RunEvent>>,
+}
+
+pub struct RunReporter {
+    format: Option<OutputFormat>,
+    token_budget: Option<i64>,
+    recipe: Option<Recipe>,
+    output: Option<Value>,
+    events: Vec<RunEvent>,
+    usage: RunUsage,
+    result: String,
+    error: bool,
+    declined: bool,
+    budget_exceeded: bool,
+    interrupted: bool,
+}
+
+impl RunReporter {
+    pub fn new(options: RunOptions) -> Self {
+        Self {
+            format: options.format,
+            token_budget: options.token_budget,
+            recipe: options.recipe,
+            output: None,
+            events: Vec::new(),
+            usage: RunUsage::default(),
+            result: String::new(),
+            error: false,
+            declined: false,
+            budget_exceeded: false,
+            interrupted: false,
+        }
+    }
+
+    pub fn message(&mut self, message: &Message, debug: bool) {
+        if self.format.is_none() {
+            output::render_message(message, debug);
+        }
+        if message.role == Role::Assistant {
+            let text = message.as_concat_text();
+            if !text.trim().is_empty() {
+                self.result = text;
+            }
+        }
+        for event in message_events(message) {
+            self.emit(event);
+        }
+    }
+
+    /// Add the usage of the provider call that produced the latest response, returning
+    /// whether the run has now used up its token budget
+    pub fn record_usage(&mut self, metadata: &SessionMetadata) -> bool {
+        let input = metadata.input_tokens.unwrap_or(0) as i64;
+        let output = metadata.output_tokens.unwrap_or(0) as i64;
+        self.usage.input_tokens += input;
+        self.usage.output_tokens += output;
+        self.usage.total_tokens += metadata
+            .total_tokens
+            .map(|total| total as i64)
+            .unwrap_or(input + output);
+
+        if self
+            .token_budget
+            .is_some_and(|budget| self.usage.total_tokens >= budget)
+        {
+            self.budget_exceeded = true;
+        }
+        self.budget_exceeded
+    }
+
+    pub fn tool_declined(&mut self, request: &ToolConfirmationRequest) {
+        self.declined = true;
+        self.emit(RunEvent::ToolDeclined {
+            id: request.id.clone(),
+            name: request.tool_name.clone(),
+        });
+    }
+
+    pub fn error(&mut self, message: String) {
+        self.error = true;
+        match self.format {
+            None => output::render_error(&message),
+            Some(OutputFormat::Text) => eprintln!("Error: {}", message),
+            Some(_) => {}
+        }
+        self.emit(RunEvent::Error { message });
+    }
+
+    pub fn interrupted(&mut self) {
+        self.interrupted = true;
+    }
+
+    /// Whether confirmations should be declined rather than prompted for
+    pub fn structured(&self) -> bool {
+        matches!(
+            self.format,
+            Some(OutputFormat::Json) | Some(OutputFormat::StreamJson)
+        )
+    }
+
+    pub fn reason(&self) -> FinishReason {
+        if self.error {
+            FinishReason::Error
+        } else if self.interrupted {
+            FinishReason::Interrupted
+        } else if self.budget_exceeded {
+            FinishReason::BudgetExceeded
+        } else if self.declined {
+            FinishReason::ToolDeclined
+        } else {
+            FinishReason::Completed
+        }
+    }
+
+    pub fn summary(&self, session_file: PathBuf) -> RunSummary {
+        let reason = self.reason();
+        RunSummary {
+            reason,
+            exit_code: reason.exit_code(),
+            result: self.result.clone(),
+            output: self.output.clone(),
+            usage: self.usage.clone(),
+            cost: configured_cost(&self.usage),
+            session_file,
+            events: None,
+        }
+    }
+
+    /// Check the final response against the recipe's response schema
+    fn check_response(&mut self) {
+        if self.reason() != FinishReason::Completed {
+            return;
+        }
+        let Some(recipe) = &self.recipe else {
+            return;
+        };
+        match recipe.check_response(&self.result) {
+            Ok(output) => self.output = output,
+            Err(e) => self.error(format!(
+                "The response does not match the recipe's response schema: {:#}",
+                e
+            )),
+        }
+    }
+
+    /// Print the end of the run and return the exit code
+    pub fn finish(mut self, session_file: PathBuf) -> i32 {
+        self.check_response();
+        let mut summary = self.summary(session_file);
+        let exit_code = summary.exit_code;
+        match self.format {
+            None => {
+                if summary.reason != FinishReason::Completed {
+                    eprintln!("Run finished: {}", reason_text(summary.reason));
+                }
+            }
+            Some(OutputFormat::Text) => {
+                if !summary.result.is_empty() {
+                    println!("{}", summary.result.trim_end());
+                }
+                if summary.reason != FinishReason::Completed {
+                    eprintln!("Run finished: {}", reason_text(summary.reason));
+                }
+            }
+            Some(OutputFormat::Json) => {
+                summary.events = Some(std::mem::take(&mut self.events));
+                print_json(&summary);
+            }
+            Some(OutputFormat::StreamJson) => print_json(&RunEvent::Finish(summary)),
+        }
+        exit_code
+    }
+
+    fn emit(&mut self, event: RunEvent) {
+        match self.format {
+            Some(OutputFormat::StreamJson) => print_json(&event),
+            Some(OutputFormat::Json) => self.events.push(event),
+            _ => {}
+        }
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    match serde_json::to_string(value) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("Failed to serialize output: {}", e),
+    }
+}
+
+fn reason_text(reason: FinishReason) -> &'static str {
+    match reason {
+        FinishReason::Completed => "completed",
+        FinishReason::Error => "stopped by an error",
+        FinishReason::BudgetExceeded => "stopped after using up the token budget",
+        FinishReason::ToolDeclined => "a tool call was declined",
+        FinishReason::Interrupted => "interrupted",
+    }
+}
+
+fn message_events(message: &Message) -> Vec<RunEvent> {
+    let role = match message.role {
+        Role::User => "user",
+        Role::Assistant => "assistant",
+    };
+    let mut events = Vec::new();
+    let text = message.as_concat_text();
+    if !text.trim().is_empty() {
+        events.push(RunEvent::Message {
+            role: role.to_string(),
+            text,
+        });
+    }
+    for content in &message.content {
+        match content {
+            MessageContent::ToolRequest(request) => {
+                if let Ok(call) = &request.tool_call {
+                    events.push(RunEvent::ToolCall {
+                        id: request.id.clone(),
+                        name: call.name.clone(),
+                        arguments: call.arguments.clone(),
+                    });
+                }
+            }
+            MessageContent::ToolResponse(response) => {
+                let (is_error, output) = match &response.tool_result {
+                    Ok(contents) => (
+                        false,
+                        contents
+                            .iter()
+                            .filter_map(|content| content.as_text())
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    ),
+                    Err(e) => (true, e.to_string()),
+                };
+                events.push(RunEvent::ToolResult {
+                    id: response.id.clone(),
+                    is_error,
+                    output,
+                });
+            }
+            _ => {}
+        }
+    }
+    events
+}
+
+fn configured_cost(usage: &RunUsage) -> Option<f64> {
+    let config = Config::global();
+    let input: Option<f64> = config.get_param("GOOSE_INPUT_TOKEN_COST").ok();
+    let output: Option<f64> = config.get_param("GOOSE_OUTPUT_TOKEN_COST").ok();
+    cost(usage, input, output)
+}
+
+/// Cost in USD given prices per million tokens
+fn cost(usage: &RunUsage, input: Option<f64>, output: Option<f64>) -> Option<f64> {
+    if input.is_none() && output.is_none() {
+        return None;
+    }
+    Some(
+        (usage.input_tokens as f64 * input.unwrap_or(0.0)
+            + usage.output_tokens as f64 * output.unwrap_or(0.0))
+            / 1_000_000.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::content::Content;
+    use mcp_core::handler::ToolError;
+    use mcp_core::tool::ToolCall;
+    use serde_json::json;
+
+    #[test]
+    fn test_message_events() {
+        let request = Message::assistant()
+            .with_text("Listing files")
+            .with_tool_request("1", Ok(ToolCall::new("shell", json!({"command": "ls"}))));
+        let response = Message::user()
+            .with_tool_response("1", Ok(vec![Content::text("a.txt")]))
+            .with_tool_response("2", Err(ToolError::ExecutionError("failed".into())));
+
+        let events: Vec<Value> = message_events(&request)
+            .into_iter()
+            .chain(message_events(&response))
+            .map(|event| serde_json::to_value(event).unwrap())
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                json!({"type": "message", "role": "assistant", "text": "Listing files"}),
+                json!({"type": "tool_call", "id": "1", "name": "shell", "arguments": {"command": "ls"}}),
+                json!({"type": "tool_result", "id": "1", "is_error": false, "output": "a.txt"}),
+                json!({"type": "tool_result", "id": "2", "is_error": true, "output": "Execution failed: failed"}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_usage_budget_and_exit_codes() {
+        let mut reporter = RunReporter::new(RunOptions {
+            format: Some(OutputFormat::Json),
+            token_budget: Some(1000),
+            recipe: None,
+        });
+        let metadata = SessionMetadata {
+            input_tokens: Some(400),
+            output_tokens: Some(100),
+            total_tokens: Some(500),
+            ..Default::default()
+        };
+
+        assert!(!reporter.record_usage(&metadata));
+        assert_eq!(reporter.reason(), FinishReason::Completed);
+        reporter.tool_declined(&ToolConfirmationRequest {
+            id: "1".to_string(),
+            tool_name: "shell".to_string(),
+            arguments: json!({}),
+            prompt: None,
+        });
+        assert_eq!(reporter.reason().exit_code(), exit_code::TOOL_DECLINED);
+        assert!(reporter.record_usage(&metadata));
+        assert_eq!(reporter.reason().exit_code(), exit_code::BUDGET_EXCEEDED);
+
+        let summary = reporter.summary(PathBuf::from("run.jsonl"));
+        assert_eq!(
+            summary.usage,
+            RunUsage {
+                input_tokens: 800,
+                output_tokens: 200,
+                total_tokens: 1000
+            }
+        );
+        assert_eq!(cost(&summary.usage, Some(3.0), Some(15.0)), Some(0.0054));
+        assert_eq!(cost(&summary.usage, None, None), None);
+    }
+}
@@ -0,0 +1,469 @@
+//! The computercontroller cache and its persisted index.
+//!
+//! Every file the extension caches is recorded in `index.json` with its source, mime type,
+//! size, content hash and creation and last access times. The index is what the extension
+//! lists as resources, so cached items stay available across restarts. Storing content that
+//! is already cached returns the existing file, and the cache is kept under its limits by
+//! dropping items past the age limit and then the least recently used ones.
+//!
+//! Configured through environment variables:
+//! - `GOOSE_CACHE_MAX_MB`: total size limit, default 500
+//! - `GOOSE_CACHE_MAX_AGE_DAYS`: age limit, default 30, `0` to keep items regardless of age
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const INDEX_FILE: &str = "index.json";
+const DEFAULT_MAX_MB: u64 = 500;
+const DEFAULT_MAX_AGE_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Copy)]
+pub struct CacheLimits {
+    pub max_bytes: u64,
+    pub max_age: Option<Duration>,
+}
+
+impl Default for CacheLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_MB * 1024 * 1024,
+            max_age: Some(Duration::days(DEFAULT_MAX_AGE_DAYS)),
+        }
+    }
+}
+
+impl CacheLimits {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let max_bytes = std::env::var("GOOSE_CACHE_MAX_MB")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(|mb| mb.saturating_mul(1024 * 1024))
+            .unwrap_or(defaults.max_bytes);
+        let max_age = match std::env::var("GOOSE_CACHE_MAX_AGE_DAYS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+        {
+            // An age too long for a Duration keeps items regardless of age too
+            Some(days) => Some(days)
+                .filter(|days| *days > 0)
+                .and_then(Duration::try_days),
+            None => defaults.max_age,
+        };
+        Self { max_bytes, max_age }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// File name inside the cache directory
+    pub file: String,
+    /// Where the content came from, such as the URL of a scraped page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub mime_type: String,
+    pub size: u64,
+    /// Hex encoded SHA-256 of the content
+    pub hash: String,
+    pub created: DateTime<Utc>,
+    pub accessed: DateTime<Utc>,
+}
+
+impl CacheEntry {
+    /// Whether the content is text, as opposed to bytes that need encoding to be read
+    pub fn is_text(&self) -> bool {
+        let mime = self.mime_type.as_str();
+        mime.starts_with("text/")
+            || mime.ends_with("json")
+            || mime.ends_with("xml")
+            || mime == "application/javascript"
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct IndexFile {
+    entries: Vec<CacheEntry>,
+}
+
+pub struct CacheIndex {
+    dir: PathBuf,
+    limits: CacheLimits,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+impl CacheIndex {
+    /// Load the index for a cache directory, reconciling it with the files that are there.
+    ///
+    /// Entries whose file is gone are dropped, and files missing from the index, such as those
+    /// cached before the index existed, are added to it.
+    pub fn load(dir: &Path, limits: CacheLimits) -> Self {
+        let saved: IndexFile = fs::read_to_string(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|content| match serde_json::from_str(&content) {
+                Ok(index) => Some(index),
+                Err(e) => {
+                    tracing::warn!("Ignoring unreadable cache index: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        let mut entries: BTreeMap<String, CacheEntry> = saved
+            .entries
+            .into_iter()
+            .filter(|entry| dir.join(&entry.file).is_file())
+            .map(|entry| (entry.file.clone(), entry))
+            .collect();
+
+        for dir_entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            let path = dir_entry.path();
+            let Some(file) = path.file_name().and_then(|f| f.to_str()) else {
+                continue;
+            };
+            if !path.is_file() || file == INDEX_FILE || file.starts_with('.') {
+                continue;
+            }
+            if entries.contains_key(file) {
+                continue;
+            }
+            let Ok(content) = fs::read(&path) else {
+                continue;
+            };
+            let modified = dir_entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .map(DateTime::<Utc>::from)
+                .unwrap_or_else(|_| Utc::now());
+            entries.insert(
+                file.to_string(),
+                CacheEntry {
+                    file: file.to_string(),
+                    source: None,
+                    mime_type: mime_from_extension(&path).to_string(),
+                    size: content.len() as u64,
+                    hash: hash(&content),
+                    created: modified,
+                    accessed: modified,
+                },
+            );
+        }
+
+        let mut index = Self {
+            dir: dir.to_path_buf(),
+            limits,
+            entries,
+        };
+        index.enforce_limits(None);
+        if let Err(e) = index.save() {
+            tracing::warn!("Failed to save cache index: {}", e);
+        }
+        index
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &CacheEntry> {
+        self.entries.values()
+    }
+
+    pub fn path_of(&self, entry: &CacheEntry) -> PathBuf {
+        self.dir.join(&entry.file)
+    }
+
+    /// The entry for a path inside the cache directory
+    pub fn get(&self, path: &Path) -> Option<&CacheEntry> {
+        self.file_name(path)
+            .and_then(|file| self.entries.get(&file))
+    }
+
+    fn file_name(&self, path: &Path) -> Option<String> {
+        let parent = path.parent()?;
+        if parent != self.dir {
+            return None;
+        }
+        path.file_name()?.to_str().map(str::to_string)
+    }
+
+    /// Cache content, or return the existing file when the same content is already cached.
+    ///
+    /// Returns the path of the cached file and the paths of any files evicted to make room.
+    pub fn store(
+        &mut self,
+        content: &[u8],
+        prefix: &str,
+        extension: &str,
+        mime_type: &str,
+        source: Option<&str>,
+    ) -> std::io::Result<(PathBuf, Vec<PathBuf>)> {
+        let hash = hash(content);
+        let now = Utc::now();
+
+        let existing = self
+            .entries
+            .values_mut()
+            .find(|entry| entry.hash == hash && self.dir.join(&entry.file).is_file());
+        let file = match existing {
+            Some(entry) => {
+                entry.accessed = now;
+                if let Some(source) = source {
+                    entry.source = Some(source.to_string());
+                }
+                entry.file.clone()
+            }
+            None => {
+                // The hash keeps names unique when several items are cached in the same second
+                let file = format!(
+                    "{}_{}_{}.{}",
+                    prefix,
+                    chrono::Local::now().format("%Y%m%d_%H%M%S"),
+                    &hash[..8],
+                    extension
+                );
+                fs::write(self.dir.join(&file), content)?;
+                self.entries.insert(
+                    file.clone(),
+                    CacheEntry {
+                        file: file.clone(),
+                        source: source.map(str::to_string),
+                        mime_type: mime_type.to_string(),
+                        size: content.len() as u64,
+                        hash,
+                        created: now,
+                        accessed: now,
+                    },
+                );
+                file
+            }
+        };
+
+        let evicted = self.enforce_limits(Some(&file));
+        self.save()?;
+        Ok((self.dir.join(file), evicted))
+    }
+
+    /// Record that a cached file was read, so it counts as recently used
+    pub fn touch(&mut self, path: &Path) {
+        let Some(file) = self.file_name(path) else {
+            return;
+        };
+        if let Some(entry) = self.entries.get_mut(&file) {
+            entry.accessed = Utc::now();
+            if let Err(e) = self.save() {
+                tracing::warn!("Failed to save cache index: {}", e);
+            }
+        }
+    }
+
+    /// Delete a cached file and its entry
+    pub fn remove(&mut self, path: &Path) -> std::io::Result<()> {
+        fs::remove_file(path)?;
+        if let Some(file) = self.file_name(path) {
+            self.entries.remove(&file);
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Delete everything in the cache directory
+    pub fn clear(&mut self) -> std::io::Result<()> {
+        fs::remove_dir_all(&self.dir)?;
+        fs::create_dir_all(&self.dir)?;
+        self.entries.clear();
+        self.save()
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.entries.values().map(|e| e.size).sum()
+    }
+
+    pub fn limits(&self) -> CacheLimits {
+        self.limits
+    }
+
+    /// Drop entries past the age limit, then the least recently used until the cache fits.
+    /// The entry named by `keep` is never dropped.
+    fn enforce_limits(&mut self, keep: Option<&str>) -> Vec<PathBuf> {
+        let mut evict: Vec<String> = Vec::new();
+
+        if let Some(cutoff) = self
+            .limits
+            .max_age
+            .and_then(|max_age| Utc::now().checked_sub_signed(max_age))
+        {
+            evict.extend(
+                self.entries
+                    .values()
+                    .filter(|e| e.created < cutoff && Some(e.file.as_str()) != keep)
+                    .map(|e| e.file.clone()),
+            );
+        }
+
+        let mut size: u64 = self
+            .entries
+            .values()
+            .filter(|e| !evict.contains(&e.file))
+            .map(|e| e.size)
+            .sum();
+        if size > self.limits.max_bytes {
+            let mut by_access: Vec<&CacheEntry> = self
+                .entries
+                .values()
+                .filter(|e| !evict.contains(&e.file) && Some(e.file.as_str()) != keep)
+                .collect();
+            by_access.sort_by_key(|e| e.accessed);
+            for entry in by_access {
+                if size <= self.limits.max_bytes {
+                    break;
+                }
+                size -= entry.size;
+                evict.push(entry.file.clone());
+            }
+        }
+
+        evict
+            .into_iter()
+            .filter_map(|file| {
+                self.entries.remove(&file)?;
+                let path = self.dir.join(&file);
+                match fs::remove_file(&path) {
+                    Ok(()) => Some(path),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Some(path),
+                    Err(e) => {
+                        tracing::warn!("Failed to evict {}: {}", path.display(), e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Write the index next to the cached files, through a rename so it is never left torn
+    fn save(&self) -> std::io::Result<()> {
+        let index = IndexFile {
+            entries: self.entries.values().cloned().collect(),
+        };
+        let content = serde_json::to_string_pretty(&index)?;
+        let temp = self.dir.join(format!(".{}.tmp", INDEX_FILE));
+        fs::write(&temp, content)?;
+        fs::rename(temp, self.dir.join(INDEX_FILE))
+    }
+}
+
+fn hash(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn mime_from_extension(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .as_deref()
+    {
+        Some("md") => "text/markdown",
+        Some("txt") | Some("log") => "text/plain",
+        Some("json") => "application/json",
+        Some("html") | Some("htm") => "text/html",
+        Some("csv") => "text/csv",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max_bytes: u64) -> CacheLimits {
+        CacheLimits {
+            max_bytes,
+            max_age: Some(Duration::days(30)),
+        }
+    }
+
+    #[test]
+    fn test_store_dedupes_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = CacheIndex::load(dir.path(), limits(1024));
+
+        let (first, _) = cache
+            .store(b"hello", "web", "txt", "text/plain", None)
+            .unwrap();
+        let (second, _) = cache
+            .store(
+                b"hello",
+                "web",
+                "txt",
+                "text/plain",
+                Some("https://example.com"),
+            )
+            .unwrap();
+        assert_eq!(first, second);
+        assert_eq!(cache.entries().count(), 1);
+
+        // A file cached before the index existed is picked up on load
+        fs::write(dir.path().join("old_output.json"), "{}").unwrap();
+
+        let cache = CacheIndex::load(dir.path(), limits(1024));
+        let entry = cache.get(&first).unwrap();
+        assert_eq!(entry.source.as_deref(), Some("https://example.com"));
+        assert_eq!(entry.mime_type, "text/plain");
+        let old = cache.get(&dir.path().join("old_output.json")).unwrap();
+        assert_eq!(old.mime_type, "application/json");
+        assert!(old.is_text());
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = CacheIndex::load(dir.path(), limits(25));
+
+        let (a, _) = cache
+            .store(&[b'a'; 10], "a", "txt", "text/plain", None)
+            .unwrap();
+        let (b, _) = cache
+            .store(&[b'b'; 10], "b", "txt", "text/plain", None)
+            .unwrap();
+        cache.touch(&a);
+        let (c, evicted) = cache
+            .store(&[b'c'; 10], "c", "txt", "text/plain", None)
+            .unwrap();
+
+        assert_eq!(evicted, vec![b.clone()]);
+        assert!(!b.exists());
+        assert!(a.exists() && c.exists());
+        assert_eq!(cache.total_size(), 20);
+    }
+
+    #[test]
+    fn test_items_past_max_age_are_evicted_on_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = CacheIndex::load(dir.path(), limits(1024));
+        let (path, _) = cache
+            .store(b"stale", "web", "txt", "text/plain", None)
+            .unwrap();
+
+        let file = path.file_name().unwrap().to_str().unwrap().to_string();
+        cache.entries.get_mut(&file).unwrap().created = Utc::now() - Duration::days(31);
+        cache.save().unwrap();
+
+        // An age limit reaching past the earliest representable time keeps everything
+        let unlimited = CacheLimits {
+            max_bytes: 1024,
+            max_age: Some(Duration::MAX),
+        };
+        let cache = CacheIndex::load(dir.path(), unlimited);
+        assert_eq!(cache.entries().count(), 1);
+
+        let cache = CacheIndex::load(dir.path(), limits(1024));
+        assert_eq!(cache.entries().count(), 0);
+        assert!(!path.exists());
+    }
+}
@@ -18,6 +18,10 @@ enum UpdateMode {
         width: Option<u32>,
         height: Option<u32>,
     },
+    AddTable {
+        rows: Vec<Vec<String>>,
+        header: bool,
+    },
 }
 
 #[derive(Debug, Clone, Default)]
@@ -106,51 +110,33 @@ pub async fn docx_tool(
 
             // Extract document structure and text
             for element in docx.document.children.iter() {
-                if let DocumentChild::Paragraph(p) = element {
-                    // Check for heading style
-                    if let Some(style) = p.property.style.as_ref() {
-                        if style.val.starts_with("Heading") {
-                            current_level = Some(style.val.clone());
-                            structure.push(format!("{}: ", style.val));
-                        }
-                    }
-
-                    // Extract text from runs
-                    let para_text: String = p
-                        .children
-                        .iter()
-                        .filter_map(|child| {
-                            if let ParagraphChild::Run(run) = child {
-                                Some(
-                                    run.children
-                                        .iter()
-                                        .filter_map(|rc| {
-                                            if let RunChild::Text(t) = rc {
-                                                Some(t.text.clone())
-                                            } else {
-                                                None
-                                            }
-                                        })
-                                        .collect::<Vec<_>>()
-                                        .join(""),
-                                )
-                            } else {
-                                None
+                match element {
+                    DocumentChild::Paragraph(p) => {
+                        // Check for heading style
+                        if let Some(style) = p.property.style.as_ref() {
+                            if style.val.starts_with("Heading") {
+                                current_level = Some(style.val.clone());
+                                structure.push(format!("{}: ", style.val));
                             }
-                        })
-                        .collect::<Vec<_>>()
-                        .join("");
+                        }
 
-                    if !para_text.trim().is_empty() {
-                        if current_level.is_some() {
-                            if let Some(s) = structure.last_mut() {
-                                s.push_str(&para_text);
+                        let para_text = paragraph_text(p);
+                        if !para_text.trim().is_empty() {
+                            if current_level.is_some() {
+                                if let Some(s) = structure.last_mut() {
+                                    s.push_str(&para_text);
+                                }
+                                current_level = None;
                             }
-                            current_level = None;
+                            text.push_str(&para_text);
+                            text.push('\n');
                         }
-                        text.push_str(&para_text);
+                    }
+                    DocumentChild::Table(table) => {
+                        text.push_str(&table_to_markdown(&table_rows(table)));
                         text.push('\n');
                     }
+                    _ => {}
                 }
             }
 
@@ -168,11 +154,18 @@ pub async fn docx_tool(
         }
 
         "update_doc" => {
-            let content = content.ok_or_else(|| {
-                ToolError::InvalidParameters(
-                    "Content parameter required for update_doc".to_string(),
-                )
-            })?;
+            let is_table =
+                params.and_then(|p| p.get("mode")).and_then(|v| v.as_str()) == Some("table");
+            let content = match content {
+                Some(content) => content,
+                // A table can be added without a caption
+                None if is_table => "",
+                None => {
+                    return Err(ToolError::InvalidParameters(
+                        "Content parameter required for update_doc".to_string(),
+                    ))
+                }
+            };
 
             // Parse update mode and style from params
             let (mode, style) = if let Some(params) = params {
@@ -235,8 +228,34 @@ pub async fn docx_tool(
                             height,
                         }
                     }
+                    "table" => {
+                        let rows = params
+                            .get("rows")
+                            .and_then(|v| v.as_array())
+                            .ok_or_else(|| {
+                                ToolError::InvalidParameters(
+                                    "rows parameter required for table mode".to_string(),
+                                )
+                            })?
+                            .iter()
+                            .map(|row| {
+                                row.as_array()
+                                    .map(|cells| cells.iter().map(cell_string).collect())
+                                    .ok_or_else(|| {
+                                        ToolError::InvalidParameters(
+                                            "Each table row must be an array of cells".to_string(),
+                                        )
+                                    })
+                            })
+                            .collect::<Result<Vec<Vec<String>>, _>>()?;
+                        let header = params
+                            .get("header")
+                            .and_then(|v| v.as_bool())
+                            .unwrap_or(true);
+                        UpdateMode::AddTable { rows, header }
+                    }
                     _ => return Err(ToolError::InvalidParameters(
-                        "Invalid mode. Must be 'append', 'replace', 'structured', or 'add_image'"
+                        "Invalid mode. Must be 'append', 'replace', 'structured', 'add_image', or 'table'"
                             .to_string(),
                     )),
                 };
@@ -539,16 +558,767 @@ pub async fn docx_tool(
                         path
                     ))])
                 }
+
+                UpdateMode::AddTable { rows, header } => {
+                    if rows.is_empty() {
+                        return Err(ToolError::InvalidParameters(
+                            "Table must have at least one row".to_string(),
+                        ));
+                    }
+                    let mut doc = if std::path::Path::new(path).exists() {
+                        read_document(path)?
+                    } else {
+                        Docx::new()
+                    };
+
+                    if !content.trim().is_empty() {
+                        let mut caption = Paragraph::new();
+                        let mut run = Run::new().add_text(content);
+                        if let Some(style) = &style {
+                            caption = style.apply_to_paragraph(caption);
+                            run = style.apply_to_run(run);
+                        }
+                        doc = doc.add_paragraph(caption.add_run(run));
+                    }
+
+                    let columns = rows.iter().map(Vec::len).max().unwrap_or(0).max(1);
+                    let table_rows = rows
+                        .iter()
+                        .enumerate()
+                        .map(|(i, row)| {
+                            let cells = (0..columns)
+                                .map(|c| {
+                                    let mut run =
+                                        Run::new().add_text(row.get(c).map_or("", String::as_str));
+                                    if header && i == 0 {
+                                        run = run.bold();
+                                    }
+                                    TableCell::new().add_paragraph(Paragraph::new().add_run(run))
+                                })
+                                .collect();
+                            TableRow::new(cells)
+                        })
+                        .collect();
+                    let table =
+                        Table::new(table_rows).set_grid(vec![TABLE_WIDTH / columns; columns]);
+                    doc = doc.add_table(table);
+
+                    write_document(doc, path)?;
+                    Ok(vec![Content::text(format!(
+                        "Successfully added a {}x{} table to {}",
+                        rows.len(),
+                        columns,
+                        path
+                    ))])
+                }
             }
         }
 
+        "extract_tables" => extract_tables(path),
+        "outline" => outline(path, params),
+        "list_comments" => list_comments(path),
+        "add_comment" => add_comment(path, content, params),
+        "list_changes" => list_changes(path),
+        "accept_changes" => resolve_changes(path, params, true),
+        "reject_changes" => resolve_changes(path, params, false),
+
         _ => Err(ToolError::InvalidParameters(format!(
-            "Invalid operation: {}. Valid operations are: 'extract_text', 'update_doc'",
+            "Invalid operation: {}. Valid operations are: 'extract_text', 'extract_tables', \
+             'outline', 'list_comments', 'add_comment', 'list_changes', 'accept_changes', \
+             'reject_changes', 'update_doc'",
             operation
         ))),
     }
 }
 
+/// Width shared between the columns of tables created by `update_doc`, in twips
+const TABLE_WIDTH: usize = 9000;
+
+const DEFAULT_AUTHOR: &str = "Goose";
+
+fn read_document(path: &str) -> Result<Docx, ToolError> {
+    let file = fs::read(path)
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to read DOCX file: {}", e)))?;
+    read_docx(&file)
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to parse DOCX file: {}", e)))
+}
+
+fn write_document(doc: Docx, path: &str) -> Result<(), ToolError> {
+    let mut buf = Vec::new();
+    doc.build()
+        .pack(&mut Cursor::new(&mut buf))
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to build DOCX: {}", e)))?;
+    fs::write(path, &buf)
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to write DOCX file: {}", e)))
+}
+
+fn cell_string(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn run_text(run: &Run) -> String {
+    let mut text = String::new();
+    for child in &run.children {
+        match child {
+            RunChild::Text(t) => text.push_str(&t.text),
+            RunChild::Tab(_) => text.push('\t'),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// docx-rs keeps the text of deletions private, but it is part of the serialized form
+fn deleted_text(run: &Run) -> String {
+    run.children
+        .iter()
+        .filter_map(|child| match child {
+            RunChild::DeleteText(t) => serde_json::to_value(t).ok(),
+            _ => None,
+        })
+        .filter_map(|v| v.get("text").and_then(|t| t.as_str()).map(String::from))
+        .collect()
+}
+
+fn comment_end_id(end: &CommentRangeEnd) -> Option<usize> {
+    serde_json::to_value(end)
+        .ok()?
+        .get("id")?
+        .as_u64()
+        .map(|id| id as usize)
+}
+
+/// The text of a paragraph as it reads with all tracked changes accepted
+fn paragraph_text(p: &Paragraph) -> String {
+    children_text(&p.children)
+}
+
+fn children_text(children: &[ParagraphChild]) -> String {
+    let mut text = String::new();
+    for child in children {
+        match child {
+            ParagraphChild::Run(run) => text.push_str(&run_text(run)),
+            ParagraphChild::Insert(insert) => {
+                for child in &insert.children {
+                    if let InsertChild::Run(run) = child {
+                        text.push_str(&run_text(run));
+                    }
+                }
+            }
+            ParagraphChild::Hyperlink(link) => text.push_str(&children_text(&link.children)),
+            _ => {}
+        }
+    }
+    text
+}
+
+fn table_rows(table: &Table) -> Vec<Vec<String>> {
+    table
+        .rows
+        .iter()
+        .map(|TableChild::TableRow(row)| {
+            row.cells
+                .iter()
+                .map(|TableRowChild::TableCell(cell)| {
+                    cell.children
+                        .iter()
+                        .filter_map(|content| match content {
+                            TableCellContent::Paragraph(p) => Some(paragraph_text(p)),
+                            TableCellContent::Table(t) => Some(
+                                table_rows(t)
+                                    .iter()
+                                    .map(|row| row.join(" "))
+                                    .collect::<Vec<_>>()
+                                    .join(" "),
+                            ),
+                            _ => None,
+                        })
+                        .filter(|text| !text.trim().is_empty())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn table_to_markdown(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+    let mut markdown = String::new();
+    for (i, row) in rows.iter().enumerate() {
+        let cells: Vec<String> = (0..columns)
+            .map(|c| {
+                row.get(c)
+                    .map_or(String::new(), |cell| cell.trim().replace('|', "\\|"))
+            })
+            .collect();
+        markdown.push_str(&format!("| {} |\n", cells.join(" | ")));
+        if i == 0 {
+            markdown.push_str(&format!("|{}\n", " --- |".repeat(columns)));
+        }
+    }
+    markdown
+}
+
+/// Every paragraph in document order, including those inside (nested) tables
+fn all_paragraphs(doc: &Docx) -> Vec<&Paragraph> {
+    fn from_table<'a>(table: &'a Table, out: &mut Vec<&'a Paragraph>) {
+        for TableChild::TableRow(row) in &table.rows {
+            for TableRowChild::TableCell(cell) in &row.cells {
+                for content in &cell.children {
+                    match content {
+                        TableCellContent::Paragraph(p) => out.push(p),
+                        TableCellContent::Table(t) => from_table(t, out),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    for child in &doc.document.children {
+        match child {
+            DocumentChild::Paragraph(p) => out.push(p.as_ref()),
+            DocumentChild::Table(t) => from_table(t, &mut out),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Mutable counterpart of `all_paragraphs`, visiting paragraphs in the same order
+fn for_each_paragraph_mut(doc: &mut Docx, f: &mut impl FnMut(&mut Paragraph)) {
+    fn in_table(table: &mut Table, f: &mut impl FnMut(&mut Paragraph)) {
+        for TableChild::TableRow(row) in &mut table.rows {
+            for TableRowChild::TableCell(cell) in &mut row.cells {
+                for content in &mut cell.children {
+                    match content {
+                        TableCellContent::Paragraph(p) => f(p),
+                        TableCellContent::Table(t) => in_table(t, f),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    for child in &mut doc.document.children {
+        match child {
+            DocumentChild::Paragraph(p) => f(p),
+            DocumentChild::Table(t) => in_table(t, f),
+            _ => {}
+        }
+    }
+}
+
+fn heading_level(p: &Paragraph) -> Option<usize> {
+    if let Some(style) = &p.property.style {
+        if style.val == "Title" {
+            return Some(1);
+        }
+        if let Some(level) = style
+            .val
+            .strip_prefix("Heading")
+            .and_then(|l| l.trim().parse::<usize>().ok())
+        {
+            return Some(level.clamp(1, 6));
+        }
+    }
+    // Custom heading styles usually carry an explicit outline level
+    p.property
+        .outline_lvl
+        .as_ref()
+        .filter(|l| l.v < 9)
+        .map(|l| (l.v + 1).min(6))
+}
+
+fn extract_tables(path: &str) -> Result<Vec<Content>, ToolError> {
+    let docx = read_document(path)?;
+    let tables: Vec<String> = docx
+        .document
+        .children
+        .iter()
+        .filter_map(|child| match child {
+            DocumentChild::Table(table) => Some(table_rows(table)),
+            _ => None,
+        })
+        .enumerate()
+        .map(|(i, rows)| {
+            let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+            format!(
+                "Table {} ({} rows x {} columns):\n{}",
+                i + 1,
+                rows.len(),
+                columns,
+                table_to_markdown(&rows)
+            )
+        })
+        .collect();
+
+    if tables.is_empty() {
+        return Ok(vec![Content::text(format!("No tables found in {}", path))]);
+    }
+    Ok(vec![Content::text(tables.join("\n"))])
+}
+
+fn outline(path: &str, params: Option<&serde_json::Value>) -> Result<Vec<Content>, ToolError> {
+    let include_body = params
+        .and_then(|p| p.get("include_body"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let output_path = params
+        .and_then(|p| p.get("output_path"))
+        .and_then(|v| v.as_str());
+
+    let docx = read_document(path)?;
+    let mut blocks = Vec::new();
+    for child in &docx.document.children {
+        match child {
+            DocumentChild::Paragraph(p) => {
+                let text = paragraph_text(p);
+                let text = text.trim();
+                if text.is_empty() {
+                    continue;
+                }
+                if let Some(level) = heading_level(p) {
+                    blocks.push(format!("{} {}", "#".repeat(level), text));
+                } else if include_body {
+                    match &p.property.numbering_property {
+                        Some(numbering) => {
+                            let depth = numbering.level.as_ref().map_or(0, |l| l.val);
+                            // Consecutive list items belong to the same block
+                            let item = format!("{}- {}", "  ".repeat(depth), text);
+                            match blocks.last_mut() {
+                                Some(last) if last.trim_start().starts_with("- ") => {
+                                    last.push('\n');
+                                    last.push_str(&item);
+                                }
+                                _ => blocks.push(item),
+                            }
+                        }
+                        None => blocks.push(text.to_string()),
+                    }
+                }
+            }
+            DocumentChild::Table(table) if include_body => {
+                blocks.push(table_to_markdown(&table_rows(table)).trim_end().to_string())
+            }
+            _ => {}
+        }
+    }
+
+    if blocks.is_empty() {
+        return Ok(vec![Content::text(format!(
+            "No headings found in {}",
+            path
+        ))]);
+    }
+    let markdown = blocks.join("\n\n") + "\n";
+
+    if let Some(output_path) = output_path {
+        fs::write(output_path, &markdown).map_err(|e| {
+            ToolError::ExecutionError(format!("Failed to write markdown file: {}", e))
+        })?;
+        return Ok(vec![Content::text(format!(
+            "Wrote the outline of {} to {}:\n\n{}",
+            path, output_path, markdown
+        ))]);
+    }
+    Ok(vec![Content::text(markdown)])
+}
+
+/// The text each comment is anchored to, keyed by comment id
+fn comment_anchors(doc: &Docx) -> std::collections::HashMap<usize, String> {
+    use std::collections::HashMap;
+
+    fn visit(
+        children: &[ParagraphChild],
+        open: &mut Vec<usize>,
+        anchors: &mut HashMap<usize, String>,
+    ) {
+        let mut append = |open: &[usize], text: &str| {
+            for id in open {
+                anchors.entry(*id).or_default().push_str(text);
+            }
+        };
+        for child in children {
+            match child {
+                ParagraphChild::CommentStart(start) => open.push(start.id),
+                ParagraphChild::CommentEnd(end) => {
+                    let id = comment_end_id(end);
+                    open.retain(|open_id| Some(*open_id) != id);
+                }
+                ParagraphChild::Run(run) => append(open, &run_text(run)),
+                ParagraphChild::Insert(insert) => {
+                    for child in &insert.children {
+                        match child {
+                            InsertChild::Run(run) => append(open, &run_text(run)),
+                            InsertChild::CommentStart(start) => open.push(start.id),
+                            InsertChild::CommentEnd(end) => {
+                                let id = comment_end_id(end);
+                                open.retain(|open_id| Some(*open_id) != id);
+                            }
+                            InsertChild::Delete(_) => {}
+                        }
+                    }
+                }
+                ParagraphChild::Hyperlink(link) => append(open, &children_text(&link.children)),
+                _ => {}
+            }
+        }
+    }
+
+    let mut open = Vec::new();
+    let mut anchors = HashMap::new();
+    for p in all_paragraphs(doc) {
+        visit(&p.children, &mut open, &mut anchors);
+        for id in &open {
+            anchors.entry(*id).or_default().push(' ');
+        }
+    }
+    anchors
+}
+
+fn comment_text(comment: &Comment) -> String {
+    comment
+        .children
+        .iter()
+        .filter_map(|child| match child {
+            CommentChild::Paragraph(p) => Some(paragraph_text(p)),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn list_comments(path: &str) -> Result<Vec<Content>, ToolError> {
+    let docx = read_document(path)?;
+    let comments = docx.comments.inner();
+    if comments.is_empty() {
+        return Ok(vec![Content::text(format!(
+            "No comments found in {}",
+            path
+        ))]);
+    }
+
+    let anchors = comment_anchors(&docx);
+    let mut lines = vec![format!("Comments in {}:", path)];
+    for comment in comments {
+        let mut line = format!("[{}] {}", comment.id, comment.author);
+        if !comment.date.is_empty() {
+            line.push_str(&format!(" ({})", comment.date));
+        }
+        match comment.parent_comment_id {
+            Some(parent) => line.push_str(&format!(", reply to [{}]", parent)),
+            None => {
+                if let Some(anchor) = anchors.get(&comment.id) {
+                    line.push_str(&format!(" on \"{}\"", anchor.trim()));
+                }
+            }
+        }
+        line.push_str(&format!(": {}", comment_text(comment)));
+        lines.push(line);
+    }
+    Ok(vec![Content::text(lines.join("\n"))])
+}
+
+fn add_comment(
+    path: &str,
+    content: Option<&str>,
+    params: Option<&serde_json::Value>,
+) -> Result<Vec<Content>, ToolError> {
+    let content = content.filter(|c| !c.trim().is_empty()).ok_or_else(|| {
+        ToolError::InvalidParameters("Content parameter required for add_comment".to_string())
+    })?;
+    let param = |name: &str| params.and_then(|p| p.get(name));
+    let author = param("author")
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_AUTHOR);
+    let reply_to = param("reply_to")
+        .and_then(|v| v.as_u64())
+        .map(|id| id as usize);
+    let anchor_text = param("anchor_text").and_then(|v| v.as_str());
+
+    let mut docx = read_document(path)?;
+    let id = docx
+        .comments
+        .inner()
+        .iter()
+        .map(|c| c.id + 1)
+        .max()
+        .unwrap_or(0);
+
+    let mut comment = Comment::new(id)
+        .author(author)
+        .date(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+        .add_paragraph(Paragraph::new().add_run(Run::new().add_text(content)));
+    if let Some(parent) = reply_to {
+        comment = comment.parent_comment_id(parent);
+    }
+    let start = ParagraphChild::CommentStart(Box::new(CommentRangeStart::new(comment)));
+    let end = ParagraphChild::CommentEnd(CommentRangeEnd::new(id));
+
+    let placed = match (reply_to, anchor_text) {
+        // Replies share the range of the comment they answer
+        (Some(parent), _) => {
+            let mut start = Some(start);
+            let mut end = Some(end);
+            for_each_paragraph_mut(&mut docx, &mut |p| {
+                if let Some(i) = p
+                    .children
+                    .iter()
+                    .position(|c| matches!(c, ParagraphChild::CommentStart(s) if s.id == parent))
+                {
+                    if let Some(start) = start.take() {
+                        p.children.insert(i + 1, start);
+                    }
+                }
+                if let Some(i) = p.children.iter().position(
+                    |c| matches!(c, ParagraphChild::CommentEnd(e) if comment_end_id(e) == Some(parent)),
+                ) {
+                    if let Some(end) = end.take() {
+                        p.children.insert(i + 1, end);
+                    }
+                }
+            });
+            if start.is_some() || end.is_some() {
+                return Err(ToolError::InvalidParameters(format!(
+                    "Could not find comment {} to reply to",
+                    parent
+                )));
+            }
+            true
+        }
+        (None, Some(anchor)) => {
+            let mut markers = Some((start, end));
+            for_each_paragraph_mut(&mut docx, &mut |p| {
+                if markers.is_some() && paragraph_text(p).contains(anchor) {
+                    if let Some((start, end)) = markers.take() {
+                        p.children.insert(0, start);
+                        p.children.push(end);
+                    }
+                }
+            });
+            markers.is_none()
+        }
+        (None, None) => {
+            return Err(ToolError::InvalidParameters(
+                "Either anchor_text or reply_to is required for add_comment".to_string(),
+            ))
+        }
+    };
+    if !placed {
+        return Err(ToolError::ExecutionError(format!(
+            "Could not find text to comment on: {}",
+            anchor_text.unwrap_or_default()
+        )));
+    }
+
+    write_document(docx, path)?;
+    Ok(vec![Content::text(format!(
+        "Added comment [{}] to {}",
+        id, path
+    ))])
+}
+
+#[derive(Debug)]
+struct TrackedChange {
+    insertion: bool,
+    author: String,
+    date: String,
+    text: String,
+}
+
+fn tracked_changes(doc: &Docx) -> Vec<TrackedChange> {
+    let mut changes = Vec::new();
+    for p in all_paragraphs(doc) {
+        for child in &p.children {
+            match child {
+                ParagraphChild::Insert(insert) => changes.push(TrackedChange {
+                    insertion: true,
+                    author: insert.author.clone(),
+                    date: insert.date.clone(),
+                    text: insert
+                        .children
+                        .iter()
+                        .filter_map(|c| match c {
+                            InsertChild::Run(run) => Some(run_text(run)),
+                            _ => None,
+                        })
+                        .collect(),
+                }),
+                ParagraphChild::Delete(delete) => changes.push(TrackedChange {
+                    insertion: false,
+                    author: delete.author.clone(),
+                    date: delete.date.clone(),
+                    text: delete
+                        .children
+                        .iter()
+                        .filter_map(|c| match c {
+                            DeleteChild::Run(run) => Some(deleted_text(run)),
+                            _ => None,
+                        })
+                        .collect(),
+                }),
+                _ => {}
+            }
+        }
+    }
+    changes
+}
+
+fn list_changes(path: &str) -> Result<Vec<Content>, ToolError> {
+    let docx = read_document(path)?;
+    let changes = tracked_changes(&docx);
+    if changes.is_empty() {
+        return Ok(vec![Content::text(format!(
+            "No tracked changes found in {}",
+            path
+        ))]);
+    }
+
+    let mut lines = vec![format!("Tracked changes in {}:", path)];
+    for (i, change) in changes.iter().enumerate() {
+        let mut line = format!(
+            "{}. {} by {}",
+            i + 1,
+            if change.insertion {
+                "Insertion"
+            } else {
+                "Deletion"
+            },
+            change.author
+        );
+        if !change.date.is_empty() {
+            line.push_str(&format!(" ({})", change.date));
+        }
+        line.push_str(&format!(": \"{}\"", change.text));
+        lines.push(line);
+    }
+    Ok(vec![Content::text(lines.join("\n"))])
+}
+
+/// Accepts or rejects tracked changes, optionally limited to the numbers shown by
+/// `list_changes` and to a single author
+fn resolve_changes(
+    path: &str,
+    params: Option<&serde_json::Value>,
+    accept: bool,
+) -> Result<Vec<Content>, ToolError> {
+    let param = |name: &str| params.and_then(|p| p.get(name));
+    let change_ids: Option<Vec<usize>> = param("change_ids")
+        .and_then(|v| v.as_array())
+        .map(|ids| {
+            ids.iter()
+                .map(|id| {
+                    id.as_u64().map(|id| id as usize).ok_or_else(|| {
+                        ToolError::InvalidParameters(
+                            "change_ids must be the numbers shown by list_changes".to_string(),
+                        )
+                    })
+                })
+                .collect()
+        })
+        .transpose()?;
+    let author = param("author").and_then(|v| v.as_str());
+
+    let mut docx = read_document(path)?;
+    let mut number = 0;
+    let mut resolved = 0;
+    for_each_paragraph_mut(&mut docx, &mut |p| {
+        let children = std::mem::take(&mut p.children);
+        for child in children {
+            let (change_author, is_insert) = match &child {
+                ParagraphChild::Insert(insert) => (insert.author.clone(), true),
+                ParagraphChild::Delete(delete) => (delete.author.clone(), false),
+                _ => {
+                    p.children.push(child);
+                    continue;
+                }
+            };
+            number += 1;
+            let selected = change_ids.as_ref().is_none_or(|ids| ids.contains(&number))
+                && author.is_none_or(|a| a == change_author);
+            if !selected {
+                p.children.push(child);
+                continue;
+            }
+            resolved += 1;
+
+            // Keeping an insertion or rejecting a deletion leaves its text in place. Comment
+            // markers inside a change always survive so no comment loses its range.
+            let keep_text = accept == is_insert;
+            match child {
+                ParagraphChild::Insert(insert) => {
+                    for child in insert.children {
+                        match child {
+                            InsertChild::Run(run) if keep_text => {
+                                p.children.push(ParagraphChild::Run(run))
+                            }
+                            InsertChild::Delete(delete) if keep_text => {
+                                p.children.push(ParagraphChild::Delete(delete))
+                            }
+                            InsertChild::CommentStart(start) => {
+                                p.children.push(ParagraphChild::CommentStart(start))
+                            }
+                            InsertChild::CommentEnd(end) => {
+                                p.children.push(ParagraphChild::CommentEnd(end))
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                ParagraphChild::Delete(delete) => {
+                    for child in delete.children {
+                        match child {
+                            DeleteChild::Run(run) if keep_text => {
+                                let text = deleted_text(&run);
+                                let restored = Run {
+                                    run_property: run.run_property,
+                                    children: Vec::new(),
+                                }
+                                .add_text(text);
+                                p.children.push(ParagraphChild::Run(Box::new(restored)));
+                            }
+                            DeleteChild::CommentStart(start) => {
+                                p.children.push(ParagraphChild::CommentStart(start))
+                            }
+                            DeleteChild::CommentEnd(end) => {
+                                p.children.push(ParagraphChild::CommentEnd(end))
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+    });
+
+    if resolved == 0 {
+        return Ok(vec![Content::text(format!(
+            "No matching tracked changes found in {}",
+            path
+        ))]);
+    }
+    write_document(docx, path)?;
+    Ok(vec![Content::text(format!(
+        "{} {} tracked change{} in {}",
+        if accept { "Accepted" } else { "Rejected" },
+        resolved,
+        if resolved == 1 { "" } else { "s" },
+        path
+    ))])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -868,4 +1638,242 @@ mod tests {
         // Clean up
         fs::remove_file(test_output_path).unwrap();
     }
+
+    #[tokio::test]
+    async fn test_docx_tables() {
+        let test_output_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/computercontroller/tests/data/test_tables.docx");
+        let path = test_output_path.to_str().unwrap();
+
+        let params = json!({
+            "mode": "table",
+            "rows": [["Name", "Qty"], ["Apples", 3], ["Pears", null], ["A | B"]]
+        });
+        let result = docx_tool(path, "update_doc", None, Some(&params)).await;
+        assert!(result.is_ok(), "DOCX table update should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(text.contains("4x2 table"), "Should report the table size");
+
+        // Tables are extracted as markdown
+        let result = docx_tool(path, "extract_tables", None, None).await;
+        assert!(result.is_ok(), "DOCX table extraction should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(
+            text.contains(
+                "Table 1 (4 rows x 2 columns):\n| Name | Qty |\n| --- | --- |\n| Apples | 3 |\n| Pears |  |\n| A \\| B |  |"
+            ),
+            "Should contain the table with escaped pipes and padded rows"
+        );
+
+        let result = docx_tool(path, "extract_text", None, None).await;
+        assert!(result.is_ok(), "DOCX text extraction should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(
+            text.contains("| Apples | 3 |"),
+            "Should contain the table in the text"
+        );
+
+        // Table mode needs rows
+        let params = json!({
+            "mode": "table"
+        });
+        let result = docx_tool(path, "update_doc", None, Some(&params)).await;
+        assert!(result.is_err(), "Should fail without rows");
+
+        // Clean up
+        fs::remove_file(test_output_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_docx_comments_and_tracked_changes() {
+        let test_output_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/computercontroller/tests/data/test_review.docx");
+        let test_markdown_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/computercontroller/tests/data/test_review.md");
+        let path = test_output_path.to_str().unwrap();
+
+        // A document with a heading, tracked changes by two authors and a comment
+        let comment = Comment::new(1)
+            .author("Carol")
+            .add_paragraph(Paragraph::new().add_run(Run::new().add_text("Check this")));
+        let doc = Docx::new()
+            .add_paragraph(
+                Paragraph::new()
+                    .style("Heading1")
+                    .add_run(Run::new().add_text("Terms")),
+            )
+            .add_paragraph(
+                Paragraph::new()
+                    .add_run(Run::new().add_text("The fee is "))
+                    .add_delete(
+                        Delete::new()
+                            .author("Bob")
+                            .add_run(Run::new().add_delete_text("100")),
+                    )
+                    .add_insert(Insert::new(Run::new().add_text("200")).author("Alice"))
+                    .add_run(Run::new().add_text(" dollars.")),
+            )
+            .add_paragraph(
+                Paragraph::new()
+                    .add_comment_start(comment)
+                    .add_run(Run::new().add_text("Payment within 30 days."))
+                    .add_comment_end(1),
+            );
+        write_document(doc, path).unwrap();
+
+        let result = docx_tool(path, "list_comments", None, None).await;
+        assert!(result.is_ok(), "Listing comments should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(
+            text.contains("[1] Carol"),
+            "Should contain the comment author"
+        );
+        assert!(
+            text.contains("on \"Payment within 30 days.\": Check this"),
+            "Should contain the commented text and the comment"
+        );
+
+        // Add a comment and a reply
+        let params = json!({
+            "anchor_text": "fee",
+            "author": "Dana"
+        });
+        let result = docx_tool(path, "add_comment", Some("Why the change?"), Some(&params)).await;
+        assert!(result.is_ok(), "Adding a comment should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(
+            text.contains("Added comment [2]"),
+            "Should report the comment id"
+        );
+
+        let params = json!({
+            "reply_to": 1
+        });
+        let result = docx_tool(path, "add_comment", Some("Agreed"), Some(&params)).await;
+        assert!(result.is_ok(), "Replying to a comment should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(
+            text.contains("Added comment [3]"),
+            "Should report the reply id"
+        );
+
+        let result = docx_tool(path, "list_comments", None, None).await;
+        assert!(result.is_ok(), "Listing comments should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(text.contains("[2] Dana"), "Should contain the new comment");
+        assert!(
+            text.contains("on \"The fee is 200 dollars.\": Why the change?"),
+            "Should anchor the new comment on its paragraph"
+        );
+        assert!(
+            text.contains("reply to [1]: Agreed"),
+            "Should contain the reply"
+        );
+
+        let params = json!({
+            "anchor_text": "nowhere"
+        });
+        let result = docx_tool(path, "add_comment", Some("x"), Some(&params)).await;
+        assert!(
+            result.is_err(),
+            "Should fail when the anchor text is missing"
+        );
+
+        // Review tracked changes
+        let result = docx_tool(path, "list_changes", None, None).await;
+        assert!(result.is_ok(), "Listing changes should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(
+            text.contains("1. Deletion by Bob"),
+            "Should contain the deletion"
+        );
+        assert!(
+            text.contains("2. Insertion by Alice"),
+            "Should contain the insertion"
+        );
+        assert!(
+            text.contains(": \"100\""),
+            "Should contain the deleted text"
+        );
+
+        let params = json!({
+            "author": "Alice"
+        });
+        let result = docx_tool(path, "accept_changes", None, Some(&params)).await;
+        assert!(result.is_ok(), "Accepting changes should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(
+            text.contains("Accepted 1 tracked change"),
+            "Should accept only Alice's change"
+        );
+
+        let result = docx_tool(path, "list_changes", None, None).await;
+        assert!(result.is_ok(), "Listing changes should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(
+            text.contains("1. Deletion by Bob"),
+            "Should keep Bob's change"
+        );
+        assert!(!text.contains("Alice"), "Should drop the accepted change");
+
+        let params = json!({
+            "change_ids": [1]
+        });
+        let result = docx_tool(path, "reject_changes", None, Some(&params)).await;
+        assert!(result.is_ok(), "Rejecting changes should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(
+            text.contains("Rejected 1 tracked change"),
+            "Should reject the change"
+        );
+
+        let result = docx_tool(path, "extract_text", None, None).await;
+        assert!(result.is_ok(), "DOCX text extraction should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(
+            text.contains("The fee is 100200 dollars."),
+            "Should keep the inserted and the restored text"
+        );
+
+        // Comments survive resolving the changes around them
+        let result = docx_tool(path, "list_comments", None, None).await;
+        assert!(result.is_ok(), "Listing comments should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(text.contains("[2] Dana"), "Should keep the comment");
+
+        // Outline the headings, then the whole document as markdown
+        let result = docx_tool(path, "outline", None, None).await;
+        assert!(result.is_ok(), "Outlining should succeed");
+        let content = result.unwrap();
+        assert_eq!(content[0].as_text().unwrap(), "# Terms\n");
+
+        let params = json!({
+            "include_body": true,
+            "output_path": test_markdown_path.to_str().unwrap()
+        });
+        let result = docx_tool(path, "outline", None, Some(&params)).await;
+        assert!(result.is_ok(), "Writing the outline should succeed");
+        let markdown = fs::read_to_string(&test_markdown_path).unwrap();
+        assert!(
+            markdown.starts_with("# Terms\n\nThe fee is 100200 dollars."),
+            "Should write the headings and body as markdown"
+        );
+
+        // Clean up
+        fs::remove_file(test_output_path).unwrap();
+        fs::remove_file(test_markdown_path).unwrap();
+    }
 }
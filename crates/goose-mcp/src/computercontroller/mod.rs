@@ -4,7 +4,13 @@ use indoc::{formatdoc, indoc};
 use reqwest::{Client, Url};
 use serde_json::{json, Value};
 use std::{
-    collections::HashMap, fs, future::Future, path::PathBuf, pin::Pin, sync::Arc, sync::Mutex,
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    sync::Arc,
+    sync::Mutex,
 };
 use tokio::process::Command;
 
@@ -19,12 +25,20 @@ use mcp_core::{
 use mcp_server::router::CapabilitiesBuilder;
 use mcp_server::Router;
 
+mod cache;
 mod docx_tool;
+mod pdf_text;
 mod pdf_tool;
+mod presentation_export;
 mod presentation_tool;
+mod robots;
+mod search;
+mod web_page;
+mod xlsx_formula;
 mod xlsx_tool;
 
 mod platform;
+use platform::virtual_display::{self, VirtualDisplay};
 use platform::{create_system_automation, SystemAutomation};
 
 /// An extension designed for non-developers to help them with common tasks like
@@ -33,10 +47,17 @@ use platform::{create_system_automation, SystemAutomation};
 pub struct ComputerControllerRouter {
     tools: Vec<Tool>,
     cache_dir: PathBuf,
-    active_resources: Arc<Mutex<HashMap<String, Resource>>>,
+    /// Index of cached files, which are also listed as resources
+    cache_index: Arc<Mutex<cache::CacheIndex>>,
+    /// Workbooks opened by xlsx_tool, kept for the session so edits accumulate until saved
+    open_workbooks: Arc<Mutex<HashMap<PathBuf, xlsx_tool::XlsxTool>>>,
     http_client: Client,
+    /// Does not follow redirects, so web_scrape can check robots.txt for every hop
+    scrape_client: Client,
     instructions: String,
     system_automation: Arc<Box<dyn SystemAutomation + Send + Sync>>,
+    /// The headless display started by the virtual_display tool, stopped when dropped
+    virtual_display: Arc<tokio::sync::Mutex<Option<VirtualDisplay>>>,
 }
 
 impl Default for ComputerControllerRouter {
@@ -51,8 +72,9 @@ impl ComputerControllerRouter {
         let web_search_tool = Tool::new(
             "web_search",
             indoc! {r#"
-                Search the web for a single word (proper noun ideally) using DuckDuckGo's API. Returns results in JSON format.
-                The results are cached locally for future reference.
+                Search the web and return the top results as a ranked list of titles, URLs and snippets.
+                The search backend is configured by the user (DuckDuckGo by default, or SearXNG, Brave or Tavily).
+                The raw results are also cached locally for future reference.
                 Be sparing as there is a limited number of api calls allowed.
             "#},
             json!({
@@ -61,7 +83,12 @@ impl ComputerControllerRouter {
                 "properties": {
                     "query": {
                         "type": "string",
-                        "description": "A single word to search for, a topic, propernoun, brand name that you may not know about"
+                        "description": "The search query"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "default": 5,
+                        "description": "Maximum number of results to return (1-20)"
                     }
                 }
             }),
@@ -71,12 +98,18 @@ impl ComputerControllerRouter {
             "web_scrape",
             indoc! {r#"
                 Fetch and save content from a web page. The content can be saved as:
-                - text (for HTML pages)
+                - markdown (default, the readable content of HTML pages without menus, ads and markup)
+                - text (the raw response, for example the full HTML)
                 - json (for API responses)
                 - binary (for images and other files)
 
-                The content is cached locally and can be accessed later using the cache_path
-                returned in the response.
+                Use a CSS selector to extract specific elements instead of the main content, and
+                links to list the links on the page. Set max_depth to also crawl pages linked from
+                this one on the same site, up to max_pages pages.
+
+                Pages disallowed by the site's robots.txt are not fetched. Every page is cached locally
+                as a resource; use the cache tool's search command to find content in pages fetched
+                earlier instead of fetching them again.
             "#},
             json!({
                 "type": "object",
@@ -88,9 +121,28 @@ impl ComputerControllerRouter {
                     },
                     "save_as": {
                         "type": "string",
-                        "enum": ["text", "json", "binary"],
-                        "default": "text",
+                        "enum": ["markdown", "text", "json", "binary"],
+                        "default": "markdown",
                         "description": "How to interpret and save the content"
+                    },
+                    "selector": {
+                        "type": "string",
+                        "description": "CSS selector for the elements to extract in markdown mode, e.g. 'table.prices' or '#content h2'"
+                    },
+                    "links": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Append the list of links on the page in markdown mode"
+                    },
+                    "max_depth": {
+                        "type": "integer",
+                        "default": 0,
+                        "description": "How many links deep to crawl from this page on the same site in markdown mode, 0 fetches only this page"
+                    },
+                    "max_pages": {
+                        "type": "integer",
+                        "default": 10,
+                        "description": "Maximum number of pages to fetch when crawling (at most 50)"
                     }
                 }
             }),
@@ -161,6 +213,58 @@ impl ComputerControllerRouter {
             }),
         );
 
+        let virtual_display_tool = Tool::new(
+            "virtual_display",
+            indoc! {r#"
+                Automate graphical applications on a headless display that goose owns (Linux only).
+                Works without a desktop session, for example in containers and CI.
+
+                Actions:
+                - start: Start an Xvfb (default) or headless Wayland display
+                - launch: Run a command, such as an application, on the display
+                - click: Click at x, y in pixels from the top left
+                - type: Type text into the focused window
+                - key: Press a key or combination in xdotool syntax, e.g. Return, ctrl+s, alt+F4
+                - screenshot: Capture the display, to see where to click next
+                - status: Show the display and the applications running on it
+                - stop: Stop the display and every application launched on it
+
+                Scripts run with automation_script also use this display while it is running.
+                Take a screenshot after actions to check their effect.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["action"],
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["start", "launch", "click", "type", "key", "screenshot", "status", "stop"],
+                        "description": "The action to perform"
+                    },
+                    "backend": {
+                        "type": "string",
+                        "enum": ["xvfb", "wayland"],
+                        "default": "xvfb",
+                        "description": "Display server for start"
+                    },
+                    "width": {"type": "integer", "default": 1280, "description": "Display width for start"},
+                    "height": {"type": "integer", "default": 800, "description": "Display height for start"},
+                    "command": {"type": "string", "description": "Command to run for launch"},
+                    "x": {"type": "integer", "description": "Horizontal position for click"},
+                    "y": {"type": "integer", "description": "Vertical position for click"},
+                    "button": {
+                        "type": "string",
+                        "enum": ["left", "middle", "right"],
+                        "default": "left",
+                        "description": "Mouse button for click"
+                    },
+                    "double": {"type": "boolean", "default": false, "description": "Double click"},
+                    "text": {"type": "string", "description": "Text for type"},
+                    "keys": {"type": "string", "description": "Key or combination for key"}
+                }
+            }),
+        );
+
         let quick_script_desc = match std::env::consts::OS {
             "windows" => indoc! {r#"
                 Create and run small PowerShell or Batch scripts for automation tasks.
@@ -217,6 +321,7 @@ impl ComputerControllerRouter {
                 Manage cached files and data:
                 - list: List all cached files
                 - view: View content of a cached file
+                - search: Search the text of cached files, such as scraped pages, for a query
                 - delete: Delete a cached file
                 - clear: Clear all cached files
             "#},
@@ -226,12 +331,16 @@ impl ComputerControllerRouter {
                 "properties": {
                     "command": {
                         "type": "string",
-                        "enum": ["list", "view", "delete", "clear"],
+                        "enum": ["list", "view", "search", "delete", "clear"],
                         "description": "The command to perform"
                     },
                     "path": {
                         "type": "string",
                         "description": "Path to the cached file for view/delete commands"
+                    },
+                    "query": {
+                        "type": "string",
+                        "description": "Words to look for with the search command"
                     }
                 }
             }),
@@ -240,11 +349,16 @@ impl ComputerControllerRouter {
         let pdf_tool = Tool::new(
             "pdf_tool",
             indoc! {r#"
-                Process PDF files to extract text and images.
+                Process PDF files to extract text, tables and images.
                 Supports operations:
-                - extract_text: Extract all text content from the PDF
+                - extract_text: Extract text in reading order, page by page. Set layout to true to keep the horizontal layout of each page instead
+                - extract_tables: Extract tables as markdown
+                - search: Find the lines containing `query`, with their page numbers
                 - extract_images: Extract and save embedded images to PNG files
 
+                All operations accept `pages` to limit them to a page selection such as "1-3,5" or "10-".
+                For long documents, search first and then extract only the pages you need.
+
                 Use this when there is a .pdf file or files that need to be processed.
             "#},
             json!({
@@ -257,8 +371,26 @@ impl ComputerControllerRouter {
                     },
                     "operation": {
                         "type": "string",
-                        "enum": ["extract_text", "extract_images"],
+                        "enum": ["extract_text", "extract_tables", "search", "extract_images"],
                         "description": "Operation to perform on the PDF"
+                    },
+                    "pages": {
+                        "type": "string",
+                        "description": "Pages to process, e.g. '1-3,5' or '10-'. Defaults to all pages"
+                    },
+                    "layout": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "For extract_text, keep the horizontal layout of each page"
+                    },
+                    "query": {
+                        "type": "string",
+                        "description": "Text to find for the search operation"
+                    },
+                    "case_sensitive": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Whether search should be case-sensitive"
                     }
                 }
             }),
@@ -270,12 +402,22 @@ impl ComputerControllerRouter {
                 Process DOCX files to extract text and create/update documents.
                 Supports operations:
                 - extract_text: Extract all text content and structure (headings, TOC) from the DOCX
+                - extract_tables: Extract every table as a markdown table
+                - outline: Export the heading outline as markdown, optionally with the body
+                  text, lists and tables (params.include_body) and written to params.output_path
+                - list_comments: List comments with their author, date and the text they refer to
+                - add_comment: Add a comment (content) to the paragraph containing
+                  params.anchor_text, or reply to the comment params.reply_to
+                - list_changes: List tracked insertions and deletions, numbered
+                - accept_changes / reject_changes: Accept or reject tracked changes, all of them
+                  or only params.change_ids (numbers from list_changes) and/or params.author
                 - update_doc: Create a new DOCX or update existing one with provided content
                   Modes:
                   - append: Add content to end of document (default)
                   - replace: Replace specific text with new content
                   - structured: Add content with specific heading level and styling
                   - add_image: Add an image to the document (with optional caption)
+                  - table: Add a table from params.rows (with optional caption)
 
                 Use this when there is a .docx file that needs to be processed or created.
             "#},
@@ -289,22 +431,68 @@ impl ComputerControllerRouter {
                     },
                     "operation": {
                         "type": "string",
-                        "enum": ["extract_text", "update_doc"],
+                        "enum": [
+                            "extract_text",
+                            "extract_tables",
+                            "outline",
+                            "list_comments",
+                            "add_comment",
+                            "list_changes",
+                            "accept_changes",
+                            "reject_changes",
+                            "update_doc"
+                        ],
                         "description": "Operation to perform on the DOCX"
                     },
                     "content": {
                         "type": "string",
-                        "description": "Content to write (required for update_doc operation)"
+                        "description": "Content to write (required for update_doc except table mode), or the comment text for add_comment"
                     },
                     "params": {
                         "type": "object",
-                        "description": "Additional parameters for update_doc operation",
+                        "description": "Additional parameters for update_doc and the other operations",
                         "properties": {
                             "mode": {
                                 "type": "string",
-                                "enum": ["append", "replace", "structured", "add_image"],
+                                "enum": ["append", "replace", "structured", "add_image", "table"],
                                 "description": "Update mode (default: append)"
                             },
+                            "rows": {
+                                "type": "array",
+                                "items": {"type": "array", "items": {}},
+                                "description": "Table cells row by row (required for table mode)"
+                            },
+                            "header": {
+                                "type": "boolean",
+                                "default": true,
+                                "description": "Whether the first table row is a bold header row"
+                            },
+                            "include_body": {
+                                "type": "boolean",
+                                "default": false,
+                                "description": "Include paragraphs, lists and tables in the outline"
+                            },
+                            "output_path": {
+                                "type": "string",
+                                "description": "Markdown file to write the outline to"
+                            },
+                            "anchor_text": {
+                                "type": "string",
+                                "description": "Text in the paragraph to attach a new comment to"
+                            },
+                            "reply_to": {
+                                "type": "integer",
+                                "description": "Id of the comment to reply to"
+                            },
+                            "author": {
+                                "type": "string",
+                                "description": "Author of a new comment, or only resolve tracked changes by this author"
+                            },
+                            "change_ids": {
+                                "type": "array",
+                                "items": {"type": "integer"},
+                                "description": "Numbers from list_changes to accept or reject (default: all)"
+                            },
                             "old_text": {
                                 "type": "string",
                                 "description": "Text to replace (required for replace mode)"
@@ -368,9 +556,18 @@ impl ComputerControllerRouter {
                 Create and manage HTML presentations with a simple, modern design.
                 Operations:
                 - create: Create new presentation with template
-                - add_slide: Add a new slide with content
-
-                Open in a browser (using a command) to show the user: open <path> 
+                - add_slide: Add a new slide with a title and optionally text, bullets, a code
+                  block, an image and speaker notes (at params.position, default the end)
+                - update_slide: Change slide params.index; the title, body and notes are only
+                  replaced when given
+                - delete_slide: Remove slide params.index
+                - move_slide: Move slide params.index to position params.to
+                - list_slides: Show the slides with their titles and contents
+                - export: Write the deck to a PowerPoint (pptx) or PDF file for people who do
+                  not use the HTML version. Speaker notes go into the PPTX notes, and into the
+                  PDF as extra pages when params.include_notes is set
+
+                Slides are numbered from 1. Open in a browser (using a command) to show the user: open <path>
 
                 For advanced edits, use developer tools to modify the HTML directly.
                 A template slide is included in comments for reference.
@@ -385,16 +582,83 @@ impl ComputerControllerRouter {
                     },
                     "operation": {
                         "type": "string",
-                        "enum": ["create", "add_slide"],
+                        "enum": [
+                            "create",
+                            "add_slide",
+                            "update_slide",
+                            "delete_slide",
+                            "move_slide",
+                            "list_slides",
+                            "export"
+                        ],
                         "description": "Operation to perform"
                     },
                     "params": {
                         "type": "object",
-                        "description": "Parameters for add_slide operation",
+                        "description": "Parameters for the operation",
                         "properties": {
+                            "title": {
+                                "type": "string",
+                                "description": "Presentation title for create, or the slide heading"
+                            },
                             "content": {
                                 "type": "string",
-                                "description": "Content for the new slide"
+                                "description": "Slide heading as HTML, kept for older decks; prefer title"
+                            },
+                            "text": {
+                                "type": "string",
+                                "description": "Body text, with paragraphs separated by blank lines"
+                            },
+                            "bullets": {
+                                "type": "array",
+                                "items": {"type": "string"},
+                                "description": "Bullet points; indent with two spaces per nesting level"
+                            },
+                            "code": {
+                                "type": "string",
+                                "description": "Code block for the slide"
+                            },
+                            "language": {
+                                "type": "string",
+                                "description": "Language of the code block, e.g. 'rust'"
+                            },
+                            "image": {
+                                "type": "string",
+                                "description": "Image path, relative to the presentation or absolute"
+                            },
+                            "caption": {
+                                "type": "string",
+                                "description": "Caption shown under the image"
+                            },
+                            "notes": {
+                                "type": "string",
+                                "description": "Speaker notes, hidden in the HTML view"
+                            },
+                            "index": {
+                                "type": "integer",
+                                "description": "Slide number for update_slide, delete_slide and move_slide"
+                            },
+                            "to": {
+                                "type": "integer",
+                                "description": "New slide number for move_slide"
+                            },
+                            "position": {
+                                "type": "integer",
+                                "description": "Slide number the new slide should get in add_slide"
+                            },
+                            "format": {
+                                "type": "string",
+                                "enum": ["pptx", "pdf"],
+                                "description": "Export format"
+                            },
+                            "output_path": {
+                                "type": "string",
+                                "description": "Where to write the export (default: the deck path with the format's extension)"
+                            },
+                            "include_notes": {
+                                "type": "boolean",
+                                "default": false,
+                                "description": "Add speaker notes pages to a PDF export"
                             }
                         }
                     }
@@ -406,14 +670,29 @@ impl ComputerControllerRouter {
             "xlsx_tool",
             indoc! {r#"
                 Process Excel (XLSX) files to read and manipulate spreadsheet data.
+                The workbook stays open between calls, so several edits can be made in a row.
+                Edits are only written to disk by the save operation.
                 Supports operations:
                 - list_worksheets: List all worksheets in the workbook (returns name, index, column_count, row_count)
                 - get_columns: Get column names from a worksheet (returns values from the first row)
                 - get_range: Get values and formulas from a cell range (e.g., "A1:C10") (returns a 2D array organized as [row][column])
                 - find_text: Search for text in a worksheet (returns a list of (row, column) coordinates)
-                - update_cell: Update a single cell's value (returns confirmation message)
                 - get_cell: Get value and formula from a specific cell (returns both value and formula if present)
-                - save: Save changes back to the file (returns confirmation message)
+                - update_cell: Update a single cell's value, values starting with "=" are formulas
+                - write_range: Write a 2D array of values ([row][column]) starting at a cell, values starting with "=" are formulas and null clears a cell
+                - insert_rows / delete_rows: Insert or delete `count` rows at `row`
+                - insert_columns / delete_columns: Insert or delete `count` columns at `col`
+                - add_worksheet: Add an empty worksheet named `worksheet`
+                - format_range: Apply formatting (bold, italic, font_color, fill_color, number_format, column_width) to a range of up to 100,000 cells
+                - import_csv: Load a CSV file into a worksheet starting at a cell, creating the worksheet if needed
+                - export_csv: Write a worksheet to a CSV file, with formulas replaced by their results
+                - create: Start a new workbook at `path`
+                - save: Write the workbook to `path`, or to `output_path` which then becomes the open file
+                - close: Close the workbook, fails if there are unsaved changes unless `discard` is true
+
+                Formula results are calculated for common functions (SUM, AVERAGE, MIN, MAX, COUNT, COUNTA, ROUND, ABS, IF, AND, OR, NOT, CONCATENATE, LEN, UPPER, LOWER) and arithmetic.
+                Other formulas show the result saved in the file.
+                Rows and columns are numbered from 1.
 
                 Use this when working with Excel spreadsheets to analyze or modify data.
             "#},
@@ -427,7 +706,12 @@ impl ComputerControllerRouter {
                     },
                     "operation": {
                         "type": "string",
-                        "enum": ["list_worksheets", "get_columns", "get_range", "find_text", "update_cell", "get_cell", "save"],
+                        "enum": [
+                            "list_worksheets", "get_columns", "get_range", "find_text", "get_cell",
+                            "update_cell", "write_range", "insert_rows", "delete_rows",
+                            "insert_columns", "delete_columns", "add_worksheet", "format_range",
+                            "import_csv", "export_csv", "create", "save", "close"
+                        ],
                         "description": "Operation to perform on the XLSX file"
                     },
                     "worksheet": {
@@ -436,7 +720,7 @@ impl ComputerControllerRouter {
                     },
                     "range": {
                         "type": "string",
-                        "description": "Cell range in A1 notation (e.g., 'A1:C10') for get_range operation"
+                        "description": "Cell range in A1 notation (e.g., 'A1:C10') for get_range and format_range operations"
                     },
                     "search_text": {
                         "type": "string",
@@ -449,15 +733,55 @@ impl ComputerControllerRouter {
                     },
                     "row": {
                         "type": "integer",
-                        "description": "Row number for update_cell and get_cell operations"
+                        "description": "Row number for update_cell, get_cell, insert_rows and delete_rows operations"
                     },
                     "col": {
                         "type": "integer",
-                        "description": "Column number for update_cell and get_cell operations"
+                        "description": "Column number for update_cell, get_cell, insert_columns and delete_columns operations"
                     },
                     "value": {
                         "type": "string",
                         "description": "New value for update_cell operation"
+                    },
+                    "cell": {
+                        "type": "string",
+                        "default": "A1",
+                        "description": "Top left cell for write_range and import_csv operations"
+                    },
+                    "values": {
+                        "type": "array",
+                        "items": {"type": "array"},
+                        "description": "Rows of values for write_range operation"
+                    },
+                    "count": {
+                        "type": "integer",
+                        "default": 1,
+                        "description": "Number of rows or columns to insert or delete"
+                    },
+                    "format": {
+                        "type": "object",
+                        "description": "Formatting for format_range operation",
+                        "properties": {
+                            "bold": {"type": "boolean"},
+                            "italic": {"type": "boolean"},
+                            "font_color": {"type": "string", "description": "Hex color, e.g. '#FF0000'"},
+                            "fill_color": {"type": "string", "description": "Hex color, e.g. '#FFFF00'"},
+                            "number_format": {"type": "string", "description": "Excel number format, e.g. '0.00%'"},
+                            "column_width": {"type": "number"}
+                        }
+                    },
+                    "csv_path": {
+                        "type": "string",
+                        "description": "CSV file for import_csv and export_csv operations"
+                    },
+                    "output_path": {
+                        "type": "string",
+                        "description": "Save to this path instead of the original file"
+                    },
+                    "discard": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Close without saving changes"
                     }
                 }
             }),
@@ -544,6 +868,11 @@ impl ComputerControllerRouter {
               - Simulating keyboard/mouse input
               - Automating UI interactions
               - Desktop environment control
+
+            virtual_display
+              - Runs applications on a headless display owned by goose, when there is no desktop session or
+                the task should not disturb the user's screen
+              - Launch the application, then alternate screenshots with clicks, typing and key presses
             "#},
         };
 
@@ -565,15 +894,16 @@ impl ComputerControllerRouter {
             {os_instructions}
 
             web_search
-              - Search the web using DuckDuckGo's API for general topics or keywords
+              - Search the web and get ranked results with snippets
+              - Use web_scrape on a result URL when you need the full page
             web_scrape
-              - Fetch content from html websites and APIs
-              - Save as text, JSON, or binary files
+              - Fetch the readable content of web pages as markdown, or raw content from APIs
+              - Extract elements with CSS selectors, list links, or crawl a few pages of a site
               - Content is cached locally for later use
-              - This is not optimised for complex websites, so don't use this as the first tool.
+              - Sites that rely on javascript to render may need a browser instead.
             cache
               - Manage your cached files
-              - List, view, delete files
+              - List, view, search, delete files
               - Clear all cached data
             The extension automatically manages:
             - Cache directory: {cache_dir}
@@ -583,61 +913,79 @@ impl ComputerControllerRouter {
             cache_dir = cache_dir.display()
         };
 
+        let mut tools = vec![
+            web_search_tool,
+            web_scrape_tool,
+            quick_script_tool,
+            computer_control_tool,
+            cache_tool,
+            pdf_tool,
+            docx_tool,
+            xlsx_tool,
+            make_presentation_tool,
+        ];
+        if cfg!(target_os = "linux") {
+            tools.push(virtual_display_tool);
+        }
+
         Self {
-            tools: vec![
-                web_search_tool,
-                web_scrape_tool,
-                quick_script_tool,
-                computer_control_tool,
-                cache_tool,
-                pdf_tool,
-                docx_tool,
-                xlsx_tool,
-                make_presentation_tool,
-            ],
+            tools,
+            cache_index: Arc::new(Mutex::new(cache::CacheIndex::load(
+                &cache_dir,
+                cache::CacheLimits::from_env(),
+            ))),
             cache_dir,
-            active_resources: Arc::new(Mutex::new(HashMap::new())),
+            open_workbooks: Arc::new(Mutex::new(HashMap::new())),
             http_client: Client::builder().user_agent("Goose/1.0").build().unwrap(),
+            scrape_client: Client::builder()
+                .user_agent("Goose/1.0")
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .unwrap(),
             instructions: instructions.clone(),
             system_automation,
+            virtual_display: Arc::new(tokio::sync::Mutex::new(None)),
         }
     }
 
-    // Helper function to generate a cache file path
-    fn get_cache_path(&self, prefix: &str, extension: &str) -> PathBuf {
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        self.cache_dir
-            .join(format!("{}_{}.{}", prefix, timestamp, extension))
-    }
-
-    // Helper function to save content to cache
+    // Helper function to save content to the cache, which also makes it available as a resource
     async fn save_to_cache(
         &self,
         content: &[u8],
         prefix: &str,
         extension: &str,
+        mime_type: &str,
+        source: Option<&str>,
     ) -> Result<PathBuf, ToolError> {
-        let cache_path = self.get_cache_path(prefix, extension);
-        fs::write(&cache_path, content)
+        let (cache_path, evicted) = self
+            .cache_index
+            .lock()
+            .unwrap()
+            .store(content, prefix, extension, mime_type, source)
             .map_err(|e| ToolError::ExecutionError(format!("Failed to write to cache: {}", e)))?;
+        for path in evicted {
+            tracing::debug!("Evicted {} from the cache", path.display());
+        }
         Ok(cache_path)
     }
 
-    // Helper function to register a file as a resource
-    fn register_as_resource(&self, cache_path: &PathBuf, mime_type: &str) -> Result<(), ToolError> {
-        let uri = Url::from_file_path(cache_path)
-            .map_err(|_| ToolError::ExecutionError("Invalid cache path".into()))?
-            .to_string();
-
-        let resource = Resource::new(
-            uri.clone(),
-            Some(mime_type.to_string()),
-            Some(cache_path.to_string_lossy().into_owned()),
+    // Helper function to describe a cached file as a resource
+    fn cache_resource(path: &std::path::Path, entry: &cache::CacheEntry) -> Option<Resource> {
+        let uri = Url::from_file_path(path).ok()?.to_string();
+        let kind = if entry.is_text() { "text" } else { "blob" };
+        let description = match &entry.source {
+            Some(source) => format!("{} from {}", entry.mime_type, source),
+            None => entry.mime_type.clone(),
+        };
+        let mut resource = Resource::new(
+            uri,
+            Some(kind.to_string()),
+            Some(path.to_string_lossy().into_owned()),
         )
-        .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
-
-        self.active_resources.lock().unwrap().insert(uri, resource);
-        Ok(())
+        .ok()?
+        .with_description(description);
+        resource.annotations = Some(mcp_core::Annotations::for_resource(0.0, entry.created));
+        Some(resource)
     }
 
     // Implement web_search tool functionality
@@ -645,62 +993,142 @@ impl ComputerControllerRouter {
         let query = params
             .get("query")
             .and_then(|v| v.as_str())
+            .filter(|q| !q.trim().is_empty())
             .ok_or_else(|| ToolError::InvalidParameters("Missing 'query' parameter".into()))?;
 
-        // Create the DuckDuckGo API URL
-        let url = format!(
-            "https://api.duckduckgo.com/?q={}&format=json&pretty=1",
-            urlencoding::encode(query)
-        );
-
-        // Fetch the results
-        let response = self.http_client.get(&url).send().await.map_err(|e| {
-            ToolError::ExecutionError(format!("Failed to fetch search results: {}", e))
-        })?;
-
-        let status = response.status();
-        if !status.is_success() {
-            return Err(ToolError::ExecutionError(format!(
-                "HTTP request failed with status: {}",
-                status
-            )));
-        }
+        let limit = params
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map(|l| l.clamp(1, 20) as usize)
+            .unwrap_or(5);
 
-        // Get the JSON response
-        let json_text = response.text().await.map_err(|e| {
-            ToolError::ExecutionError(format!("Failed to get response text: {}", e))
-        })?;
+        let backend = search::backend_from_env()?;
+        let response = backend.search(&self.http_client, query, limit).await?;
 
-        // Save to cache
+        // Keep the raw response so the full results can be read back later
         let cache_path = self
-            .save_to_cache(json_text.as_bytes(), "search", "json")
+            .save_to_cache(
+                response.raw.as_bytes(),
+                "search",
+                "json",
+                "application/json",
+                Some(&format!("{} search: {}", backend.name(), query)),
+            )
             .await?;
 
-        // Register as a resource
-        self.register_as_resource(&cache_path, "json")?;
-
         Ok(vec![Content::text(format!(
-            "Search results saved to: {}",
+            "{}\nRaw results saved to: {}",
+            search::format_results(query, backend.name(), &response.results),
             cache_path.display()
         ))])
     }
 
+    /// Fetch a site's robots.txt rules, once per origin for each call
+    async fn robots_for(
+        &self,
+        url: &Url,
+        robots: &mut HashMap<String, robots::Robots>,
+    ) -> robots::Robots {
+        let origin = url.origin().ascii_serialization();
+        if let Some(rules) = robots.get(&origin) {
+            return rules.clone();
+        }
+
+        // Sites without a readable robots.txt allow everything
+        let rules = match self
+            .http_client
+            .get(format!("{}/robots.txt", origin))
+            .send()
+            .await
+        {
+            Ok(response) if response.status().is_success() => match response.text().await {
+                Ok(content) => robots::Robots::parse(&content, "goose"),
+                Err(_) => robots::Robots::allow_all(),
+            },
+            _ => robots::Robots::allow_all(),
+        };
+        robots.insert(origin, rules.clone());
+        rules
+    }
+
+    /// Fetch a page, following redirects one hop at a time so that each hop, which may be on
+    /// another site, is checked against its robots.txt. Errors are the reason it failed.
+    async fn get_allowed(
+        &self,
+        mut url: Url,
+        robots: &mut HashMap<String, robots::Robots>,
+    ) -> Result<reqwest::Response, String> {
+        const MAX_REDIRECTS: usize = 10;
+
+        for _ in 0..=MAX_REDIRECTS {
+            let response = self
+                .scrape_client
+                .get(url.clone())
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|location| url.join(location).ok());
+            let next = match location {
+                Some(next) if response.status().is_redirection() => next,
+                _ => return Ok(response),
+            };
+            if !self
+                .robots_for(&next, robots)
+                .await
+                .is_allowed(&Self::robots_path(&next))
+            {
+                return Err(format!(
+                    "redirected to {}, which robots.txt does not allow",
+                    next
+                ));
+            }
+            url = next;
+        }
+        Err(format!("more than {} redirects", MAX_REDIRECTS))
+    }
+
+    fn robots_path(url: &Url) -> String {
+        match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        }
+    }
+
     async fn web_scrape(&self, params: Value) -> Result<Vec<Content>, ToolError> {
         let url = params
             .get("url")
             .and_then(|v| v.as_str())
             .ok_or_else(|| ToolError::InvalidParameters("Missing 'url' parameter".into()))?;
+        let url = Url::parse(url)
+            .map_err(|e| ToolError::InvalidParameters(format!("Invalid URL '{}': {}", url, e)))?;
 
         let save_as = params
             .get("save_as")
             .and_then(|v| v.as_str())
-            .unwrap_or("text");
+            .unwrap_or("markdown");
+
+        let mut robots = HashMap::new();
+        let rules = self.robots_for(&url, &mut robots).await;
+        if !rules.is_allowed(&Self::robots_path(&url)) {
+            return Err(ToolError::ExecutionError(format!(
+                "The robots.txt of {} does not allow fetching {}",
+                url.host_str().unwrap_or_default(),
+                url
+            )));
+        }
+
+        if save_as == "markdown" {
+            return self.scrape_pages(url, &params, robots).await;
+        }
 
         // Fetch the content
+        let source = url.to_string();
         let response = self
-            .http_client
-            .get(url)
-            .send()
+            .get_allowed(url, &mut robots)
             .await
             .map_err(|e| ToolError::ExecutionError(format!("Failed to fetch URL: {}", e)))?;
 
@@ -712,44 +1140,55 @@ impl ComputerControllerRouter {
             )));
         }
 
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.trim().to_string());
+
         // Process based on save_as parameter
-        let (content, extension) =
-            match save_as {
-                "text" => {
-                    let text = response.text().await.map_err(|e| {
-                        ToolError::ExecutionError(format!("Failed to get text: {}", e))
-                    })?;
-                    (text.into_bytes(), "txt")
-                }
-                "json" => {
-                    let text = response.text().await.map_err(|e| {
-                        ToolError::ExecutionError(format!("Failed to get text: {}", e))
-                    })?;
-                    // Verify it's valid JSON
-                    serde_json::from_str::<Value>(&text).map_err(|e| {
-                        ToolError::ExecutionError(format!("Invalid JSON response: {}", e))
-                    })?;
-                    (text.into_bytes(), "json")
-                }
-                "binary" => {
-                    let bytes = response.bytes().await.map_err(|e| {
-                        ToolError::ExecutionError(format!("Failed to get bytes: {}", e))
-                    })?;
-                    (bytes.to_vec(), "bin")
-                }
-                _ => {
-                    return Err(ToolError::InvalidParameters(format!(
-                    "Invalid 'save_as' parameter: {}. Valid options are: 'text', 'json', 'binary'",
+        let (content, extension, default_mime) = match save_as {
+            "text" => {
+                let text = response
+                    .text()
+                    .await
+                    .map_err(|e| ToolError::ExecutionError(format!("Failed to get text: {}", e)))?;
+                (text.into_bytes(), "txt", "text/plain")
+            }
+            "json" => {
+                let text = response
+                    .text()
+                    .await
+                    .map_err(|e| ToolError::ExecutionError(format!("Failed to get text: {}", e)))?;
+                // Verify it's valid JSON
+                serde_json::from_str::<Value>(&text).map_err(|e| {
+                    ToolError::ExecutionError(format!("Invalid JSON response: {}", e))
+                })?;
+                (text.into_bytes(), "json", "application/json")
+            }
+            "binary" => {
+                let bytes = response.bytes().await.map_err(|e| {
+                    ToolError::ExecutionError(format!("Failed to get bytes: {}", e))
+                })?;
+                (bytes.to_vec(), "bin", "application/octet-stream")
+            }
+            _ => {
+                return Err(ToolError::InvalidParameters(format!(
+                    "Invalid 'save_as' parameter: {}. Valid options are: 'markdown', 'text', 'json', 'binary'",
                     save_as
                 )));
-                }
-            };
-
-        // Save to cache
-        let cache_path = self.save_to_cache(&content, "web", extension).await?;
+            }
+        };
 
-        // Register as a resource
-        self.register_as_resource(&cache_path, save_as)?;
+        // Keep the server's type when there is one, so a raw HTML page stays text/html
+        let mime_type = match (save_as, content_type) {
+            ("json", _) | (_, None) => default_mime.to_string(),
+            (_, Some(content_type)) => content_type,
+        };
+        let cache_path = self
+            .save_to_cache(&content, "web", extension, &mime_type, Some(&source))
+            .await?;
 
         Ok(vec![Content::text(format!(
             "Content saved to: {}",
@@ -757,6 +1196,186 @@ impl ComputerControllerRouter {
         ))])
     }
 
+    /// Fetch a page as markdown, and with max_depth above 0 crawl the same-site pages it links to
+    async fn scrape_pages(
+        &self,
+        start: Url,
+        params: &Value,
+        mut robots: HashMap<String, robots::Robots>,
+    ) -> Result<Vec<Content>, ToolError> {
+        const MAX_INLINE_CHARS: usize = 20_000;
+        const MAX_CRAWL_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+        let selector = params.get("selector").and_then(|v| v.as_str());
+        if let Some(selector) = selector {
+            web_page::validate_selector(selector).map_err(ToolError::InvalidParameters)?;
+        }
+        let include_links = params
+            .get("links")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let max_depth = params
+            .get("max_depth")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        let max_pages = params
+            .get("max_pages")
+            .and_then(|v| v.as_u64())
+            .map(|n| n.clamp(1, 50) as usize)
+            .unwrap_or(10);
+
+        let mut queue = VecDeque::from([(start.clone(), 0)]);
+        let mut queued = HashSet::from([start.to_string()]);
+        let mut saved: Vec<(Url, Option<String>, PathBuf)> = Vec::new();
+        let mut skipped: Vec<String> = Vec::new();
+        let mut first_page = String::new();
+
+        while let Some((url, depth)) = queue.pop_front() {
+            if saved.len() >= max_pages {
+                break;
+            }
+            let is_start = saved.is_empty() && skipped.is_empty();
+
+            let rules = self.robots_for(&url, &mut robots).await;
+            if !rules.is_allowed(&Self::robots_path(&url)) {
+                skipped.push(format!("{} (disallowed by robots.txt)", url));
+                continue;
+            }
+            if !is_start {
+                if let Some(delay) = rules.crawl_delay() {
+                    tokio::time::sleep(delay.min(MAX_CRAWL_DELAY)).await;
+                }
+            }
+
+            let response = match self.get_allowed(url.clone(), &mut robots).await {
+                Ok(response) if response.status().is_success() => response,
+                Ok(response) if is_start => {
+                    return Err(ToolError::ExecutionError(format!(
+                        "HTTP request failed with status: {}",
+                        response.status()
+                    )))
+                }
+                Err(e) if is_start => {
+                    return Err(ToolError::ExecutionError(format!(
+                        "Failed to fetch URL: {}",
+                        e
+                    )))
+                }
+                Ok(response) => {
+                    skipped.push(format!("{} (status {})", url, response.status()));
+                    continue;
+                }
+                Err(e) => {
+                    skipped.push(format!("{} ({})", url, e));
+                    continue;
+                }
+            };
+
+            let final_url = response.url().clone();
+            let is_html = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.contains("html"))
+                .unwrap_or(true);
+            if !is_html && !is_start {
+                skipped.push(format!("{} (not an HTML page)", url));
+                continue;
+            }
+            let body = response
+                .text()
+                .await
+                .map_err(|e| ToolError::ExecutionError(format!("Failed to get text: {}", e)))?;
+
+            // Other text, like plain text or markdown files, is kept as it is
+            let (title, mut markdown, links) = if is_html {
+                let page = web_page::extract(&body, &final_url, selector)
+                    .map_err(ToolError::InvalidParameters)?;
+                (page.title, page.markdown, page.links)
+            } else {
+                (None, body, Vec::new())
+            };
+            if include_links && !links.is_empty() {
+                markdown.push_str(&format!(
+                    "\n\n## Links\n\n{}",
+                    web_page::format_links(&links)
+                ));
+            }
+
+            let document = format!(
+                "{}{}\n",
+                web_page::front_matter(&final_url, title.as_deref()),
+                markdown
+            );
+            let cache_path = self
+                .save_to_cache(
+                    document.as_bytes(),
+                    "page",
+                    "md",
+                    "text/markdown",
+                    Some(final_url.as_str()),
+                )
+                .await?;
+            if saved.is_empty() {
+                first_page = markdown;
+            }
+            saved.push((final_url.clone(), title, cache_path));
+
+            if depth < max_depth {
+                for link in links {
+                    let same_site = link.url.host_str() == start.host_str();
+                    if same_site && queued.insert(link.url.to_string()) {
+                        queue.push_back((link.url, depth + 1));
+                    }
+                }
+            }
+        }
+
+        if max_depth == 0 {
+            let Some((url, _, path)) = saved.first() else {
+                return Err(ToolError::ExecutionError(format!(
+                    "Could not fetch {}: {}",
+                    start,
+                    skipped.join(", ")
+                )));
+            };
+            let mut content = first_page;
+            if content.chars().count() > MAX_INLINE_CHARS {
+                content = content.chars().take(MAX_INLINE_CHARS).collect();
+                content.push_str("\n\n[Truncated, view the cached file for the full page]");
+            }
+            return Ok(vec![Content::text(format!(
+                "Content of {} saved to: {}\n\n{}",
+                url,
+                path.display(),
+                content
+            ))]);
+        }
+
+        let mut summary = format!("Crawled {} page(s) from {}:\n", saved.len(), start);
+        for (i, (url, title, path)) in saved.iter().enumerate() {
+            summary.push_str(&format!(
+                "\n{}. {}\n   {}\n   saved to: {}\n",
+                i + 1,
+                title.as_deref().unwrap_or("(untitled)"),
+                url,
+                path.display()
+            ));
+        }
+        if !queue.is_empty() {
+            summary.push_str(&format!(
+                "\nStopped at the {} page limit with {} more page(s) found.\n",
+                max_pages,
+                queue.len()
+            ));
+        }
+        if !skipped.is_empty() {
+            summary.push_str(&format!("\nSkipped:\n- {}\n", skipped.join("\n- ")));
+        }
+        summary.push_str("\nUse the cache tool to view or search these pages.");
+        Ok(vec![Content::text(summary)])
+    }
+
     // Implement quick_script tool functionality
     async fn quick_script(&self, params: Value) -> Result<Vec<Content>, ToolError> {
         let language = params
@@ -819,10 +1438,18 @@ impl ComputerControllerRouter {
             }
         };
 
-        // Run the script
+        // Run the script, on the virtual display when one is running
+        let display_env = self
+            .virtual_display
+            .lock()
+            .await
+            .as_ref()
+            .map(|display| display.env())
+            .unwrap_or_default();
         let output = Command::new(shell)
             .arg(shell_arg)
             .arg(&command)
+            .envs(display_env)
             .output()
             .await
             .map_err(|e| ToolError::ExecutionError(format!("Failed to run script: {}", e)))?;
@@ -842,12 +1469,15 @@ impl ComputerControllerRouter {
         // Save output if requested
         if save_output && !output_str.is_empty() {
             let cache_path = self
-                .save_to_cache(output_str.as_bytes(), "script_output", "txt")
+                .save_to_cache(
+                    output_str.as_bytes(),
+                    "script_output",
+                    "txt",
+                    "text/plain",
+                    None,
+                )
                 .await?;
             result.push_str(&format!("\n\nOutput saved to: {}", cache_path.display()));
-
-            // Register as a resource
-            self.register_as_resource(&cache_path, "text")?;
         }
 
         Ok(vec![Content::text(result)])
@@ -876,17 +1506,151 @@ impl ComputerControllerRouter {
         // Save output if requested
         if save_output && !output.is_empty() {
             let cache_path = self
-                .save_to_cache(output.as_bytes(), "automation_output", "txt")
+                .save_to_cache(
+                    output.as_bytes(),
+                    "automation_output",
+                    "txt",
+                    "text/plain",
+                    None,
+                )
                 .await?;
             result.push_str(&format!("\n\nOutput saved to: {}", cache_path.display()));
-
-            // Register as a resource
-            self.register_as_resource(&cache_path, "text")?;
         }
 
         Ok(vec![Content::text(result)])
     }
 
+    async fn virtual_display(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let action = params
+            .get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'action' parameter".into()))?;
+
+        let mut display = self.virtual_display.lock().await;
+
+        match action {
+            "start" => {
+                let backend = params
+                    .get("backend")
+                    .and_then(|v| v.as_str())
+                    .map(virtual_display::Backend::parse)
+                    .transpose()
+                    .map_err(ToolError::InvalidParameters)?
+                    .unwrap_or(virtual_display::Backend::Xvfb);
+                let width = params.get("width").and_then(|v| v.as_u64()).unwrap_or(1280);
+                let height = params.get("height").and_then(|v| v.as_u64()).unwrap_or(800);
+                if !(64..=7680).contains(&width) || !(64..=4320).contains(&height) {
+                    return Err(ToolError::InvalidParameters(format!(
+                        "Unsupported display size {}x{}",
+                        width, height
+                    )));
+                }
+                if let Some(running) = display.as_mut() {
+                    return Ok(vec![Content::text(format!(
+                        "A virtual display is already running: {}",
+                        running.describe()
+                    ))]);
+                }
+                let started = VirtualDisplay::start(backend, width as u32, height as u32)
+                    .await
+                    .map_err(ToolError::ExecutionError)?;
+                let started = display.insert(started);
+                Ok(vec![Content::text(format!(
+                    "Started {}",
+                    started.describe()
+                ))])
+            }
+            "stop" => match display.take() {
+                Some(_) => Ok(vec![Content::text(
+                    "Stopped the virtual display and its applications.",
+                )]),
+                None => Ok(vec![Content::text("No virtual display is running.")]),
+            },
+            "status" => match display.as_mut() {
+                Some(running) => Ok(vec![Content::text(running.describe())]),
+                None => Ok(vec![Content::text("No virtual display is running.")]),
+            },
+            "launch" | "click" | "type" | "key" | "screenshot" => {
+                if display.is_none() {
+                    // GOOSE_VIRTUAL_DISPLAY starts the display on first use
+                    let backend = virtual_display::Backend::from_env().ok_or_else(|| {
+                        ToolError::ExecutionError(
+                            "No virtual display is running, start one first".into(),
+                        )
+                    })?;
+                    let started = VirtualDisplay::start(backend, 1280, 800)
+                        .await
+                        .map_err(ToolError::ExecutionError)?;
+                    *display = Some(started);
+                }
+                let running = display.as_mut().unwrap();
+
+                match action {
+                    "launch" => {
+                        let command = required_str(&params, "command")?;
+                        let pid = running.launch(command).map_err(ToolError::ExecutionError)?;
+                        Ok(vec![Content::text(format!(
+                            "Launched '{}' (pid {}). Take a screenshot once its window is up.",
+                            command, pid
+                        ))])
+                    }
+                    "click" => {
+                        let x = required_u32(&params, "x")?;
+                        let y = required_u32(&params, "y")?;
+                        let button = virtual_display::MouseButton::parse(
+                            params
+                                .get("button")
+                                .and_then(|v| v.as_str())
+                                .unwrap_or("left"),
+                        )
+                        .map_err(ToolError::InvalidParameters)?;
+                        let double = params
+                            .get("double")
+                            .and_then(|v| v.as_bool())
+                            .unwrap_or(false);
+                        running
+                            .click(x, y, button, double)
+                            .await
+                            .map_err(ToolError::ExecutionError)?;
+                        Ok(vec![Content::text(format!("Clicked at ({}, {})", x, y))])
+                    }
+                    "type" => {
+                        let text = required_str(&params, "text")?;
+                        running
+                            .type_text(text)
+                            .await
+                            .map_err(ToolError::ExecutionError)?;
+                        Ok(vec![Content::text(format!(
+                            "Typed {} characters",
+                            text.chars().count()
+                        ))])
+                    }
+                    "key" => {
+                        let keys = required_str(&params, "keys")?;
+                        running.key(keys).await.map_err(ToolError::ExecutionError)?;
+                        Ok(vec![Content::text(format!("Pressed {}", keys))])
+                    }
+                    _ => {
+                        let png = running
+                            .screenshot()
+                            .await
+                            .map_err(ToolError::ExecutionError)?;
+                        let data = base64::prelude::BASE64_STANDARD.encode(png);
+                        Ok(vec![
+                            Content::text("Screenshot of the virtual display")
+                                .with_audience(vec![mcp_core::Role::Assistant]),
+                            Content::image(data, "image/png").with_priority(0.0),
+                        ])
+                    }
+                }
+            }
+            _ => Err(ToolError::InvalidParameters(format!(
+                "Invalid 'action' parameter: {}. Valid options are: 'start', 'launch', 'click', 'type', 'key', 'screenshot', 'status', 'stop'",
+                action
+            ))),
+        }
+    }
+
     async fn xlsx_tool(&self, params: Value) -> Result<Vec<Content>, ToolError> {
         let path = params
             .get("path")
@@ -898,77 +1662,110 @@ impl ComputerControllerRouter {
             .and_then(|v| v.as_str())
             .ok_or_else(|| ToolError::InvalidParameters("Missing 'operation' parameter".into()))?;
 
+        let key = workbook_key(path);
+        let mut workbooks = self.open_workbooks.lock().unwrap();
+
+        match operation {
+            "create" => {
+                if workbooks.contains_key(&key) || key.exists() {
+                    return Err(ToolError::InvalidParameters(format!(
+                        "'{}' already exists, use the other operations to edit it",
+                        path
+                    )));
+                }
+                workbooks.insert(key, xlsx_tool::XlsxTool::create());
+                return Ok(vec![Content::text(format!(
+                    "Created a new workbook with worksheet 'Sheet1'. It is written to {} when you call save.",
+                    path
+                ))]);
+            }
+            "close" => {
+                let discard = params
+                    .get("discard")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                let text = match workbooks.get(&key) {
+                    None => format!("{} is not open.", path),
+                    Some(xlsx) if xlsx.has_unsaved_changes() && !discard => {
+                        return Err(ToolError::InvalidParameters(format!(
+                            "{} has unsaved changes. Save it first, or close with discard set to true.",
+                            path
+                        )));
+                    }
+                    Some(_) => format!("Closed {}.", path),
+                };
+                workbooks.remove(&key);
+                return Ok(vec![Content::text(text)]);
+            }
+            "save" => {
+                // Saving over another open workbook would lose its edits
+                if let Some(output_path) = params.get("output_path").and_then(|v| v.as_str()) {
+                    let target = workbook_key(output_path);
+                    if target != key
+                        && workbooks
+                            .get(&target)
+                            .is_some_and(|xlsx| xlsx.has_unsaved_changes())
+                    {
+                        return Err(ToolError::InvalidParameters(format!(
+                            "{} is open with unsaved changes. Save or close it first.",
+                            output_path
+                        )));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let xlsx = match workbooks.entry(key.clone()) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => entry.insert(
+                xlsx_tool::XlsxTool::new(path)
+                    .map_err(|e| ToolError::ExecutionError(e.to_string()))?,
+            ),
+        };
+        let worksheet_param = params.get("worksheet").and_then(|v| v.as_str());
+        let worksheet_name = || {
+            xlsx.resolve_worksheet(worksheet_param)
+                .map_err(|e| ToolError::ExecutionError(e.to_string()))
+        };
+        let unsaved = "The change is kept in the open workbook until you call save.";
+
         match operation {
             "list_worksheets" => {
-                let xlsx = xlsx_tool::XlsxTool::new(path)
-                    .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
                 let worksheets = xlsx
                     .list_worksheets()
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
                 Ok(vec![Content::text(format!("{:#?}", worksheets))])
             }
             "get_columns" => {
-                let xlsx = xlsx_tool::XlsxTool::new(path)
+                let worksheet = xlsx
+                    .get_worksheet_by_name(&worksheet_name()?)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
-                let worksheet = if let Some(name) = params.get("worksheet").and_then(|v| v.as_str())
-                {
-                    xlsx.get_worksheet_by_name(name)
-                        .map_err(|e| ToolError::ExecutionError(e.to_string()))?
-                } else {
-                    xlsx.get_worksheet_by_index(0)
-                        .map_err(|e| ToolError::ExecutionError(e.to_string()))?
-                };
                 let columns = xlsx
                     .get_column_names(worksheet)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
                 Ok(vec![Content::text(format!("{:#?}", columns))])
             }
             "get_range" => {
-                let range = params
-                    .get("range")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| {
-                        ToolError::InvalidParameters("Missing 'range' parameter".into())
-                    })?;
-
-                let xlsx = xlsx_tool::XlsxTool::new(path)
+                let range = required_str(&params, "range")?;
+                let worksheet = xlsx
+                    .get_worksheet_by_name(&worksheet_name()?)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
-                let worksheet = if let Some(name) = params.get("worksheet").and_then(|v| v.as_str())
-                {
-                    xlsx.get_worksheet_by_name(name)
-                        .map_err(|e| ToolError::ExecutionError(e.to_string()))?
-                } else {
-                    xlsx.get_worksheet_by_index(0)
-                        .map_err(|e| ToolError::ExecutionError(e.to_string()))?
-                };
                 let range_data = xlsx
                     .get_range(worksheet, range)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
                 Ok(vec![Content::text(format!("{:#?}", range_data))])
             }
             "find_text" => {
-                let search_text = params
-                    .get("search_text")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| {
-                        ToolError::InvalidParameters("Missing 'search_text' parameter".into())
-                    })?;
-
+                let search_text = required_str(&params, "search_text")?;
                 let case_sensitive = params
                     .get("case_sensitive")
                     .and_then(|v| v.as_bool())
                     .unwrap_or(false);
 
-                let xlsx = xlsx_tool::XlsxTool::new(path)
+                let worksheet = xlsx
+                    .get_worksheet_by_name(&worksheet_name()?)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
-                let worksheet = if let Some(name) = params.get("worksheet").and_then(|v| v.as_str())
-                {
-                    xlsx.get_worksheet_by_name(name)
-                        .map_err(|e| ToolError::ExecutionError(e.to_string()))?
-                } else {
-                    xlsx.get_worksheet_by_index(0)
-                        .map_err(|e| ToolError::ExecutionError(e.to_string()))?
-                };
                 let matches = xlsx
                     .find_in_worksheet(worksheet, search_text, case_sensitive)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
@@ -977,68 +1774,163 @@ impl ComputerControllerRouter {
                     matches
                 ))])
             }
+            "get_cell" => {
+                let row = positive_u32(&params, "row", None)?;
+                let col = positive_u32(&params, "col", None)?;
+                let worksheet = xlsx
+                    .get_worksheet_by_name(&worksheet_name()?)
+                    .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+                let cell_value = xlsx
+                    .get_cell_value(worksheet, row, col)
+                    .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+                Ok(vec![Content::text(format!("{:#?}", cell_value))])
+            }
             "update_cell" => {
-                let row = params.get("row").and_then(|v| v.as_u64()).ok_or_else(|| {
-                    ToolError::InvalidParameters("Missing 'row' parameter".into())
-                })?;
-
-                let col = params.get("col").and_then(|v| v.as_u64()).ok_or_else(|| {
-                    ToolError::InvalidParameters("Missing 'col' parameter".into())
-                })?;
+                let row = positive_u32(&params, "row", None)?;
+                let col = positive_u32(&params, "col", None)?;
+                let value = required_str(&params, "value")?;
+                let worksheet_name = worksheet_name()?;
 
-                let value = params
-                    .get("value")
-                    .and_then(|v| v.as_str())
+                xlsx.update_cell(&worksheet_name, row, col, value)
+                    .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+                Ok(vec![Content::text(format!(
+                    "Updated cell ({}, {}) to '{}' in worksheet '{}'. {}",
+                    row, col, value, worksheet_name, unsaved
+                ))])
+            }
+            "write_range" => {
+                let values: Vec<Vec<Value>> = params
+                    .get("values")
+                    .cloned()
+                    .map(serde_json::from_value)
+                    .transpose()
+                    .map_err(|_| {
+                        ToolError::InvalidParameters(
+                            "'values' must be an array of rows, each an array of values".into(),
+                        )
+                    })?
                     .ok_or_else(|| {
-                        ToolError::InvalidParameters("Missing 'value' parameter".into())
+                        ToolError::InvalidParameters("Missing 'values' parameter".into())
                     })?;
+                let cell = params.get("cell").and_then(|v| v.as_str()).unwrap_or("A1");
+                let worksheet_name = worksheet_name()?;
 
-                let worksheet_name = params
-                    .get("worksheet")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("Sheet1");
-
-                let mut xlsx = xlsx_tool::XlsxTool::new(path)
-                    .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
-                xlsx.update_cell(worksheet_name, row as u32, col as u32, value)
-                    .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
-                xlsx.save(path)
+                let written = xlsx
+                    .write_range(&worksheet_name, cell, &values)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
                 Ok(vec![Content::text(format!(
-                    "Updated cell ({}, {}) to '{}' in worksheet '{}'",
-                    row, col, value, worksheet_name
+                    "Wrote {} in worksheet '{}'. {}",
+                    written, worksheet_name, unsaved
                 ))])
             }
-            "save" => {
-                let xlsx = xlsx_tool::XlsxTool::new(path)
+            "insert_rows" | "delete_rows" | "insert_columns" | "delete_columns" => {
+                let (index_param, noun) = if operation.ends_with("rows") {
+                    ("row", "row")
+                } else {
+                    ("col", "column")
+                };
+                let index = positive_u32(&params, index_param, None)?;
+                let count = positive_u32(&params, "count", Some(1))?;
+                let worksheet_name = worksheet_name()?;
+
+                let result = match operation {
+                    "insert_rows" => xlsx.insert_rows(&worksheet_name, index, count),
+                    "delete_rows" => xlsx.delete_rows(&worksheet_name, index, count),
+                    "insert_columns" => xlsx.insert_columns(&worksheet_name, index, count),
+                    _ => xlsx.delete_columns(&worksheet_name, index, count),
+                };
+                result.map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+                Ok(vec![Content::text(format!(
+                    "{} {} {}(s) at {} {} in worksheet '{}'. {}",
+                    if operation.starts_with("insert") {
+                        "Inserted"
+                    } else {
+                        "Deleted"
+                    },
+                    count,
+                    noun,
+                    noun,
+                    index,
+                    worksheet_name,
+                    unsaved
+                ))])
+            }
+            "add_worksheet" => {
+                let name = worksheet_param.ok_or_else(|| {
+                    ToolError::InvalidParameters("Missing 'worksheet' parameter".into())
+                })?;
+                xlsx.add_worksheet(name)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
-                xlsx.save(path)
+                Ok(vec![Content::text(format!(
+                    "Added worksheet '{}'. {}",
+                    name, unsaved
+                ))])
+            }
+            "format_range" => {
+                let range = required_str(&params, "range")?;
+                let format: xlsx_tool::CellFormat = params
+                    .get("format")
+                    .cloned()
+                    .map(serde_json::from_value)
+                    .transpose()
+                    .map_err(|e| ToolError::InvalidParameters(format!("Invalid 'format': {}", e)))?
+                    .ok_or_else(|| {
+                        ToolError::InvalidParameters("Missing 'format' parameter".into())
+                    })?;
+                let worksheet_name = worksheet_name()?;
+
+                xlsx.format_range(&worksheet_name, range, &format)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
-                Ok(vec![Content::text("File saved successfully.")])
+                Ok(vec![Content::text(format!(
+                    "Formatted {} in worksheet '{}'. {}",
+                    range, worksheet_name, unsaved
+                ))])
             }
-            "get_cell" => {
-                let row = params.get("row").and_then(|v| v.as_u64()).ok_or_else(|| {
-                    ToolError::InvalidParameters("Missing 'row' parameter".into())
-                })?;
+            "import_csv" => {
+                let csv_path = required_str(&params, "csv_path")?;
+                let cell = params.get("cell").and_then(|v| v.as_str()).unwrap_or("A1");
+                // The worksheet is created if it does not exist yet
+                let worksheet_name = match worksheet_param {
+                    Some(name) => name.to_string(),
+                    None => worksheet_name()?,
+                };
 
-                let col = params.get("col").and_then(|v| v.as_u64()).ok_or_else(|| {
-                    ToolError::InvalidParameters("Missing 'col' parameter".into())
-                })?;
+                let (rows, columns) = xlsx
+                    .import_csv(&worksheet_name, csv_path, cell)
+                    .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+                Ok(vec![Content::text(format!(
+                    "Imported {} rows and {} columns from {} into worksheet '{}' at {}. {}",
+                    rows, columns, csv_path, worksheet_name, cell, unsaved
+                ))])
+            }
+            "export_csv" => {
+                let csv_path = required_str(&params, "csv_path")?;
+                let worksheet_name = worksheet_name()?;
 
-                let xlsx = xlsx_tool::XlsxTool::new(path)
+                let rows = xlsx
+                    .export_csv(&worksheet_name, csv_path)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
-                let worksheet = if let Some(name) = params.get("worksheet").and_then(|v| v.as_str())
-                {
-                    xlsx.get_worksheet_by_name(name)
-                        .map_err(|e| ToolError::ExecutionError(e.to_string()))?
-                } else {
-                    xlsx.get_worksheet_by_index(0)
-                        .map_err(|e| ToolError::ExecutionError(e.to_string()))?
-                };
-                let cell_value = xlsx
-                    .get_cell_value(worksheet, row as u32, col as u32)
+                Ok(vec![Content::text(format!(
+                    "Exported {} rows from worksheet '{}' to {}",
+                    rows, worksheet_name, csv_path
+                ))])
+            }
+            "save" => {
+                let output_path = params.get("output_path").and_then(|v| v.as_str());
+                let target = output_path.unwrap_or(path);
+                xlsx.save(target)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
-                Ok(vec![Content::text(format!("{:#?}", cell_value))])
+
+                // After saving elsewhere, later edits continue in the new file
+                if let Some(output_path) = output_path {
+                    if let Some(xlsx) = workbooks.remove(&key) {
+                        workbooks.insert(workbook_key(output_path), xlsx);
+                    }
+                }
+                Ok(vec![Content::text(format!(
+                    "Saved workbook to {}.",
+                    target
+                ))])
             }
             _ => Err(ToolError::InvalidParameters(format!(
                 "Invalid operation: {}",
@@ -1079,7 +1971,104 @@ impl ComputerControllerRouter {
             .and_then(|v| v.as_str())
             .ok_or_else(|| ToolError::InvalidParameters("Missing 'operation' parameter".into()))?;
 
-        crate::computercontroller::pdf_tool::pdf_tool(path, operation, &self.cache_dir).await
+        let options = pdf_tool::PdfOptions {
+            pages: params
+                .get("pages")
+                .and_then(|v| v.as_str())
+                .map(String::from),
+            layout: params
+                .get("layout")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            query: params
+                .get("query")
+                .and_then(|v| v.as_str())
+                .map(String::from),
+            case_sensitive: params
+                .get("case_sensitive")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+        };
+
+        crate::computercontroller::pdf_tool::pdf_tool(path, operation, &options, &self.cache_dir)
+            .await
+    }
+
+    /// Rank cached text files by how many of the query words they contain
+    fn search_cache(&self, query: &str) -> Result<Vec<Content>, ToolError> {
+        const MAX_HITS: usize = 10;
+
+        let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        if terms.is_empty() {
+            return Err(ToolError::InvalidParameters(
+                "The search query must not be empty".into(),
+            ));
+        }
+
+        let files: Vec<(PathBuf, Option<String>)> = {
+            let index = self.cache_index.lock().unwrap();
+            index
+                .entries()
+                .filter(|entry| entry.is_text())
+                .map(|entry| (index.path_of(entry), entry.source.clone()))
+                .collect()
+        };
+        let mut hits = Vec::new();
+        for (path, source) in files {
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let lower = content.to_lowercase();
+            let matched = terms.iter().filter(|t| lower.contains(t.as_str())).count();
+            if matched == 0 {
+                continue;
+            }
+            let occurrences: usize = terms
+                .iter()
+                .map(|t| lower.matches(t.as_str()).count())
+                .sum();
+
+            // Show the line that mentions the most query words
+            let snippet = content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with("url: "))
+                .max_by_key(|line| {
+                    let line = line.to_lowercase();
+                    terms.iter().filter(|t| line.contains(t.as_str())).count()
+                })
+                .unwrap_or_default();
+            let snippet = if snippet.chars().count() > 200 {
+                format!("{}...", snippet.chars().take(200).collect::<String>())
+            } else {
+                snippet.to_string()
+            };
+            let source = source.or_else(|| web_page::source_url(&content).map(str::to_string));
+            hits.push((matched, occurrences, path, source, snippet));
+        }
+
+        if hits.is_empty() {
+            return Ok(vec![Content::text(format!(
+                "No cached files mention \"{}\"",
+                query
+            ))]);
+        }
+        hits.sort_by_key(|hit| std::cmp::Reverse((hit.0, hit.1)));
+
+        let mut output = format!("Cached files matching \"{}\":\n", query);
+        for (matched, _, path, source, snippet) in hits.iter().take(MAX_HITS) {
+            output.push_str(&format!(
+                "\n{} ({}/{} words)\n",
+                path.display(),
+                matched,
+                terms.len()
+            ));
+            if let Some(source) = source {
+                output.push_str(&format!("  source: {}\n", source));
+            }
+            output.push_str(&format!("  {}\n", snippet));
+        }
+        Ok(vec![Content::text(output)])
     }
 
     async fn cache(&self, params: Value) -> Result<Vec<Content>, ToolError> {
@@ -1090,20 +2079,28 @@ impl ComputerControllerRouter {
 
         match command {
             "list" => {
-                let mut files = Vec::new();
-                for entry in fs::read_dir(&self.cache_dir).map_err(|e| {
-                    ToolError::ExecutionError(format!("Failed to read cache directory: {}", e))
-                })? {
-                    let entry = entry.map_err(|e| {
-                        ToolError::ExecutionError(format!("Failed to read directory entry: {}", e))
-                    })?;
-                    files.push(format!("{}", entry.path().display()));
+                let index = self.cache_index.lock().unwrap();
+                let mut entries: Vec<_> = index.entries().collect();
+                entries.sort_by_key(|entry| std::cmp::Reverse(entry.accessed));
+                let mut output = format!(
+                    "Cached files ({} of {} used):\n",
+                    format_size(index.total_size()),
+                    format_size(index.limits().max_bytes)
+                );
+                for entry in entries {
+                    output.push_str(&format!(
+                        "\n{}\n  {}, {}, created {}",
+                        index.path_of(entry).display(),
+                        entry.mime_type,
+                        format_size(entry.size),
+                        entry.created.format("%Y-%m-%d %H:%M")
+                    ));
+                    if let Some(source) = &entry.source {
+                        output.push_str(&format!(", from {}", source));
+                    }
+                    output.push('\n');
                 }
-                files.sort();
-                Ok(vec![Content::text(format!(
-                    "Cached files:\n{}",
-                    files.join("\n")
-                ))])
+                Ok(vec![Content::text(output)])
             }
             "view" => {
                 let path = params.get("path").and_then(|v| v.as_str()).ok_or_else(|| {
@@ -1113,46 +2110,47 @@ impl ComputerControllerRouter {
                 let content = fs::read_to_string(path).map_err(|e| {
                     ToolError::ExecutionError(format!("Failed to read file: {}", e))
                 })?;
+                self.cache_index
+                    .lock()
+                    .unwrap()
+                    .touch(std::path::Path::new(path));
 
                 Ok(vec![Content::text(format!(
                     "Content of {}:\n\n{}",
                     path, content
                 ))])
             }
+            "search" => {
+                let query = params.get("query").and_then(|v| v.as_str()).ok_or_else(|| {
+                    ToolError::InvalidParameters("Missing 'query' parameter for search".into())
+                })?;
+                self.search_cache(query)
+            }
             "delete" => {
                 let path = params.get("path").and_then(|v| v.as_str()).ok_or_else(|| {
                     ToolError::InvalidParameters("Missing 'path' parameter for delete".into())
                 })?;
 
-                fs::remove_file(path).map_err(|e| {
-                    ToolError::ExecutionError(format!("Failed to delete file: {}", e))
-                })?;
-
-                // Remove from active resources if present
-                if let Ok(url) = Url::from_file_path(path) {
-                    self.active_resources
-                        .lock()
-                        .unwrap()
-                        .remove(&url.to_string());
-                }
+                // Removing it from the index also stops listing it as a resource
+                self.cache_index
+                    .lock()
+                    .unwrap()
+                    .remove(std::path::Path::new(path))
+                    .map_err(|e| {
+                        ToolError::ExecutionError(format!("Failed to delete file: {}", e))
+                    })?;
 
                 Ok(vec![Content::text(format!("Deleted file: {}", path))])
             }
             "clear" => {
-                fs::remove_dir_all(&self.cache_dir).map_err(|e| {
+                self.cache_index.lock().unwrap().clear().map_err(|e| {
                     ToolError::ExecutionError(format!("Failed to clear cache directory: {}", e))
                 })?;
-                fs::create_dir_all(&self.cache_dir).map_err(|e| {
-                    ToolError::ExecutionError(format!("Failed to recreate cache directory: {}", e))
-                })?;
-
-                // Clear active resources
-                self.active_resources.lock().unwrap().clear();
 
                 Ok(vec![Content::text("Cache cleared successfully.")])
             }
             _ => Err(ToolError::InvalidParameters(format!(
-                "Invalid 'command' parameter: {}. Valid options are: 'list', 'view', 'delete', 'clear'",
+                "Invalid 'command' parameter: {}. Valid options are: 'list', 'view', 'search', 'delete', 'clear'",
                 command
             )))
         }
@@ -1192,6 +2190,7 @@ impl Router for ComputerControllerRouter {
                 "web_scrape" => this.web_scrape(arguments).await,
                 "automation_script" => this.quick_script(arguments).await,
                 "computer_control" => this.computer_control(arguments).await,
+                "virtual_display" => this.virtual_display(arguments).await,
                 "cache" => this.cache(arguments).await,
                 "pdf_tool" => this.pdf_tool(arguments).await,
                 "docx_tool" => this.docx_tool(arguments).await,
@@ -1220,8 +2219,11 @@ impl Router for ComputerControllerRouter {
     }
 
     fn list_resources(&self) -> Vec<Resource> {
-        let active_resources = self.active_resources.lock().unwrap();
-        let resources = active_resources.values().cloned().collect();
+        let index = self.cache_index.lock().unwrap();
+        let resources: Vec<Resource> = index
+            .entries()
+            .filter_map(|entry| Self::cache_resource(&index.path_of(entry), entry))
+            .collect();
         tracing::info!("Listing resources: {:?}", resources);
         resources
     }
@@ -1234,12 +2236,6 @@ impl Router for ComputerControllerRouter {
         let this = self.clone();
 
         Box::pin(async move {
-            let active_resources = this.active_resources.lock().unwrap();
-            let resource = active_resources
-                .get(&uri)
-                .ok_or_else(|| ResourceError::NotFound(format!("Resource not found: {}", uri)))?
-                .clone();
-
             let url = Url::parse(&uri)
                 .map_err(|e| ResourceError::NotFound(format!("Invalid URI: {}", e)))?;
 
@@ -1253,20 +2249,25 @@ impl Router for ComputerControllerRouter {
                 .to_file_path()
                 .map_err(|_| ResourceError::NotFound("Invalid file path in URI".into()))?;
 
-            match resource.mime_type.as_str() {
-                "text" | "json" => fs::read_to_string(&path).map_err(|e| {
+            let is_text = {
+                let mut index = this.cache_index.lock().unwrap();
+                let is_text = index
+                    .get(&path)
+                    .ok_or_else(|| ResourceError::NotFound(format!("Resource not found: {}", uri)))?
+                    .is_text();
+                index.touch(&path);
+                is_text
+            };
+
+            if is_text {
+                fs::read_to_string(&path).map_err(|e| {
                     ResourceError::ExecutionError(format!("Failed to read file: {}", e))
-                }),
-                "binary" => {
-                    let bytes = fs::read(&path).map_err(|e| {
-                        ResourceError::ExecutionError(format!("Failed to read file: {}", e))
-                    })?;
-                    Ok(base64::prelude::BASE64_STANDARD.encode(bytes))
-                }
-                mime_type => Err(ResourceError::NotFound(format!(
-                    "Unsupported mime type: {}",
-                    mime_type
-                ))),
+                })
+            } else {
+                let bytes = fs::read(&path).map_err(|e| {
+                    ResourceError::ExecutionError(format!("Failed to read file: {}", e))
+                })?;
+                Ok(base64::prelude::BASE64_STANDARD.encode(bytes))
             }
         })
     }
@@ -1288,3 +2289,67 @@ impl Router for ComputerControllerRouter {
         })
     }
 }
+
+/// Open workbooks are keyed by absolute path, so that every spelling of a path, including one
+/// for a file that does not exist yet, shares the same session
+fn workbook_key(path: &str) -> PathBuf {
+    let path = std::path::Path::new(path);
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => std::path::Path::new("."),
+    };
+    match (parent.canonicalize(), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn required_str<'a>(params: &'a Value, name: &str) -> Result<&'a str, ToolError> {
+    params
+        .get(name)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| ToolError::InvalidParameters(format!("Missing '{}' parameter", name)))
+}
+
+fn required_u32(params: &Value, name: &str) -> Result<u32, ToolError> {
+    let value = params
+        .get(name)
+        .ok_or_else(|| ToolError::InvalidParameters(format!("Missing '{}' parameter", name)))?;
+    value
+        .as_u64()
+        .and_then(|v| u32::try_from(v).ok())
+        .ok_or_else(|| {
+            ToolError::InvalidParameters(format!(
+                "'{}' must be a whole number up to {}, got {}",
+                name,
+                u32::MAX,
+                value
+            ))
+        })
+}
+
+/// A 1-based row, column or count, where 0 is never valid
+fn positive_u32(params: &Value, name: &str, default: Option<u32>) -> Result<u32, ToolError> {
+    let value = match default {
+        Some(default) if params.get(name).is_none() => default,
+        _ => required_u32(params, name)?,
+    };
+    if value == 0 {
+        return Err(ToolError::InvalidParameters(format!(
+            "'{}' must be at least 1",
+            name
+        )));
+    }
+    Ok(value)
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+        b if b >= 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{} B", b),
+    }
+}
@@ -0,0 +1,1593 @@
+//! Positioned text extraction from PDF pages.
+//!
+//! Content streams are interpreted with the text and graphics state needed to know where each
+//! piece of text lands on the page, and glyph codes are decoded through the font's ToUnicode
+//! map, its composite (CID) structure or its simple encoding with `/Differences`. The resulting
+//! spans are then grouped into lines, columns and tables.
+
+use lopdf::{content::Content as PdfContent, Dictionary, Document, Object, ObjectId};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::rc::Rc;
+
+/// Form XObjects nested deeper than this are not followed
+const MAX_FORM_DEPTH: usize = 8;
+/// Text wider than this many points is bucketed more coarsely when looking for columns
+const MAX_GUTTER_BUCKETS: usize = 4096;
+
+/// A run of text drawn in one go, positioned in page space (points, origin bottom left)
+#[derive(Debug, Clone)]
+pub struct TextSpan {
+    pub x: f64,
+    pub end_x: f64,
+    pub y: f64,
+    pub size: f64,
+    pub text: String,
+}
+
+/// Text shown on a page, in the order it was drawn
+pub fn page_spans(doc: &Document, page_id: ObjectId) -> Vec<TextSpan> {
+    let mut interpreter = Interpreter {
+        doc,
+        spans: Vec::new(),
+    };
+    let resources = page_resources(doc, page_id);
+    if let Ok(content) = doc.get_page_content(page_id) {
+        interpreter.run(&content, &resources, IDENTITY, 0);
+    }
+    interpreter.spans
+}
+
+/// Resource dictionaries that apply to a page, the page's own first followed by inherited ones
+/// Collects the page's own resources followed by those inherited from its
+/// ancestors in the page tree, whether stored inline or by reference.
+fn page_resources(doc: &Document, page_id: ObjectId) -> Vec<&Dictionary> {
+    let mut resources = Vec::new();
+    let mut node = doc.get_dictionary(page_id).ok();
+    let mut depth = 0;
+    while let Some(dict) = node {
+        if let Ok(res) = dict.get(b"Resources") {
+            if let Ok(res) = deref(doc, res).as_dict() {
+                resources.push(res);
+            }
+        }
+        depth += 1;
+        if depth > 32 {
+            break;
+        }
+        node = dict
+            .get(b"Parent")
+            .and_then(Object::as_reference)
+            .and_then(|id| doc.get_dictionary(id))
+            .ok();
+    }
+    resources
+}
+
+fn deref<'a>(doc: &'a Document, object: &'a Object) -> &'a Object {
+    match object {
+        Object::Reference(id) => doc.get_object(*id).unwrap_or(object),
+        _ => object,
+    }
+}
+
+fn number(object: &Object) -> Option<f64> {
+    match object {
+        Object::Integer(i) => Some(*i as f64),
+        Object::Real(r) => Some(*r as f64),
+        _ => None,
+    }
+}
+
+fn stream_content(doc: &Document, object: &Object) -> Option<Vec<u8>> {
+    let stream = deref(doc, object).as_stream().ok()?;
+    stream
+        .decompressed_content()
+        .ok()
+        .or_else(|| Some(stream.content.clone()))
+}
+
+/// Look up a named resource such as a font or XObject
+fn find_resource<'a>(
+    doc: &'a Document,
+    resources: &[&'a Dictionary],
+    category: &[u8],
+    name: &[u8],
+) -> Option<&'a Object> {
+    resources.iter().find_map(|dict| {
+        let group = deref(doc, dict.get(category).ok()?).as_dict().ok()?;
+        group.get(name).ok().map(|object| deref(doc, object))
+    })
+}
+
+type Matrix = [f64; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// `a` followed by `b`, in the row vector convention used by PDF
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    [
+        a[0] * b[0] + a[1] * b[2],
+        a[0] * b[1] + a[1] * b[3],
+        a[2] * b[0] + a[3] * b[2],
+        a[2] * b[1] + a[3] * b[3],
+        a[4] * b[0] + a[5] * b[2] + b[4],
+        a[4] * b[1] + a[5] * b[3] + b[5],
+    ]
+}
+
+fn translate(tx: f64, ty: f64, m: &Matrix) -> Matrix {
+    multiply(&[1.0, 0.0, 0.0, 1.0, tx, ty], m)
+}
+
+fn matrix(operands: &[Object]) -> Option<Matrix> {
+    let values: Vec<f64> = operands.iter().filter_map(number).collect();
+    values.try_into().ok()
+}
+
+#[derive(Clone)]
+struct TextState {
+    font: Option<Rc<FontDecoder>>,
+    size: f64,
+    char_spacing: f64,
+    word_spacing: f64,
+    /// Horizontal scaling as a fraction
+    scale: f64,
+    leading: f64,
+    rise: f64,
+}
+
+impl Default for TextState {
+    fn default() -> Self {
+        Self {
+            font: None,
+            size: 0.0,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            scale: 1.0,
+            leading: 0.0,
+            rise: 0.0,
+        }
+    }
+}
+
+struct Interpreter<'a> {
+    doc: &'a Document,
+    spans: Vec<TextSpan>,
+}
+
+impl<'a> Interpreter<'a> {
+    fn run(&mut self, content: &[u8], resources: &[&'a Dictionary], ctm: Matrix, depth: usize) {
+        let Ok(content) = PdfContent::decode(content) else {
+            tracing::warn!("Failed to decode a PDF content stream");
+            return;
+        };
+
+        let mut fonts: HashMap<Vec<u8>, Rc<FontDecoder>> = HashMap::new();
+        let mut stack = Vec::new();
+        let mut ctm = ctm;
+        let mut state = TextState::default();
+        let mut tm = IDENTITY;
+        let mut tlm = IDENTITY;
+
+        for operation in content.operations {
+            let operands = &operation.operands;
+            let arg = |i: usize| operands.get(i).and_then(number).unwrap_or(0.0);
+            match operation.operator.as_str() {
+                "q" => stack.push((ctm, state.clone())),
+                "Q" => {
+                    if let Some((saved_ctm, saved_state)) = stack.pop() {
+                        ctm = saved_ctm;
+                        state = saved_state;
+                    }
+                }
+                "cm" => {
+                    if let Some(m) = matrix(operands) {
+                        ctm = multiply(&m, &ctm);
+                    }
+                }
+                "BT" => {
+                    tm = IDENTITY;
+                    tlm = IDENTITY;
+                }
+                "Tm" => {
+                    if let Some(m) = matrix(operands) {
+                        tm = m;
+                        tlm = m;
+                    }
+                }
+                "Td" => {
+                    tlm = translate(arg(0), arg(1), &tlm);
+                    tm = tlm;
+                }
+                "TD" => {
+                    state.leading = -arg(1);
+                    tlm = translate(arg(0), arg(1), &tlm);
+                    tm = tlm;
+                }
+                "T*" => {
+                    tlm = translate(0.0, -state.leading, &tlm);
+                    tm = tlm;
+                }
+                "Tc" => state.char_spacing = arg(0),
+                "Tw" => state.word_spacing = arg(0),
+                "Tz" => state.scale = arg(0) / 100.0,
+                "TL" => state.leading = arg(0),
+                "Ts" => state.rise = arg(0),
+                "Tf" => {
+                    state.size = arg(1);
+                    state.font = operands
+                        .first()
+                        .and_then(|o| o.as_name().ok())
+                        .and_then(|name| {
+                            if let Some(font) = fonts.get(name) {
+                                return Some(font.clone());
+                            }
+                            let dict = find_resource(self.doc, resources, b"Font", name)?
+                                .as_dict()
+                                .ok()?;
+                            let font = Rc::new(FontDecoder::new(self.doc, dict));
+                            fonts.insert(name.to_vec(), font.clone());
+                            Some(font)
+                        });
+                }
+                "Tj" | "'" | "\"" => {
+                    if operation.operator == "\"" {
+                        state.word_spacing = arg(0);
+                        state.char_spacing = arg(1);
+                    }
+                    if operation.operator != "Tj" {
+                        tlm = translate(0.0, -state.leading, &tlm);
+                        tm = tlm;
+                    }
+                    if let Some(Object::String(bytes, _)) = operands.last() {
+                        self.show(bytes, &state, &mut tm, &ctm);
+                    }
+                }
+                "TJ" => {
+                    if let Some(Object::Array(items)) = operands.first() {
+                        for item in items {
+                            match item {
+                                Object::String(bytes, _) => self.show(bytes, &state, &mut tm, &ctm),
+                                other => {
+                                    let adjust = number(other).unwrap_or(0.0);
+                                    let tx = -adjust / 1000.0 * state.size * state.scale;
+                                    tm = translate(tx, 0.0, &tm);
+                                }
+                            }
+                        }
+                    }
+                }
+                "Do" if depth < MAX_FORM_DEPTH => {
+                    let Some(name) = operands.first().and_then(|o| o.as_name().ok()) else {
+                        continue;
+                    };
+                    let Some(Ok(form)) =
+                        find_resource(self.doc, resources, b"XObject", name).map(|o| o.as_stream())
+                    else {
+                        continue;
+                    };
+                    if form.dict.get(b"Subtype").and_then(|s| s.as_name()).ok() != Some(b"Form") {
+                        continue;
+                    }
+                    let form_matrix = form
+                        .dict
+                        .get(b"Matrix")
+                        .ok()
+                        .and_then(|m| m.as_array().ok())
+                        .and_then(|m| matrix(m))
+                        .unwrap_or(IDENTITY);
+                    let mut form_resources = Vec::new();
+                    if let Ok(own) = form.dict.get(b"Resources") {
+                        if let Ok(own) = deref(self.doc, own).as_dict() {
+                            form_resources.push(own);
+                        }
+                    }
+                    form_resources.extend(resources.iter().copied());
+                    let content = form
+                        .decompressed_content()
+                        .unwrap_or_else(|_| form.content.clone());
+                    self.run(
+                        &content,
+                        &form_resources,
+                        multiply(&form_matrix, &ctm),
+                        depth + 1,
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Show a string, advancing the text matrix and recording the span
+    fn show(&mut self, bytes: &[u8], state: &TextState, tm: &mut Matrix, ctm: &Matrix) {
+        let Some(font) = &state.font else {
+            return;
+        };
+
+        let start = multiply(&translate(0.0, state.rise, tm), ctm);
+        let mut text = String::new();
+        for glyph in font.decode(bytes) {
+            let mut advance = glyph.width / 1000.0 * state.size + state.char_spacing;
+            if glyph.is_space {
+                advance += state.word_spacing;
+            }
+            *tm = translate(advance * state.scale, 0.0, tm);
+            text.push_str(&glyph.text);
+        }
+        let end = multiply(&translate(0.0, state.rise, tm), ctm);
+
+        if text.is_empty() {
+            return;
+        }
+        let size = state.size * start[2].hypot(start[3]);
+        self.spans.push(TextSpan {
+            x: start[4].min(end[4]),
+            end_x: start[4].max(end[4]),
+            y: start[5],
+            size: if size > 0.0 { size } else { state.size.abs() },
+            text,
+        });
+    }
+}
+
+struct Glyph {
+    text: String,
+    /// Advance width in thousandths of the font size
+    width: f64,
+    /// Single byte code 32, which word spacing applies to
+    is_space: bool,
+}
+
+enum FontKind {
+    /// One byte per glyph, decoded through an encoding table
+    Simple { table: Vec<String> },
+    /// Multi-byte codes, only decodable through ToUnicode
+    Composite,
+}
+
+struct FontDecoder {
+    kind: FontKind,
+    to_unicode: Option<CMap>,
+    widths: HashMap<u32, f64>,
+    default_width: f64,
+}
+
+impl FontDecoder {
+    fn new(doc: &Document, font: &Dictionary) -> Self {
+        let to_unicode = font
+            .get(b"ToUnicode")
+            .ok()
+            .and_then(|o| stream_content(doc, o))
+            .map(|content| CMap::parse(&content));
+        let is_composite = font.get(b"Subtype").and_then(|s| s.as_name()).ok() == Some(b"Type0");
+
+        if is_composite {
+            let descendant = font
+                .get(b"DescendantFonts")
+                .ok()
+                .and_then(|o| deref(doc, o).as_array().ok())
+                .and_then(|a| a.first())
+                .and_then(|o| deref(doc, o).as_dict().ok());
+            let default_width = descendant
+                .and_then(|d| d.get(b"DW").ok())
+                .and_then(number)
+                .unwrap_or(1000.0);
+            let widths = descendant
+                .and_then(|d| d.get(b"W").ok())
+                .and_then(|o| deref(doc, o).as_array().ok())
+                .map(|w| cid_widths(doc, w))
+                .unwrap_or_default();
+            return Self {
+                kind: FontKind::Composite,
+                to_unicode,
+                widths,
+                default_width,
+            };
+        }
+
+        let mut widths = HashMap::new();
+        let first_char = font.get(b"FirstChar").ok().and_then(number).unwrap_or(0.0) as u32;
+        if let Some(array) = font
+            .get(b"Widths")
+            .ok()
+            .and_then(|o| deref(doc, o).as_array().ok())
+        {
+            for (i, width) in array.iter().enumerate() {
+                if let Some(width) = number(deref(doc, width)) {
+                    widths.insert(first_char + i as u32, width);
+                }
+            }
+        }
+        // Standard 14 fonts may come without widths, half an em is a fair guess
+        let default_width = font
+            .get(b"FontDescriptor")
+            .ok()
+            .and_then(|o| deref(doc, o).as_dict().ok())
+            .and_then(|d| d.get(b"MissingWidth").ok())
+            .and_then(number)
+            .filter(|w| *w > 0.0)
+            .unwrap_or(500.0);
+
+        Self {
+            kind: FontKind::Simple {
+                table: simple_encoding(doc, font),
+            },
+            to_unicode,
+            widths,
+            default_width,
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Vec<Glyph> {
+        let mut glyphs = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            let length = match (&self.kind, &self.to_unicode) {
+                (FontKind::Simple { .. }, _) => 1,
+                (FontKind::Composite, Some(cmap)) => cmap.code_length(&bytes[i..]),
+                (FontKind::Composite, None) => 2,
+            }
+            .min(bytes.len() - i);
+            let code = bytes[i..i + length]
+                .iter()
+                .fold(0u32, |acc, b| acc << 8 | *b as u32);
+            i += length;
+
+            let text = self
+                .to_unicode
+                .as_ref()
+                .and_then(|cmap| cmap.lookup(code, length))
+                .or_else(|| match &self.kind {
+                    FontKind::Simple { table } => Some(table[code as usize].clone()),
+                    FontKind::Composite => None,
+                })
+                .unwrap_or_else(|| char::REPLACEMENT_CHARACTER.to_string());
+            glyphs.push(Glyph {
+                text,
+                width: self
+                    .widths
+                    .get(&code)
+                    .copied()
+                    .unwrap_or(self.default_width),
+                is_space: length == 1 && code == 32,
+            });
+        }
+        glyphs
+    }
+}
+
+/// Parse a CIDFont `/W` array: `c [w1 w2 ...]` or `c_first c_last w`
+fn cid_widths(doc: &Document, array: &[Object]) -> HashMap<u32, f64> {
+    let mut widths = HashMap::new();
+    let mut i = 0;
+    while i < array.len() {
+        let Some(first) = number(deref(doc, &array[i])) else {
+            break;
+        };
+        match array.get(i + 1).map(|o| deref(doc, o)) {
+            Some(Object::Array(list)) => {
+                for (offset, width) in list.iter().enumerate() {
+                    let Some(code) = u32::try_from(offset)
+                        .ok()
+                        .and_then(|offset| (first as u32).checked_add(offset))
+                    else {
+                        break;
+                    };
+                    if let Some(width) = number(width) {
+                        widths.insert(code, width);
+                    }
+                }
+                i += 2;
+            }
+            Some(last) => {
+                let (Some(last), Some(width)) = (
+                    number(last),
+                    array.get(i + 2).and_then(|o| number(deref(doc, o))),
+                ) else {
+                    break;
+                };
+                // CIDs are two bytes, so a longer range is malformed and is cut short
+                let (first, last) = (first as u32, last as u32);
+                if last >= first {
+                    for code in first..=last.min(first.saturating_add(0xFFFF)) {
+                        widths.insert(code, width);
+                    }
+                }
+                i += 3;
+            }
+            None => break,
+        }
+    }
+    widths
+}
+
+/// The text for each of the 256 codes of a simple font, from its base encoding and
+/// `/Differences`
+fn simple_encoding(doc: &Document, font: &Dictionary) -> Vec<String> {
+    let encoding = font.get(b"Encoding").ok().map(|o| deref(doc, o));
+    let base = match encoding {
+        Some(Object::Name(name)) => Some(name.as_slice()),
+        Some(Object::Dictionary(dict)) => dict.get(b"BaseEncoding").and_then(|o| o.as_name()).ok(),
+        _ => None,
+    };
+    let mut table = base_encoding(base.unwrap_or(b"StandardEncoding"));
+
+    if let Some(Ok(differences)) = encoding
+        .and_then(|o| o.as_dict().ok())
+        .and_then(|d| d.get(b"Differences").ok())
+        .map(|o| deref(doc, o).as_array())
+    {
+        let mut code = 0usize;
+        for item in differences {
+            match item {
+                Object::Integer(start) => code = *start as usize,
+                Object::Name(name) => {
+                    if let (Some(slot), Some(text)) = (table.get_mut(code), glyph_text(name)) {
+                        *slot = text;
+                    }
+                    code += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+    table
+}
+
+/// One of the predefined encodings, read from lopdf since it does not expose its tables
+fn base_encoding(name: &[u8]) -> Vec<String> {
+    let name = match name {
+        b"WinAnsiEncoding" | b"MacRomanEncoding" | b"MacExpertEncoding" => name,
+        _ => b"StandardEncoding".as_slice(),
+    };
+    let mut probe = Dictionary::new();
+    probe.set("Type", Object::Name(b"Font".to_vec()));
+    probe.set("Encoding", Object::Name(name.to_vec()));
+    let doc = Document::new();
+    let encoding = probe.get_font_encoding(&doc).ok();
+
+    (0u8..=255)
+        .map(|byte| match byte {
+            // Whitespace control codes are not in the tables but turn up in practice
+            b'\t' | b'\n' | b'\r' => (byte as char).to_string(),
+            _ => encoding
+                .as_ref()
+                .and_then(|e| e.bytes_to_string(&[byte]).ok())
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Glyph names for 0xA0 to 0xFF, in Latin-1 order
+const LATIN1_GLYPHS: [&str; 96] = [
+    "space",
+    "exclamdown",
+    "cent",
+    "sterling",
+    "currency",
+    "yen",
+    "brokenbar",
+    "section",
+    "dieresis",
+    "copyright",
+    "ordfeminine",
+    "guillemotleft",
+    "logicalnot",
+    "hyphen",
+    "registered",
+    "macron",
+    "degree",
+    "plusminus",
+    "twosuperior",
+    "threesuperior",
+    "acute",
+    "mu",
+    "paragraph",
+    "periodcentered",
+    "cedilla",
+    "onesuperior",
+    "ordmasculine",
+    "guillemotright",
+    "onequarter",
+    "onehalf",
+    "threequarters",
+    "questiondown",
+    "Agrave",
+    "Aacute",
+    "Acircumflex",
+    "Atilde",
+    "Adieresis",
+    "Aring",
+    "AE",
+    "Ccedilla",
+    "Egrave",
+    "Eacute",
+    "Ecircumflex",
+    "Edieresis",
+    "Igrave",
+    "Iacute",
+    "Icircumflex",
+    "Idieresis",
+    "Eth",
+    "Ntilde",
+    "Ograve",
+    "Oacute",
+    "Ocircumflex",
+    "Otilde",
+    "Odieresis",
+    "multiply",
+    "Oslash",
+    "Ugrave",
+    "Uacute",
+    "Ucircumflex",
+    "Udieresis",
+    "Yacute",
+    "Thorn",
+    "germandbls",
+    "agrave",
+    "aacute",
+    "acircumflex",
+    "atilde",
+    "adieresis",
+    "aring",
+    "ae",
+    "ccedilla",
+    "egrave",
+    "eacute",
+    "ecircumflex",
+    "edieresis",
+    "igrave",
+    "iacute",
+    "icircumflex",
+    "idieresis",
+    "eth",
+    "ntilde",
+    "ograve",
+    "oacute",
+    "ocircumflex",
+    "otilde",
+    "odieresis",
+    "divide",
+    "oslash",
+    "ugrave",
+    "uacute",
+    "ucircumflex",
+    "udieresis",
+    "yacute",
+    "thorn",
+    "ydieresis",
+];
+
+/// Glyph names for 0x20 to 0x7E
+const ASCII_GLYPHS: [&str; 95] = [
+    "space",
+    "exclam",
+    "quotedbl",
+    "numbersign",
+    "dollar",
+    "percent",
+    "ampersand",
+    "quotesingle",
+    "parenleft",
+    "parenright",
+    "asterisk",
+    "plus",
+    "comma",
+    "hyphen",
+    "period",
+    "slash",
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "colon",
+    "semicolon",
+    "less",
+    "equal",
+    "greater",
+    "question",
+    "at",
+    "A",
+    "B",
+    "C",
+    "D",
+    "E",
+    "F",
+    "G",
+    "H",
+    "I",
+    "J",
+    "K",
+    "L",
+    "M",
+    "N",
+    "O",
+    "P",
+    "Q",
+    "R",
+    "S",
+    "T",
+    "U",
+    "V",
+    "W",
+    "X",
+    "Y",
+    "Z",
+    "bracketleft",
+    "backslash",
+    "bracketright",
+    "asciicircum",
+    "underscore",
+    "grave",
+    "a",
+    "b",
+    "c",
+    "d",
+    "e",
+    "f",
+    "g",
+    "h",
+    "i",
+    "j",
+    "k",
+    "l",
+    "m",
+    "n",
+    "o",
+    "p",
+    "q",
+    "r",
+    "s",
+    "t",
+    "u",
+    "v",
+    "w",
+    "x",
+    "y",
+    "z",
+    "braceleft",
+    "bar",
+    "braceright",
+    "asciitilde",
+];
+
+/// Text for a glyph name from a `/Differences` array, covering the names common in Latin text
+fn glyph_text(name: &[u8]) -> Option<String> {
+    let name = std::str::from_utf8(name).ok()?;
+    // Variants such as "a.sc" or "f_i" decode like their base names
+    let base = name.split('.').next().unwrap_or(name);
+    if base.contains('_') && base.len() > 1 {
+        return base.split('_').map(glyph_text_single).collect();
+    }
+    glyph_text_single(base)
+}
+
+fn glyph_text_single(name: &str) -> Option<String> {
+    if let Some(index) = ASCII_GLYPHS.iter().position(|g| *g == name) {
+        return Some(((0x20 + index) as u8 as char).to_string());
+    }
+    if let Some(index) = LATIN1_GLYPHS.iter().position(|g| *g == name) {
+        return char::from_u32(0xA0 + index as u32).map(String::from);
+    }
+    let special = match name {
+        "quoteleft" => "\u{2018}",
+        "quoteright" => "\u{2019}",
+        "quotedblleft" => "\u{201C}",
+        "quotedblright" => "\u{201D}",
+        "quotesinglbase" => "\u{201A}",
+        "quotedblbase" => "\u{201E}",
+        "endash" => "\u{2013}",
+        "emdash" => "\u{2014}",
+        "bullet" => "\u{2022}",
+        "ellipsis" => "\u{2026}",
+        "dagger" => "\u{2020}",
+        "daggerdbl" => "\u{2021}",
+        "trademark" => "\u{2122}",
+        "perthousand" => "\u{2030}",
+        "minus" => "\u{2212}",
+        "fraction" => "\u{2044}",
+        "Euro" => "\u{20AC}",
+        "OE" => "\u{0152}",
+        "oe" => "\u{0153}",
+        "Scaron" => "\u{0160}",
+        "scaron" => "\u{0161}",
+        "Zcaron" => "\u{017D}",
+        "zcaron" => "\u{017E}",
+        "Ydieresis" => "\u{0178}",
+        "dotlessi" => "\u{0131}",
+        "florin" => "\u{0192}",
+        "circumflex" => "\u{02C6}",
+        "tilde" => "\u{02DC}",
+        "guilsinglleft" => "\u{2039}",
+        "guilsinglright" => "\u{203A}",
+        "nbspace" => "\u{00A0}",
+        "sfthyphen" => "\u{00AD}",
+        // Ligatures are spelled out so the text stays searchable
+        "ff" => "ff",
+        "fi" => "fi",
+        "fl" => "fl",
+        "ffi" => "ffi",
+        "ffl" => "ffl",
+        _ => "",
+    };
+    if !special.is_empty() {
+        return Some(special.to_string());
+    }
+
+    // uniXXXX (possibly several code points) and uXXXX[XX]
+    let hex_chars = |hex: &str, width: usize| -> Option<String> {
+        if hex.is_empty() || !hex.len().is_multiple_of(width) {
+            return None;
+        }
+        (0..hex.len() / width)
+            .map(|i| {
+                u32::from_str_radix(&hex[i * width..(i + 1) * width], 16)
+                    .ok()
+                    .and_then(char::from_u32)
+            })
+            .collect()
+    };
+    if let Some(hex) = name.strip_prefix("uni") {
+        return hex_chars(hex, 4);
+    }
+    if let Some(hex) = name
+        .strip_prefix('u')
+        .filter(|h| (4..=6).contains(&h.len()))
+    {
+        return hex_chars(hex, hex.len());
+    }
+    None
+}
+
+/// A ToUnicode CMap, mapping character codes of a given byte length to text
+#[derive(Debug, Default)]
+pub struct CMap {
+    /// Valid code ranges as (byte length, low, high)
+    codespace: Vec<(usize, u32, u32)>,
+    chars: HashMap<(usize, u32), String>,
+    ranges: Vec<(usize, u32, u32, RangeTarget)>,
+}
+
+#[derive(Debug)]
+enum RangeTarget {
+    /// The first code maps to this UTF-16 sequence, later codes increment its last unit
+    Start(Vec<u16>),
+    /// One entry per code
+    List(Vec<String>),
+}
+
+#[derive(Debug, PartialEq)]
+enum CMapToken {
+    Hex(Vec<u8>),
+    Word(String),
+    ArrayStart,
+    ArrayEnd,
+}
+
+impl CMap {
+    pub fn parse(content: &[u8]) -> Self {
+        let tokens = cmap_tokens(content);
+        let mut cmap = CMap::default();
+        let mut i = 0;
+
+        let hex = |token: Option<&CMapToken>| match token {
+            Some(CMapToken::Hex(bytes)) => Some(bytes.clone()),
+            _ => None,
+        };
+        let is_end = |token: &CMapToken, end: &str| matches!(token, CMapToken::Word(w) if w == end);
+
+        while i < tokens.len() {
+            let CMapToken::Word(word) = &tokens[i] else {
+                i += 1;
+                continue;
+            };
+            i += 1;
+            match word.as_str() {
+                "begincodespacerange" => {
+                    while i + 1 < tokens.len() && !is_end(&tokens[i], "endcodespacerange") {
+                        if let (Some(low), Some(high)) =
+                            (hex(tokens.get(i)), hex(tokens.get(i + 1)))
+                        {
+                            cmap.codespace
+                                .push((low.len(), code_value(&low), code_value(&high)));
+                        }
+                        i += 2;
+                    }
+                }
+                "beginbfchar" => {
+                    while i + 1 < tokens.len() && !is_end(&tokens[i], "endbfchar") {
+                        if let (Some(source), Some(target)) =
+                            (hex(tokens.get(i)), hex(tokens.get(i + 1)))
+                        {
+                            cmap.chars
+                                .insert((source.len(), code_value(&source)), utf16_text(&target));
+                        }
+                        i += 2;
+                    }
+                }
+                "beginbfrange" => {
+                    while i + 2 < tokens.len() && !is_end(&tokens[i], "endbfrange") {
+                        let (Some(low), Some(high)) = (hex(tokens.get(i)), hex(tokens.get(i + 1)))
+                        else {
+                            i += 1;
+                            continue;
+                        };
+                        let length = low.len();
+                        let (low, high) = (code_value(&low), code_value(&high));
+                        i += 2;
+                        match &tokens[i] {
+                            CMapToken::Hex(start) => {
+                                let units = start
+                                    .chunks(2)
+                                    .map(|c| c.iter().fold(0u16, |acc, b| acc << 8 | *b as u16))
+                                    .collect();
+                                cmap.ranges
+                                    .push((length, low, high, RangeTarget::Start(units)));
+                                i += 1;
+                            }
+                            CMapToken::ArrayStart => {
+                                let mut list = Vec::new();
+                                i += 1;
+                                while i < tokens.len() && tokens[i] != CMapToken::ArrayEnd {
+                                    if let CMapToken::Hex(target) = &tokens[i] {
+                                        list.push(utf16_text(target));
+                                    }
+                                    i += 1;
+                                }
+                                cmap.ranges
+                                    .push((length, low, high, RangeTarget::List(list)));
+                                i += 1;
+                            }
+                            _ => i += 1,
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        cmap
+    }
+
+    /// Byte length of the code at the start of `bytes`, from the codespace ranges
+    fn code_length(&self, bytes: &[u8]) -> usize {
+        for length in 1..=4.min(bytes.len()) {
+            let code = code_value(&bytes[..length]);
+            if self
+                .codespace
+                .iter()
+                .any(|(l, low, high)| *l == length && (*low..=*high).contains(&code))
+            {
+                return length;
+            }
+        }
+        if self.codespace.is_empty() {
+            // Without codespace ranges, use the length of the mapped codes
+            self.chars
+                .keys()
+                .map(|(length, _)| *length)
+                .chain(self.ranges.iter().map(|(length, ..)| *length))
+                .max()
+                .unwrap_or(2)
+        } else {
+            self.codespace.iter().map(|(l, ..)| *l).min().unwrap_or(2)
+        }
+    }
+
+    fn lookup(&self, code: u32, length: usize) -> Option<String> {
+        if let Some(text) = self.chars.get(&(length, code)) {
+            return Some(text.clone());
+        }
+        self.ranges
+            .iter()
+            .find(|(l, low, high, _)| *l == length && (*low..=*high).contains(&code))
+            .and_then(|(_, low, _, target)| {
+                let offset = code - low;
+                match target {
+                    RangeTarget::Start(units) => {
+                        let mut units = units.clone();
+                        let last = units.last_mut()?;
+                        *last = last.wrapping_add(offset as u16);
+                        Some(String::from_utf16_lossy(&units))
+                    }
+                    RangeTarget::List(list) => list.get(offset as usize).cloned(),
+                }
+            })
+    }
+}
+
+fn code_value(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0u32, |acc, b| acc << 8 | *b as u32)
+}
+
+fn utf16_text(bytes: &[u8]) -> String {
+    if bytes.len() == 1 {
+        return (bytes[0] as char).to_string();
+    }
+    let units: Vec<u16> = bytes
+        .chunks(2)
+        .map(|c| c.iter().fold(0u16, |acc, b| acc << 8 | *b as u16))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn cmap_tokens(content: &[u8]) -> Vec<CMapToken> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < content.len() {
+        match content[i] {
+            b'%' => {
+                while i < content.len() && content[i] != b'\n' && content[i] != b'\r' {
+                    i += 1;
+                }
+            }
+            b'<' if content.get(i + 1) == Some(&b'<') => i += 2,
+            b'>' if content.get(i + 1) == Some(&b'>') => i += 2,
+            b'<' => {
+                let end = content[i..]
+                    .iter()
+                    .position(|b| *b == b'>')
+                    .map_or(content.len(), |p| i + p);
+                let digits: Vec<u8> = content[i + 1..end]
+                    .iter()
+                    .copied()
+                    .filter(u8::is_ascii_hexdigit)
+                    .collect();
+                let bytes = digits
+                    .chunks(2)
+                    .filter_map(|pair| {
+                        let pair = if pair.len() == 1 {
+                            vec![pair[0], b'0']
+                        } else {
+                            pair.to_vec()
+                        };
+                        u8::from_str_radix(std::str::from_utf8(&pair).ok()?, 16).ok()
+                    })
+                    .collect();
+                tokens.push(CMapToken::Hex(bytes));
+                i = end + 1;
+            }
+            b'[' => {
+                tokens.push(CMapToken::ArrayStart);
+                i += 1;
+            }
+            b']' => {
+                tokens.push(CMapToken::ArrayEnd);
+                i += 1;
+            }
+            b'(' => {
+                // Literal strings are not used for mappings in practice, skip them
+                let mut depth = 0;
+                while i < content.len() {
+                    match content[i] {
+                        b'\\' => i += 1,
+                        b'(' => depth += 1,
+                        b')' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            b if b.is_ascii_whitespace() => i += 1,
+            _ => {
+                let start = i;
+                while i < content.len()
+                    && !content[i].is_ascii_whitespace()
+                    && !b"<>[]()%".contains(&content[i])
+                {
+                    i += 1;
+                }
+                if i == start {
+                    i += 1;
+                    continue;
+                }
+                tokens.push(CMapToken::Word(
+                    String::from_utf8_lossy(&content[start..i]).into_owned(),
+                ));
+            }
+        }
+    }
+    tokens
+}
+
+/// A stretch of a line, split from its neighbours by a wide gap
+#[derive(Debug, Clone)]
+pub struct Cell {
+    pub x: f64,
+    pub end_x: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub y: f64,
+    pub size: f64,
+    pub cells: Vec<Cell>,
+}
+
+impl Line {
+    pub fn text(&self) -> String {
+        self.cells
+            .iter()
+            .map(|c| c.text.as_str())
+            .collect::<Vec<_>>()
+            .join("  ")
+    }
+
+    fn x(&self) -> f64 {
+        self.cells.first().map_or(0.0, |c| c.x)
+    }
+
+    fn end_x(&self) -> f64 {
+        self.cells.last().map_or(0.0, |c| c.end_x)
+    }
+}
+
+/// Group spans into lines from top to bottom, with the text of each line in reading order
+pub fn build_lines(mut spans: Vec<TextSpan>) -> Vec<Line> {
+    spans.retain(|s| !s.text.trim().is_empty());
+    spans.sort_by(|a, b| b.y.total_cmp(&a.y).then(a.x.total_cmp(&b.x)));
+
+    let mut grouped: Vec<(f64, f64, Vec<TextSpan>)> = Vec::new();
+    for span in spans {
+        match grouped.last_mut() {
+            Some((y, size, members)) if (*y - span.y).abs() <= 0.5 * size.max(span.size) => {
+                *size = size.max(span.size);
+                members.push(span);
+            }
+            _ => grouped.push((span.y, span.size, vec![span])),
+        }
+    }
+
+    grouped
+        .into_iter()
+        .map(|(y, size, mut members)| {
+            members.sort_by(|a, b| a.x.total_cmp(&b.x));
+            let mut cells: Vec<Cell> = Vec::new();
+            for span in members {
+                let text = span.text.trim_end_matches(['\n', '\r']);
+                match cells.last_mut() {
+                    Some(cell) if span.x - cell.end_x < size => {
+                        // Gaps wider than a fifth of an em separate words
+                        if span.x - cell.end_x > 0.2 * size
+                            && !cell.text.ends_with(' ')
+                            && !text.starts_with(' ')
+                        {
+                            cell.text.push(' ');
+                        }
+                        cell.text.push_str(text);
+                        cell.end_x = cell.end_x.max(span.end_x);
+                    }
+                    _ => cells.push(Cell {
+                        x: span.x,
+                        end_x: span.end_x,
+                        text: text.to_string(),
+                    }),
+                }
+            }
+            for cell in &mut cells {
+                cell.text = cell.text.split_whitespace().collect::<Vec<_>>().join(" ");
+            }
+            Line { y, size, cells }
+        })
+        .collect()
+}
+
+fn median_size(lines: &[Line]) -> f64 {
+    let mut sizes: Vec<f64> = lines.iter().map(|l| l.size).collect();
+    sizes.sort_by(f64::total_cmp);
+    sizes.get(sizes.len() / 2).copied().unwrap_or(10.0)
+}
+
+/// Find a vertical gutter separating two columns of text, as an x range
+fn find_gutter(lines: &[Line]) -> Option<(f64, f64)> {
+    let min_x = lines.iter().map(Line::x).reduce(f64::min)?;
+    let max_x = lines.iter().map(Line::end_x).reduce(f64::max)?;
+    let width = max_x - min_x;
+    if !width.is_finite() || width <= 0.0 || lines.len() < 4 {
+        return None;
+    }
+
+    // Count how many lines cover each point across the page. Buckets are a point wide, but
+    // coordinates come from the content stream, so wider text is bucketed more coarsely.
+    let scale = (width / MAX_GUTTER_BUCKETS as f64).max(1.0);
+    let buckets = (width / scale).ceil() as usize + 1;
+    let bucket = |x: f64| (((x - min_x) / scale).max(0.0) as usize).min(buckets - 1);
+    let mut coverage = vec![0usize; buckets];
+    for line in lines {
+        for cell in &line.cells {
+            let start = bucket(cell.x);
+            let end = bucket(cell.end_x);
+            for count in &mut coverage[start..=end.max(start)] {
+                *count += 1;
+            }
+        }
+    }
+
+    // The widest nearly empty band in the middle half of the text, allowing for the odd
+    // centred heading crossing it
+    let threshold = (lines.len() / 10).max(1);
+    let (low, high) = (buckets / 4, buckets * 3 / 4);
+    let mut best: Option<(usize, usize)> = None;
+    let mut start = None;
+    for (i, count) in coverage.iter().enumerate().take(high + 1).skip(low) {
+        if *count <= threshold {
+            start.get_or_insert(i);
+        } else if let Some(s) = start.take() {
+            if best.is_none_or(|(bs, be)| i - s > be - bs) {
+                best = Some((s, i));
+            }
+        }
+    }
+    if let Some(s) = start {
+        if best.is_none_or(|(bs, be)| high + 1 - s > be - bs) {
+            best = Some((s, high + 1));
+        }
+    }
+
+    let (s, e) = best?;
+    if ((e - s) as f64 * scale) < median_size(lines) {
+        return None;
+    }
+    let gutter = (min_x + s as f64 * scale, min_x + e as f64 * scale);
+    let left = lines
+        .iter()
+        .filter(|l| l.cells.iter().any(|c| c.end_x <= gutter.0))
+        .count();
+    let right = lines
+        .iter()
+        .filter(|l| l.cells.iter().any(|c| c.x >= gutter.1))
+        .count();
+    (left >= 3 && right >= 3).then_some(gutter)
+}
+
+/// Plain text in reading order. Two column layouts are read column by column, with full
+/// width lines such as titles and tables kept in place.
+pub fn render_text(lines: &[Line]) -> String {
+    let mut full_width = vec![false; lines.len()];
+    for (range, _) in table_runs(lines) {
+        full_width[range].fill(true);
+    }
+    let flowing: Vec<Line> = lines
+        .iter()
+        .zip(&full_width)
+        .filter(|(_, full)| !**full)
+        .map(|(line, _)| line.clone())
+        .collect();
+    let Some((gutter_start, gutter_end)) = find_gutter(&flowing) else {
+        return render_flow(lines.iter());
+    };
+
+    let mut output = Vec::new();
+    let mut left: Vec<Line> = Vec::new();
+    let mut right: Vec<Line> = Vec::new();
+    let mut block: Vec<&Line> = Vec::new();
+
+    for (line, full) in lines.iter().zip(full_width) {
+        let left_cells: Vec<Cell> = line
+            .cells
+            .iter()
+            .filter(|c| c.end_x <= gutter_start)
+            .cloned()
+            .collect();
+        let right_cells: Vec<Cell> = line
+            .cells
+            .iter()
+            .filter(|c| c.x >= gutter_end)
+            .cloned()
+            .collect();
+
+        if full || left_cells.len() + right_cells.len() < line.cells.len() {
+            // Crosses the gutter, so everything above it is read first
+            for column in [&mut left, &mut right] {
+                if !column.is_empty() {
+                    output.push(render_flow(column.iter()));
+                    column.clear();
+                }
+            }
+            block.push(line);
+            continue;
+        }
+        if !block.is_empty() {
+            output.push(render_flow(block.drain(..)));
+        }
+        for (cells, column) in [(left_cells, &mut left), (right_cells, &mut right)] {
+            if !cells.is_empty() {
+                column.push(Line {
+                    y: line.y,
+                    size: line.size,
+                    cells,
+                });
+            }
+        }
+    }
+    if !block.is_empty() {
+        output.push(render_flow(block.drain(..)));
+    }
+    for column in [left, right] {
+        if !column.is_empty() {
+            output.push(render_flow(column.iter()));
+        }
+    }
+
+    output.join("\n\n")
+}
+
+/// Lines joined top to bottom, with a blank line where the vertical gap suggests a new
+/// paragraph
+fn render_flow<'a>(lines: impl Iterator<Item = &'a Line>) -> String {
+    let mut text = String::new();
+    let mut previous: Option<&Line> = None;
+    for line in lines {
+        if let Some(previous) = previous {
+            text.push('\n');
+            if previous.y - line.y > 1.8 * previous.size.max(line.size) {
+                text.push('\n');
+            }
+        }
+        text.push_str(&line.text());
+        previous = Some(line);
+    }
+    text
+}
+
+/// Text laid out on a character grid, keeping columns and indentation
+pub fn render_layout(lines: &[Line]) -> String {
+    let Some(min_x) = lines.iter().map(Line::x).reduce(f64::min) else {
+        return String::new();
+    };
+    let char_width = median_size(lines) * 0.5;
+
+    let mut output = String::new();
+    let mut previous_y: Option<f64> = None;
+    for line in lines {
+        if let Some(previous_y) = previous_y {
+            let gap = ((previous_y - line.y) / (line.size * 1.2)).round() as usize;
+            output.push_str(&"\n".repeat(gap.clamp(1, 3)));
+        }
+        let mut row = String::new();
+        for cell in &line.cells {
+            let column = ((cell.x - min_x) / char_width).round().max(0.0) as usize;
+            let current = row.chars().count();
+            if column > current {
+                row.push_str(&" ".repeat(column - current));
+            } else if current > 0 {
+                row.push(' ');
+            }
+            row.push_str(&cell.text);
+        }
+        output.push_str(row.trim_end());
+        previous_y = Some(line.y);
+    }
+    output
+}
+
+/// Rows of cells from runs of lines whose cells line up in columns
+pub fn find_tables(lines: &[Line]) -> Vec<Vec<Vec<String>>> {
+    table_runs(lines)
+        .into_iter()
+        .map(|(_, rows)| rows)
+        .collect()
+}
+
+/// Tables along with the range of lines they were found in
+fn table_runs(lines: &[Line]) -> Vec<(Range<usize>, Vec<Vec<String>>)> {
+    let mut tables = Vec::new();
+    let mut start = 0;
+
+    for (i, line) in lines.iter().enumerate() {
+        let joins = line.cells.len() >= 2
+            && (start == i || lines[i - 1].y - line.y <= 2.5 * lines[i - 1].size.max(line.size));
+        if !joins {
+            if let Some(rows) = table_from_rows(&lines[start..i]) {
+                tables.push((start..i, rows));
+            }
+            start = if line.cells.len() >= 2 { i } else { i + 1 };
+        }
+    }
+    if let Some(rows) = table_from_rows(&lines[start.min(lines.len())..]) {
+        tables.push((start..lines.len(), rows));
+    }
+
+    tables
+}
+
+fn table_from_rows(rows: &[Line]) -> Option<Vec<Vec<String>>> {
+    if rows.len() < 2 {
+        return None;
+    }
+
+    // Columns are the x ranges covered by cells across all rows
+    let mut intervals: Vec<(f64, f64)> = rows
+        .iter()
+        .flat_map(|row| row.cells.iter().map(|c| (c.x, c.end_x)))
+        .collect();
+    intervals.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut columns: Vec<(f64, f64)> = Vec::new();
+    for (start, end) in intervals {
+        match columns.last_mut() {
+            Some(column) if start <= column.1 => column.1 = column.1.max(end),
+            _ => columns.push((start, end)),
+        }
+    }
+    if columns.len() < 2 {
+        return None;
+    }
+
+    let average_length = rows
+        .iter()
+        .flat_map(|row| row.cells.iter().map(|c| c.text.chars().count()))
+        .sum::<usize>() as f64
+        / rows.iter().map(|row| row.cells.len()).sum::<usize>() as f64;
+    if columns.len() == 2 && average_length > 25.0 {
+        // Two runs of prose side by side are columns of text, not a table
+        return None;
+    }
+
+    Some(
+        rows.iter()
+            .map(|row| {
+                let mut cells = vec![String::new(); columns.len()];
+                for cell in &row.cells {
+                    let index = columns
+                        .iter()
+                        .position(|(start, end)| cell.x >= *start && cell.x <= *end)
+                        .unwrap_or(0);
+                    if !cells[index].is_empty() {
+                        cells[index].push(' ');
+                    }
+                    cells[index].push_str(&cell.text);
+                }
+                cells
+            })
+            .collect(),
+    )
+}
+
+/// A table as markdown, with the first row as the header
+pub fn table_to_markdown(rows: &[Vec<String>]) -> String {
+    let format_row = |row: &Vec<String>| {
+        let cells: Vec<String> = row.iter().map(|c| c.replace('|', "\\|")).collect();
+        format!("| {} |", cells.join(" | "))
+    };
+    let mut lines = Vec::new();
+    if let Some(header) = rows.first() {
+        lines.push(format_row(header));
+        lines.push(format!("|{}", " --- |".repeat(header.len())));
+    }
+    lines.extend(rows.iter().skip(1).map(format_row));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cmap_parsing() {
+        let cmap = CMap::parse(
+            b"/CIDInit /ProcSet findresource begin
+            12 dict begin
+            begincmap
+            1 begincodespacerange <0000> <FFFF> endcodespacerange
+            2 beginbfchar
+            <0003> <0020>
+            <0010> <00660069>
+            endbfchar
+            2 beginbfrange
+            <0024> <0026> <0041>
+            <0030> <0031> [<00E9> <6771>]
+            endbfrange
+            endcmap",
+        );
+
+        assert_eq!(cmap.code_length(&[0x00, 0x24]), 2);
+        assert_eq!(cmap.lookup(0x0003, 2).as_deref(), Some(" "));
+        assert_eq!(cmap.lookup(0x0010, 2).as_deref(), Some("fi"));
+        assert_eq!(cmap.lookup(0x0026, 2).as_deref(), Some("C"));
+        assert_eq!(cmap.lookup(0x0031, 2).as_deref(), Some("東"));
+        assert_eq!(cmap.lookup(0x0027, 2), None);
+    }
+
+    #[test]
+    fn test_glyph_names() {
+        assert_eq!(glyph_text(b"eacute").as_deref(), Some("é"));
+        assert_eq!(glyph_text(b"quoteright").as_deref(), Some("\u{2019}"));
+        assert_eq!(glyph_text(b"uni0041").as_deref(), Some("A"));
+        assert_eq!(glyph_text(b"f_i").as_deref(), Some("fi"));
+        assert_eq!(glyph_text(b"seven.oldstyle").as_deref(), Some("7"));
+        assert_eq!(glyph_text(b"g123"), None);
+    }
+
+    #[test]
+    fn test_cid_widths() {
+        let doc = Document::with_version("1.5");
+        let widths = cid_widths(
+            &doc,
+            &[
+                Object::Integer(1),
+                Object::Array(vec![Object::Integer(500), Object::Integer(600)]),
+                Object::Integer(10),
+                Object::Integer(12),
+                Object::Integer(700),
+                // Backwards and huge ranges must not stall or blow up
+                Object::Integer(20),
+                Object::Integer(15),
+                Object::Integer(800),
+                Object::Integer(100),
+                Object::Integer(u32::MAX as i64),
+                Object::Integer(900),
+                Object::Integer(u32::MAX as i64),
+                Object::Array(vec![Object::Integer(1), Object::Integer(2)]),
+            ],
+        );
+        assert_eq!(widths[&2], 600.0);
+        assert_eq!(widths[&12], 700.0);
+        assert!(!widths.contains_key(&15));
+        assert_eq!(widths[&(100 + 0xFFFF)], 900.0);
+        assert!(!widths.contains_key(&(101 + 0xFFFF)));
+        assert_eq!(widths[&u32::MAX], 1.0);
+        assert_eq!(widths.len(), 2 + 3 + 0x10000 + 1);
+    }
+
+    fn span(x: f64, y: f64, text: &str) -> TextSpan {
+        TextSpan {
+            x,
+            end_x: x + text.len() as f64 * 5.0,
+            y,
+            size: 10.0,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_lines_and_tables() {
+        let lines = build_lines(vec![
+            span(200.0, 700.0, "Price"),
+            span(50.0, 700.0, "Item"),
+            span(50.0, 688.0, "Apples"),
+            span(200.0, 688.0, "1.50"),
+            span(50.0, 676.0, "Pea"),
+            span(66.0, 676.0, "rs"),
+            span(200.0, 676.0, "2.00"),
+        ]);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[2].text(), "Pears  2.00");
+
+        let tables = find_tables(&lines);
+        assert_eq!(tables.len(), 1);
+        assert_eq!(
+            table_to_markdown(&tables[0]),
+            "| Item | Price |\n| --- | --- |\n| Apples | 1.50 |\n| Pears | 2.00 |"
+        );
+    }
+
+    #[test]
+    fn test_gutter_with_far_away_text() {
+        let mut spans = Vec::new();
+        for i in 0..6 {
+            let y = 700.0 - i as f64 * 12.0;
+            spans.push(span(50.0, y, "left column text"));
+            spans.push(span(300.0, y, "right column text"));
+        }
+        let (start, end) = find_gutter(&build_lines(spans.clone())).unwrap();
+        assert!(start >= 130.0 && end <= 300.0);
+
+        // Text placed absurdly far out must not size the buckets
+        spans.push(span(1e12, 600.0, "stray"));
+        let _ = find_gutter(&build_lines(spans));
+    }
+}
@@ -1,113 +1,163 @@
-use lopdf::{content::Content as PdfContent, Document, Object};
+use lopdf::{Document, Object, ObjectId};
 use mcp_core::{Content, ToolError};
 use std::{fs, path::Path};
 
+use super::pdf_text::{
+    build_lines, find_tables, page_spans, render_layout, render_text, table_to_markdown,
+};
+
+const MAX_SEARCH_RESULTS: usize = 100;
+
+/// Options shared by the pdf_tool operations
+#[derive(Debug, Default)]
+pub struct PdfOptions {
+    /// Page selection such as "1-3,5" or "4-", all pages when unset
+    pub pages: Option<String>,
+    /// Keep the horizontal layout of the page instead of reflowing text
+    pub layout: bool,
+    /// Text to look for in the search operation
+    pub query: Option<String>,
+    pub case_sensitive: bool,
+}
+
+/// Parse a page selection like "1-3,5,8-" into sorted page numbers
+fn parse_pages(spec: &str, page_count: u32) -> Result<Vec<u32>, ToolError> {
+    let invalid = |part: &str| {
+        ToolError::InvalidParameters(format!(
+            "Invalid page selection '{}', the PDF has {} pages. Use ranges like '1-3,5'",
+            part, page_count
+        ))
+    };
+    let mut pages = std::collections::BTreeSet::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.trim(), end.trim()),
+            None => (part, part),
+        };
+        let start: u32 = if start.is_empty() {
+            1
+        } else {
+            start.parse().map_err(|_| invalid(part))?
+        };
+        let end: u32 = if end.is_empty() {
+            page_count
+        } else {
+            end.parse().map_err(|_| invalid(part))?
+        };
+        if start == 0 || start > end || end > page_count {
+            return Err(invalid(part));
+        }
+        pages.extend(start..=end);
+    }
+    if pages.is_empty() {
+        return Err(invalid(spec));
+    }
+    Ok(pages.into_iter().collect())
+}
+
 pub async fn pdf_tool(
     path: &str,
     operation: &str,
+    options: &PdfOptions,
     cache_dir: &Path,
 ) -> Result<Vec<Content>, ToolError> {
     // Open and parse the PDF file
     let doc = Document::load(path)
         .map_err(|e| ToolError::ExecutionError(format!("Failed to open PDF file: {}", e)))?;
 
+    let all_pages = doc.get_pages();
+    let selected: Vec<(u32, ObjectId)> = match &options.pages {
+        Some(spec) => parse_pages(spec, all_pages.len() as u32)?
+            .into_iter()
+            .filter_map(|n| all_pages.get(&n).map(|id| (n, *id)))
+            .collect(),
+        None => all_pages.into_iter().collect(),
+    };
+    let page_lines = || {
+        selected
+            .iter()
+            .map(|(page_num, page_id)| (*page_num, build_lines(page_spans(&doc, *page_id))))
+    };
+
     let result = match operation {
         "extract_text" => {
             let mut text = String::new();
+            let mut found_text = false;
+            for (page_num, lines) in page_lines() {
+                found_text |= !lines.is_empty();
+                let page_text = if options.layout {
+                    render_layout(&lines)
+                } else {
+                    render_text(&lines)
+                };
+                text.push_str(&format!("Page {}:\n{}\n\n", page_num, page_text));
+            }
 
-            // Iterate over each page in the document
-            for (page_num, page_id) in doc.get_pages() {
-                text.push_str(&format!("Page {}:\n", page_num));
-
-                // Try to get text from page contents
-                if let Ok(page_obj) = doc.get_object(page_id) {
-                    if let Ok(page_dict) = page_obj.as_dict() {
-                        // Try to get text from Contents stream
-                        if let Ok(contents) =
-                            page_dict.get(b"Contents").and_then(|c| c.as_reference())
-                        {
-                            if let Ok(content_obj) = doc.get_object(contents) {
-                                if let Ok(stream) = content_obj.as_stream() {
-                                    if let Ok(content_data) = stream.get_plain_content() {
-                                        if let Ok(content) = PdfContent::decode(&content_data) {
-                                            // Process each operation in the content stream
-                                            for operation in content.operations {
-                                                match operation.operator.as_ref() {
-                                                    // "Tj" operator: show text
-                                                    "Tj" => {
-                                                        for operand in operation.operands {
-                                                            if let Object::String(ref bytes, _) =
-                                                                operand
-                                                            {
-                                                                if let Ok(s) =
-                                                                    std::str::from_utf8(bytes)
-                                                                {
-                                                                    text.push_str(s);
-                                                                }
-                                                            }
-                                                        }
-                                                        text.push(' ');
-                                                    }
-                                                    // "TJ" operator: show text with positioning
-                                                    "TJ" => {
-                                                        if let Some(Object::Array(ref arr)) =
-                                                            operation.operands.first()
-                                                        {
-                                                            let mut last_was_text = false;
-                                                            for element in arr {
-                                                                match element {
-                                                                    Object::String(
-                                                                        ref bytes,
-                                                                        _,
-                                                                    ) => {
-                                                                        if let Ok(s) =
-                                                                            std::str::from_utf8(
-                                                                                bytes,
-                                                                            )
-                                                                        {
-                                                                            if last_was_text {
-                                                                                text.push(' ');
-                                                                            }
-                                                                            text.push_str(s);
-                                                                            last_was_text = true;
-                                                                        }
-                                                                    }
-                                                                    Object::Integer(offset) => {
-                                                                        // Large negative offsets often indicate word spacing
-                                                                        if *offset < -100 {
-                                                                            text.push(' ');
-                                                                            last_was_text = false;
-                                                                        }
-                                                                    }
-                                                                    Object::Real(offset) => {
-                                                                        if *offset < -100.0 {
-                                                                            text.push(' ');
-                                                                            last_was_text = false;
-                                                                        }
-                                                                    }
-                                                                    _ => {}
-                                                                }
-                                                            }
-                                                            text.push(' ');
-                                                        }
-                                                    }
-                                                    _ => (), // Ignore other operators
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
+            if !found_text {
+                "No text found in PDF".to_string()
+            } else {
+                format!("Extracted text from PDF:\n\n{}", text.trim_end())
+            }
+        }
+
+        "extract_tables" => {
+            let mut tables = Vec::new();
+            for (page_num, lines) in page_lines() {
+                for (index, rows) in find_tables(&lines).iter().enumerate() {
+                    tables.push(format!(
+                        "Page {}, table {}:\n{}",
+                        page_num,
+                        index + 1,
+                        table_to_markdown(rows)
+                    ));
+                }
+            }
+
+            if tables.is_empty() {
+                "No tables found in PDF".to_string()
+            } else {
+                format!("Found {} tables:\n\n{}", tables.len(), tables.join("\n\n"))
+            }
+        }
+
+        "search" => {
+            let query = options.query.as_deref().filter(|q| !q.is_empty()).ok_or_else(|| {
+                ToolError::InvalidParameters("Missing 'query' parameter for search".into())
+            })?;
+            let needle = if options.case_sensitive {
+                query.to_string()
+            } else {
+                query.to_lowercase()
+            };
+
+            let mut matches = Vec::new();
+            for (page_num, lines) in page_lines() {
+                for line in &lines {
+                    let text = line.text();
+                    let haystack = if options.case_sensitive {
+                        text.clone()
+                    } else {
+                        text.to_lowercase()
+                    };
+                    if haystack.contains(&needle) {
+                        matches.push(format!("Page {}: {}", page_num, text));
                     }
                 }
-                text.push('\n');
             }
 
-            if text.trim().is_empty() {
-                "No text found in PDF".to_string()
+            if matches.is_empty() {
+                format!("No matches found for '{}'", query)
             } else {
-                format!("Extracted text from PDF:\n\n{}", text)
+                let total = matches.len();
+                matches.truncate(MAX_SEARCH_RESULTS);
+                let mut result = format!("Found {} matches for '{}':\n{}", total, query, matches.join("\n"));
+                if total > MAX_SEARCH_RESULTS {
+                    result.push_str(&format!(
+                        "\n[{} more matches not shown, narrow the search with 'pages']",
+                        total - MAX_SEARCH_RESULTS
+                    ));
+                }
+                result
             }
         }
 
@@ -164,7 +214,7 @@ pub async fn pdf_tool(
             }
 
             // Process each page
-            for (page_num, page_id) in doc.get_pages() {
+            for &(page_num, page_id) in &selected {
                 let page = doc.get_object(page_id).map_err(|e| {
                     ToolError::ExecutionError(format!("Failed to get page {}: {}", page_num, e))
                 })?;
@@ -313,7 +363,7 @@ pub async fn pdf_tool(
 
         _ => {
             return Err(ToolError::InvalidParameters(format!(
-                "Invalid operation: {}. Valid operations are: 'extract_text', 'extract_images'",
+                "Invalid operation: {}. Valid operations are: 'extract_text', 'extract_tables', 'search', 'extract_images'",
                 operation
             )))
         }
@@ -335,7 +385,13 @@ mod tests {
 
         println!("Testing text extraction from: {}", test_pdf_path.display());
 
-        let result = pdf_tool(test_pdf_path.to_str().unwrap(), "extract_text", &cache_dir).await;
+        let result = pdf_tool(
+            test_pdf_path.to_str().unwrap(),
+            "extract_text",
+            &PdfOptions::default(),
+            &cache_dir,
+        )
+        .await;
 
         assert!(result.is_ok(), "PDF text extraction should succeed");
         let content = result.unwrap();
@@ -361,6 +417,7 @@ mod tests {
         let result = pdf_tool(
             test_pdf_path.to_str().unwrap(),
             "extract_images",
+            &PdfOptions::default(),
             &cache_dir,
         )
         .await;
@@ -399,7 +456,13 @@ mod tests {
     #[tokio::test]
     async fn test_pdf_invalid_path() {
         let cache_dir = tempfile::tempdir().unwrap().into_path();
-        let result = pdf_tool("nonexistent.pdf", "extract_text", &cache_dir).await;
+        let result = pdf_tool(
+            "nonexistent.pdf",
+            "extract_text",
+            &PdfOptions::default(),
+            &cache_dir,
+        )
+        .await;
 
         assert!(result.is_err(), "Should fail with invalid path");
     }
@@ -413,10 +476,197 @@ mod tests {
         let result = pdf_tool(
             test_pdf_path.to_str().unwrap(),
             "invalid_operation",
+            &PdfOptions::default(),
             &cache_dir,
         )
         .await;
 
         assert!(result.is_err(), "Should fail with invalid operation");
     }
+
+    #[tokio::test]
+    async fn test_pdf_font_encodings() {
+        let test_pdf_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/computercontroller/tests/data/cid_fonts.pdf");
+        let cache_dir = tempfile::tempdir().unwrap().into_path();
+
+        let result = pdf_tool(
+            test_pdf_path.to_str().unwrap(),
+            "extract_text",
+            &PdfOptions::default(),
+            &cache_dir,
+        )
+        .await;
+
+        assert!(result.is_ok(), "PDF text extraction should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+
+        // Page 1 has a CID font with a ToUnicode map and a simple font with /Differences,
+        // drawn from two content streams
+        assert!(
+            text.contains("Café 東京"),
+            "Should decode the CID font through its ToUnicode map"
+        );
+        assert!(
+            text.contains("find it\u{2019}s here"),
+            "Should decode the /Differences glyph names"
+        );
+        assert!(
+            text.contains("Hello World"),
+            "Should read every content stream"
+        );
+
+        // Page 2 inherits its resources and draws text through a form XObject
+        assert!(
+            text.contains("Page 2:\nSecond page with the keyword needle"),
+            "Should use inherited resources"
+        );
+        assert!(
+            text.contains("Text inside a form"),
+            "Should contain text from the form XObject"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pdf_page_ranges_and_search() {
+        let test_pdf_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/computercontroller/tests/data/cid_fonts.pdf");
+        let cache_dir = tempfile::tempdir().unwrap().into_path();
+
+        let options = PdfOptions {
+            pages: Some("2".to_string()),
+            ..Default::default()
+        };
+        let result = pdf_tool(
+            test_pdf_path.to_str().unwrap(),
+            "extract_text",
+            &options,
+            &cache_dir,
+        )
+        .await;
+
+        assert!(
+            result.is_ok(),
+            "PDF text extraction of a page range should succeed"
+        );
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(
+            !text.contains("Page 1:"),
+            "Should skip pages outside the range"
+        );
+        assert!(
+            text.contains("Page 2:"),
+            "Should contain the requested page"
+        );
+
+        let options = PdfOptions {
+            query: Some("NEEDLE".to_string()),
+            ..Default::default()
+        };
+        let result = pdf_tool(
+            test_pdf_path.to_str().unwrap(),
+            "search",
+            &options,
+            &cache_dir,
+        )
+        .await;
+
+        assert!(result.is_ok(), "PDF search should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(
+            text.starts_with("Found 1 matches"),
+            "Should match regardless of case"
+        );
+        assert!(
+            text.contains("Page 2: Second page with the keyword needle"),
+            "Should show the matching line with its page"
+        );
+
+        for pages in ["3", "2-1", "0", "x"] {
+            let options = PdfOptions {
+                pages: Some(pages.to_string()),
+                ..Default::default()
+            };
+            let result = pdf_tool(
+                test_pdf_path.to_str().unwrap(),
+                "extract_text",
+                &options,
+                &cache_dir,
+            )
+            .await;
+            assert!(result.is_err(), "Should reject page range '{}'", pages);
+        }
+        assert_eq!(parse_pages("1-2, 4-", 5).unwrap(), vec![1, 2, 4, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_pdf_reading_order_and_tables() {
+        let test_pdf_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/computercontroller/tests/data/layout.pdf");
+        let cache_dir = tempfile::tempdir().unwrap().into_path();
+
+        let result = pdf_tool(
+            test_pdf_path.to_str().unwrap(),
+            "extract_text",
+            &PdfOptions::default(),
+            &cache_dir,
+        )
+        .await;
+
+        assert!(result.is_ok(), "PDF text extraction should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+
+        // The title comes first, then the whole left column, then the right column
+        let title = text.find("Quarterly Report").unwrap();
+        let left_end = text.find("anything in the second column.").unwrap();
+        let right_start = text.find("The second column follows after").unwrap();
+        let table = text.find("Region").unwrap();
+        assert!(
+            title < left_end && left_end < right_start && right_start < table,
+            "Should read the columns in order"
+        );
+
+        let result = pdf_tool(
+            test_pdf_path.to_str().unwrap(),
+            "extract_tables",
+            &PdfOptions::default(),
+            &cache_dir,
+        )
+        .await;
+
+        assert!(result.is_ok(), "PDF table extraction should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        assert!(
+            text.contains(
+                "| Region | Revenue | Growth |\n| --- | --- | --- |\n| North | 1200 | 4% |"
+            ),
+            "Should contain the table as markdown"
+        );
+
+        let options = PdfOptions {
+            layout: true,
+            ..Default::default()
+        };
+        let result = pdf_tool(
+            test_pdf_path.to_str().unwrap(),
+            "extract_text",
+            &options,
+            &cache_dir,
+        )
+        .await;
+
+        assert!(result.is_ok(), "PDF layout extraction should succeed");
+        let content = result.unwrap();
+        let text = content[0].as_text().unwrap();
+        let row = text.lines().find(|l| l.starts_with("North")).unwrap();
+        assert!(
+            row.find("1200").unwrap() > 20,
+            "Should keep the table columns apart"
+        );
+    }
 }
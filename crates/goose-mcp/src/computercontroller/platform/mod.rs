@@ -1,5 +1,6 @@
 mod linux;
 mod macos;
+pub mod virtual_display;
 mod windows;
 
 #[cfg(target_os = "windows")]
@@ -0,0 +1,574 @@
+//! A virtual display owned by goose, for UI automation without a desktop session.
+//!
+//! Two backends are supported:
+//! - `xvfb`: an Xvfb server, driven with `xdotool` and captured with `xwd`
+//! - `wayland`: a headless sway session, driven with `swaymsg` and `wtype` and captured
+//!   with `grim`
+//!
+//! Applications launched on the display, and the display server itself, are stopped when the
+//! display is dropped. Setting `GOOSE_VIRTUAL_DISPLAY` to a backend name starts that display
+//! on first use instead of requiring an explicit start.
+
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+
+/// How long to wait for a display server to accept clients
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Xvfb,
+    Wayland,
+}
+
+impl Backend {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "xvfb" | "x11" => Ok(Backend::Xvfb),
+            "wayland" | "sway" => Ok(Backend::Wayland),
+            other => Err(format!(
+                "Unknown virtual display backend '{}'. Valid options are: xvfb, wayland",
+                other
+            )),
+        }
+    }
+
+    /// The backend to start on first use, from `GOOSE_VIRTUAL_DISPLAY`
+    pub fn from_env() -> Option<Self> {
+        let name = std::env::var("GOOSE_VIRTUAL_DISPLAY").ok()?;
+        match Self::parse(&name) {
+            Ok(backend) => Some(backend),
+            Err(e) => {
+                tracing::warn!("Ignoring GOOSE_VIRTUAL_DISPLAY: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Programs the backend needs, with the packages that usually provide them
+    fn dependencies(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Backend::Xvfb => &[
+                ("Xvfb", "xvfb"),
+                ("xdotool", "xdotool"),
+                ("xwd", "x11-apps"),
+            ],
+            Backend::Wayland => &[("sway", "sway"), ("wtype", "wtype"), ("grim", "grim")],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+impl MouseButton {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "left" => Ok(MouseButton::Left),
+            "middle" => Ok(MouseButton::Middle),
+            "right" => Ok(MouseButton::Right),
+            other => Err(format!(
+                "Unknown mouse button '{}'. Valid options are: left, middle, right",
+                other
+            )),
+        }
+    }
+
+    fn number(&self) -> u8 {
+        match self {
+            MouseButton::Left => 1,
+            MouseButton::Middle => 2,
+            MouseButton::Right => 3,
+        }
+    }
+}
+
+pub struct VirtualDisplay {
+    backend: Backend,
+    width: u32,
+    height: u32,
+    /// Environment that points clients at this display
+    env: Vec<(String, String)>,
+    server: Child,
+    apps: Vec<(String, Child)>,
+    /// The private runtime directory of a Wayland session
+    _runtime_dir: Option<tempfile::TempDir>,
+}
+
+impl VirtualDisplay {
+    pub async fn start(backend: Backend, width: u32, height: u32) -> Result<Self, String> {
+        check_dependencies(backend)?;
+        match backend {
+            Backend::Xvfb => Self::start_xvfb(width, height).await,
+            Backend::Wayland => Self::start_sway(width, height).await,
+        }
+    }
+
+    async fn start_xvfb(width: u32, height: u32) -> Result<Self, String> {
+        let number = free_x_display().ok_or("No free X display number between :99 and :199")?;
+        let display = format!(":{}", number);
+        let socket = PathBuf::from(format!("/tmp/.X11-unix/X{}", number));
+
+        let mut server = Command::new("Xvfb")
+            .arg(&display)
+            .args(["-screen", "0", &format!("{}x{}x24", width, height)])
+            .args(["-nolisten", "tcp"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to start Xvfb: {}", e))?;
+
+        wait_for(&mut server, "Xvfb", || socket.exists()).await?;
+
+        Ok(Self {
+            backend: Backend::Xvfb,
+            width,
+            height,
+            env: vec![("DISPLAY".to_string(), display)],
+            server,
+            apps: Vec::new(),
+            _runtime_dir: None,
+        })
+    }
+
+    async fn start_sway(width: u32, height: u32) -> Result<Self, String> {
+        // A private runtime directory makes the session's sockets easy to find
+        let runtime_dir = tempfile::Builder::new()
+            .prefix("goose-wayland-")
+            .tempdir()
+            .map_err(|e| format!("Failed to create a runtime directory: {}", e))?;
+        let config = runtime_dir.path().join("sway.config");
+        std::fs::write(
+            &config,
+            format!("output HEADLESS-1 resolution {}x{}\n", width, height),
+        )
+        .map_err(|e| format!("Failed to write the sway config: {}", e))?;
+
+        let mut server = Command::new("sway")
+            .arg("--config")
+            .arg(&config)
+            .env("XDG_RUNTIME_DIR", runtime_dir.path())
+            .env("WLR_BACKENDS", "headless")
+            .env("WLR_RENDERER", "pixman")
+            .env("WLR_LIBINPUT_NO_DEVICES", "1")
+            .env_remove("WAYLAND_DISPLAY")
+            .env_remove("DISPLAY")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to start sway: {}", e))?;
+
+        let find = |pattern: fn(&str) -> bool| {
+            std::fs::read_dir(runtime_dir.path())
+                .ok()?
+                .flatten()
+                .filter_map(|e| e.file_name().into_string().ok())
+                .find(|name| pattern(name))
+        };
+        let is_wayland = |name: &str| name.starts_with("wayland-") && !name.ends_with(".lock");
+        let is_ipc = |name: &str| name.starts_with("sway-ipc.") && name.ends_with(".sock");
+        wait_for(&mut server, "sway", || {
+            find(is_wayland).is_some() && find(is_ipc).is_some()
+        })
+        .await?;
+
+        let runtime = runtime_dir.path().to_string_lossy().into_owned();
+        let wayland_display = find(is_wayland).unwrap_or_default();
+        let swaysock = runtime_dir
+            .path()
+            .join(find(is_ipc).unwrap_or_default())
+            .to_string_lossy()
+            .into_owned();
+
+        Ok(Self {
+            backend: Backend::Wayland,
+            width,
+            height,
+            env: vec![
+                ("XDG_RUNTIME_DIR".to_string(), runtime),
+                ("WAYLAND_DISPLAY".to_string(), wayland_display),
+                ("SWAYSOCK".to_string(), swaysock),
+            ],
+            server,
+            apps: Vec::new(),
+            _runtime_dir: Some(runtime_dir),
+        })
+    }
+
+    /// Environment variables that make a program use this display
+    pub fn env(&self) -> Vec<(String, String)> {
+        let mut env = self.env.clone();
+        // Clients of one display server should not pick up the other
+        match self.backend {
+            Backend::Xvfb => env.push(("WAYLAND_DISPLAY".to_string(), String::new())),
+            Backend::Wayland => env.push(("DISPLAY".to_string(), String::new())),
+        }
+        env
+    }
+
+    pub fn describe(&mut self) -> String {
+        let name = match self.backend {
+            Backend::Xvfb => format!("Xvfb display {}", self.env[0].1),
+            Backend::Wayland => format!("headless sway display {}", self.env[1].1),
+        };
+        let mut description = format!("{} at {}x{}", name, self.width, self.height);
+        if self.server.try_wait().ok().flatten().is_some() {
+            description.push_str(", but the display server has exited");
+        }
+
+        self.apps
+            .retain_mut(|(_, child)| child.try_wait().ok().flatten().is_none());
+        if self.apps.is_empty() {
+            description.push_str("\nNo applications running.");
+        } else {
+            description.push_str("\nRunning applications:");
+            for (command, child) in &self.apps {
+                description.push_str(&format!(
+                    "\n- {} (pid {})",
+                    command,
+                    child.id().unwrap_or_default()
+                ));
+            }
+        }
+        description
+    }
+
+    /// Start an application on the display; it is stopped with the display
+    pub fn launch(&mut self, command: &str) -> Result<u32, String> {
+        let child = Command::new("bash")
+            .args(["-c", command])
+            .envs(self.env())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to launch '{}': {}", command, e))?;
+        let pid = child.id().unwrap_or_default();
+        self.apps.push((command.to_string(), child));
+        Ok(pid)
+    }
+
+    pub async fn click(
+        &self,
+        x: u32,
+        y: u32,
+        button: MouseButton,
+        double: bool,
+    ) -> Result<(), String> {
+        if x >= self.width || y >= self.height {
+            return Err(format!(
+                "({}, {}) is outside the {}x{} display",
+                x, y, self.width, self.height
+            ));
+        }
+        match self.backend {
+            Backend::Xvfb => {
+                self.run("xdotool", &xdotool_click_args(x, y, button, double))
+                    .await
+            }
+            Backend::Wayland => {
+                self.run(
+                    "swaymsg",
+                    &["seat", "-", "cursor", "set", &x.to_string(), &y.to_string()],
+                )
+                .await?;
+                let button = format!("button{}", button.number());
+                for _ in 0..if double { 2 } else { 1 } {
+                    self.run("swaymsg", &["seat", "-", "cursor", "press", &button])
+                        .await?;
+                    self.run("swaymsg", &["seat", "-", "cursor", "release", &button])
+                        .await?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub async fn type_text(&self, text: &str) -> Result<(), String> {
+        match self.backend {
+            Backend::Xvfb => {
+                self.run("xdotool", &["type", "--delay", "12", "--", text])
+                    .await
+            }
+            Backend::Wayland => self.run("wtype", &["--", text]).await,
+        }
+    }
+
+    /// Press a key or combination in xdotool syntax, such as `Return` or `ctrl+shift+t`
+    pub async fn key(&self, keys: &str) -> Result<(), String> {
+        match self.backend {
+            Backend::Xvfb => self.run("xdotool", &["key", "--", keys]).await,
+            Backend::Wayland => {
+                let args = wtype_key_args(keys)?;
+                let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                self.run("wtype", &args).await
+            }
+        }
+    }
+
+    /// Capture the whole display as PNG
+    pub async fn screenshot(&self) -> Result<Vec<u8>, String> {
+        match self.backend {
+            Backend::Xvfb => {
+                let xwd = self
+                    .output("xwd", &["-root", "-silent", "-display", &self.env[0].1])
+                    .await?;
+                xwd_to_png(&xwd)
+            }
+            Backend::Wayland => self.output("grim", &["-"]).await,
+        }
+    }
+
+    async fn run<S: AsRef<str>>(&self, program: &str, args: &[S]) -> Result<(), String> {
+        self.output(program, args).await.map(|_| ())
+    }
+
+    async fn output<S: AsRef<str>>(&self, program: &str, args: &[S]) -> Result<Vec<u8>, String> {
+        let output = Command::new(program)
+            .args(args.iter().map(AsRef::as_ref))
+            .envs(self.env())
+            .output()
+            .await
+            .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "{} failed: {}",
+                program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(output.stdout)
+    }
+}
+
+fn check_dependencies(backend: Backend) -> Result<(), String> {
+    let missing: Vec<String> = backend
+        .dependencies()
+        .iter()
+        .filter(|(program, _)| which(program).is_none())
+        .map(|(program, package)| format!("{} (package {})", program, package))
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "The virtual display needs programs that are not installed: {}",
+            missing.join(", ")
+        ))
+    }
+}
+
+fn which(program: &str) -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+/// The first X display number with neither a lock file nor a socket
+fn free_x_display() -> Option<u32> {
+    (99..200).find(|n| {
+        !PathBuf::from(format!("/tmp/.X{}-lock", n)).exists()
+            && !PathBuf::from(format!("/tmp/.X11-unix/X{}", n)).exists()
+    })
+}
+
+/// Wait until `ready` holds, failing early if the server exits
+async fn wait_for(
+    server: &mut Child,
+    name: &str,
+    mut ready: impl FnMut() -> bool,
+) -> Result<(), String> {
+    let deadline = tokio::time::Instant::now() + STARTUP_TIMEOUT;
+    while !ready() {
+        if let Ok(Some(status)) = server.try_wait() {
+            return Err(format!("{} exited during startup with {}", name, status));
+        }
+        if tokio::time::Instant::now() > deadline {
+            return Err(format!(
+                "{} did not start within {} seconds",
+                name,
+                STARTUP_TIMEOUT.as_secs()
+            ));
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    Ok(())
+}
+
+fn xdotool_click_args(x: u32, y: u32, button: MouseButton, double: bool) -> Vec<String> {
+    let mut args = vec![
+        "mousemove".to_string(),
+        x.to_string(),
+        y.to_string(),
+        "click".to_string(),
+    ];
+    if double {
+        args.extend(["--repeat".to_string(), "2".to_string()]);
+    }
+    args.push(button.number().to_string());
+    args
+}
+
+/// Translate an xdotool style combination like `ctrl+shift+t` into wtype arguments
+fn wtype_key_args(keys: &str) -> Result<Vec<String>, String> {
+    let parts: Vec<&str> = keys.split('+').map(str::trim).collect();
+    let (key, modifiers) = parts
+        .split_last()
+        .filter(|(key, _)| !key.is_empty())
+        .ok_or_else(|| format!("Invalid key combination '{}'", keys))?;
+
+    let modifiers = modifiers
+        .iter()
+        .map(|m| match m.to_lowercase().as_str() {
+            "ctrl" | "control" => Ok("ctrl"),
+            "shift" => Ok("shift"),
+            "alt" => Ok("alt"),
+            "altgr" => Ok("altgr"),
+            "super" | "logo" | "win" | "meta" => Ok("logo"),
+            other => Err(format!("Unknown modifier '{}' in '{}'", other, keys)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut args = Vec::new();
+    for modifier in &modifiers {
+        args.extend(["-M".to_string(), modifier.to_string()]);
+    }
+    args.extend(["-k".to_string(), key.to_string()]);
+    for modifier in modifiers.iter().rev() {
+        args.extend(["-m".to_string(), modifier.to_string()]);
+    }
+    Ok(args)
+}
+
+/// Convert an X window dump of a TrueColor display to PNG
+fn xwd_to_png(data: &[u8]) -> Result<Vec<u8>, String> {
+    const HEADER_FIELDS: usize = 25;
+    if data.len() < HEADER_FIELDS * 4 {
+        return Err("The window dump is too short".to_string());
+    }
+    // The header is always big endian
+    let field = |i: usize| u32::from_be_bytes(data[i * 4..i * 4 + 4].try_into().unwrap());
+    let header_size = field(0) as usize;
+    let (pixmap_format, width, height) = (field(2), field(4), field(5));
+    let lsb_first = field(7) == 0;
+    let bits_per_pixel = field(11);
+    let bytes_per_line = field(12) as usize;
+    let masks = [field(14), field(15), field(16)];
+    let colormap_entries = field(19) as usize;
+
+    if pixmap_format != 2 || !matches!(bits_per_pixel, 16 | 24 | 32) {
+        return Err(format!(
+            "Unsupported window dump: format {}, {} bits per pixel",
+            pixmap_format, bits_per_pixel
+        ));
+    }
+    let bytes_per_pixel = bits_per_pixel as usize / 8;
+    let pixels_start = header_size + colormap_entries * 12;
+    let needed = pixels_start + bytes_per_line * height as usize;
+    if data.len() < needed || bytes_per_line < width as usize * bytes_per_pixel {
+        return Err("The window dump is truncated".to_string());
+    }
+
+    let channel = |pixel: u32, mask: u32| -> u8 {
+        if mask == 0 {
+            return 0;
+        }
+        let value = (pixel & mask) >> mask.trailing_zeros();
+        let max = mask >> mask.trailing_zeros();
+        (value * 255 / max) as u8
+    };
+
+    let mut image = image::RgbImage::new(width, height);
+    for y in 0..height as usize {
+        let row = &data[pixels_start + y * bytes_per_line..];
+        for x in 0..width as usize {
+            let bytes = &row[x * bytes_per_pixel..(x + 1) * bytes_per_pixel];
+            let pixel = if lsb_first {
+                bytes.iter().rev().fold(0u32, |p, b| (p << 8) | *b as u32)
+            } else {
+                bytes.iter().fold(0u32, |p, b| (p << 8) | *b as u32)
+            };
+            image.put_pixel(
+                x as u32,
+                y as u32,
+                image::Rgb([
+                    channel(pixel, masks[0]),
+                    channel(pixel, masks[1]),
+                    channel(pixel, masks[2]),
+                ]),
+            );
+        }
+    }
+
+    let mut png = Vec::new();
+    image::DynamicImage::ImageRgb8(image)
+        .write_to(
+            &mut std::io::Cursor::new(&mut png),
+            image::ImageOutputFormat::Png,
+        )
+        .map_err(|e| format!("Failed to encode the screenshot: {}", e))?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_arguments() {
+        assert_eq!(
+            xdotool_click_args(10, 20, MouseButton::Right, true),
+            vec!["mousemove", "10", "20", "click", "--repeat", "2", "3"]
+        );
+        assert_eq!(
+            wtype_key_args("ctrl+shift+t").unwrap(),
+            vec!["-M", "ctrl", "-M", "shift", "-k", "t", "-m", "shift", "-m", "ctrl"]
+        );
+        assert_eq!(wtype_key_args("Return").unwrap(), vec!["-k", "Return"]);
+        assert!(wtype_key_args("hyper+x").is_err());
+        assert!(wtype_key_args("ctrl+").is_err());
+        assert!(Backend::parse("xvfb").is_ok() && Backend::parse("vnc").is_err());
+    }
+
+    #[test]
+    fn test_xwd_to_png() {
+        // A 2x1 dump with 32 bit little endian pixels: red, then blue
+        let mut header = [0u32; 25];
+        header[0] = 100 + 4; // header size, including a 4 byte window name
+        header[1] = 7;
+        header[2] = 2;
+        header[3] = 24;
+        header[4] = 2;
+        header[5] = 1;
+        header[7] = 0;
+        header[11] = 32;
+        header[12] = 8;
+        header[14] = 0xff0000;
+        header[15] = 0x00ff00;
+        header[16] = 0x0000ff;
+        header[19] = 1;
+
+        let mut data: Vec<u8> = header.iter().flat_map(|f| f.to_be_bytes()).collect();
+        data.extend(b"xwd\0");
+        data.extend([0u8; 12]); // one colormap entry
+        data.extend(0x00ff0000u32.to_le_bytes());
+        data.extend(0x000000ffu32.to_le_bytes());
+
+        let png = xwd_to_png(&data).unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (2, 1));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0]);
+        assert_eq!(image.get_pixel(1, 0).0, [0, 0, 255]);
+
+        assert!(xwd_to_png(&data[..50]).is_err());
+    }
+}
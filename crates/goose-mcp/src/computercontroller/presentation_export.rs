@@ -0,0 +1,1267 @@
+//! Writes HTML decks out as PPTX and PDF files.
+//!
+//! Both formats share one layout on a 16:9 page measured in points, so a slide looks the
+//! same in either. PDFs are drawn directly with the standard Helvetica and Courier fonts,
+//! which means no browser or office suite is needed to export.
+
+use super::presentation_tool::{plain_text, Block, Slide};
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use lopdf::content::{Content as PdfContent, Operation};
+use lopdf::{dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use mcp_core::ToolError;
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+const PAGE_WIDTH: f64 = 960.0;
+const PAGE_HEIGHT: f64 = 540.0;
+const MARGIN: f64 = 48.0;
+const GAP: f64 = 14.0;
+const TITLE_SIZE: f64 = 32.0;
+const BODY_SIZE: f64 = 20.0;
+const CODE_SIZE: f64 = 14.0;
+const CAPTION_SIZE: f64 = 14.0;
+const NOTES_SIZE: f64 = 14.0;
+const BULLET_INDENT: f64 = 28.0;
+
+/// Points to EMU, the unit used by PowerPoint
+const EMU: f64 = 12700.0;
+
+const TITLE_COLOR: (f64, f64, f64) = (0.122, 0.161, 0.216);
+const TEXT_COLOR: (f64, f64, f64) = (0.216, 0.255, 0.32);
+const ACCENT_COLOR: (f64, f64, f64) = (0.129, 0.588, 0.953);
+const CODE_BACKGROUND: (f64, f64, f64) = (0.953, 0.957, 0.965);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Font {
+    Regular,
+    Bold,
+    Mono,
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Mono => "F3",
+        }
+    }
+}
+
+/// Glyph widths of the standard fonts for the printable ASCII range, in 1/1000 em
+#[rustfmt::skip]
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+#[rustfmt::skip]
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+fn char_width(c: char, font: Font) -> f64 {
+    let width = match font {
+        Font::Mono => 600,
+        Font::Regular | Font::Bold => {
+            let table = if font == Font::Bold {
+                &HELVETICA_BOLD_WIDTHS
+            } else {
+                &HELVETICA_WIDTHS
+            };
+            match c as u32 {
+                code @ 32..=126 => table[(code - 32) as usize],
+                _ => 556,
+            }
+        }
+    };
+    width as f64 / 1000.0
+}
+
+fn text_width(text: &str, size: f64, font: Font) -> f64 {
+    text.chars().map(|c| char_width(c, font)).sum::<f64>() * size
+}
+
+/// Breaks text into lines no wider than `width`, splitting words only when they do not fit
+/// on a line of their own
+fn wrap(text: &str, width: f64, size: f64, font: Font) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", line, word)
+        };
+        if text_width(&candidate, size, font) <= width {
+            line = candidate;
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        for c in word.chars() {
+            if !line.is_empty() && text_width(&format!("{}{}", line, c), size, font) > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            line.push(c);
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// A box on the page, with y measured from the top
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+#[derive(Debug)]
+struct Paragraph {
+    bullet: Option<usize>,
+    text: String,
+    lines: Vec<String>,
+}
+
+enum Element {
+    Title {
+        frame: Frame,
+        text: String,
+        lines: Vec<String>,
+    },
+    Accent(Frame),
+    Paragraphs {
+        frame: Frame,
+        size: f64,
+        centered: bool,
+        items: Vec<Paragraph>,
+    },
+    Code {
+        frame: Frame,
+        lines: Vec<String>,
+    },
+    Image {
+        frame: Frame,
+        image: DynamicImage,
+    },
+}
+
+fn line_height(size: f64) -> f64 {
+    size * 1.3
+}
+
+fn paragraphs(
+    items: &[(Option<usize>, String)],
+    x: f64,
+    y: f64,
+    width: f64,
+    size: f64,
+    centered: bool,
+) -> Element {
+    let items: Vec<Paragraph> = items
+        .iter()
+        .map(|(bullet, text)| {
+            let indent = bullet.map_or(0.0, |level| (level + 1) as f64 * BULLET_INDENT);
+            Paragraph {
+                bullet: *bullet,
+                text: text.clone(),
+                lines: wrap(text, width - indent, size, Font::Regular),
+            }
+        })
+        .collect();
+    let lines: usize = items.iter().map(|p| p.lines.len()).sum();
+    let spacing = items.len().saturating_sub(1) as f64 * size * 0.4;
+    Element::Paragraphs {
+        frame: Frame {
+            x,
+            y,
+            w: width,
+            h: lines as f64 * line_height(size) + spacing,
+        },
+        size,
+        centered,
+        items,
+    }
+}
+
+fn resolve_image(src: &str, base_dir: &Path) -> Result<DynamicImage, String> {
+    if src.starts_with("http://") || src.starts_with("https://") || src.starts_with("data:") {
+        return Err(format!(
+            "image {} is not a local file and was left out",
+            src
+        ));
+    }
+    let path = Path::new(src.strip_prefix("file://").unwrap_or(src));
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base_dir.join(path)
+    };
+    image::open(&path).map_err(|e| format!("could not load image {}: {}", path.display(), e))
+}
+
+/// Places the parts of a slide on the page. Problems that do not stop the export, such as
+/// missing images or text running off the page, are added to `warnings`.
+fn layout(
+    slide: &Slide,
+    number: usize,
+    base_dir: &Path,
+    warnings: &mut Vec<String>,
+) -> Vec<Element> {
+    let width = PAGE_WIDTH - 2.0 * MARGIN;
+    let bottom = PAGE_HEIGHT - MARGIN;
+    let mut elements = Vec::new();
+    let mut y = MARGIN;
+
+    let title = plain_text(&slide.title);
+    if !title.is_empty() {
+        let lines = wrap(&title, width, TITLE_SIZE, Font::Bold);
+        let h = lines.len() as f64 * line_height(TITLE_SIZE);
+        elements.push(Element::Title {
+            frame: Frame {
+                x: MARGIN,
+                y,
+                w: width,
+                h,
+            },
+            text: title,
+            lines,
+        });
+        y += h + 6.0;
+        elements.push(Element::Accent(Frame {
+            x: MARGIN,
+            y,
+            w: 80.0,
+            h: 4.0,
+        }));
+        y += 4.0 + GAP * 1.5;
+    }
+
+    let mut pending: Vec<(Option<usize>, String)> = Vec::new();
+    let flush =
+        |pending: &mut Vec<(Option<usize>, String)>, y: &mut f64, elements: &mut Vec<Element>| {
+            if pending.is_empty() {
+                return;
+            }
+            let element = paragraphs(pending, MARGIN, *y, width, BODY_SIZE, false);
+            if let Element::Paragraphs { frame, .. } = &element {
+                *y += frame.h + GAP;
+            }
+            elements.push(element);
+            pending.clear();
+        };
+
+    let images = slide
+        .blocks
+        .iter()
+        .filter(|b| matches!(b, Block::Image { .. }))
+        .count();
+    for (i, block) in slide.blocks.iter().enumerate() {
+        match block {
+            Block::Text(text) => pending.push((None, text.clone())),
+            Block::Bullet { level, text } => pending.push((Some(*level), text.clone())),
+            Block::Code { code, .. } => {
+                flush(&mut pending, &mut y, &mut elements);
+                let columns = ((width - 20.0) / (CODE_SIZE * 0.6)).floor() as usize;
+                let lines: Vec<String> = code
+                    .lines()
+                    .flat_map(|line| {
+                        let chars: Vec<char> = line.replace('\t', "    ").chars().collect();
+                        if chars.is_empty() {
+                            return vec![String::new()];
+                        }
+                        chars
+                            .chunks(columns.max(1))
+                            .map(|c| c.iter().collect())
+                            .collect::<Vec<String>>()
+                    })
+                    .collect();
+                let h = lines.len() as f64 * line_height(CODE_SIZE) + 20.0;
+                elements.push(Element::Code {
+                    frame: Frame {
+                        x: MARGIN,
+                        y,
+                        w: width,
+                        h,
+                    },
+                    lines,
+                });
+                y += h + GAP;
+            }
+            Block::Image { src, caption } => {
+                flush(&mut pending, &mut y, &mut elements);
+                let caption_height = caption
+                    .as_ref()
+                    .map_or(0.0, |_| line_height(CAPTION_SIZE) + 6.0);
+                match resolve_image(src, base_dir) {
+                    Ok(image) => {
+                        // Images share what is left of the page after the text below them
+                        let later_text: f64 = slide.blocks[i + 1..]
+                            .iter()
+                            .filter(|b| !matches!(b, Block::Image { .. }))
+                            .count() as f64
+                            * line_height(BODY_SIZE);
+                        let remaining = images
+                            .saturating_sub(
+                                slide.blocks[..i]
+                                    .iter()
+                                    .filter(|b| matches!(b, Block::Image { .. }))
+                                    .count(),
+                            )
+                            .max(1) as f64;
+                        let available =
+                            ((bottom - y - later_text) / remaining - caption_height).max(72.0);
+                        let (px_w, px_h) = image.dimensions();
+                        let scale = (width / px_w.max(1) as f64)
+                            .min(available / px_h.max(1) as f64)
+                            .min(1.0);
+                        let (w, h) = (px_w as f64 * scale, px_h as f64 * scale);
+                        elements.push(Element::Image {
+                            frame: Frame {
+                                x: MARGIN + (width - w) / 2.0,
+                                y,
+                                w,
+                                h,
+                            },
+                            image,
+                        });
+                        y += h + 6.0;
+                    }
+                    Err(warning) => {
+                        warnings.push(format!("slide {}: {}", number, warning));
+                        elements.push(paragraphs(
+                            &[(None, format!("[Image: {}]", src))],
+                            MARGIN,
+                            y,
+                            width,
+                            CAPTION_SIZE,
+                            true,
+                        ));
+                        y += line_height(CAPTION_SIZE) + 6.0;
+                    }
+                }
+                if let Some(caption) = caption {
+                    let element = paragraphs(
+                        &[(None, caption.clone())],
+                        MARGIN,
+                        y,
+                        width,
+                        CAPTION_SIZE,
+                        true,
+                    );
+                    if let Element::Paragraphs { frame, .. } = &element {
+                        y += frame.h;
+                    }
+                    elements.push(element);
+                }
+                y += GAP;
+            }
+        }
+    }
+    flush(&mut pending, &mut y, &mut elements);
+
+    if y - GAP > bottom + 1.0 {
+        warnings.push(format!(
+            "slide {} has more content than fits on the page, consider splitting it",
+            number
+        ));
+    }
+    elements
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn hex_color((r, g, b): (f64, f64, f64)) -> String {
+    format!(
+        "{:02X}{:02X}{:02X}",
+        (r * 255.0).round() as u8,
+        (g * 255.0).round() as u8,
+        (b * 255.0).round() as u8
+    )
+}
+
+fn png_bytes(image: &DynamicImage) -> Result<Vec<u8>, ToolError> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to encode image: {}", e)))?;
+    Ok(png)
+}
+
+const NS: &str = r#"xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main""#;
+const XML_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#;
+const REL_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const EMPTY_GROUP: &str =
+    r#"<p:nvGrpSpPr><p:cNvPr id="1" name=""/><p:cNvGrpSpPr/><p:nvPr/></p:nvGrpSpPr><p:grpSpPr/>"#;
+
+fn relationships(rels: &[(String, &str, String)]) -> String {
+    let mut xml = format!(
+        r#"{}<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+        XML_HEADER
+    );
+    for (id, kind, target) in rels {
+        let kind = if kind.starts_with("http") {
+            kind.to_string()
+        } else {
+            format!("{}/{}", REL_NS, kind)
+        };
+        xml.push_str(&format!(
+            r#"<Relationship Id="{}" Type="{}" Target="{}"/>"#,
+            id, kind, target
+        ));
+    }
+    xml.push_str("</Relationships>");
+    xml
+}
+
+fn theme() -> String {
+    let colors = [
+        ("dk1", "000000"),
+        ("lt1", "FFFFFF"),
+        ("dk2", "1F2937"),
+        ("lt2", "F3F4F6"),
+        ("accent1", "2196F3"),
+        ("accent2", "4CAF50"),
+        ("accent3", "FFC107"),
+        ("accent4", "F44336"),
+        ("accent5", "9C27B0"),
+        ("accent6", "FF9800"),
+        ("hlink", "1565C0"),
+        ("folHlink", "6A1B9A"),
+    ]
+    .iter()
+    .map(|(name, rgb)| format!(r#"<a:{0}><a:srgbClr val="{1}"/></a:{0}>"#, name, rgb))
+    .collect::<String>();
+    let fill = r#"<a:solidFill><a:schemeClr val="phClr"/></a:solidFill>"#;
+    let line = r#"<a:ln w="6350"><a:solidFill><a:schemeClr val="phClr"/></a:solidFill></a:ln>"#;
+    let effect = "<a:effectStyle><a:effectLst/></a:effectStyle>";
+    let font = r#"<a:latin typeface="Calibri"/><a:ea typeface=""/><a:cs typeface=""/>"#;
+    format!(
+        r#"{}<a:theme xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" name="Goose"><a:themeElements><a:clrScheme name="Goose">{}</a:clrScheme><a:fontScheme name="Goose"><a:majorFont>{}</a:majorFont><a:minorFont>{}</a:minorFont></a:fontScheme><a:fmtScheme name="Goose"><a:fillStyleLst>{}</a:fillStyleLst><a:lnStyleLst>{}</a:lnStyleLst><a:effectStyleLst>{}</a:effectStyleLst><a:bgFillStyleLst>{}</a:bgFillStyleLst></a:fmtScheme></a:themeElements></a:theme>"#,
+        XML_HEADER,
+        colors,
+        font,
+        font,
+        fill.repeat(3),
+        line.repeat(3),
+        effect.repeat(3),
+        fill.repeat(3)
+    )
+}
+
+fn xfrm(frame: &Frame) -> String {
+    format!(
+        r#"<a:xfrm><a:off x="{}" y="{}"/><a:ext cx="{}" cy="{}"/></a:xfrm>"#,
+        (frame.x * EMU).round() as i64,
+        (frame.y * EMU).round() as i64,
+        (frame.w * EMU).round() as i64,
+        (frame.h * EMU).round().max(1.0) as i64
+    )
+}
+
+fn text_shape(
+    id: usize,
+    name: &str,
+    frame: &Frame,
+    fill: Option<&str>,
+    paragraphs: &str,
+) -> String {
+    let fill = fill
+        .map(|rgb| format!(r#"<a:solidFill><a:srgbClr val="{}"/></a:solidFill>"#, rgb))
+        .unwrap_or_default();
+    format!(
+        r#"<p:sp><p:nvSpPr><p:cNvPr id="{}" name="{}"/><p:cNvSpPr txBox="1"/><p:nvPr/></p:nvSpPr><p:spPr>{}<a:prstGeom prst="rect"><a:avLst/></a:prstGeom>{}</p:spPr><p:txBody><a:bodyPr wrap="square" lIns="0" tIns="0" rIns="0" bIns="0"><a:normAutofit/></a:bodyPr><a:lstStyle/>{}</p:txBody></p:sp>"#,
+        id,
+        name,
+        xfrm(frame),
+        fill,
+        paragraphs
+    )
+}
+
+fn run(text: &str, size: f64, bold: bool, color: (f64, f64, f64), mono: bool) -> String {
+    format!(
+        r#"<a:r><a:rPr lang="en-US" sz="{}"{} dirty="0"><a:solidFill><a:srgbClr val="{}"/></a:solidFill>{}</a:rPr><a:t>{}</a:t></a:r>"#,
+        (size * 100.0).round() as i64,
+        if bold { r#" b="1""# } else { "" },
+        hex_color(color),
+        if mono {
+            r#"<a:latin typeface="Courier New"/>"#
+        } else {
+            ""
+        },
+        xml_escape(text)
+    )
+}
+
+fn slide_xml(elements: &[Element], images: &mut Vec<Vec<u8>>) -> Result<String, ToolError> {
+    let mut shapes = String::new();
+    for (i, element) in elements.iter().enumerate() {
+        let id = i + 2;
+        match element {
+            Element::Title { frame, text, .. } => {
+                let p = format!(
+                    "<a:p>{}</a:p>",
+                    run(text, TITLE_SIZE, true, TITLE_COLOR, false)
+                );
+                shapes.push_str(&text_shape(id, "Title", frame, None, &p));
+            }
+            Element::Accent(frame) => {
+                shapes.push_str(&format!(
+                    r#"<p:sp><p:nvSpPr><p:cNvPr id="{}" name="Accent"/><p:cNvSpPr/><p:nvPr/></p:nvSpPr><p:spPr>{}<a:prstGeom prst="rect"><a:avLst/></a:prstGeom><a:solidFill><a:srgbClr val="{}"/></a:solidFill><a:ln><a:noFill/></a:ln></p:spPr></p:sp>"#,
+                    id,
+                    xfrm(frame),
+                    hex_color(ACCENT_COLOR)
+                ));
+            }
+            Element::Paragraphs {
+                frame,
+                size,
+                centered,
+                items,
+            } => {
+                let mut body = String::new();
+                for item in items {
+                    let properties = match item.bullet {
+                        Some(level) => {
+                            let margin = ((level + 1) as f64 * BULLET_INDENT * EMU).round() as i64;
+                            format!(
+                                r#"<a:pPr marL="{}" lvl="{}" indent="-{}"><a:spcBef><a:spcPts val="600"/></a:spcBef><a:buFont typeface="Arial"/><a:buChar char="•"/></a:pPr>"#,
+                                margin,
+                                level.min(8),
+                                (BULLET_INDENT * EMU) as i64
+                            )
+                        }
+                        None if *centered => r#"<a:pPr algn="ctr"><a:buNone/></a:pPr>"#.to_string(),
+                        None => r#"<a:pPr><a:spcBef><a:spcPts val="600"/></a:spcBef><a:buNone/></a:pPr>"#.to_string(),
+                    };
+                    body.push_str(&format!(
+                        "<a:p>{}{}</a:p>",
+                        properties,
+                        run(&item.text, *size, false, TEXT_COLOR, false)
+                    ));
+                }
+                shapes.push_str(&text_shape(id, "Text", frame, None, &body));
+            }
+            Element::Code { frame, lines } => {
+                let body: String = lines
+                    .iter()
+                    .map(|line| {
+                        format!(
+                            "<a:p>{}</a:p>",
+                            run(line, CODE_SIZE, false, TITLE_COLOR, true)
+                        )
+                    })
+                    .collect();
+                let shape = text_shape(id, "Code", frame, Some(&hex_color(CODE_BACKGROUND)), &body)
+                    .replace(
+                        r#"lIns="0" tIns="0" rIns="0" bIns="0""#,
+                        r#"lIns="127000" tIns="127000" rIns="127000" bIns="127000""#,
+                    );
+                shapes.push_str(&shape);
+            }
+            Element::Image { frame, image } => {
+                images.push(png_bytes(image)?);
+                shapes.push_str(&format!(
+                    r#"<p:pic><p:nvPicPr><p:cNvPr id="{0}" name="Picture {0}"/><p:cNvPicPr><a:picLocks noChangeAspect="1"/></p:cNvPicPr><p:nvPr/></p:nvPicPr><p:blipFill><a:blip r:embed="rId{1}"/><a:stretch><a:fillRect/></a:stretch></p:blipFill><p:spPr>{2}<a:prstGeom prst="rect"><a:avLst/></a:prstGeom></p:spPr></p:pic>"#,
+                    id,
+                    images.len() + 1,
+                    xfrm(frame)
+                ));
+            }
+        }
+    }
+    Ok(format!(
+        r#"{}<p:sld {}><p:cSld><p:spTree>{}{}</p:spTree></p:cSld><p:clrMapOvr><a:masterClrMapping/></p:clrMapOvr></p:sld>"#,
+        XML_HEADER, NS, EMPTY_GROUP, shapes
+    ))
+}
+
+fn notes_xml(notes: &str) -> String {
+    let paragraphs: String = notes
+        .lines()
+        .map(|line| {
+            format!(
+                r#"<a:p><a:r><a:rPr lang="en-US" dirty="0"/><a:t>{}</a:t></a:r></a:p>"#,
+                xml_escape(line)
+            )
+        })
+        .collect();
+    format!(
+        r#"{}<p:notes {}><p:cSld><p:spTree>{}<p:sp><p:nvSpPr><p:cNvPr id="2" name="Slide Image Placeholder 1"/><p:cNvSpPr><a:spLocks noGrp="1" noRot="1" noChangeAspect="1"/></p:cNvSpPr><p:nvPr><p:ph type="sldImg"/></p:nvPr></p:nvSpPr><p:spPr/></p:sp><p:sp><p:nvSpPr><p:cNvPr id="3" name="Notes Placeholder 2"/><p:cNvSpPr><a:spLocks noGrp="1"/></p:cNvSpPr><p:nvPr><p:ph type="body" idx="1"/></p:nvPr></p:nvSpPr><p:spPr/><p:txBody><a:bodyPr/><a:lstStyle/>{}</p:txBody></p:sp></p:spTree></p:cSld><p:clrMapOvr><a:masterClrMapping/></p:clrMapOvr></p:notes>"#,
+        XML_HEADER, NS, EMPTY_GROUP, paragraphs
+    )
+}
+
+fn notes_master_xml() -> String {
+    format!(
+        r#"{}<p:notesMaster {}><p:cSld><p:bg><p:bgRef idx="1001"><a:schemeClr val="bg1"/></p:bgRef></p:bg><p:spTree>{}<p:sp><p:nvSpPr><p:cNvPr id="2" name="Slide Image Placeholder 1"/><p:cNvSpPr><a:spLocks noGrp="1" noRot="1" noChangeAspect="1"/></p:cNvSpPr><p:nvPr><p:ph type="sldImg" idx="2"/></p:nvPr></p:nvSpPr><p:spPr><a:xfrm><a:off x="381000" y="685800"/><a:ext cx="6096000" cy="3429000"/></a:xfrm><a:prstGeom prst="rect"><a:avLst/></a:prstGeom><a:noFill/><a:ln w="12700"><a:solidFill><a:prstClr val="black"/></a:solidFill></a:ln></p:spPr></p:sp><p:sp><p:nvSpPr><p:cNvPr id="3" name="Notes Placeholder 2"/><p:cNvSpPr><a:spLocks noGrp="1"/></p:cNvSpPr><p:nvPr><p:ph type="body" sz="quarter" idx="3"/></p:nvPr></p:nvSpPr><p:spPr><a:xfrm><a:off x="685800" y="4400550"/><a:ext cx="5486400" cy="3600450"/></a:xfrm><a:prstGeom prst="rect"><a:avLst/></a:prstGeom></p:spPr><p:txBody><a:bodyPr vert="horz" lIns="91440" tIns="45720" rIns="91440" bIns="45720" rtlCol="0"/><a:lstStyle/><a:p><a:endParaRPr lang="en-US"/></a:p></p:txBody></p:sp></p:spTree></p:cSld>{}</p:notesMaster>"#,
+        XML_HEADER, NS, EMPTY_GROUP, CLR_MAP
+    )
+}
+
+const CLR_MAP: &str = r#"<p:clrMap bg1="lt1" tx1="dk1" bg2="lt2" tx2="dk2" accent1="accent1" accent2="accent2" accent3="accent3" accent4="accent4" accent5="accent5" accent6="accent6" hlink="hlink" folHlink="folHlink"/>"#;
+
+/// Writes the slides as a PowerPoint file and returns any warnings
+pub fn write_pptx(
+    slides: &[Slide],
+    base_dir: &Path,
+    output: &Path,
+) -> Result<Vec<String>, ToolError> {
+    let mut warnings = Vec::new();
+    let mut parts: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut overrides = Vec::new();
+    let has_notes = slides.iter().any(|s| s.notes.is_some());
+    let mut media = 0;
+
+    for (i, slide) in slides.iter().enumerate() {
+        let number = i + 1;
+        let elements = layout(slide, number, base_dir, &mut warnings);
+        let mut images = Vec::new();
+        let xml = slide_xml(&elements, &mut images)?;
+        parts.push((format!("ppt/slides/slide{}.xml", number), xml.into_bytes()));
+        overrides.push((
+            format!("/ppt/slides/slide{}.xml", number),
+            "application/vnd.openxmlformats-officedocument.presentationml.slide+xml".to_string(),
+        ));
+
+        let mut rels = vec![(
+            "rId1".to_string(),
+            "slideLayout",
+            "../slideLayouts/slideLayout1.xml".to_string(),
+        )];
+        for (n, png) in images.into_iter().enumerate() {
+            media += 1;
+            parts.push((format!("ppt/media/image{}.png", media), png));
+            rels.push((
+                format!("rId{}", n + 2),
+                "image",
+                format!("../media/image{}.png", media),
+            ));
+        }
+        if let Some(notes) = &slide.notes {
+            rels.push((
+                format!("rId{}", rels.len() + 1),
+                "notesSlide",
+                format!("../notesSlides/notesSlide{}.xml", number),
+            ));
+            parts.push((
+                format!("ppt/notesSlides/notesSlide{}.xml", number),
+                notes_xml(notes).into_bytes(),
+            ));
+            parts.push((
+                format!("ppt/notesSlides/_rels/notesSlide{}.xml.rels", number),
+                relationships(&[
+                    (
+                        "rId1".to_string(),
+                        "notesMaster",
+                        "../notesMasters/notesMaster1.xml".to_string(),
+                    ),
+                    (
+                        "rId2".to_string(),
+                        "slide",
+                        format!("../slides/slide{}.xml", number),
+                    ),
+                ])
+                .into_bytes(),
+            ));
+            overrides.push((
+                format!("/ppt/notesSlides/notesSlide{}.xml", number),
+                "application/vnd.openxmlformats-officedocument.presentationml.notesSlide+xml"
+                    .to_string(),
+            ));
+        }
+        parts.push((
+            format!("ppt/slides/_rels/slide{}.xml.rels", number),
+            relationships(&rels).into_bytes(),
+        ));
+    }
+
+    // Presentation part and its relationships
+    let mut presentation_rels = vec![(
+        "rId1".to_string(),
+        "slideMaster",
+        "slideMasters/slideMaster1.xml".to_string(),
+    )];
+    let mut slide_ids = String::new();
+    for i in 0..slides.len() {
+        let rid = format!("rId{}", presentation_rels.len() + 1);
+        slide_ids.push_str(&format!(r#"<p:sldId id="{}" r:id="{}"/>"#, 256 + i, rid));
+        presentation_rels.push((rid, "slide", format!("slides/slide{}.xml", i + 1)));
+    }
+    let mut notes_master = String::new();
+    if has_notes {
+        let rid = format!("rId{}", presentation_rels.len() + 1);
+        notes_master = format!(
+            r#"<p:notesMasterIdLst><p:notesMasterId r:id="{}"/></p:notesMasterIdLst>"#,
+            rid
+        );
+        presentation_rels.push((
+            rid,
+            "notesMaster",
+            "notesMasters/notesMaster1.xml".to_string(),
+        ));
+    }
+    for (kind, target) in [
+        ("theme", "theme/theme1.xml"),
+        ("presProps", "presProps.xml"),
+        ("viewProps", "viewProps.xml"),
+        ("tableStyles", "tableStyles.xml"),
+    ] {
+        presentation_rels.push((
+            format!("rId{}", presentation_rels.len() + 1),
+            kind,
+            target.to_string(),
+        ));
+    }
+    let slide_list = if slide_ids.is_empty() {
+        String::new()
+    } else {
+        format!("<p:sldIdLst>{}</p:sldIdLst>", slide_ids)
+    };
+    parts.push((
+        "ppt/presentation.xml".to_string(),
+        format!(
+            r#"{}<p:presentation {} saveSubsetFonts="1"><p:sldMasterIdLst><p:sldMasterId id="2147483648" r:id="rId1"/></p:sldMasterIdLst>{}{}<p:sldSz cx="{}" cy="{}"/><p:notesSz cx="6858000" cy="9144000"/></p:presentation>"#,
+            XML_HEADER,
+            NS,
+            notes_master,
+            slide_list,
+            (PAGE_WIDTH * EMU) as i64,
+            (PAGE_HEIGHT * EMU) as i64
+        )
+        .into_bytes(),
+    ));
+    parts.push((
+        "ppt/_rels/presentation.xml.rels".to_string(),
+        relationships(&presentation_rels).into_bytes(),
+    ));
+
+    // Master, layout, theme and the small property parts PowerPoint expects
+    parts.push((
+        "ppt/slideMasters/slideMaster1.xml".to_string(),
+        format!(
+            r#"{}<p:sldMaster {}><p:cSld><p:bg><p:bgRef idx="1001"><a:schemeClr val="bg1"/></p:bgRef></p:bg><p:spTree>{}</p:spTree></p:cSld>{}<p:sldLayoutIdLst><p:sldLayoutId id="2147483649" r:id="rId1"/></p:sldLayoutIdLst></p:sldMaster>"#,
+            XML_HEADER, NS, EMPTY_GROUP, CLR_MAP
+        )
+        .into_bytes(),
+    ));
+    parts.push((
+        "ppt/slideMasters/_rels/slideMaster1.xml.rels".to_string(),
+        relationships(&[
+            (
+                "rId1".to_string(),
+                "slideLayout",
+                "../slideLayouts/slideLayout1.xml".to_string(),
+            ),
+            (
+                "rId2".to_string(),
+                "theme",
+                "../theme/theme1.xml".to_string(),
+            ),
+        ])
+        .into_bytes(),
+    ));
+    parts.push((
+        "ppt/slideLayouts/slideLayout1.xml".to_string(),
+        format!(
+            r#"{}<p:sldLayout {} type="blank" preserve="1"><p:cSld name="Blank"><p:spTree>{}</p:spTree></p:cSld><p:clrMapOvr><a:masterClrMapping/></p:clrMapOvr></p:sldLayout>"#,
+            XML_HEADER, NS, EMPTY_GROUP
+        )
+        .into_bytes(),
+    ));
+    parts.push((
+        "ppt/slideLayouts/_rels/slideLayout1.xml.rels".to_string(),
+        relationships(&[(
+            "rId1".to_string(),
+            "slideMaster",
+            "../slideMasters/slideMaster1.xml".to_string(),
+        )])
+        .into_bytes(),
+    ));
+    parts.push(("ppt/theme/theme1.xml".to_string(), theme().into_bytes()));
+    if has_notes {
+        parts.push((
+            "ppt/notesMasters/notesMaster1.xml".to_string(),
+            notes_master_xml().into_bytes(),
+        ));
+        parts.push((
+            "ppt/notesMasters/_rels/notesMaster1.xml.rels".to_string(),
+            relationships(&[(
+                "rId1".to_string(),
+                "theme",
+                "../theme/theme2.xml".to_string(),
+            )])
+            .into_bytes(),
+        ));
+        parts.push(("ppt/theme/theme2.xml".to_string(), theme().into_bytes()));
+        overrides.push((
+            "/ppt/notesMasters/notesMaster1.xml".to_string(),
+            "application/vnd.openxmlformats-officedocument.presentationml.notesMaster+xml"
+                .to_string(),
+        ));
+        overrides.push((
+            "/ppt/theme/theme2.xml".to_string(),
+            "application/vnd.openxmlformats-officedocument.theme+xml".to_string(),
+        ));
+    }
+    parts.push((
+        "ppt/presProps.xml".to_string(),
+        format!(r#"{}<p:presentationPr {}/>"#, XML_HEADER, NS).into_bytes(),
+    ));
+    parts.push((
+        "ppt/viewProps.xml".to_string(),
+        format!(r#"{}<p:viewPr {}/>"#, XML_HEADER, NS).into_bytes(),
+    ));
+    parts.push((
+        "ppt/tableStyles.xml".to_string(),
+        format!(
+            r#"{}<a:tblStyleLst xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" def="{{5C22544A-7EE6-4342-B048-85BDC9FD1C3A}}"/>"#,
+            XML_HEADER
+        )
+        .into_bytes(),
+    ));
+
+    let title = slides
+        .first()
+        .map(|s| plain_text(&s.title))
+        .unwrap_or_default();
+    parts.push((
+        "docProps/core.xml".to_string(),
+        format!(
+            r#"{}<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"><dc:title>{}</dc:title><dc:creator>Goose</dc:creator></cp:coreProperties>"#,
+            XML_HEADER,
+            xml_escape(&title)
+        )
+        .into_bytes(),
+    ));
+    parts.push((
+        "docProps/app.xml".to_string(),
+        format!(
+            r#"{}<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/extended-properties"><Application>Goose</Application><Slides>{}</Slides></Properties>"#,
+            XML_HEADER,
+            slides.len()
+        )
+        .into_bytes(),
+    ));
+    parts.push((
+        "_rels/.rels".to_string(),
+        relationships(&[
+            (
+                "rId1".to_string(),
+                "officeDocument",
+                "ppt/presentation.xml".to_string(),
+            ),
+            (
+                "rId2".to_string(),
+                "http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties",
+                "docProps/core.xml".to_string(),
+            ),
+            (
+                "rId3".to_string(),
+                "extended-properties",
+                "docProps/app.xml".to_string(),
+            ),
+        ])
+        .into_bytes(),
+    ));
+
+    for (part, kind) in [
+        (
+            "/ppt/presentation.xml",
+            "presentationml.presentation.main+xml",
+        ),
+        (
+            "/ppt/slideMasters/slideMaster1.xml",
+            "presentationml.slideMaster+xml",
+        ),
+        (
+            "/ppt/slideLayouts/slideLayout1.xml",
+            "presentationml.slideLayout+xml",
+        ),
+        ("/ppt/theme/theme1.xml", "theme+xml"),
+        ("/ppt/presProps.xml", "presentationml.presProps+xml"),
+        ("/ppt/viewProps.xml", "presentationml.viewProps+xml"),
+        ("/ppt/tableStyles.xml", "presentationml.tableStyles+xml"),
+        ("/docProps/app.xml", "extended-properties+xml"),
+    ] {
+        overrides.push((
+            part.to_string(),
+            format!("application/vnd.openxmlformats-officedocument.{}", kind),
+        ));
+    }
+    overrides.push((
+        "/docProps/core.xml".to_string(),
+        "application/vnd.openxmlformats-package.core-properties+xml".to_string(),
+    ));
+    let content_types = format!(
+        r#"{}<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Default Extension="png" ContentType="image/png"/>{}</Types>"#,
+        XML_HEADER,
+        overrides
+            .iter()
+            .map(|(part, kind)| format!(
+                r#"<Override PartName="{}" ContentType="{}"/>"#,
+                part, kind
+            ))
+            .collect::<String>()
+    );
+    parts.insert(
+        0,
+        (
+            "[Content_Types].xml".to_string(),
+            content_types.into_bytes(),
+        ),
+    );
+
+    let file = std::fs::File::create(output)
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to create PPTX file: {}", e)))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for (name, data) in parts {
+        zip.start_file(name, options)
+            .and_then(|_| zip.write_all(&data).map_err(Into::into))
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to write PPTX file: {}", e)))?;
+    }
+    zip.finish()
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to write PPTX file: {}", e)))?;
+    Ok(warnings)
+}
+
+/// Encodes text for the WinAnsi encoding of the standard fonts, replacing anything it
+/// cannot represent
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' => c as u8,
+            '\u{a0}'..='\u{ff}' => c as u32 as u8,
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '™' => 0x99,
+            _ => b'?',
+        })
+        .collect()
+}
+
+struct PdfPage {
+    ops: Vec<Operation>,
+    images: Vec<DynamicImage>,
+}
+
+impl PdfPage {
+    fn new() -> Self {
+        Self {
+            ops: Vec::new(),
+            images: Vec::new(),
+        }
+    }
+
+    fn color(&mut self, (r, g, b): (f64, f64, f64)) {
+        self.ops.push(Operation::new(
+            "rg",
+            vec![(r as f32).into(), (g as f32).into(), (b as f32).into()],
+        ));
+    }
+
+    fn rect(&mut self, frame: &Frame, color: (f64, f64, f64)) {
+        self.color(color);
+        self.ops.push(Operation::new(
+            "re",
+            [frame.x, PAGE_HEIGHT - frame.y - frame.h, frame.w, frame.h]
+                .into_iter()
+                .map(|v| (v as f32).into())
+                .collect(),
+        ));
+        self.ops.push(Operation::new("f", vec![]));
+    }
+
+    /// Draws one line of text with its baseline `size` below `top`
+    fn text(
+        &mut self,
+        text: &str,
+        x: f64,
+        top: f64,
+        size: f64,
+        font: Font,
+        color: (f64, f64, f64),
+    ) {
+        self.color(color);
+        let baseline = PAGE_HEIGHT - top - size;
+        self.ops.push(Operation::new("BT", vec![]));
+        self.ops.push(Operation::new(
+            "Tf",
+            vec![font.resource().into(), (size as f32).into()],
+        ));
+        self.ops.push(Operation::new(
+            "Td",
+            vec![(x as f32).into(), (baseline as f32).into()],
+        ));
+        self.ops.push(Operation::new(
+            "Tj",
+            vec![Object::String(win_ansi(text), StringFormat::Literal)],
+        ));
+        self.ops.push(Operation::new("ET", vec![]));
+    }
+
+    fn image(&mut self, frame: &Frame, image: DynamicImage) {
+        self.images.push(image);
+        let name = format!("Im{}", self.images.len());
+        self.ops.push(Operation::new("q", vec![]));
+        self.ops.push(Operation::new(
+            "cm",
+            [
+                frame.w,
+                0.0,
+                0.0,
+                frame.h,
+                frame.x,
+                PAGE_HEIGHT - frame.y - frame.h,
+            ]
+            .into_iter()
+            .map(|v| (v as f32).into())
+            .collect(),
+        ));
+        self.ops.push(Operation::new("Do", vec![name.into()]));
+        self.ops.push(Operation::new("Q", vec![]));
+    }
+
+    fn paragraphs(&mut self, frame: &Frame, size: f64, centered: bool, items: &[Paragraph]) {
+        let mut top = frame.y;
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                top += size * 0.4;
+            }
+            let indent = item
+                .bullet
+                .map_or(0.0, |level| (level + 1) as f64 * BULLET_INDENT);
+            if let Some(level) = item.bullet {
+                let bullet = if level == 0 { "•" } else { "–" };
+                self.text(
+                    bullet,
+                    frame.x + level as f64 * BULLET_INDENT + 8.0,
+                    top,
+                    size,
+                    Font::Regular,
+                    ACCENT_COLOR,
+                );
+            }
+            for line in &item.lines {
+                let x = if centered {
+                    frame.x + (frame.w - text_width(line, size, Font::Regular)) / 2.0
+                } else {
+                    frame.x + indent
+                };
+                self.text(line, x, top, size, Font::Regular, TEXT_COLOR);
+                top += line_height(size);
+            }
+        }
+    }
+}
+
+fn draw_slide(elements: Vec<Element>) -> PdfPage {
+    let mut page = PdfPage::new();
+    for element in elements {
+        match element {
+            Element::Title { frame, lines, .. } => {
+                for (i, line) in lines.iter().enumerate() {
+                    let top = frame.y + i as f64 * line_height(TITLE_SIZE);
+                    page.text(line, frame.x, top, TITLE_SIZE, Font::Bold, TITLE_COLOR);
+                }
+            }
+            Element::Accent(frame) => page.rect(&frame, ACCENT_COLOR),
+            Element::Paragraphs {
+                frame,
+                size,
+                centered,
+                items,
+            } => page.paragraphs(&frame, size, centered, &items),
+            Element::Code { frame, lines } => {
+                page.rect(&frame, CODE_BACKGROUND);
+                for (i, line) in lines.iter().enumerate() {
+                    let top = frame.y + 10.0 + i as f64 * line_height(CODE_SIZE);
+                    page.text(
+                        line,
+                        frame.x + 10.0,
+                        top,
+                        CODE_SIZE,
+                        Font::Mono,
+                        TITLE_COLOR,
+                    );
+                }
+            }
+            Element::Image { frame, image } => page.image(&frame, image),
+        }
+    }
+    page
+}
+
+fn draw_notes(number: usize, slide: &Slide, notes: &str) -> PdfPage {
+    let mut page = PdfPage::new();
+    let width = PAGE_WIDTH - 2.0 * MARGIN;
+    let title = plain_text(&slide.title);
+    let heading = if title.is_empty() {
+        format!("Notes for slide {}", number)
+    } else {
+        format!("Notes for slide {}: {}", number, title)
+    };
+    page.text(&heading, MARGIN, MARGIN, 20.0, Font::Bold, TITLE_COLOR);
+    let items: Vec<Paragraph> = notes
+        .split("\n\n")
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| Paragraph {
+            bullet: None,
+            text: p.to_string(),
+            lines: wrap(p, width, NOTES_SIZE, Font::Regular),
+        })
+        .collect();
+    let frame = Frame {
+        x: MARGIN,
+        y: MARGIN + 40.0,
+        w: width,
+        h: PAGE_HEIGHT - 2.0 * MARGIN - 40.0,
+    };
+    page.paragraphs(&frame, NOTES_SIZE, false, &items);
+    page
+}
+
+fn image_xobject(image: &DynamicImage) -> Stream {
+    // PDF images have no alpha here, so blend transparent pixels onto the white page
+    let rgba = image.to_rgba8();
+    let mut rgb = Vec::with_capacity((rgba.width() * rgba.height() * 3) as usize);
+    for pixel in rgba.pixels() {
+        let alpha = pixel[3] as u32;
+        for channel in 0..3 {
+            rgb.push(((pixel[channel] as u32 * alpha + 255 * (255 - alpha)) / 255) as u8);
+        }
+    }
+    let mut stream = Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => rgba.width() as i64,
+            "Height" => rgba.height() as i64,
+            "ColorSpace" => "DeviceRGB",
+            "BitsPerComponent" => 8,
+        },
+        rgb,
+    );
+    // An uncompressed image is still valid, just larger
+    let _ = stream.compress();
+    stream
+}
+
+fn font_object(doc: &mut Document, base_font: &str, widths: Option<&[u16; 95]>) -> ObjectId {
+    let widths: Vec<Object> = match widths {
+        Some(widths) => widths.iter().map(|w| (*w as i64).into()).collect(),
+        None => vec![600.into(); 95],
+    };
+    doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => base_font,
+        "Encoding" => "WinAnsiEncoding",
+        "FirstChar" => 32,
+        "LastChar" => 126,
+        "Widths" => widths,
+    })
+}
+
+/// Draws the slides into a PDF, optionally followed by a page of speaker notes for each
+/// slide that has them, and returns any warnings
+pub fn write_pdf(
+    slides: &[Slide],
+    base_dir: &Path,
+    output: &Path,
+    include_notes: bool,
+) -> Result<Vec<String>, ToolError> {
+    let mut warnings = Vec::new();
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let fonts = dictionary! {
+        "F1" => font_object(&mut doc, "Helvetica", Some(&HELVETICA_WIDTHS)),
+        "F2" => font_object(&mut doc, "Helvetica-Bold", Some(&HELVETICA_BOLD_WIDTHS)),
+        "F3" => font_object(&mut doc, "Courier", None),
+    };
+    let fonts_id = doc.add_object(fonts);
+
+    let mut pages = Vec::new();
+    for (i, slide) in slides.iter().enumerate() {
+        let elements = layout(slide, i + 1, base_dir, &mut warnings);
+        pages.push(draw_slide(elements));
+        if let (true, Some(notes)) = (include_notes, &slide.notes) {
+            pages.push(draw_notes(i + 1, slide, notes));
+        }
+    }
+
+    let mut kids = Vec::new();
+    for page in pages {
+        let mut xobjects = lopdf::Dictionary::new();
+        for (n, image) in page.images.iter().enumerate() {
+            let id = doc.add_object(image_xobject(image));
+            xobjects.set(format!("Im{}", n + 1), id);
+        }
+        let content = PdfContent {
+            operations: page.ops,
+        }
+        .encode()
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to encode PDF page: {}", e)))?;
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), (PAGE_WIDTH as i64).into(), (PAGE_HEIGHT as i64).into()],
+            "Contents" => content_id,
+            "Resources" => dictionary! {
+                "Font" => fonts_id,
+                "XObject" => xobjects,
+            },
+        });
+        kids.push(Object::Reference(page_id));
+    }
+
+    let count = kids.len() as i64;
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.compress();
+    doc.save(output)
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to write PDF file: {}", e)))?;
+    Ok(warnings)
+}
@@ -1,6 +1,9 @@
+use super::presentation_export;
 use mcp_core::{Content, ToolError};
+use regex::Regex;
 use serde_json::Value;
 use std::fs;
+use std::path::{Path, PathBuf};
 
 const TEMPLATE: &str = r#"<html>
 <head>
@@ -186,6 +189,533 @@ const TEMPLATE: &str = r#"<html>
 </body>
 </html>"#;
 
+/// Where new slides are inserted. Everything between the slide wrapper and this marker
+/// belongs to the deck.
+const MARKER: &str = "<!-- ADD_SLIDES_HERE";
+
+/// One block of slide body content
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Text(String),
+    Bullet {
+        level: usize,
+        text: String,
+    },
+    Code {
+        language: Option<String>,
+        code: String,
+    },
+    Image {
+        src: String,
+        caption: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Slide {
+    /// The heading, which may contain inline HTML
+    pub title: String,
+    pub blocks: Vec<Block>,
+    pub notes: Option<String>,
+}
+
+/// An HTML deck split into its slides, keeping everything around them untouched
+struct Deck {
+    html: String,
+    /// Byte ranges of each slide, from the start of its first line to after its closing tag
+    spans: Vec<std::ops::Range<usize>>,
+}
+
+impl Deck {
+    fn load(path: &str) -> Result<Self, ToolError> {
+        let html = fs::read_to_string(path).map_err(|e| {
+            ToolError::ExecutionError(format!("Failed to read presentation file: {}", e))
+        })?;
+        Self::parse(html)
+    }
+
+    fn parse(html: String) -> Result<Self, ToolError> {
+        let invalid = || ToolError::ExecutionError("Invalid presentation file format".into());
+        let wrapper = html.find("class=\"slide-wrapper\"").ok_or_else(invalid)?;
+        let start = wrapper + html[wrapper..].find('>').ok_or_else(invalid)? + 1;
+        let end = html.find(MARKER).ok_or_else(invalid)?;
+        if end < start {
+            return Err(invalid());
+        }
+
+        let mut spans = Vec::new();
+        let mut i = start;
+        while i < end {
+            let rest = &html[i..end];
+            if rest.starts_with("<!--") {
+                i += rest.find("-->").map_or(rest.len(), |e| e + 3);
+            } else if rest.starts_with("<div class=\"slide\"") {
+                let close = matching_div_end(&html[i..end]).ok_or_else(invalid)? + i;
+                let line_start = html[..i].rfind('\n').map_or(i, |n| n + 1);
+                let line_start = if html[line_start..i].trim().is_empty() {
+                    line_start
+                } else {
+                    i
+                };
+                let close = if html[close..].starts_with('\n') {
+                    close + 1
+                } else {
+                    close
+                };
+                spans.push(line_start..close);
+                i = close;
+            } else {
+                i += rest.chars().next().map_or(1, char::len_utf8);
+            }
+        }
+        Ok(Self { html, spans })
+    }
+
+    fn slides(&self) -> Vec<String> {
+        self.spans
+            .iter()
+            .map(|span| self.html[span.clone()].to_string())
+            .collect()
+    }
+
+    /// Writes the deck back with the given slide HTML in place of the current slides
+    fn save(&self, path: &str, slides: &[String]) -> Result<(), ToolError> {
+        let mut html = String::new();
+        let mut last = 0;
+        for (i, span) in self.spans.iter().enumerate() {
+            html.push_str(&self.html[last..span.start]);
+            if let Some(slide) = slides.get(i) {
+                html.push_str(slide);
+            }
+            last = span.end;
+        }
+        let marker = self.html.find(MARKER).unwrap_or(self.html.len());
+        html.push_str(&self.html[last..marker]);
+        for slide in slides.iter().skip(self.spans.len()) {
+            html.push_str(slide.trim_start());
+            html.push_str("\n            ");
+        }
+        html.push_str(&self.html[marker..]);
+
+        let count = Regex::new(r"--num-slides: \d+").unwrap();
+        let html = count.replace(&html, format!("--num-slides: {}", slides.len()));
+
+        fs::write(path, html.as_ref()).map_err(|e| {
+            ToolError::ExecutionError(format!("Failed to update presentation file: {}", e))
+        })
+    }
+}
+
+/// Length of the `<div>` element at the start of `html`, including its closing tag
+fn matching_div_end(html: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < html.len() {
+        let rest = &html[i..];
+        if rest.starts_with("<!--") {
+            i += rest.find("-->")? + 3;
+        } else if rest.starts_with("<div") {
+            depth += 1;
+            i += 4;
+        } else if rest.starts_with("</div>") {
+            depth -= 1;
+            i += 6;
+            if depth == 0 {
+                return Some(i);
+            }
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Plain text of an HTML fragment, with whitespace collapsed
+pub fn plain_text(html: &str) -> String {
+    let tags = Regex::new(r"(?s)<[^>]*>").unwrap();
+    let text = decode_entities(&tags.replace_all(html, " "));
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let pattern = Regex::new(&format!(r#"(?i)\b{}\s*=\s*("([^"]*)"|'([^']*)')"#, name)).unwrap();
+    let captures = pattern.captures(tag)?;
+    captures
+        .get(2)
+        .or_else(|| captures.get(3))
+        .map(|m| decode_entities(m.as_str()))
+}
+
+#[derive(Debug)]
+enum Capture {
+    Heading,
+    Paragraph { caption: bool },
+    Item { level: usize },
+    Code { language: Option<String> },
+    Notes,
+}
+
+/// Reads a slide back from its HTML. Anything outside the elements this tool writes
+/// becomes plain text.
+pub fn parse_slide(html: &str) -> Slide {
+    let token = Regex::new(r"(?s)<!--.*?-->|</?([a-zA-Z][a-zA-Z0-9]*)[^>]*>|[^<]+|<").unwrap();
+    let mut slide = Slide::default();
+    let mut capture: Option<(Capture, String)> = None;
+    let mut list_depth = 0usize;
+    let mut div_depth = 0usize;
+
+    fn flush(slide: &mut Slide, capture: &mut Option<(Capture, String)>) {
+        let Some((kind, raw)) = capture.take() else {
+            return;
+        };
+        match kind {
+            Capture::Heading => {
+                let title = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+                if slide.title.is_empty() {
+                    slide.title = title;
+                } else if !title.is_empty() {
+                    slide.blocks.push(Block::Text(plain_text(&title)));
+                }
+            }
+            Capture::Paragraph { caption } => {
+                let text = plain_text(&raw);
+                if text.is_empty() {
+                    return;
+                }
+                if caption {
+                    if let Some(Block::Image { caption, .. }) = slide.blocks.last_mut() {
+                        *caption = Some(text);
+                        return;
+                    }
+                }
+                slide.blocks.push(Block::Text(text));
+            }
+            Capture::Item { level } => {
+                let text = plain_text(&raw);
+                if !text.is_empty() {
+                    slide.blocks.push(Block::Bullet { level, text });
+                }
+            }
+            Capture::Code { language } => {
+                let tags = Regex::new(r"(?s)<[^>]*>").unwrap();
+                let code = decode_entities(&tags.replace_all(&raw, ""));
+                let code = code.trim_matches('\n').to_string();
+                if !code.trim().is_empty() {
+                    slide.blocks.push(Block::Code { language, code });
+                }
+            }
+            Capture::Notes => {
+                let tags = Regex::new(r"(?s)<[^>]*>").unwrap();
+                let notes = decode_entities(&tags.replace_all(&raw, ""))
+                    .trim()
+                    .to_string();
+                if !notes.is_empty() {
+                    slide.notes = Some(notes);
+                }
+            }
+        }
+    }
+
+    for m in token.captures_iter(html) {
+        let raw = m.get(0).unwrap().as_str();
+        if raw.starts_with("<!--") {
+            continue;
+        }
+        let Some(name) = m.get(1).map(|n| n.as_str().to_ascii_lowercase()) else {
+            // Text
+            match &mut capture {
+                Some((_, text)) => text.push_str(raw),
+                None => {
+                    let text = plain_text(raw);
+                    if !text.is_empty() {
+                        slide.blocks.push(Block::Text(text));
+                    }
+                }
+            }
+            continue;
+        };
+        let closing = raw.starts_with("</");
+
+        // Inside code and notes everything is kept, and headings keep their inline markup
+        if let Some((kind, text)) = &mut capture {
+            let ends = match kind {
+                Capture::Code { .. } => name == "pre",
+                Capture::Notes => name == "aside",
+                _ => false,
+            };
+            if let (Capture::Code { language }, "code", false) =
+                (&mut *kind, name.as_str(), closing)
+            {
+                if language.is_none() {
+                    *language = attribute(raw, "class")
+                        .and_then(|c| c.strip_prefix("language-").map(String::from));
+                }
+                continue;
+            }
+            if matches!(kind, Capture::Code { .. } | Capture::Notes) && !ends {
+                if name == "br" {
+                    text.push('\n');
+                }
+                continue;
+            }
+            if matches!(kind, Capture::Heading)
+                && !matches!(
+                    name.as_str(),
+                    "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "div"
+                )
+            {
+                text.push_str(raw);
+                continue;
+            }
+        }
+
+        match (name.as_str(), closing) {
+            ("div", false) => {
+                div_depth += 1;
+            }
+            ("div", true) => {
+                flush(&mut slide, &mut capture);
+                div_depth = div_depth.saturating_sub(1);
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                flush(&mut slide, &mut capture);
+                capture = Some((Capture::Heading, String::new()));
+            }
+            ("p", false) => {
+                flush(&mut slide, &mut capture);
+                let caption = attribute(raw, "class").is_some_and(|c| c.contains("caption"));
+                capture = Some((Capture::Paragraph { caption }, String::new()));
+            }
+            ("ul" | "ol", false) => {
+                flush(&mut slide, &mut capture);
+                list_depth += 1;
+            }
+            ("ul" | "ol", true) => {
+                flush(&mut slide, &mut capture);
+                list_depth = list_depth.saturating_sub(1);
+            }
+            ("li", false) => {
+                flush(&mut slide, &mut capture);
+                let level = list_depth.saturating_sub(1);
+                capture = Some((Capture::Item { level }, String::new()));
+            }
+            ("pre", false) => {
+                flush(&mut slide, &mut capture);
+                capture = Some((Capture::Code { language: None }, String::new()));
+            }
+            ("aside", false) if attribute(raw, "class").is_some_and(|c| c.contains("notes")) => {
+                flush(&mut slide, &mut capture);
+                capture = Some((Capture::Notes, String::new()));
+            }
+            ("img", _) => {
+                flush(&mut slide, &mut capture);
+                if let Some(src) = attribute(raw, "src") {
+                    let caption = attribute(raw, "alt").filter(|a| !a.trim().is_empty());
+                    slide.blocks.push(Block::Image { src, caption });
+                }
+            }
+            ("br", _) => {
+                if let Some((_, text)) = &mut capture {
+                    text.push(' ');
+                }
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" | "li" | "pre" | "aside", true) => {
+                flush(&mut slide, &mut capture);
+            }
+            _ => {}
+        }
+    }
+    flush(&mut slide, &mut capture);
+    slide
+}
+
+pub fn render_slide(slide: &Slide) -> String {
+    const INDENT: &str = "                ";
+    let mut html = String::from("            <div class=\"slide\">\n");
+    if !slide.title.is_empty() {
+        html.push_str(&format!("{}<h1>{}</h1>\n", INDENT, slide.title));
+    }
+
+    let mut depth = 0;
+    let mut item_open = vec![false];
+    let close_lists =
+        |html: &mut String, depth: &mut usize, item_open: &mut Vec<bool>, to: usize| {
+            while *depth > to {
+                if item_open.pop() == Some(true) {
+                    html.push_str("</li>");
+                }
+                html.push_str("</ul>");
+                *depth -= 1;
+            }
+        };
+
+    for block in &slide.blocks {
+        if !matches!(block, Block::Bullet { .. }) && depth > 0 {
+            close_lists(&mut html, &mut depth, &mut item_open, 0);
+            html.push('\n');
+        }
+        match block {
+            Block::Text(text) => {
+                html.push_str(&format!("{}<p>{}</p>\n", INDENT, escape_html(text)));
+            }
+            Block::Bullet { level, text } => {
+                let target = level.min(&4) + 1;
+                if depth == 0 {
+                    html.push_str(INDENT);
+                }
+                close_lists(&mut html, &mut depth, &mut item_open, target);
+                if depth == target && item_open.last() == Some(&true) {
+                    html.push_str("</li>");
+                }
+                while depth < target {
+                    html.push_str("<ul>");
+                    item_open.push(false);
+                    depth += 1;
+                }
+                html.push_str(&format!("<li>{}", escape_html(text)));
+                if let Some(open) = item_open.last_mut() {
+                    *open = true;
+                }
+            }
+            Block::Code { language, code } => {
+                let class = language
+                    .as_ref()
+                    .map(|l| format!(" class=\"language-{}\"", escape_html(l)))
+                    .unwrap_or_default();
+                html.push_str(&format!(
+                    "{}<pre><code{}>{}</code></pre>\n",
+                    INDENT,
+                    class,
+                    escape_html(code)
+                ));
+            }
+            Block::Image { src, caption } => {
+                html.push_str(&format!(
+                    "{}<img src=\"{}\" alt=\"{}\" style=\"max-width: 90%; max-height: 300px;\">\n",
+                    INDENT,
+                    escape_html(src),
+                    escape_html(caption.as_deref().unwrap_or_default())
+                ));
+                if let Some(caption) = caption {
+                    html.push_str(&format!(
+                        "{}<p class=\"caption\">{}</p>\n",
+                        INDENT,
+                        escape_html(caption)
+                    ));
+                }
+            }
+        }
+    }
+    if depth > 0 {
+        close_lists(&mut html, &mut depth, &mut item_open, 0);
+        html.push('\n');
+    }
+
+    if let Some(notes) = &slide.notes {
+        html.push_str(&format!(
+            "{}<aside class=\"notes\" style=\"display: none\">{}</aside>\n",
+            INDENT,
+            escape_html(notes)
+        ));
+    }
+    html.push_str("            </div>\n");
+    html
+}
+
+/// Reads the slide fields of add_slide and update_slide. The body is only returned when
+/// one of its fields is given, so an update can leave it alone.
+fn slide_fields(params: &Value) -> (Option<String>, Option<Vec<Block>>, Option<String>) {
+    let str_param = |name: &str| params.get(name).and_then(|v| v.as_str());
+
+    let title = str_param("title")
+        .map(escape_html)
+        .or_else(|| str_param("content").map(String::from));
+
+    let mut blocks = Vec::new();
+    let mut has_body = false;
+    if let Some(text) = str_param("text") {
+        has_body = true;
+        blocks.extend(
+            text.split("\n\n")
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(|p| Block::Text(p.split_whitespace().collect::<Vec<_>>().join(" "))),
+        );
+    }
+    if let Some(bullets) = params.get("bullets").and_then(|v| v.as_array()) {
+        has_body = true;
+        for bullet in bullets {
+            let (level, text) = match bullet {
+                Value::String(s) => {
+                    let indent = s.len() - s.trim_start().len();
+                    (indent / 2, s.trim().to_string())
+                }
+                other => (
+                    other.get("level").and_then(|v| v.as_u64()).unwrap_or(0) as usize,
+                    other
+                        .get("text")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                ),
+            };
+            if !text.is_empty() {
+                blocks.push(Block::Bullet { level, text });
+            }
+        }
+    }
+    if let Some(code) = str_param("code") {
+        has_body = true;
+        blocks.push(Block::Code {
+            language: str_param("language").map(String::from),
+            code: code.to_string(),
+        });
+    }
+    if let Some(src) = str_param("image") {
+        has_body = true;
+        blocks.push(Block::Image {
+            src: src.to_string(),
+            caption: str_param("caption").map(String::from),
+        });
+    }
+
+    let notes = str_param("notes").map(String::from);
+    (title, has_body.then_some(blocks), notes)
+}
+
+/// Converts a 1-based slide number from the params into an index
+fn slide_index(params: Option<&Value>, name: &str, count: usize) -> Result<usize, ToolError> {
+    let number = params
+        .and_then(|p| p.get(name))
+        .and_then(|v| v.as_u64())
+        .ok_or_else(|| ToolError::InvalidParameters(format!("Missing '{}' parameter", name)))?
+        as usize;
+    if number == 0 || number > count {
+        return Err(ToolError::InvalidParameters(format!(
+            "Slide {} does not exist, the presentation has {} slides",
+            number, count
+        )));
+    }
+    Ok(number - 1)
+}
+
 pub async fn make_presentation(
     path: &str,
     operation: &str,
@@ -213,59 +743,182 @@ pub async fn make_presentation(
             ))])
         }
         "add_slide" => {
-            let content = params
-                .and_then(|p| p.get("content"))
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| {
-                    ToolError::InvalidParameters("Missing 'content' parameter for slide".into())
-                })?;
-
-            // Read existing file
-            let mut html = fs::read_to_string(path).map_err(|e| {
-                ToolError::ExecutionError(format!("Failed to read presentation file: {}", e))
-            })?;
-
-            // Find the marker comment
-            let marker = "<!-- ADD_SLIDES_HERE";
-            let insert_pos = html.find(marker).ok_or_else(|| {
-                ToolError::ExecutionError("Invalid presentation file format".into())
-            })?;
-
-            // Count actual slides (excluding template)
-            let current_slides = html.matches("class=\"slide\"").count() - 1; // -1 for template
-            let new_count = current_slides + 1;
+            let (title, blocks, notes) = params.map(slide_fields).unwrap_or_default();
+            if title.is_none() && blocks.is_none() {
+                return Err(ToolError::InvalidParameters(
+                    "Missing 'content' parameter for slide".into(),
+                ));
+            }
 
-            // Update the num-slides value
-            html = html.replace(
-                &format!("--num-slides: {}", current_slides),
-                &format!("--num-slides: {}", new_count),
-            );
+            let deck = Deck::load(path)?;
+            let mut slides = deck.slides();
+            let slide = render_slide(&Slide {
+                title: title.unwrap_or_default(),
+                blocks: blocks.unwrap_or_default(),
+                notes,
+            });
+            match params
+                .and_then(|p| p.get("position"))
+                .and_then(|v| v.as_u64())
+            {
+                Some(position) if position >= 1 && (position as usize) <= slides.len() => {
+                    slides.insert(position as usize - 1, slide)
+                }
+                _ => slides.push(slide),
+            }
+            deck.save(path, &slides)?;
 
-            // Create new slide HTML
-            let slide_html = format!(
-                r#"            <div class="slide">
-                <h1>{}</h1>
-            </div>
+            Ok(vec![Content::text(format!(
+                "Added new slide to presentation. You can view it with: open {}\nNote: when creating, or adding a slide, if the content for a slide is long, edit it so that it uses appropriate size, formatting, lists etc (and can even split it to other slides if needed).",
+                path
+            ))])
+        }
+        "update_slide" => {
+            let deck = Deck::load(path)?;
+            let mut slides = deck.slides();
+            let index = slide_index(params, "index", slides.len())?;
+            let (title, blocks, notes) = params.map(slide_fields).unwrap_or_default();
 
-            {}"#,
-                content, marker
-            );
+            let mut slide = parse_slide(&slides[index]);
+            if let Some(title) = title {
+                slide.title = title;
+            }
+            if let Some(blocks) = blocks {
+                slide.blocks = blocks;
+            }
+            if let Some(notes) = notes {
+                slide.notes = (!notes.trim().is_empty()).then_some(notes);
+            }
+            slides[index] = render_slide(&slide);
+            deck.save(path, &slides)?;
 
-            // Insert the new slide
-            html.replace_range(insert_pos..insert_pos + marker.len(), &slide_html);
+            Ok(vec![Content::text(format!(
+                "Updated slide {} in {}",
+                index + 1,
+                path
+            ))])
+        }
+        "delete_slide" => {
+            let deck = Deck::load(path)?;
+            let mut slides = deck.slides();
+            let index = slide_index(params, "index", slides.len())?;
+            slides.remove(index);
+            deck.save(path, &slides)?;
 
-            // Save the file
-            fs::write(path, html).map_err(|e| {
-                ToolError::ExecutionError(format!("Failed to update presentation file: {}", e))
-            })?;
+            Ok(vec![Content::text(format!(
+                "Deleted slide {} from {}, {} slides remain",
+                index + 1,
+                path,
+                slides.len()
+            ))])
+        }
+        "move_slide" => {
+            let deck = Deck::load(path)?;
+            let mut slides = deck.slides();
+            let from = slide_index(params, "index", slides.len())?;
+            let to = slide_index(params, "to", slides.len())?;
+            let slide = slides.remove(from);
+            slides.insert(to, slide);
+            deck.save(path, &slides)?;
 
             Ok(vec![Content::text(format!(
-                "Added new slide to presentation. You can view it with: open {}\nNote: when creating, or adding a slide, if the content for a slide is long, edit it so that it uses appropriate size, formatting, lists etc (and can even split it to other slides if needed).",
+                "Moved slide {} to position {} in {}",
+                from + 1,
+                to + 1,
                 path
             ))])
         }
+        "list_slides" => {
+            let deck = Deck::load(path)?;
+            let lines: Vec<String> = deck
+                .slides()
+                .iter()
+                .map(|html| parse_slide(html))
+                .enumerate()
+                .map(|(i, slide)| {
+                    let mut parts = Vec::new();
+                    let count =
+                        |f: fn(&Block) -> bool| slide.blocks.iter().filter(|b| f(b)).count();
+                    for (n, label) in [
+                        (count(|b| matches!(b, Block::Text(_))), "paragraph"),
+                        (count(|b| matches!(b, Block::Bullet { .. })), "bullet"),
+                        (count(|b| matches!(b, Block::Code { .. })), "code block"),
+                        (count(|b| matches!(b, Block::Image { .. })), "image"),
+                    ] {
+                        if n > 0 {
+                            parts.push(format!("{} {}{}", n, label, if n == 1 { "" } else { "s" }));
+                        }
+                    }
+                    if slide.notes.is_some() {
+                        parts.push("speaker notes".to_string());
+                    }
+                    let title = plain_text(&slide.title);
+                    format!(
+                        "{}. {}{}",
+                        i + 1,
+                        if title.is_empty() {
+                            "(untitled)"
+                        } else {
+                            &title
+                        },
+                        if parts.is_empty() {
+                            String::new()
+                        } else {
+                            format!(" ({})", parts.join(", "))
+                        }
+                    )
+                })
+                .collect();
+            Ok(vec![Content::text(format!(
+                "{} has {} slides:\n{}",
+                path,
+                lines.len(),
+                lines.join("\n")
+            ))])
+        }
+        "export" => {
+            let format = params
+                .and_then(|p| p.get("format"))
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| ToolError::InvalidParameters("Missing 'format' parameter".into()))?;
+            if !matches!(format, "pptx" | "pdf") {
+                return Err(ToolError::InvalidParameters(format!(
+                    "Invalid format: {}. Supported formats are: pptx, pdf",
+                    format
+                )));
+            }
+            let output = params
+                .and_then(|p| p.get("output_path"))
+                .and_then(|v| v.as_str())
+                .map(PathBuf::from)
+                .unwrap_or_else(|| Path::new(path).with_extension(format));
+            let include_notes = params
+                .and_then(|p| p.get("include_notes"))
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+            let deck = Deck::load(path)?;
+            let slides: Vec<Slide> = deck.slides().iter().map(|s| parse_slide(s)).collect();
+            let base_dir = Path::new(path).parent().unwrap_or(Path::new("."));
+            let warnings = match format {
+                "pptx" => presentation_export::write_pptx(&slides, base_dir, &output)?,
+                _ => presentation_export::write_pdf(&slides, base_dir, &output, include_notes)?,
+            };
+
+            let mut message = format!(
+                "Exported {} slides from {} to {}",
+                slides.len(),
+                path,
+                output.display()
+            );
+            for warning in warnings {
+                message.push_str(&format!("\nWarning: {}", warning));
+            }
+            Ok(vec![Content::text(message)])
+        }
         _ => Err(ToolError::InvalidParameters(format!(
-            "Invalid operation: {}. Valid operations are: create, add_slide",
+            "Invalid operation: {}. Valid operations are: create, add_slide, update_slide, \
+             delete_slide, move_slide, list_slides, export",
             operation
         ))),
     }
@@ -395,4 +1048,288 @@ mod tests {
             _ => panic!("Expected InvalidParameters error"),
         }
     }
+
+    #[tokio::test]
+    async fn test_edit_reorder_and_delete_slides() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_path = test_dir.path().join("deck.html");
+        let path_str = test_path.to_str().unwrap();
+
+        let result = make_presentation(path_str, "create", None).await;
+        assert!(result.is_ok(), "Should successfully create presentation");
+
+        // Add slides with bullets and notes, code, and plain content
+        let params = serde_json::json!({
+            "title": "Agenda",
+            "bullets": ["Intro", "  Details", "Wrap up"],
+            "notes": "Say hi"
+        });
+        let result = make_presentation(path_str, "add_slide", Some(&params)).await;
+        assert!(result.is_ok(), "Should add a bullet slide");
+        let params = serde_json::json!({
+            "title": "Code",
+            "code": "fn main() {\n    println!(\"<hi>\");\n}",
+            "language": "rust"
+        });
+        let result = make_presentation(path_str, "add_slide", Some(&params)).await;
+        assert!(result.is_ok(), "Should add a code slide");
+        let params = serde_json::json!({
+            "content": "Closing"
+        });
+        let result = make_presentation(path_str, "add_slide", Some(&params)).await;
+        assert!(result.is_ok(), "Should add a content slide");
+
+        let content = fs::read_to_string(&test_path).unwrap();
+        assert!(
+            content.contains("--num-slides: 4"),
+            "Should have correct slide count"
+        );
+        assert!(
+            content.contains("<aside class=\"notes\" style=\"display: none\">Say hi</aside>"),
+            "Should contain hidden speaker notes"
+        );
+
+        // Verify the listing
+        let list = make_presentation(path_str, "list_slides", None)
+            .await
+            .unwrap();
+        let list = list[0].as_text().unwrap();
+        assert!(
+            list.contains("1. Your Presentation (1 paragraph)"),
+            "Should list the title slide: {}",
+            list
+        );
+        assert!(
+            list.contains("2. Agenda (3 bullets, speaker notes)"),
+            "Should list the bullet slide: {}",
+            list
+        );
+        assert!(
+            list.contains("3. Code (1 code block)"),
+            "Should list the code slide: {}",
+            list
+        );
+        assert!(
+            list.contains("4. Closing"),
+            "Should list the content slide: {}",
+            list
+        );
+
+        // Slides read back the way they were written
+        let deck = Deck::load(path_str).unwrap();
+        let slides = deck.slides();
+        let agenda = parse_slide(&slides[1]);
+        assert_eq!(
+            agenda.blocks[1],
+            Block::Bullet {
+                level: 1,
+                text: "Details".to_string()
+            }
+        );
+        assert_eq!(render_slide(&agenda), slides[1]);
+        let code = parse_slide(&slides[2]);
+        assert_eq!(
+            code.blocks,
+            vec![Block::Code {
+                language: Some("rust".to_string()),
+                code: "fn main() {\n    println!(\"<hi>\");\n}".to_string()
+            }]
+        );
+
+        // Move, update and delete slides
+        let params = serde_json::json!({
+            "index": 4,
+            "to": 2
+        });
+        let result = make_presentation(path_str, "move_slide", Some(&params)).await;
+        assert!(result.is_ok(), "Should move a slide");
+        let params = serde_json::json!({
+            "index": 3,
+            "text": "Only text now"
+        });
+        let result = make_presentation(path_str, "update_slide", Some(&params)).await;
+        assert!(result.is_ok(), "Should update a slide");
+        let params = serde_json::json!({
+            "index": 1
+        });
+        let result = make_presentation(path_str, "delete_slide", Some(&params)).await;
+        assert!(result.is_ok(), "Should delete a slide");
+
+        let list = make_presentation(path_str, "list_slides", None)
+            .await
+            .unwrap();
+        let list = list[0].as_text().unwrap();
+        assert!(
+            list.contains("has 3 slides"),
+            "Should have 3 slides: {}",
+            list
+        );
+        assert!(
+            list.contains("1. Closing\n2. Agenda (1 paragraph, speaker notes)\n3. Code"),
+            "Should list the slides in their new order: {}",
+            list
+        );
+        let content = fs::read_to_string(&test_path).unwrap();
+        assert!(
+            content.contains("--num-slides: 3"),
+            "Should have correct slide count"
+        );
+        assert!(
+            content.contains("SLIDE_TEMPLATE"),
+            "Should keep the slide template"
+        );
+
+        // Deleting a slide that does not exist fails
+        let params = serde_json::json!({
+            "index": 9
+        });
+        let result = make_presentation(path_str, "delete_slide", Some(&params)).await;
+        match result {
+            Err(ToolError::InvalidParameters(_)) => {}
+            _ => panic!("Expected InvalidParameters error"),
+        }
+
+        // Clean up
+        test_dir.close().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_pptx_and_pdf() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_path = test_dir.path().join("deck.html");
+        let path_str = test_path.to_str().unwrap();
+
+        let params = serde_json::json!({
+            "title": "Launch Plan"
+        });
+        let result = make_presentation(path_str, "create", Some(&params)).await;
+        assert!(result.is_ok(), "Should successfully create presentation");
+
+        image::RgbaImage::from_pixel(40, 20, image::Rgba([200, 30, 30, 255]))
+            .save(test_dir.path().join("chart.png"))
+            .unwrap();
+        let params = serde_json::json!({
+            "title": "Results",
+            "bullets": ["Revenue up", "Costs down"],
+            "image": "chart.png",
+            "caption": "Quarterly chart",
+            "notes": "Mention the new market"
+        });
+        let result = make_presentation(path_str, "add_slide", Some(&params)).await;
+        assert!(result.is_ok(), "Should add an image slide");
+        let params = serde_json::json!({
+            "title": "Snippet",
+            "code": "let x = 1;"
+        });
+        let result = make_presentation(path_str, "add_slide", Some(&params)).await;
+        assert!(result.is_ok(), "Should add a code slide");
+
+        // Export to PowerPoint next to the deck
+        let params = serde_json::json!({
+            "format": "pptx"
+        });
+        let exported = make_presentation(path_str, "export", Some(&params))
+            .await
+            .unwrap();
+        let exported = exported[0].as_text().unwrap();
+        assert!(
+            exported.contains("Exported 3 slides"),
+            "Should export every slide: {}",
+            exported
+        );
+        assert!(
+            !exported.contains("Warning"),
+            "Should export without warnings: {}",
+            exported
+        );
+
+        let pptx = test_dir.path().join("deck.pptx");
+        let mut archive = zip::ZipArchive::new(fs::File::open(&pptx).unwrap()).unwrap();
+        let mut read = |name: &str| {
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut archive.by_name(name).unwrap(), &mut text).unwrap();
+            text
+        };
+        assert!(
+            read("ppt/presentation.xml").contains(r#"<p:sldId id="258" r:id="rId4"/>"#),
+            "Should list the third slide"
+        );
+        let slide = read("ppt/slides/slide2.xml");
+        assert!(
+            slide.contains("<a:t>Results</a:t>") && slide.contains("<a:t>Costs down</a:t>"),
+            "Should contain the slide text"
+        );
+        assert!(
+            slide.contains(r#"<a:blip r:embed="rId2"/>"#),
+            "Should embed the image"
+        );
+        assert!(
+            read("ppt/slides/_rels/slide2.xml.rels").contains("../media/image1.png"),
+            "Should link the image"
+        );
+        assert!(
+            read("ppt/notesSlides/notesSlide2.xml").contains("Mention the new market"),
+            "Should contain the speaker notes"
+        );
+        assert!(
+            read("[Content_Types].xml").contains("/ppt/notesSlides/notesSlide2.xml"),
+            "Should declare the notes part"
+        );
+
+        // Export to PDF with a page of speaker notes
+        let pdf = test_dir.path().join("out.pdf");
+        let params = serde_json::json!({
+            "format": "pdf",
+            "output_path": pdf.to_str().unwrap(),
+            "include_notes": true
+        });
+        let exported = make_presentation(path_str, "export", Some(&params))
+            .await
+            .unwrap();
+        let exported = exported[0].as_text().unwrap();
+        assert!(
+            exported.contains("Exported 3 slides"),
+            "Should export every slide: {}",
+            exported
+        );
+        let doc = lopdf::Document::load(&pdf).unwrap();
+        assert_eq!(doc.get_pages().len(), 4, "three slides and one notes page");
+
+        let text = crate::computercontroller::pdf_tool::pdf_tool(
+            pdf.to_str().unwrap(),
+            "extract_text",
+            &Default::default(),
+            test_dir.path(),
+        )
+        .await
+        .unwrap();
+        let text = text[0].as_text().unwrap();
+        for expected in [
+            "Launch Plan",
+            "Revenue up",
+            "Quarterly chart",
+            "let x = 1;",
+            "Mention the new market",
+        ] {
+            assert!(
+                text.contains(expected),
+                "Should contain {:?} in {}",
+                expected,
+                text
+            );
+        }
+
+        // Unsupported formats are rejected
+        let params = serde_json::json!({
+            "format": "key"
+        });
+        let result = make_presentation(path_str, "export", Some(&params)).await;
+        match result {
+            Err(ToolError::InvalidParameters(_)) => {}
+            _ => panic!("Expected InvalidParameters error"),
+        }
+
+        // Clean up
+        test_dir.close().unwrap();
+    }
 }
@@ -0,0 +1,201 @@
+//! A robots.txt parser, so web_scrape only fetches pages a site allows crawlers to fetch.
+//!
+//! Follows RFC 9309: the group naming our product token applies if there is one, otherwise
+//! the `*` group. The longest matching rule wins, with `Allow` winning ties, and rules may use
+//! `*` wildcards and a `$` end anchor.
+
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default)]
+pub struct Robots {
+    /// (allow, pattern) pairs from the group that applies to us
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+#[derive(Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    /// Rules for a site without a robots.txt, which allow everything
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// Parse robots.txt content for the agent with the given product token, such as "goose"
+    pub fn parse(content: &str, agent: &str) -> Self {
+        let mut groups: Vec<Group> = Vec::new();
+        // A user-agent line after rules starts a new group
+        let mut in_rules = true;
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_lowercase().as_str() {
+                "user-agent" => {
+                    if in_rules || groups.is_empty() {
+                        groups.push(Group::default());
+                        in_rules = false;
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_lowercase());
+                    }
+                }
+                key @ ("allow" | "disallow") => {
+                    in_rules = true;
+                    // An empty disallow allows everything, so it adds no rule
+                    if let (Some(group), false) = (groups.last_mut(), value.is_empty()) {
+                        group.rules.push((key == "allow", value.to_string()));
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    if let (Some(group), Ok(seconds)) = (groups.last_mut(), value.parse::<f64>()) {
+                        // Delays too long for a Duration are still a request to go slowly
+                        if seconds.is_finite() && seconds >= 0.0 {
+                            group.crawl_delay =
+                                Some(Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let agent = agent.to_lowercase();
+        let named: Vec<&Group> = groups
+            .iter()
+            .filter(|g| {
+                // Product tokens match exactly, so "mongoose" rules are not ours
+                g.agents.contains(&agent)
+            })
+            .collect();
+        let applicable = if named.is_empty() {
+            groups
+                .iter()
+                .filter(|g| g.agents.iter().any(|a| a == "*"))
+                .collect()
+        } else {
+            named
+        };
+
+        Self {
+            rules: applicable
+                .iter()
+                .flat_map(|g| g.rules.iter().cloned())
+                .collect(),
+            crawl_delay: applicable.iter().find_map(|g| g.crawl_delay),
+        }
+    }
+
+    /// Whether a path, including its query string, may be fetched
+    pub fn is_allowed(&self, path: &str) -> bool {
+        if path == "/robots.txt" {
+            return true;
+        }
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if !path.starts_with(parts[0]) {
+        return false;
+    }
+    if parts.len() == 1 {
+        return !anchored || path.len() == pattern.len();
+    }
+
+    let mut position = parts[0].len();
+    for part in &parts[1..parts.len() - 1] {
+        match path[position..].find(part) {
+            Some(found) => position += found + part.len(),
+            None => return false,
+        }
+    }
+    let last = parts[parts.len() - 1];
+    if anchored {
+        path.len() >= position + last.len() && path.ends_with(last)
+    } else {
+        path[position..].contains(last)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "
+        # Example
+        User-agent: *
+        Disallow: /private/
+        Allow: /private/public
+        Disallow: /*.pdf$
+        Crawl-delay: 2
+
+        User-agent: otherbot
+        User-agent: goose
+        Disallow: /nogoose
+        Disallow:
+    ";
+
+    #[test]
+    fn test_default_group_rules() {
+        let robots = Robots::parse(ROBOTS, "somebot");
+        assert!(robots.is_allowed("/"));
+        assert!(!robots.is_allowed("/private/page"));
+        assert!(robots.is_allowed("/private/public/page"));
+        assert!(!robots.is_allowed("/files/report.pdf"));
+        assert!(robots.is_allowed("/files/report.pdf?download=1"));
+        assert!(robots.is_allowed("/robots.txt"));
+        assert_eq!(robots.crawl_delay(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_named_group_replaces_default() {
+        let robots = Robots::parse(ROBOTS, "goose");
+        assert!(robots.is_allowed("/private/page"));
+        assert!(!robots.is_allowed("/nogoose/page"));
+        assert_eq!(robots.crawl_delay(), None);
+
+        assert!(Robots::allow_all().is_allowed("/anything"));
+    }
+
+    #[test]
+    fn test_agent_matches_whole_product_token() {
+        let content = "User-agent: mongoose\nDisallow: /\n\nUser-agent: GOOSE\nDisallow: /mine";
+        let robots = Robots::parse(content, "goose");
+        assert!(robots.is_allowed("/page"));
+        assert!(!robots.is_allowed("/mine"));
+
+        let robots = Robots::parse(content, "goosebot");
+        assert!(robots.is_allowed("/mine"));
+    }
+
+    #[test]
+    fn test_out_of_range_crawl_delay() {
+        let robots = Robots::parse("User-agent: *\nCrawl-delay: 1e300", "goose");
+        assert_eq!(robots.crawl_delay(), Some(Duration::MAX));
+        let robots = Robots::parse("User-agent: *\nCrawl-delay: -5", "goose");
+        assert_eq!(robots.crawl_delay(), None);
+    }
+}
@@ -0,0 +1,631 @@
+//! Web search backends for the web_search tool.
+//!
+//! Each backend turns a query into a ranked list of results and keeps the raw response so it
+//! can be cached as a resource. The backend is chosen through environment variables:
+//! - `GOOSE_SEARCH_BACKEND`: `duckduckgo` (default), `searxng`, `brave`, `tavily` or `local`
+//! - `GOOSE_SEARCH_SEARXNG_URL`: base URL of the SearXNG instance, required for `searxng`
+//! - `BRAVE_API_KEY`: subscription token, required for `brave`
+//! - `TAVILY_API_KEY`: API key, required for `tavily`
+//! - `GOOSE_SEARCH_LOCAL_FILE`: JSON file of results to search, required for `local`
+
+use async_trait::async_trait;
+use mcp_core::handler::ToolError;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Snippets longer than this are cut at a word boundary
+const MAX_SNIPPET_CHARS: usize = 300;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+    /// Relevance reported by the backend, higher is better
+    pub score: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResponse {
+    pub results: Vec<SearchResult>,
+    /// The response body as returned by the backend
+    pub raw: String,
+}
+
+#[async_trait]
+pub trait SearchBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn search(
+        &self,
+        client: &Client,
+        query: &str,
+        limit: usize,
+    ) -> Result<SearchResponse, ToolError>;
+}
+
+/// Build the backend selected by `GOOSE_SEARCH_BACKEND`
+pub fn backend_from_env() -> Result<Box<dyn SearchBackend>, ToolError> {
+    let required = |name: &str| {
+        std::env::var(name)
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .ok_or_else(|| {
+                ToolError::ExecutionError(format!(
+                    "{} must be set to use this search backend",
+                    name
+                ))
+            })
+    };
+
+    match std::env::var("GOOSE_SEARCH_BACKEND")
+        .unwrap_or_default()
+        .to_lowercase()
+        .as_str()
+    {
+        "" | "duckduckgo" | "ddg" => Ok(Box::new(DuckDuckGoBackend::default())),
+        "searxng" => Ok(Box::new(SearxngBackend::new(required(
+            "GOOSE_SEARCH_SEARXNG_URL",
+        )?))),
+        "brave" => Ok(Box::new(BraveBackend::new(required("BRAVE_API_KEY")?))),
+        "tavily" => Ok(Box::new(TavilyBackend::new(required("TAVILY_API_KEY")?))),
+        "local" => Ok(Box::new(LocalBackend::new(PathBuf::from(required(
+            "GOOSE_SEARCH_LOCAL_FILE",
+        )?)))),
+        other => Err(ToolError::ExecutionError(format!(
+            "Unknown GOOSE_SEARCH_BACKEND '{}'. Valid options are: duckduckgo, searxng, brave, tavily, local",
+            other
+        ))),
+    }
+}
+
+/// Order results by backend score when there is one, drop duplicate URLs and keep `limit`
+pub fn rank(mut results: Vec<SearchResult>, limit: usize) -> Vec<SearchResult> {
+    if results.iter().all(|r| r.score.is_some()) {
+        // A stable sort keeps the backend order for equal scores
+        results.sort_by(|a, b| {
+            b.score
+                .unwrap_or_default()
+                .total_cmp(&a.score.unwrap_or_default())
+        });
+    }
+
+    let mut seen = HashSet::new();
+    results
+        .into_iter()
+        .filter(|r| !r.url.is_empty() && seen.insert(r.url.trim_end_matches('/').to_string()))
+        .map(|r| SearchResult {
+            title: clean_text(&r.title),
+            snippet: truncate(&clean_text(&r.snippet), MAX_SNIPPET_CHARS),
+            ..r
+        })
+        .take(limit)
+        .collect()
+}
+
+/// Render ranked results as a numbered list for the model
+pub fn format_results(query: &str, backend: &str, results: &[SearchResult]) -> String {
+    if results.is_empty() {
+        return format!("No results for \"{}\" from {}.", query, backend);
+    }
+
+    let mut output = format!("Results for \"{}\" from {}:\n", query, backend);
+    for (i, result) in results.iter().enumerate() {
+        let title = if result.title.is_empty() {
+            &result.url
+        } else {
+            &result.title
+        };
+        output.push_str(&format!("\n{}. {}\n   {}\n", i + 1, title, result.url));
+        if !result.snippet.is_empty() {
+            output.push_str(&format!("   {}\n", result.snippet));
+        }
+    }
+    output
+}
+
+/// Strip markup the backends put in titles and snippets, such as <strong> highlights
+fn clean_text(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => plain.push(c),
+            _ => {}
+        }
+    }
+    let plain = plain
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'");
+    plain.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    let cut = match cut.rfind(' ') {
+        Some(space) if space > max_chars / 2 => &cut[..space],
+        _ => &cut,
+    };
+    format!("{}...", cut.trim_end())
+}
+
+fn str_field(value: &Value, key: &str) -> String {
+    value
+        .get(key)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn parse_json(raw: &str, backend: &str) -> Result<Value, ToolError> {
+    serde_json::from_str(raw)
+        .map_err(|e| ToolError::ExecutionError(format!("Invalid response from {}: {}", backend, e)))
+}
+
+async fn read_body(response: reqwest::Response, backend: &str) -> Result<String, ToolError> {
+    let status = response.status();
+    let body = response.text().await.map_err(|e| {
+        ToolError::ExecutionError(format!("Failed to read {} response: {}", backend, e))
+    })?;
+    if !status.is_success() {
+        return Err(ToolError::ExecutionError(format!(
+            "{} search failed with status {}: {}",
+            backend,
+            status,
+            truncate(body.trim(), 200)
+        )));
+    }
+    Ok(body)
+}
+
+fn send_error(backend: &str, e: reqwest::Error) -> ToolError {
+    ToolError::ExecutionError(format!("Failed to fetch {} results: {}", backend, e))
+}
+
+/// The DuckDuckGo instant answer API, which needs no key but only answers well known topics
+#[derive(Debug, Clone)]
+pub struct DuckDuckGoBackend {
+    endpoint: String,
+}
+
+impl Default for DuckDuckGoBackend {
+    fn default() -> Self {
+        Self {
+            endpoint: "https://api.duckduckgo.com/".to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for DuckDuckGoBackend {
+    fn name(&self) -> &'static str {
+        "DuckDuckGo"
+    }
+
+    async fn search(
+        &self,
+        client: &Client,
+        query: &str,
+        limit: usize,
+    ) -> Result<SearchResponse, ToolError> {
+        let response = client
+            .get(&self.endpoint)
+            .query(&[("q", query), ("format", "json"), ("no_html", "1")])
+            .send()
+            .await
+            .map_err(|e| send_error(self.name(), e))?;
+        let raw = read_body(response, self.name()).await?;
+        let results = parse_duckduckgo(&parse_json(&raw, self.name())?);
+        Ok(SearchResponse {
+            results: rank(results, limit),
+            raw,
+        })
+    }
+}
+
+fn parse_duckduckgo(json: &Value) -> Vec<SearchResult> {
+    let mut results = Vec::new();
+
+    let abstract_url = str_field(json, "AbstractURL");
+    if !abstract_url.is_empty() {
+        results.push(SearchResult {
+            title: str_field(json, "Heading"),
+            url: abstract_url,
+            snippet: str_field(json, "AbstractText"),
+            score: None,
+        });
+    }
+
+    fn collect_topics(topics: &[Value], results: &mut Vec<SearchResult>) {
+        for topic in topics {
+            // Topic groups nest their entries under "Topics"
+            if let Some(nested) = topic.get("Topics").and_then(|t| t.as_array()) {
+                collect_topics(nested, results);
+                continue;
+            }
+            let text = str_field(topic, "Text");
+            let title = text.split(" - ").next().unwrap_or_default().to_string();
+            results.push(SearchResult {
+                title,
+                url: str_field(topic, "FirstURL"),
+                snippet: text,
+                score: None,
+            });
+        }
+    }
+
+    for key in ["Results", "RelatedTopics"] {
+        if let Some(topics) = json.get(key).and_then(|t| t.as_array()) {
+            collect_topics(topics, &mut results);
+        }
+    }
+    results
+}
+
+/// A SearXNG instance with the JSON output format enabled
+#[derive(Debug, Clone)]
+pub struct SearxngBackend {
+    base_url: String,
+}
+
+impl SearxngBackend {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for SearxngBackend {
+    fn name(&self) -> &'static str {
+        "SearXNG"
+    }
+
+    async fn search(
+        &self,
+        client: &Client,
+        query: &str,
+        limit: usize,
+    ) -> Result<SearchResponse, ToolError> {
+        let response = client
+            .get(format!("{}/search", self.base_url))
+            .query(&[("q", query), ("format", "json")])
+            .send()
+            .await
+            .map_err(|e| send_error(self.name(), e))?;
+        let raw = read_body(response, self.name()).await?;
+        let results = parse_searxng(&parse_json(&raw, self.name())?);
+        Ok(SearchResponse {
+            results: rank(results, limit),
+            raw,
+        })
+    }
+}
+
+fn parse_searxng(json: &Value) -> Vec<SearchResult> {
+    json.get("results")
+        .and_then(|r| r.as_array())
+        .map(|results| {
+            results
+                .iter()
+                .map(|r| SearchResult {
+                    title: str_field(r, "title"),
+                    url: str_field(r, "url"),
+                    snippet: str_field(r, "content"),
+                    score: r.get("score").and_then(|s| s.as_f64()),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The Brave Search web API
+#[derive(Debug, Clone)]
+pub struct BraveBackend {
+    api_key: String,
+    endpoint: String,
+}
+
+impl BraveBackend {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            api_key: api_key.into(),
+            endpoint: "https://api.search.brave.com/res/v1/web/search".to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for BraveBackend {
+    fn name(&self) -> &'static str {
+        "Brave"
+    }
+
+    async fn search(
+        &self,
+        client: &Client,
+        query: &str,
+        limit: usize,
+    ) -> Result<SearchResponse, ToolError> {
+        // Brave accepts at most 20 results per request
+        let count = limit.clamp(1, 20).to_string();
+        let response = client
+            .get(&self.endpoint)
+            .query(&[("q", query), ("count", count.as_str())])
+            .header("Accept", "application/json")
+            .header("X-Subscription-Token", &self.api_key)
+            .send()
+            .await
+            .map_err(|e| send_error(self.name(), e))?;
+        let raw = read_body(response, self.name()).await?;
+        let results = parse_brave(&parse_json(&raw, self.name())?);
+        Ok(SearchResponse {
+            results: rank(results, limit),
+            raw,
+        })
+    }
+}
+
+fn parse_brave(json: &Value) -> Vec<SearchResult> {
+    json.pointer("/web/results")
+        .and_then(|r| r.as_array())
+        .map(|results| {
+            results
+                .iter()
+                .map(|r| SearchResult {
+                    title: str_field(r, "title"),
+                    url: str_field(r, "url"),
+                    snippet: str_field(r, "description"),
+                    score: None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The Tavily search API
+#[derive(Debug, Clone)]
+pub struct TavilyBackend {
+    api_key: String,
+    endpoint: String,
+}
+
+impl TavilyBackend {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            api_key: api_key.into(),
+            endpoint: "https://api.tavily.com/search".to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for TavilyBackend {
+    fn name(&self) -> &'static str {
+        "Tavily"
+    }
+
+    async fn search(
+        &self,
+        client: &Client,
+        query: &str,
+        limit: usize,
+    ) -> Result<SearchResponse, ToolError> {
+        let response = client
+            .post(&self.endpoint)
+            .bearer_auth(&self.api_key)
+            .json(&json!({
+                "query": query,
+                "max_results": limit.clamp(1, 20),
+            }))
+            .send()
+            .await
+            .map_err(|e| send_error(self.name(), e))?;
+        let raw = read_body(response, self.name()).await?;
+        let results = parse_tavily(&parse_json(&raw, self.name())?);
+        Ok(SearchResponse {
+            results: rank(results, limit),
+            raw,
+        })
+    }
+}
+
+fn parse_tavily(json: &Value) -> Vec<SearchResult> {
+    json.get("results")
+        .and_then(|r| r.as_array())
+        .map(|results| {
+            results
+                .iter()
+                .map(|r| SearchResult {
+                    title: str_field(r, "title"),
+                    url: str_field(r, "url"),
+                    snippet: str_field(r, "content"),
+                    score: r.get("score").and_then(|s| s.as_f64()),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Searches a JSON file of results instead of the network, for tests and offline use.
+///
+/// The file holds an array of `{"title", "url", "snippet"}` objects, or an object with that
+/// array under "results". Entries are scored by how many query terms they contain.
+#[derive(Debug, Clone)]
+pub struct LocalBackend {
+    path: PathBuf,
+}
+
+impl LocalBackend {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for LocalBackend {
+    fn name(&self) -> &'static str {
+        "local index"
+    }
+
+    async fn search(
+        &self,
+        _client: &Client,
+        query: &str,
+        limit: usize,
+    ) -> Result<SearchResponse, ToolError> {
+        let content = tokio::fs::read_to_string(&self.path).await.map_err(|e| {
+            ToolError::ExecutionError(format!(
+                "Failed to read local search file {}: {}",
+                self.path.display(),
+                e
+            ))
+        })?;
+        let json = parse_json(&content, self.name())?;
+        let entries = json
+            .get("results")
+            .unwrap_or(&json)
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+
+        let terms: Vec<String> = query.split_whitespace().map(|t| t.to_lowercase()).collect();
+        let results: Vec<SearchResult> = entries
+            .iter()
+            .filter_map(|entry| {
+                let title = str_field(entry, "title");
+                let snippet = str_field(entry, "snippet");
+                let haystack = format!("{} {}", title, snippet).to_lowercase();
+                let hits = terms.iter().filter(|t| haystack.contains(*t)).count();
+                (hits > 0).then(|| SearchResult {
+                    title,
+                    url: str_field(entry, "url"),
+                    snippet,
+                    score: Some(hits as f64),
+                })
+            })
+            .collect();
+
+        let results = rank(results, limit);
+        let raw = serde_json::to_string_pretty(&json!({
+            "query": query,
+            "results": results
+                .iter()
+                .map(|r| json!({"title": r.title, "url": r.url, "snippet": r.snippet, "score": r.score}))
+                .collect::<Vec<_>>(),
+        }))
+        .unwrap_or_default();
+        Ok(SearchResponse { results, raw })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(url: &str, score: Option<f64>) -> SearchResult {
+        SearchResult {
+            title: url.to_string(),
+            url: url.to_string(),
+            snippet: String::new(),
+            score,
+        }
+    }
+
+    #[test]
+    fn test_rank_sorts_dedupes_and_limits() {
+        let ranked = rank(
+            vec![
+                result("https://a.example", Some(0.2)),
+                result("https://b.example", Some(0.9)),
+                result("https://b.example/", Some(0.5)),
+                result("https://c.example", Some(0.4)),
+            ],
+            2,
+        );
+        let urls: Vec<_> = ranked.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://b.example", "https://c.example"]);
+
+        // Without scores the backend order is kept
+        let ranked = rank(
+            vec![
+                result("https://z.example", None),
+                result("https://a.example", None),
+            ],
+            5,
+        );
+        assert_eq!(ranked[0].url, "https://z.example");
+    }
+
+    #[test]
+    fn test_parse_backend_responses() {
+        let searxng = parse_searxng(&json!({"results": [
+            {"title": "Rust", "url": "https://rust-lang.org", "content": "A language", "score": 2.5}
+        ]}));
+        assert_eq!(searxng[0].snippet, "A language");
+        assert_eq!(searxng[0].score, Some(2.5));
+
+        let brave = parse_brave(&json!({"web": {"results": [
+            {"title": "Rust", "url": "https://rust-lang.org", "description": "<strong>Rust</strong> &amp; Cargo"}
+        ]}}));
+        assert_eq!(rank(brave, 5)[0].snippet, "Rust & Cargo");
+
+        let tavily = parse_tavily(&json!({"results": [
+            {"title": "Rust", "url": "https://rust-lang.org", "content": "Fast", "score": 0.8}
+        ]}));
+        assert_eq!(tavily[0].score, Some(0.8));
+
+        let ddg = parse_duckduckgo(&json!({
+            "Heading": "Rust",
+            "AbstractURL": "https://en.wikipedia.org/wiki/Rust",
+            "AbstractText": "Rust is a language.",
+            "RelatedTopics": [
+                {"Text": "Cargo - The package manager", "FirstURL": "https://duckduckgo.com/Cargo"},
+                {"Name": "Related", "Topics": [
+                    {"Text": "Ferris - The mascot", "FirstURL": "https://duckduckgo.com/Ferris"}
+                ]}
+            ]
+        }));
+        let titles: Vec<_> = ddg.iter().map(|r| r.title.as_str()).collect();
+        assert_eq!(titles, vec!["Rust", "Cargo", "Ferris"]);
+    }
+
+    #[tokio::test]
+    async fn test_local_backend_ranks_by_matching_terms() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.json");
+        std::fs::write(
+            &path,
+            json!([
+                {"title": "Goose docs", "url": "https://docs.example/goose", "snippet": "Extensions for goose"},
+                {"title": "Goose extensions", "url": "https://docs.example/ext", "snippet": "Build goose extensions"},
+                {"title": "Ducks", "url": "https://docs.example/ducks", "snippet": "Unrelated"}
+            ])
+            .to_string(),
+        )
+        .unwrap();
+
+        let backend = LocalBackend::new(path);
+        let response = backend
+            .search(&Client::new(), "goose extensions", 5)
+            .await
+            .unwrap();
+        assert_eq!(response.results.len(), 2);
+        assert_eq!(response.results[0].url, "https://docs.example/goose");
+
+        let text = format_results("goose extensions", backend.name(), &response.results);
+        assert!(text.contains("1. Goose docs\n   https://docs.example/goose"));
+        assert!(serde_json::from_str::<Value>(&response.raw).is_ok());
+    }
+}
@@ -0,0 +1,673 @@
+//! Readable content extraction for web_scrape.
+//!
+//! Pages are converted from HTML to markdown. In readable mode the main content is picked the
+//! way reader views do it: an `article` or `main` element when the page has one, otherwise the
+//! element holding the most paragraph text, with navigation, sidebars and other boilerplate
+//! dropped. A CSS selector can be given instead to extract specific elements.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+/// Elements that never contain readable content
+const SKIPPED_TAGS: &[&str] = &[
+    "head", "script", "style", "noscript", "template", "svg", "canvas", "iframe", "object", "form",
+    "button", "input", "select", "textarea", "dialog",
+];
+
+/// Elements that hold page chrome rather than content, dropped in readable mode
+const BOILERPLATE_TAGS: &[&str] = &["nav", "footer", "aside"];
+
+/// Class and id names that mark page chrome
+static BOILERPLATE_NAMES: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(comments?|sidebar|navbar|nav|menu|footer|cookies?|banner|breadcrumbs?|share|social|related|promo|advert|ads|popup|modal|newsletter|subscribe)\b").unwrap()
+});
+
+fn selector(css: &str) -> Selector {
+    Selector::parse(css).unwrap()
+}
+
+static MAIN_CONTENT: Lazy<Selector> = Lazy::new(|| selector("article, main, [role=main]"));
+static PARAGRAPHS: Lazy<Selector> = Lazy::new(|| selector("p, pre, td, blockquote"));
+static ANCHORS: Lazy<Selector> = Lazy::new(|| selector("a[href]"));
+static BASE: Lazy<Selector> = Lazy::new(|| selector("base[href]"));
+static TITLE: Lazy<Selector> = Lazy::new(|| selector("title"));
+static OG_TITLE: Lazy<Selector> = Lazy::new(|| selector("meta[property='og:title']"));
+static H1: Lazy<Selector> = Lazy::new(|| selector("h1"));
+static BODY: Lazy<Selector> = Lazy::new(|| selector("body"));
+static ROWS: Lazy<Selector> = Lazy::new(|| selector("tr"));
+static LAYOUT_CELLS: Lazy<Selector> =
+    Lazy::new(|| selector("table table, td p, td div, td ul, td h1, td h2, td h3"));
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub text: String,
+    pub url: Url,
+}
+
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub title: Option<String>,
+    pub markdown: String,
+    /// Every http(s) link on the page, resolved and without fragments
+    pub links: Vec<Link>,
+}
+
+/// Check a CSS selector before any page is fetched
+pub fn validate_selector(css: &str) -> Result<(), String> {
+    Selector::parse(css)
+        .map(|_| ())
+        .map_err(|e| format!("Invalid CSS selector '{}': {}", css, e))
+}
+
+/// Extract a page as markdown, either its readable content or the elements matching `css`
+pub fn extract(html: &str, url: &Url, css: Option<&str>) -> Result<Page, String> {
+    let document = Html::parse_document(html);
+    let base = document
+        .select(&BASE)
+        .next()
+        .and_then(|b| b.attr("href"))
+        .and_then(|href| url.join(href).ok())
+        .unwrap_or_else(|| url.clone());
+
+    let markdown = match css {
+        Some(css) => {
+            let selector = Selector::parse(css)
+                .map_err(|e| format!("Invalid CSS selector '{}': {}", css, e))?;
+            document
+                .select(&selector)
+                .map(|element| render(element, &base, false))
+                .filter(|markdown| !markdown.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        }
+        None => render(content_root(&document), &base, true),
+    };
+
+    Ok(Page {
+        title: title(&document),
+        markdown,
+        links: links(&document, &base),
+    })
+}
+
+/// Render links as a markdown list
+pub fn format_links(links: &[Link]) -> String {
+    links
+        .iter()
+        .map(|link| {
+            if link.text.is_empty() {
+                format!("- <{}>", link.url)
+            } else {
+                format!("- [{}]({})", link.text, link.url)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The front matter written at the top of cached pages, so they can be traced back to a URL.
+/// It holds no fetch time, so fetching an unchanged page again matches the cached copy.
+pub fn front_matter(url: &Url, title: Option<&str>) -> String {
+    let mut header = format!("---\nurl: {}\n", url);
+    if let Some(title) = title {
+        header.push_str(&format!("title: {}\n", title.replace('\n', " ")));
+    }
+    header.push_str("---\n\n");
+    header
+}
+
+/// The source URL from a cached page's front matter
+pub fn source_url(content: &str) -> Option<&str> {
+    let front_matter = content.strip_prefix("---\n")?;
+    let end = front_matter.find("\n---")?;
+    front_matter[..end]
+        .lines()
+        .find_map(|line| line.strip_prefix("url: "))
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn element_text(element: ElementRef) -> String {
+    collapse_whitespace(&element.text().collect::<String>())
+}
+
+fn title(document: &Html) -> Option<String> {
+    document
+        .select(&TITLE)
+        .next()
+        .map(element_text)
+        .or_else(|| {
+            document
+                .select(&OG_TITLE)
+                .next()
+                .and_then(|m| m.attr("content"))
+                .map(collapse_whitespace)
+        })
+        .or_else(|| document.select(&H1).next().map(element_text))
+        .filter(|t| !t.is_empty())
+}
+
+fn links(document: &Html, base: &Url) -> Vec<Link> {
+    let mut seen = HashSet::new();
+    let mut links = Vec::new();
+    for anchor in document.select(&ANCHORS) {
+        let Some(mut url) = anchor.attr("href").and_then(|href| base.join(href).ok()) else {
+            continue;
+        };
+        if !matches!(url.scheme(), "http" | "https") {
+            continue;
+        }
+        url.set_fragment(None);
+        if seen.insert(url.to_string()) {
+            links.push(Link {
+                text: element_text(anchor),
+                url,
+            });
+        }
+    }
+    links
+}
+
+fn is_skipped(element: ElementRef, readable: bool) -> bool {
+    let value = element.value();
+    let name = value.name();
+    if SKIPPED_TAGS.contains(&name) {
+        return true;
+    }
+    if !readable {
+        return false;
+    }
+    BOILERPLATE_TAGS.contains(&name)
+        || value.attr("hidden").is_some()
+        || value.attr("aria-hidden") == Some("true")
+        || value
+            .attr("class")
+            .is_some_and(|c| BOILERPLATE_NAMES.is_match(c))
+        || value
+            .attr("id")
+            .is_some_and(|i| BOILERPLATE_NAMES.is_match(i))
+}
+
+fn in_boilerplate(element: ElementRef) -> bool {
+    element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .any(|ancestor| is_skipped(ancestor, true))
+}
+
+/// Pick the element holding the main content of the page
+fn content_root(document: &Html) -> ElementRef<'_> {
+    let body = document
+        .select(&BODY)
+        .next()
+        .unwrap_or_else(|| document.root_element());
+
+    // Pages that mark up their content make this easy
+    let marked = document
+        .select(&MAIN_CONTENT)
+        .filter(|element| !in_boilerplate(*element))
+        .map(|element| (element_text(element).len(), element))
+        .max_by_key(|(len, _)| *len);
+    if let Some((len, element)) = marked {
+        if len >= 200 {
+            return element;
+        }
+    }
+
+    // Otherwise credit each block of text to its parent and, at half weight, its grandparent
+    let mut scores: HashMap<_, (f64, ElementRef)> = HashMap::new();
+    for paragraph in document.select(&PARAGRAPHS) {
+        if in_boilerplate(paragraph) {
+            continue;
+        }
+        let text = element_text(paragraph);
+        if text.len() < 25 {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (text.len() as f64 / 100.0).min(3.0);
+        let ancestors = paragraph.ancestors().filter_map(ElementRef::wrap).take(2);
+        for (ancestor, weight) in ancestors.zip([1.0, 0.5]) {
+            scores.entry(ancestor.id()).or_insert((0.0, ancestor)).0 += score * weight;
+        }
+    }
+
+    scores
+        .into_values()
+        .map(|(score, element)| (score * (1.0 - link_density(element)), element))
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, element)| element)
+        .unwrap_or(body)
+}
+
+/// The share of an element's text that sits inside links
+fn link_density(element: ElementRef) -> f64 {
+    let total = element_text(element).len();
+    if total == 0 {
+        return 0.0;
+    }
+    let linked: usize = element
+        .select(&ANCHORS)
+        .map(|a| element_text(a).len())
+        .sum();
+    linked as f64 / total as f64
+}
+
+fn render(element: ElementRef, base: &Url, readable: bool) -> String {
+    let mut writer = MarkdownWriter::new(base, readable);
+    writer.element(element);
+    writer.finish()
+}
+
+/// Writes markdown while tracking whitespace, line prefixes for lists and quotes, and
+/// inline markers that should only appear around non-empty text
+struct MarkdownWriter<'a> {
+    base: &'a Url,
+    readable: bool,
+    out: String,
+    /// Prepended to every new line: list indentation and quote markers
+    prefixes: Vec<String>,
+    /// Trailing newlines in `out`, 2 meaning a blank line
+    newlines: usize,
+    at_line_start: bool,
+    pending_space: bool,
+    /// Opening markers waiting for the first word they apply to
+    pending_open: String,
+    /// A list marker has been written but nothing after it yet
+    item_start: bool,
+    list_depth: usize,
+}
+
+impl<'a> MarkdownWriter<'a> {
+    fn new(base: &'a Url, readable: bool) -> Self {
+        Self {
+            base,
+            readable,
+            out: String::new(),
+            prefixes: Vec::new(),
+            newlines: 2,
+            at_line_start: true,
+            pending_space: false,
+            pending_open: String::new(),
+            item_start: false,
+            list_depth: 0,
+        }
+    }
+
+    fn finish(self) -> String {
+        self.out
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string()
+    }
+
+    fn prefix(&self) -> String {
+        self.prefixes.concat()
+    }
+
+    fn word(&mut self, word: &str) {
+        if self.at_line_start {
+            self.out.push_str(&self.prefix());
+            self.at_line_start = false;
+        } else if self.pending_space && !self.item_start {
+            self.out.push(' ');
+        }
+        self.out.push_str(&self.pending_open);
+        self.pending_open.clear();
+        self.out.push_str(word);
+        self.pending_space = false;
+        self.item_start = false;
+        self.newlines = 0;
+    }
+
+    fn text(&mut self, text: &str) {
+        if text.starts_with(char::is_whitespace) && !self.at_line_start {
+            self.pending_space = true;
+        }
+        let mut words = text.split_whitespace().peekable();
+        let had_words = words.peek().is_some();
+        while let Some(word) = words.next() {
+            self.word(word);
+            if words.peek().is_some() {
+                self.pending_space = true;
+            }
+        }
+        if had_words && text.ends_with(char::is_whitespace) {
+            self.pending_space = true;
+        }
+    }
+
+    fn open(&mut self, marker: &str) {
+        self.pending_open.push_str(marker);
+    }
+
+    /// Close an inline marker, or drop its opener if nothing was written in between
+    fn close(&mut self, marker: &str, opener: &str) {
+        if self.pending_open.ends_with(opener) {
+            let len = self.pending_open.len() - opener.len();
+            self.pending_open.truncate(len);
+        } else {
+            self.out.push_str(marker);
+            self.newlines = 0;
+        }
+    }
+
+    fn line_break(&mut self) {
+        if self.item_start {
+            return;
+        }
+        if self.newlines == 0 {
+            self.out.push('\n');
+            self.newlines = 1;
+        }
+        self.at_line_start = true;
+        self.pending_space = false;
+    }
+
+    fn block_break(&mut self) {
+        self.line_break();
+        // Blank lines inside list items would turn them into loose lists
+        if self.list_depth == 0 && self.newlines == 1 {
+            self.out.push_str(self.prefix().trim_end());
+            self.out.push('\n');
+            self.newlines = 2;
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        let prefix = self.prefix();
+        if line.is_empty() {
+            self.out.push_str(prefix.trim_end());
+        } else {
+            self.out.push_str(&prefix);
+            self.out.push_str(line);
+        }
+        self.out.push('\n');
+        self.newlines = 1;
+        self.at_line_start = true;
+        self.pending_space = false;
+    }
+
+    fn children(&mut self, element: ElementRef) {
+        for child in element.children() {
+            if let Some(text) = child.value().as_text() {
+                self.text(text);
+            } else if let Some(child) = ElementRef::wrap(child) {
+                self.element(child);
+            }
+        }
+    }
+
+    fn resolve(&self, href: &str) -> Option<Url> {
+        let href = href.trim();
+        if href.is_empty() || href.starts_with("javascript:") || href.starts_with("data:") {
+            return None;
+        }
+        self.base.join(href).ok()
+    }
+
+    fn element(&mut self, element: ElementRef) {
+        if is_skipped(element, self.readable) {
+            return;
+        }
+        let name = element.value().name();
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse().unwrap_or(1);
+                let marker = format!("{} ", "#".repeat(level));
+                self.block_break();
+                self.open(&marker);
+                self.children(element);
+                self.close("", &marker);
+                self.block_break();
+            }
+            "p" | "div" | "section" | "article" | "main" | "header" | "figure" | "figcaption"
+            | "dl" | "dt" | "dd" | "address" | "details" | "summary" => {
+                self.block_break();
+                self.children(element);
+                self.block_break();
+            }
+            "br" => self.line_break(),
+            "hr" => {
+                self.block_break();
+                self.word("---");
+                self.block_break();
+            }
+            "pre" => self.code_block(element),
+            "code" | "kbd" | "samp" | "tt" => {
+                let code = element_text(element);
+                if !code.is_empty() {
+                    let fence = if code.contains('`') { "``" } else { "`" };
+                    self.word(&format!("{}{}{}", fence, code, fence));
+                }
+            }
+            "strong" | "b" => {
+                self.open("**");
+                self.children(element);
+                self.close("**", "**");
+            }
+            "em" | "i" => {
+                self.open("*");
+                self.children(element);
+                self.close("*", "*");
+            }
+            "del" | "s" | "strike" => {
+                self.open("~~");
+                self.children(element);
+                self.close("~~", "~~");
+            }
+            "a" => match element.attr("href").and_then(|href| self.resolve(href)) {
+                Some(url) => {
+                    self.open("[");
+                    self.children(element);
+                    self.close(&format!("]({})", url), "[");
+                }
+                None => self.children(element),
+            },
+            "img" => {
+                if let Some(src) = element.attr("src").and_then(|src| self.resolve(src)) {
+                    let alt = collapse_whitespace(element.attr("alt").unwrap_or_default());
+                    self.word(&format!("![{}]({})", alt, src));
+                }
+            }
+            "ul" | "ol" => self.list(element, name == "ol"),
+            "li" => self.item(element, "- "),
+            "blockquote" => {
+                self.block_break();
+                self.prefixes.push("> ".to_string());
+                self.children(element);
+                self.line_break();
+                self.prefixes.pop();
+                self.block_break();
+            }
+            "table" if element.select(&LAYOUT_CELLS).next().is_none() => self.table(element),
+            _ => self.children(element),
+        }
+    }
+
+    fn list(&mut self, element: ElementRef, ordered: bool) {
+        let start: usize = element
+            .attr("start")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1);
+        self.block_break();
+        self.list_depth += 1;
+        let items = element
+            .children()
+            .filter_map(ElementRef::wrap)
+            .filter(|child| child.value().name() == "li");
+        for (i, item) in items.enumerate() {
+            let marker = if ordered {
+                format!("{}. ", start + i)
+            } else {
+                "- ".to_string()
+            };
+            self.item(item, &marker);
+        }
+        self.list_depth -= 1;
+        self.block_break();
+    }
+
+    fn item(&mut self, element: ElementRef, marker: &str) {
+        self.line_break();
+        if self.at_line_start {
+            self.out.push_str(&self.prefix());
+        }
+        self.out.push_str(marker);
+        self.newlines = 0;
+        self.at_line_start = false;
+        self.pending_space = false;
+        self.item_start = true;
+        self.prefixes.push(" ".repeat(marker.len()));
+        self.children(element);
+        self.prefixes.pop();
+        self.item_start = false;
+        self.line_break();
+    }
+
+    fn code_block(&mut self, element: ElementRef) {
+        let language = std::iter::once(element)
+            .chain(element.children().filter_map(ElementRef::wrap))
+            .filter_map(|e| e.attr("class"))
+            .flat_map(str::split_whitespace)
+            .find_map(|class| {
+                class
+                    .strip_prefix("language-")
+                    .or_else(|| class.strip_prefix("lang-"))
+            })
+            .unwrap_or_default()
+            .to_string();
+        let code = element.text().collect::<String>();
+
+        self.block_break();
+        self.line(&format!("```{}", language));
+        for line in code.trim_matches('\n').lines() {
+            self.line(line.trim_end());
+        }
+        self.line("```");
+        self.block_break();
+    }
+
+    fn table(&mut self, element: ElementRef) {
+        let rows: Vec<Vec<String>> = element
+            .select(&ROWS)
+            .map(|row| {
+                row.children()
+                    .filter_map(ElementRef::wrap)
+                    .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+                    .map(|cell| {
+                        let mut writer = MarkdownWriter::new(self.base, self.readable);
+                        writer.children(cell);
+                        collapse_whitespace(&writer.finish()).replace('|', "\\|")
+                    })
+                    .collect()
+            })
+            .filter(|cells: &Vec<String>| !cells.is_empty())
+            .collect();
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+
+        self.block_break();
+        for (i, row) in rows.iter().enumerate() {
+            let mut cells = row.clone();
+            cells.resize(columns, String::new());
+            self.line(&format!("| {} |", cells.join(" | ")));
+            if i == 0 {
+                self.line(&format!("|{}", " --- |".repeat(columns)));
+            }
+        }
+        self.block_break();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url() -> Url {
+        Url::parse("https://example.com/docs/page.html").unwrap()
+    }
+
+    #[test]
+    fn test_readable_extraction_drops_boilerplate() {
+        let html = r#"<html><head><title>Guide</title><script>var x = 1;</script></head>
+            <body>
+              <nav><a href="/">Home</a> <a href="/about">About</a></nav>
+              <div class="sidebar"><p>Sign up for our newsletter, today, right now, please.</p></div>
+              <article>
+                <h1>Getting   started</h1>
+                <p>Install the <strong>goose</strong> CLI, then run <code>goose session</code>
+                   to start. See the <a href="install.html#linux">install guide</a>.</p>
+                <ul><li>First step</li><li>Second step<ul><li>Nested</li></ul></li></ul>
+                <pre><code class="language-sh">goose configure
+goose session</code></pre>
+                <table><tr><th>Key</th><th>Value</th></tr><tr><td>a|b</td><td>1</td></tr></table>
+                <p>This paragraph pads the article so it is clearly the main content of the page.</p>
+              </article>
+              <footer>Copyright</footer>
+            </body></html>"#;
+
+        let page = extract(html, &url(), None).unwrap();
+        assert_eq!(page.title.as_deref(), Some("Guide"));
+        assert_eq!(
+            page.markdown,
+            "# Getting started\n\n\
+             Install the **goose** CLI, then run `goose session` to start. See the \
+             [install guide](https://example.com/docs/install.html#linux).\n\n\
+             - First step\n\
+             - Second step\n  - Nested\n\n\
+             ```sh\ngoose configure\ngoose session\n```\n\n\
+             | Key | Value |\n| --- | --- |\n| a\\|b | 1 |\n\n\
+             This paragraph pads the article so it is clearly the main content of the page."
+        );
+
+        let urls: Vec<_> = page.links.iter().map(|l| l.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/",
+                "https://example.com/about",
+                "https://example.com/docs/install.html"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scored_root_and_selector() {
+        let html = r#"<body>
+            <div id="menu"><p><a href="/a">A long list of links, one, two, three</a></p></div>
+            <div id="story">
+              <p>The first paragraph of the story, with enough text, and commas, to score.</p>
+              <p>The second paragraph of the story carries on, adding more text to the score.</p>
+            </div>
+            <ol start="3"><li class="price">Ten</li><li class="price">Twenty</li></ol>
+        </body>"#;
+
+        let page = extract(html, &url(), None).unwrap();
+        assert!(page.markdown.starts_with("The first paragraph"));
+        assert!(!page.markdown.contains("links"));
+
+        let page = extract(html, &url(), Some("li.price")).unwrap();
+        assert_eq!(page.markdown, "- Ten\n\n- Twenty");
+        assert!(validate_selector("li[").is_err());
+    }
+
+    #[test]
+    fn test_front_matter_round_trip() {
+        let content = format!("{}# Body", front_matter(&url(), Some("Guide")));
+        assert_eq!(
+            source_url(&content),
+            Some("https://example.com/docs/page.html")
+        );
+        assert_eq!(source_url("# No front matter"), None);
+    }
+}
@@ -0,0 +1,695 @@
+//! A small evaluator for spreadsheet formulas.
+//!
+//! umya-spreadsheet stores formulas but does not calculate them, so formulas written by the
+//! tool would have no value until the file is opened in a spreadsheet application. This covers
+//! arithmetic, comparisons, text concatenation, cell and range references and a handful of
+//! common functions. Anything else is reported as unsupported so callers can fall back to the
+//! cached result stored in the file.
+
+use anyhow::{bail, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Empty,
+    /// A spreadsheet error such as `#DIV/0!`
+    Error(String),
+}
+
+impl Value {
+    fn as_number(&self) -> Result<f64, Value> {
+        match self {
+            Value::Number(n) => Ok(*n),
+            Value::Bool(b) => Ok(if *b { 1.0 } else { 0.0 }),
+            Value::Empty => Ok(0.0),
+            Value::Text(t) => t
+                .trim()
+                .parse()
+                .map_err(|_| Value::Error("#VALUE!".to_string())),
+            Value::Error(_) => Err(self.clone()),
+        }
+    }
+
+    fn as_bool(&self) -> Result<bool, Value> {
+        match self {
+            Value::Bool(b) => Ok(*b),
+            Value::Text(t) if t.eq_ignore_ascii_case("true") => Ok(true),
+            Value::Text(t) if t.eq_ignore_ascii_case("false") => Ok(false),
+            Value::Text(_) => Err(Value::Error("#VALUE!".to_string())),
+            other => other.as_number().map(|n| n != 0.0),
+        }
+    }
+
+    fn as_text(&self) -> Result<String, Value> {
+        match self {
+            Value::Error(_) => Err(self.clone()),
+            other => Ok(other.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Text(t) => write!(f, "{}", t),
+            Value::Bool(b) => write!(f, "{}", if *b { "TRUE" } else { "FALSE" }),
+            Value::Empty => Ok(()),
+            Value::Error(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// A cell position, columns and rows start at 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellRef {
+    pub sheet: Option<String>,
+    pub col: u32,
+    pub row: u32,
+}
+
+/// Supplies cell values while evaluating, resolving nested formulas as needed
+pub trait CellSource {
+    fn value(&self, cell: &CellRef) -> Value;
+}
+
+/// Evaluate a formula, with or without its leading `=`.
+///
+/// Returns an error for syntax the evaluator does not understand or unsupported functions.
+/// Spreadsheet errors such as division by zero are returned as `Value::Error`.
+pub fn evaluate(formula: &str, source: &dyn CellSource) -> Result<Value> {
+    let formula = formula.trim();
+    let formula = formula.strip_prefix('=').unwrap_or(formula);
+    let tokens = tokenize(formula)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.expression(0)?;
+    if parser.pos != parser.tokens.len() {
+        bail!("Unexpected {:?} in formula", parser.tokens[parser.pos]);
+    }
+    eval(&expr, source)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Text(String),
+    /// Function names, booleans and cell references, possibly sheet qualified
+    Name(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+    Colon,
+}
+
+fn tokenize(formula: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = formula.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ' ' | '\t' | '\n' => i += 1,
+            '0'..='9' | '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                    i += 1;
+                    if i < chars.len() && (chars[i] == '+' || chars[i] == '-') {
+                        i += 1;
+                    }
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+                let text: String = chars[start..i].iter().collect();
+                tokens.push(Token::Number(text.parse()?));
+            }
+            '"' => {
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => bail!("Unterminated string in formula"),
+                        Some('"') if chars.get(i + 1) == Some(&'"') => {
+                            text.push('"');
+                            i += 2;
+                        }
+                        Some('"') => {
+                            i += 1;
+                            break;
+                        }
+                        Some(c) => {
+                            text.push(*c);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            '\'' => {
+                // Quoted sheet name, e.g. 'My Sheet'!A1
+                let mut name = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => bail!("Unterminated sheet name in formula"),
+                        Some('\'') if chars.get(i + 1) == Some(&'\'') => {
+                            name.push('\'');
+                            i += 2;
+                        }
+                        Some('\'') => {
+                            i += 1;
+                            break;
+                        }
+                        Some(c) => {
+                            name.push(*c);
+                            i += 1;
+                        }
+                    }
+                }
+                if chars.get(i) != Some(&'!') {
+                    bail!("Expected '!' after sheet name '{}'", name);
+                }
+                i += 1;
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '$') {
+                    i += 1;
+                }
+                let cell: String = chars[start..i].iter().collect();
+                tokens.push(Token::Name(format!("{}!{}", name, cell)));
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '$' | '.' | '!'))
+                {
+                    i += 1;
+                }
+                tokens.push(Token::Name(chars[start..i].iter().collect()));
+            }
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            ',' | ';' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            ':' => {
+                tokens.push(Token::Colon);
+                i += 1;
+            }
+            _ => {
+                let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+                let op = match two.as_str() {
+                    "<=" => "<=",
+                    ">=" => ">=",
+                    "<>" => "<>",
+                    _ => match c {
+                        '+' => "+",
+                        '-' => "-",
+                        '*' => "*",
+                        '/' => "/",
+                        '^' => "^",
+                        '&' => "&",
+                        '=' => "=",
+                        '<' => "<",
+                        '>' => ">",
+                        '%' => "%",
+                        _ => bail!("Unexpected character '{}' in formula", c),
+                    },
+                };
+                tokens.push(Token::Op(op));
+                i += op.len();
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+enum Expr {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Cell(CellRef),
+    Range(CellRef, CellRef),
+    Negate(Box<Expr>),
+    Percent(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+fn binding_power(op: &str) -> Option<u8> {
+    match op {
+        "=" | "<>" | "<" | ">" | "<=" | ">=" => Some(1),
+        "&" => Some(2),
+        "+" | "-" => Some(3),
+        "*" | "/" => Some(4),
+        "^" => Some(5),
+        _ => None,
+    }
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn expression(&mut self, min_power: u8) -> Result<Expr> {
+        let mut lhs = self.unary()?;
+        while let Some(Token::Op(op)) = self.peek().cloned() {
+            if op == "%" {
+                self.pos += 1;
+                lhs = Expr::Percent(Box::new(lhs));
+                continue;
+            }
+            let Some(power) = binding_power(op) else {
+                break;
+            };
+            if power <= min_power {
+                break;
+            }
+            self.pos += 1;
+            // Exponentiation binds to the right, everything else to the left
+            let rhs = self.expression(if op == "^" { power - 1 } else { power })?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        match self.peek() {
+            // Negation binds tighter than any binary operator, so -2^2 is 4 like in Excel
+            Some(Token::Op("-")) => {
+                self.pos += 1;
+                Ok(Expr::Negate(Box::new(self.unary()?)))
+            }
+            Some(Token::Op("+")) => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Text(t)) => Ok(Expr::Text(t)),
+            Some(Token::LParen) => {
+                let expr = self.expression(0)?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => bail!("Expected ')' in formula"),
+                }
+            }
+            Some(Token::Name(name)) => {
+                if self.peek() == Some(&Token::LParen) {
+                    self.pos += 1;
+                    return self.call(name.to_ascii_uppercase());
+                }
+                if name.eq_ignore_ascii_case("true") {
+                    return Ok(Expr::Bool(true));
+                }
+                if name.eq_ignore_ascii_case("false") {
+                    return Ok(Expr::Bool(false));
+                }
+                let start = parse_reference(&name)?;
+                if self.peek() == Some(&Token::Colon) {
+                    self.pos += 1;
+                    let Some(Token::Name(end)) = self.next() else {
+                        bail!("Expected a cell after ':' in formula");
+                    };
+                    let mut end = parse_reference(&end)?;
+                    end.sheet = end.sheet.or_else(|| start.sheet.clone());
+                    return Ok(Expr::Range(start, end));
+                }
+                Ok(Expr::Cell(start))
+            }
+            other => bail!("Unexpected {:?} in formula", other),
+        }
+    }
+
+    fn call(&mut self, name: String) -> Result<Expr> {
+        let mut args = Vec::new();
+        if self.peek() == Some(&Token::RParen) {
+            self.pos += 1;
+            return Ok(Expr::Call(name, args));
+        }
+        loop {
+            args.push(self.expression(0)?);
+            match self.next() {
+                Some(Token::Comma) => continue,
+                Some(Token::RParen) => break,
+                _ => bail!("Expected ',' or ')' in call to {}", name),
+            }
+        }
+        Ok(Expr::Call(name, args))
+    }
+}
+
+/// Parse a reference like `B2`, `$B$2` or `Sheet2!B2`
+pub fn parse_reference(reference: &str) -> Result<CellRef> {
+    let (sheet, cell) = match reference.rsplit_once('!') {
+        Some((sheet, cell)) => (Some(sheet.to_string()), cell),
+        None => (None, reference),
+    };
+    let cell = cell.replace('$', "");
+    let split = cell
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(cell.len());
+    let (letters, digits) = cell.split_at(split);
+    if letters.is_empty()
+        || digits.is_empty()
+        || !letters.chars().all(|c| c.is_ascii_alphabetic())
+        || !digits.chars().all(|c| c.is_ascii_digit())
+    {
+        bail!("Unsupported name '{}' in formula", reference);
+    }
+    let col = letters.chars().fold(0u32, |acc, c| {
+        acc * 26 + (c.to_ascii_uppercase() as u32 - 'A' as u32 + 1)
+    });
+    let row = digits.parse()?;
+    Ok(CellRef { sheet, col, row })
+}
+
+fn range_values(start: &CellRef, end: &CellRef, source: &dyn CellSource) -> Vec<Value> {
+    let mut values = Vec::new();
+    for row in start.row.min(end.row)..=start.row.max(end.row) {
+        for col in start.col.min(end.col)..=start.col.max(end.col) {
+            values.push(source.value(&CellRef {
+                sheet: start.sheet.clone(),
+                col,
+                row,
+            }));
+        }
+    }
+    values
+}
+
+/// Returns early with the spreadsheet error if a conversion fails
+macro_rules! try_value {
+    ($expr:expr) => {
+        match $expr {
+            Ok(v) => v,
+            Err(error) => return Ok(error),
+        }
+    };
+}
+
+fn eval(expr: &Expr, source: &dyn CellSource) -> Result<Value> {
+    Ok(match expr {
+        Expr::Number(n) => Value::Number(*n),
+        Expr::Text(t) => Value::Text(t.clone()),
+        Expr::Bool(b) => Value::Bool(*b),
+        Expr::Cell(cell) => source.value(cell),
+        Expr::Range(..) => bail!("Ranges can only be used as function arguments"),
+        Expr::Negate(inner) => Value::Number(-try_value!(eval(inner, source)?.as_number())),
+        Expr::Percent(inner) => Value::Number(try_value!(eval(inner, source)?.as_number()) / 100.0),
+        Expr::Binary(op, lhs, rhs) => {
+            let lhs = eval(lhs, source)?;
+            let rhs = eval(rhs, source)?;
+            binary(op, &lhs, &rhs)
+        }
+        Expr::Call(name, args) => return call(name, args, source),
+    })
+}
+
+fn binary(op: &str, lhs: &Value, rhs: &Value) -> Value {
+    if let Value::Error(_) = lhs {
+        return lhs.clone();
+    }
+    if let Value::Error(_) = rhs {
+        return rhs.clone();
+    }
+    match op {
+        "&" => Value::Text(format!("{}{}", lhs, rhs)),
+        "=" | "<>" | "<" | ">" | "<=" | ">=" => {
+            let ordering = match (lhs.as_number(), rhs.as_number()) {
+                (Ok(a), Ok(b))
+                    if !matches!(lhs, Value::Text(_)) && !matches!(rhs, Value::Text(_)) =>
+                {
+                    a.partial_cmp(&b)
+                }
+                _ => Some(
+                    lhs.to_string()
+                        .to_lowercase()
+                        .cmp(&rhs.to_string().to_lowercase()),
+                ),
+            };
+            let Some(ordering) = ordering else {
+                return Value::Error("#NUM!".to_string());
+            };
+            Value::Bool(match op {
+                "=" => ordering.is_eq(),
+                "<>" => ordering.is_ne(),
+                "<" => ordering.is_lt(),
+                ">" => ordering.is_gt(),
+                "<=" => ordering.is_le(),
+                _ => ordering.is_ge(),
+            })
+        }
+        _ => {
+            let (a, b) = match (lhs.as_number(), rhs.as_number()) {
+                (Ok(a), Ok(b)) => (a, b),
+                (Err(e), _) | (_, Err(e)) => return e,
+            };
+            match op {
+                "+" => Value::Number(a + b),
+                "-" => Value::Number(a - b),
+                "*" => Value::Number(a * b),
+                "/" if b == 0.0 => Value::Error("#DIV/0!".to_string()),
+                "/" => Value::Number(a / b),
+                _ => Value::Number(a.powf(b)),
+            }
+        }
+    }
+}
+
+/// Flatten arguments, expanding ranges into their cells
+fn flatten(args: &[Expr], source: &dyn CellSource) -> Result<Vec<(Value, bool)>> {
+    let mut values = Vec::new();
+    for arg in args {
+        match arg {
+            Expr::Range(start, end) => values.extend(
+                range_values(start, end, source)
+                    .into_iter()
+                    .map(|value| (value, true)),
+            ),
+            Expr::Cell(cell) => values.push((source.value(cell), true)),
+            other => values.push((eval(other, source)?, false)),
+        }
+    }
+    Ok(values)
+}
+
+/// Numbers from the arguments. Text and booleans in cells are skipped like spreadsheets do,
+/// while literal arguments are converted.
+fn numbers(args: &[Expr], source: &dyn CellSource) -> Result<Result<Vec<f64>, Value>> {
+    let mut numbers = Vec::new();
+    for (value, from_cell) in flatten(args, source)? {
+        match value {
+            Value::Error(_) => return Ok(Err(value)),
+            Value::Number(n) => numbers.push(n),
+            Value::Empty => {}
+            _ if from_cell => {}
+            other => match other.as_number() {
+                Ok(n) => numbers.push(n),
+                Err(e) => return Ok(Err(e)),
+            },
+        }
+    }
+    Ok(Ok(numbers))
+}
+
+fn arg<'a>(name: &str, args: &'a [Expr], index: usize) -> Result<&'a Expr> {
+    args.get(index)
+        .ok_or_else(|| anyhow::anyhow!("{} is missing argument {}", name, index + 1))
+}
+
+fn call(name: &str, args: &[Expr], source: &dyn CellSource) -> Result<Value> {
+    Ok(match name {
+        "SUM" => Value::Number(try_value!(numbers(args, source)?).iter().sum()),
+        "AVERAGE" => {
+            let numbers = try_value!(numbers(args, source)?);
+            if numbers.is_empty() {
+                Value::Error("#DIV/0!".to_string())
+            } else {
+                Value::Number(numbers.iter().sum::<f64>() / numbers.len() as f64)
+            }
+        }
+        "MIN" => Value::Number(
+            try_value!(numbers(args, source)?)
+                .into_iter()
+                .reduce(f64::min)
+                .unwrap_or(0.0),
+        ),
+        "MAX" => Value::Number(
+            try_value!(numbers(args, source)?)
+                .into_iter()
+                .reduce(f64::max)
+                .unwrap_or(0.0),
+        ),
+        "COUNT" => Value::Number(
+            flatten(args, source)?
+                .iter()
+                .filter(|(value, _)| matches!(value, Value::Number(_)))
+                .count() as f64,
+        ),
+        "COUNTA" => Value::Number(
+            flatten(args, source)?
+                .iter()
+                .filter(|(value, _)| *value != Value::Empty)
+                .count() as f64,
+        ),
+        "ROUND" => {
+            let value = try_value!(eval(arg(name, args, 0)?, source)?.as_number());
+            let digits = match args.get(1) {
+                Some(expr) => try_value!(eval(expr, source)?.as_number()),
+                None => 0.0,
+            };
+            let factor = 10f64.powi(digits as i32);
+            Value::Number((value * factor).round() / factor)
+        }
+        "ABS" => Value::Number(try_value!(eval(arg(name, args, 0)?, source)?.as_number()).abs()),
+        "IF" => {
+            let condition = try_value!(eval(arg(name, args, 0)?, source)?.as_bool());
+            match (condition, args.get(1), args.get(2)) {
+                (true, Some(expr), _) | (false, _, Some(expr)) => eval(expr, source)?,
+                (true, None, _) => Value::Bool(true),
+                (false, _, None) => Value::Bool(false),
+            }
+        }
+        "AND" | "OR" => {
+            let mut values = Vec::new();
+            for (value, _) in flatten(args, source)? {
+                if value != Value::Empty {
+                    values.push(try_value!(value.as_bool()));
+                }
+            }
+            Value::Bool(if name == "AND" {
+                values.iter().all(|v| *v)
+            } else {
+                values.iter().any(|v| *v)
+            })
+        }
+        "NOT" => Value::Bool(!try_value!(eval(arg(name, args, 0)?, source)?.as_bool())),
+        "CONCAT" | "CONCATENATE" => {
+            let mut text = String::new();
+            for (value, _) in flatten(args, source)? {
+                text.push_str(&try_value!(value.as_text()));
+            }
+            Value::Text(text)
+        }
+        "LEN" => Value::Number(
+            try_value!(eval(arg(name, args, 0)?, source)?.as_text())
+                .chars()
+                .count() as f64,
+        ),
+        "UPPER" => {
+            Value::Text(try_value!(eval(arg(name, args, 0)?, source)?.as_text()).to_uppercase())
+        }
+        "LOWER" => {
+            Value::Text(try_value!(eval(arg(name, args, 0)?, source)?.as_text()).to_lowercase())
+        }
+        _ => bail!("Unsupported function {}", name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct Cells(HashMap<(u32, u32), Value>);
+
+    impl CellSource for Cells {
+        fn value(&self, cell: &CellRef) -> Value {
+            self.0
+                .get(&(cell.col, cell.row))
+                .cloned()
+                .unwrap_or(Value::Empty)
+        }
+    }
+
+    fn cells() -> Cells {
+        Cells(HashMap::from([
+            ((1, 1), Value::Number(2.0)),
+            ((1, 2), Value::Number(3.0)),
+            ((1, 3), Value::Text("header".to_string())),
+            ((2, 1), Value::Number(0.0)),
+        ]))
+    }
+
+    #[test]
+    fn test_arithmetic_and_precedence() {
+        let cells = cells();
+        assert_eq!(evaluate("=1+2*3", &cells).unwrap(), Value::Number(7.0));
+        assert_eq!(evaluate("=(1+2)*3", &cells).unwrap(), Value::Number(9.0));
+        assert_eq!(evaluate("=-A1^2", &cells).unwrap(), Value::Number(4.0));
+        assert_eq!(evaluate("=2^3^2", &cells).unwrap(), Value::Number(512.0));
+        assert_eq!(evaluate("=50%", &cells).unwrap(), Value::Number(0.5));
+        assert_eq!(
+            evaluate("=A1/B1", &cells).unwrap(),
+            Value::Error("#DIV/0!".to_string())
+        );
+    }
+
+    #[test]
+    fn test_functions_and_ranges() {
+        let cells = cells();
+        assert_eq!(evaluate("=SUM(A1:A3)", &cells).unwrap(), Value::Number(5.0));
+        assert_eq!(
+            evaluate("=AVERAGE($A$1:A2)", &cells).unwrap(),
+            Value::Number(2.5)
+        );
+        assert_eq!(
+            evaluate("=COUNTA(A1:A4)", &cells).unwrap(),
+            Value::Number(3.0)
+        );
+        assert_eq!(
+            evaluate("=IF(A2>A1, \"up\", \"down\")", &cells).unwrap(),
+            Value::Text("up".to_string())
+        );
+        assert_eq!(
+            evaluate("=UPPER(A3)&\"-\"&ROUND(10/3, 2)", &cells).unwrap(),
+            Value::Text("HEADER-3.33".to_string())
+        );
+        assert!(evaluate("=VLOOKUP(A1, A1:B3, 2)", &cells).is_err());
+    }
+
+    #[test]
+    fn test_parse_reference() {
+        let cell = parse_reference("Q1!$C$12").unwrap();
+        assert_eq!(cell.sheet.as_deref(), Some("Q1"));
+        assert_eq!((cell.col, cell.row), (3, 12));
+
+        let tokens = tokenize("'Q1 Data'!B2").unwrap();
+        assert_eq!(tokens, vec![Token::Name("Q1 Data!B2".to_string())]);
+        assert!(parse_reference("TOTAL").is_err());
+    }
+}
@@ -1,8 +1,15 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use umya_spreadsheet::{Spreadsheet, Worksheet};
 
+use super::xlsx_formula::{self, CellRef, CellSource, Value};
+
+/// format_range styles each cell separately, so larger ranges are refused
+const MAX_FORMAT_CELLS: u64 = 100_000;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WorksheetInfo {
     name: String,
@@ -15,6 +22,9 @@ pub struct WorksheetInfo {
 pub struct CellValue {
     value: String,
     formula: Option<String>,
+    /// Set when a formula could not be evaluated and `value` is the result cached in the file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -27,15 +37,47 @@ pub struct RangeData {
     values: Vec<Vec<CellValue>>,
 }
 
+/// Basic formatting applied to every cell in a range
+#[derive(Debug, Default, Deserialize)]
+pub struct CellFormat {
+    pub bold: Option<bool>,
+    pub italic: Option<bool>,
+    /// Hex color such as "#FF0000"
+    pub font_color: Option<String>,
+    /// Hex color such as "#FFFF00"
+    pub fill_color: Option<String>,
+    /// Excel number format code such as "0.00" or "yyyy-mm-dd"
+    pub number_format: Option<String>,
+    /// Width applied to every column in the range
+    pub column_width: Option<f64>,
+}
+
+/// An open workbook. Edits are kept in memory until `save` is called.
 pub struct XlsxTool {
     workbook: Spreadsheet,
+    dirty: bool,
 }
 
 impl XlsxTool {
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
         let workbook =
             umya_spreadsheet::reader::xlsx::read(path).context("Failed to read Excel file")?;
-        Ok(Self { workbook })
+        Ok(Self {
+            workbook,
+            dirty: false,
+        })
+    }
+
+    /// A new workbook with a single empty worksheet named "Sheet1"
+    pub fn create() -> Self {
+        Self {
+            workbook: umya_spreadsheet::new_file(),
+            dirty: true,
+        }
+    }
+
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.dirty
     }
 
     pub fn list_worksheets(&self) -> Result<Vec<WorksheetInfo>> {
@@ -65,20 +107,29 @@ impl XlsxTool {
             .context("Worksheet index out of bounds")
     }
 
+    /// The name of the requested worksheet, or of the first worksheet if none is given
+    pub fn resolve_worksheet(&self, name: Option<&str>) -> Result<String> {
+        match name {
+            Some(name) => Ok(self.get_worksheet_by_name(name)?.get_name().to_string()),
+            None => Ok(self.get_worksheet_by_index(0)?.get_name().to_string()),
+        }
+    }
+
+    fn get_worksheet_mut(&mut self, name: &str) -> Result<&mut Worksheet> {
+        self.workbook
+            .get_sheet_by_name_mut(name)
+            .context("Worksheet not found")
+    }
+
     fn get_worksheet_dimensions(&self, worksheet: &Worksheet) -> Result<(usize, usize)> {
         // Returns (column_count, row_count) for the worksheet
         let mut max_col = 0;
         let mut max_row = 0;
 
-        // Iterate through all rows
-        for row_num in 1..=worksheet.get_highest_row() {
-            for col_num in 1..=worksheet.get_highest_column() {
-                if let Some(cell) = worksheet.get_cell((row_num, col_num)) {
-                    let coord = cell.get_coordinate();
-                    max_col = max_col.max(*coord.get_col_num() as usize);
-                    max_row = max_row.max(*coord.get_row_num() as usize);
-                }
-            }
+        for cell in worksheet.get_cell_collection() {
+            let coord = cell.get_coordinate();
+            max_col = max_col.max(*coord.get_col_num() as usize);
+            max_row = max_row.max(*coord.get_row_num() as usize);
         }
 
         Ok((max_col, max_row))
@@ -87,7 +138,7 @@ impl XlsxTool {
     pub fn get_column_names(&self, worksheet: &Worksheet) -> Result<Vec<String>> {
         let mut names = Vec::new();
         for col_num in 1..=worksheet.get_highest_column() {
-            if let Some(cell) = worksheet.get_cell((1, col_num)) {
+            if let Some(cell) = worksheet.get_cell((col_num, 1)) {
                 names.push(cell.get_value().into_owned());
             } else {
                 names.push(String::new());
@@ -98,28 +149,14 @@ impl XlsxTool {
 
     pub fn get_range(&self, worksheet: &Worksheet, range: &str) -> Result<RangeData> {
         let (start_col, start_row, end_col, end_row) = parse_range(range)?;
+        let source = WorkbookCells::new(&self.workbook);
         let mut values = Vec::new();
 
         // Iterate through rows first, then columns
         for row_idx in start_row..=end_row {
             let mut row_values = Vec::new();
             for col_idx in start_col..=end_col {
-                let cell_value = if let Some(cell) = worksheet.get_cell((row_idx, col_idx)) {
-                    CellValue {
-                        value: cell.get_value().into_owned(),
-                        formula: if cell.get_formula().is_empty() {
-                            None
-                        } else {
-                            Some(cell.get_formula().to_string())
-                        },
-                    }
-                } else {
-                    CellValue {
-                        value: String::new(),
-                        formula: None,
-                    }
-                };
-                row_values.push(cell_value);
+                row_values.push(source.cell_value(worksheet, col_idx, row_idx));
             }
             values.push(row_values);
         }
@@ -140,20 +177,226 @@ impl XlsxTool {
         col: u32,
         value: &str,
     ) -> Result<()> {
-        let worksheet = self
-            .workbook
-            .get_sheet_by_name_mut(worksheet_name)
-            .context("Worksheet not found")?;
-
-        worksheet
-            .get_cell_mut((row, col))
-            .set_value(value.to_string());
+        let worksheet = self.get_worksheet_mut(worksheet_name)?;
+        set_cell(
+            worksheet,
+            col,
+            row,
+            &serde_json::Value::String(value.to_string()),
+        );
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Write a block of values with its top left corner at `start`. Strings starting with `=`
+    /// are stored as formulas and null clears a cell. Returns the range that was written.
+    pub fn write_range(
+        &mut self,
+        worksheet_name: &str,
+        start: &str,
+        values: &[Vec<serde_json::Value>],
+    ) -> Result<String> {
+        let (start_col, start_row) = parse_cell_reference(start)?;
+        let worksheet = self.get_worksheet_mut(worksheet_name)?;
+
+        let mut width = 0;
+        for (row_offset, row) in values.iter().enumerate() {
+            width = width.max(row.len() as u32);
+            for (col_offset, value) in row.iter().enumerate() {
+                set_cell(
+                    worksheet,
+                    start_col + col_offset as u32,
+                    start_row + row_offset as u32,
+                    value,
+                );
+            }
+        }
+        self.dirty = true;
+
+        Ok(format!(
+            "{}{}:{}{}",
+            column_number_to_letter(start_col),
+            start_row,
+            column_number_to_letter(start_col + width.max(1) - 1),
+            start_row + (values.len() as u32).max(1) - 1
+        ))
+    }
+
+    pub fn insert_rows(&mut self, worksheet_name: &str, row: u32, count: u32) -> Result<()> {
+        self.get_worksheet_mut(worksheet_name)?
+            .insert_new_row(&row, &count);
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub fn delete_rows(&mut self, worksheet_name: &str, row: u32, count: u32) -> Result<()> {
+        self.get_worksheet_mut(worksheet_name)?
+            .remove_row(&row, &count);
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub fn insert_columns(&mut self, worksheet_name: &str, col: u32, count: u32) -> Result<()> {
+        self.get_worksheet_mut(worksheet_name)?
+            .insert_new_column_by_index(&col, &count);
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub fn delete_columns(&mut self, worksheet_name: &str, col: u32, count: u32) -> Result<()> {
+        self.get_worksheet_mut(worksheet_name)?
+            .remove_column_by_index(&col, &count);
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub fn add_worksheet(&mut self, name: &str) -> Result<()> {
+        self.workbook
+            .new_sheet(name)
+            .map_err(|e| anyhow::anyhow!("Failed to add worksheet '{}': {}", name, e))?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub fn format_range(
+        &mut self,
+        worksheet_name: &str,
+        range: &str,
+        format: &CellFormat,
+    ) -> Result<()> {
+        let (start_col, start_row, end_col, end_row) = parse_range(range)?;
+        let span = |start: u32, end: u32| (u64::from(end) + 1).saturating_sub(u64::from(start));
+        let cells = span(start_col, end_col) * span(start_row, end_row);
+        if cells > MAX_FORMAT_CELLS {
+            anyhow::bail!(
+                "Range {} has {} cells, format at most {} cells at a time",
+                range,
+                cells,
+                MAX_FORMAT_CELLS
+            );
+        }
+        let font_color = format.font_color.as_deref().map(argb).transpose()?;
+        let fill_color = format.fill_color.as_deref().map(argb).transpose()?;
+        let worksheet = self.get_worksheet_mut(worksheet_name)?;
+
+        for row in start_row..=end_row {
+            for col in start_col..=end_col {
+                let style = worksheet.get_style_mut((col, row));
+                if let Some(bold) = format.bold {
+                    style.get_font_mut().set_bold(bold);
+                }
+                if let Some(italic) = format.italic {
+                    style.get_font_mut().set_italic(italic);
+                }
+                if let Some(color) = &font_color {
+                    style.get_font_mut().get_color_mut().set_argb(color.clone());
+                }
+                if let Some(color) = &fill_color {
+                    style.set_background_color(color.clone());
+                }
+                if let Some(code) = &format.number_format {
+                    style.get_number_format_mut().set_format_code(code.clone());
+                }
+            }
+        }
+
+        if let Some(width) = format.column_width {
+            for col in start_col..=end_col {
+                worksheet
+                    .get_column_dimension_mut(&column_number_to_letter(col))
+                    .set_width(width);
+            }
+        }
+
+        self.dirty = true;
         Ok(())
     }
 
-    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+    /// Load a CSV file into a worksheet with its top left corner at `start`, creating the
+    /// worksheet if needed. Returns the number of rows and columns written.
+    pub fn import_csv<P: AsRef<Path>>(
+        &mut self,
+        worksheet_name: &str,
+        csv_path: P,
+        start: &str,
+    ) -> Result<(usize, usize)> {
+        let text = std::fs::read_to_string(csv_path).context("Failed to read CSV file")?;
+        let rows = parse_csv(&text);
+        let (start_col, start_row) = parse_cell_reference(start)?;
+
+        if self.workbook.get_sheet_by_name(worksheet_name).is_none() {
+            self.add_worksheet(worksheet_name)?;
+        }
+        let worksheet = self.get_worksheet_mut(worksheet_name)?;
+
+        let mut width = 0;
+        for (row_offset, row) in rows.iter().enumerate() {
+            width = width.max(row.len());
+            for (col_offset, field) in row.iter().enumerate() {
+                // CSV fields are data, so they are never treated as formulas
+                worksheet
+                    .get_cell_mut((start_col + col_offset as u32, start_row + row_offset as u32))
+                    .set_value(field.clone());
+            }
+        }
+        self.dirty = true;
+
+        Ok((rows.len(), width))
+    }
+
+    /// Write the used range of a worksheet to a CSV file, with formulas replaced by their
+    /// results. Returns the number of rows written.
+    pub fn export_csv<P: AsRef<Path>>(&self, worksheet_name: &str, csv_path: P) -> Result<usize> {
+        let worksheet = self.get_worksheet_by_name(worksheet_name)?;
+        let (max_col, max_row) = self.get_worksheet_dimensions(worksheet)?;
+        let source = WorkbookCells::new(&self.workbook);
+
+        let mut csv = String::new();
+        for row in 1..=max_row as u32 {
+            let fields: Vec<String> = (1..=max_col as u32)
+                .map(|col| csv_field(&source.cell_value(worksheet, col, row).value))
+                .collect();
+            csv.push_str(&fields.join(","));
+            csv.push_str("\r\n");
+        }
+
+        std::fs::write(csv_path, csv).context("Failed to write CSV file")?;
+        Ok(max_row)
+    }
+
+    /// Store the current result of every formula the evaluator understands, so other
+    /// applications reading the file see up to date values
+    fn recalculate(&mut self) {
+        let mut results = Vec::new();
+        let source = WorkbookCells::new(&self.workbook);
+        for worksheet in self.workbook.get_sheet_collection() {
+            for cell in worksheet.get_cell_collection() {
+                if !cell.is_formula() {
+                    continue;
+                }
+                let coord = cell.get_coordinate();
+                let (col, row) = (*coord.get_col_num(), *coord.get_row_num());
+                if let Ok(value) = source.evaluate(worksheet.get_name(), cell.get_formula()) {
+                    results.push((worksheet.get_name().to_string(), col, row, value));
+                }
+            }
+        }
+
+        for (sheet, col, row, value) in results {
+            if let Some(worksheet) = self.workbook.get_sheet_by_name_mut(&sheet) {
+                worksheet
+                    .get_cell_mut((col, row))
+                    .get_cell_value_mut()
+                    .set_formula_result_default(value.to_string());
+            }
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.recalculate();
         umya_spreadsheet::writer::xlsx::write(&self.workbook, path)
             .context("Failed to save Excel file")?;
+        self.dirty = false;
         Ok(())
     }
 
@@ -173,7 +416,7 @@ impl XlsxTool {
 
         for row_num in 1..=worksheet.get_highest_row() {
             for col_num in 1..=worksheet.get_highest_column() {
-                if let Some(cell) = worksheet.get_cell((row_num, col_num)) {
+                if let Some(cell) = worksheet.get_cell((col_num, row_num)) {
                     let cell_value = if !case_sensitive {
                         cell.get_value().to_lowercase()
                     } else {
@@ -192,39 +435,215 @@ impl XlsxTool {
     }
 
     pub fn get_cell_value(&self, worksheet: &Worksheet, row: u32, col: u32) -> Result<CellValue> {
-        let cell = worksheet.get_cell((row, col)).context("Cell not found")?;
+        worksheet.get_cell((col, row)).context("Cell not found")?;
+        let source = WorkbookCells::new(&self.workbook);
+        Ok(source.cell_value(worksheet, col, row))
+    }
+}
 
-        Ok(CellValue {
-            value: cell.get_value().into_owned(),
-            formula: if cell.get_formula().is_empty() {
-                None
-            } else {
-                Some(cell.get_formula().to_string())
-            },
-        })
+/// Evaluates formulas against the workbook, remembering results so shared inputs are only
+/// computed once
+struct WorkbookCells<'a> {
+    workbook: &'a Spreadsheet,
+    /// Worksheets of the formulas being evaluated, unqualified references point to the last
+    sheets: RefCell<Vec<String>>,
+    results: RefCell<HashMap<(String, u32, u32), Value>>,
+    in_progress: RefCell<HashSet<(String, u32, u32)>>,
+}
+
+impl<'a> WorkbookCells<'a> {
+    fn new(workbook: &'a Spreadsheet) -> Self {
+        Self {
+            workbook,
+            sheets: RefCell::new(Vec::new()),
+            results: RefCell::new(HashMap::new()),
+            in_progress: RefCell::new(HashSet::new()),
+        }
+    }
+
+    fn evaluate(&self, sheet: &str, formula: &str) -> Result<Value> {
+        self.sheets.borrow_mut().push(sheet.to_string());
+        let result = xlsx_formula::evaluate(formula, self);
+        self.sheets.borrow_mut().pop();
+        result
+    }
+
+    /// The displayed value of a cell, evaluating formulas where possible
+    fn cell_value(&self, worksheet: &Worksheet, col: u32, row: u32) -> CellValue {
+        let Some(cell) = worksheet.get_cell((col, row)) else {
+            return CellValue {
+                value: String::new(),
+                formula: None,
+                note: None,
+            };
+        };
+        if !cell.is_formula() {
+            return CellValue {
+                value: cell.get_value().into_owned(),
+                formula: None,
+                note: None,
+            };
+        }
+
+        let (value, note) = match self.evaluate(worksheet.get_name(), cell.get_formula()) {
+            Ok(value) => (value.to_string(), None),
+            Err(e) => (
+                cell.get_value().into_owned(),
+                Some(format!("Showing the value saved in the file, {}", e)),
+            ),
+        };
+        CellValue {
+            value,
+            formula: Some(cell.get_formula().to_string()),
+            note,
+        }
     }
 }
 
-fn parse_range(range: &str) -> Result<(u32, u32, u32, u32)> {
-    // Handle ranges like "A1:B10"
-    let parts: Vec<&str> = range.split(':').collect();
-    if parts.len() != 2 {
-        anyhow::bail!("Invalid range format. Expected format: 'A1:B10'");
+impl CellSource for WorkbookCells<'_> {
+    fn value(&self, cell_ref: &CellRef) -> Value {
+        let sheet = match &cell_ref.sheet {
+            Some(sheet) => sheet.clone(),
+            None => self.sheets.borrow().last().cloned().unwrap_or_default(),
+        };
+        let Some(worksheet) = self.workbook.get_sheet_by_name(&sheet) else {
+            return Value::Error("#REF!".to_string());
+        };
+        let Some(cell) = worksheet.get_cell((cell_ref.col, cell_ref.row)) else {
+            return Value::Empty;
+        };
+
+        if !cell.is_formula() {
+            let text = cell.get_value();
+            return match cell.get_data_type() {
+                "n" => cell.get_value_number().map_or(Value::Empty, Value::Number),
+                "b" => Value::Bool(text == "TRUE"),
+                "e" => Value::Error(text.into_owned()),
+                _ if text.is_empty() => Value::Empty,
+                _ => Value::Text(text.into_owned()),
+            };
+        }
+
+        let key = (sheet.clone(), cell_ref.col, cell_ref.row);
+        if let Some(value) = self.results.borrow().get(&key) {
+            return value.clone();
+        }
+        if !self.in_progress.borrow_mut().insert(key.clone()) {
+            return Value::Error("#CIRCULAR!".to_string());
+        }
+        let value = self
+            .evaluate(&sheet, cell.get_formula())
+            // Fall back to the cached result for formulas the evaluator does not support
+            .unwrap_or_else(|_| Value::Text(cell.get_value().into_owned()));
+        self.in_progress.borrow_mut().remove(&key);
+        self.results.borrow_mut().insert(key, value.clone());
+        value
     }
+}
 
-    let start = parse_cell_reference(parts[0])?;
-    let end = parse_cell_reference(parts[1])?;
+fn set_cell(worksheet: &mut Worksheet, col: u32, row: u32, value: &serde_json::Value) {
+    let cell = worksheet.get_cell_mut((col, row));
+    match value {
+        serde_json::Value::Null => {
+            cell.set_blank();
+        }
+        serde_json::Value::Bool(b) => {
+            cell.set_value_bool(*b);
+        }
+        serde_json::Value::Number(n) => {
+            cell.set_value_number(n.as_f64().unwrap_or_default());
+        }
+        serde_json::Value::String(s) if s.len() > 1 && s.starts_with('=') => {
+            cell.set_blank();
+            cell.set_formula(&s[1..]);
+        }
+        serde_json::Value::String(s) => {
+            cell.set_value(s.clone());
+        }
+        other => {
+            cell.set_value(other.to_string());
+        }
+    }
+}
+
+/// Convert "#RRGGBB" or "RRGGBB" to the ARGB form used in xlsx files
+fn argb(color: &str) -> Result<String> {
+    let hex = color.trim_start_matches('#').to_ascii_uppercase();
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!(
+            "Invalid color '{}', expected a hex color like #FF0000",
+            color
+        );
+    }
+    match hex.len() {
+        6 => Ok(format!("FF{}", hex)),
+        8 => Ok(hex),
+        _ => anyhow::bail!(
+            "Invalid color '{}', expected a hex color like #FF0000",
+            color
+        ),
+    }
+}
+
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => in_quotes = true,
+            ',' if !in_quotes => row.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' if !in_quotes => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    rows
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn parse_range(range: &str) -> Result<(u32, u32, u32, u32)> {
+    // Handle ranges like "A1:B10", a single cell is treated as a one cell range
+    let parts: Vec<&str> = range.split(':').collect();
+    let (start, end) = match parts.as_slice() {
+        [cell] => (parse_cell_reference(cell)?, parse_cell_reference(cell)?),
+        [start, end] => (parse_cell_reference(start)?, parse_cell_reference(end)?),
+        _ => anyhow::bail!("Invalid range format. Expected format: 'A1:B10'"),
+    };
 
     Ok((start.0, start.1, end.0, end.1))
 }
 
 fn parse_cell_reference(reference: &str) -> Result<(u32, u32)> {
-    // Parse Excel cell reference (e.g., "A1") and return (column, row)
+    // Parse Excel cell reference (e.g., "A1" or "$A$1") and return (column, row)
     let mut col_str = String::new();
     let mut row_str = String::new();
     let mut parsing_row = false;
 
-    for c in reference.chars() {
+    for c in reference.trim().chars().filter(|c| *c != '$') {
         if c.is_alphabetic() {
             if parsing_row {
                 anyhow::bail!("Invalid cell reference format");
@@ -255,9 +674,20 @@ fn column_letter_to_number(column: &str) -> Result<u32> {
     Ok(result)
 }
 
+fn column_number_to_letter(mut column: u32) -> String {
+    let mut letters = Vec::new();
+    while column > 0 {
+        let remainder = (column - 1) % 26;
+        letters.push((b'A' + remainder as u8) as char);
+        column = (column - 1) / 26;
+    }
+    letters.iter().rev().collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
     use std::path::PathBuf;
 
     fn get_test_file() -> PathBuf {
@@ -328,4 +758,107 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_rows_and_columns_are_not_transposed() -> Result<()> {
+        let xlsx = XlsxTool::new(get_test_file())?;
+        let worksheet = xlsx.get_worksheet_by_index(0)?;
+        assert_eq!(xlsx.get_cell_value(worksheet, 1, 2)?.value, "Country");
+
+        let range = xlsx.get_range(worksheet, "A1:C2")?;
+        assert_eq!(range.values.len(), 2);
+        assert_eq!(range.values[0].len(), 3);
+        assert_eq!(range.values[0][1].value, "Country");
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_range_with_formulas() -> Result<()> {
+        let mut xlsx = XlsxTool::create();
+        let written = xlsx.write_range(
+            "Sheet1",
+            "A1",
+            &[
+                vec![json!("Item"), json!("Price")],
+                vec![json!("Apples"), json!(1.5)],
+                vec![json!("Pears"), json!(2)],
+                vec![json!("Total"), json!("=SUM(B2:B3)")],
+            ],
+        )?;
+        assert_eq!(written, "A1:B4");
+
+        let worksheet = xlsx.get_worksheet_by_name("Sheet1")?;
+        let total = xlsx.get_cell_value(worksheet, 4, 2)?;
+        assert_eq!(total.value, "3.5");
+        assert_eq!(total.formula.as_deref(), Some("SUM(B2:B3)"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_structure_edits_and_save() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("book.xlsx");
+
+        let mut xlsx = XlsxTool::create();
+        xlsx.write_range("Sheet1", "A1", &[vec![json!(1)], vec![json!(2)]])?;
+        xlsx.insert_rows("Sheet1", 1, 1)?;
+        xlsx.update_cell("Sheet1", 1, 1, "Header")?;
+        xlsx.insert_columns("Sheet1", 1, 1)?;
+        xlsx.delete_columns("Sheet1", 1, 1)?;
+        xlsx.add_worksheet("Summary")?;
+        xlsx.update_cell("Summary", 1, 1, "=SUM(Sheet1!A2:A3)*2")?;
+        xlsx.format_range(
+            "Summary",
+            "A1",
+            &CellFormat {
+                bold: Some(true),
+                fill_color: Some("#FFFF00".to_string()),
+                number_format: Some("0.00".to_string()),
+                ..Default::default()
+            },
+        )?;
+        assert!(xlsx.add_worksheet("Summary").is_err());
+        assert!(xlsx
+            .format_range("Summary", "A1:XFD1048576", &CellFormat::default())
+            .is_err());
+        assert!(xlsx.has_unsaved_changes());
+
+        xlsx.save(&path)?;
+        assert!(!xlsx.has_unsaved_changes());
+
+        let reopened = XlsxTool::new(&path)?;
+        let sheet1 = reopened.get_worksheet_by_name("Sheet1")?;
+        let column = reopened.get_range(sheet1, "A1:A3")?;
+        let values: Vec<&str> = column.values.iter().map(|r| r[0].value.as_str()).collect();
+        assert_eq!(values, ["Header", "1", "2"]);
+
+        // The saved file carries the computed result for other applications
+        let summary = reopened.get_worksheet_by_name("Summary")?;
+        assert_eq!(summary.get_cell((1, 1)).unwrap().get_value(), "6");
+        assert!(summary.get_style((1, 1)).get_font().unwrap().get_bold());
+        Ok(())
+    }
+
+    #[test]
+    fn test_csv_round_trip() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("input.csv");
+        let output = dir.path().join("output.csv");
+        std::fs::write(
+            &input,
+            "name,notes\r\n\"Smith, J\",\"said \"\"hi\"\"\"\r\nLee,=1+1\r\n",
+        )?;
+
+        let mut xlsx = XlsxTool::create();
+        assert_eq!(xlsx.import_csv("People", &input, "A1")?, (3, 2));
+        let worksheet = xlsx.get_worksheet_by_name("People")?;
+        assert!(xlsx.get_cell_value(worksheet, 3, 2)?.formula.is_none());
+
+        assert_eq!(xlsx.export_csv("People", &output)?, 3);
+        assert_eq!(
+            std::fs::read_to_string(&input)?,
+            std::fs::read_to_string(&output)?
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,474 @@
+//! Lightweight git checkpoints of the working tree.
+//!
+//! A checkpoint is a commit of the full working tree (tracked and untracked files, respecting
+//! .gitignore) stored on a hidden ref, so the user's branches, index and stash are never touched.
+//! Files matched by .gooseignore are left out, so their contents never reach the object store.
+//! Each checkpoint's parent is the previous checkpoint, which makes `git log` on the ref the
+//! checkpoint history.
+
+use anyhow::{anyhow, Context, Result};
+use ignore::gitignore::Gitignore;
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tempfile::TempDir;
+
+use super::load_ignore_patterns;
+
+/// The hidden ref that holds the chain of checkpoint commits
+pub const CHECKPOINT_REF: &str = "refs/goose/checkpoints";
+
+/// Maximum number of characters of diff output returned to callers
+const MAX_DIFF_CHARS: usize = 100_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Checkpoint {
+    /// Full commit id of the checkpoint
+    pub id: String,
+    /// Unix timestamp when the checkpoint was created
+    pub created: i64,
+    /// Label given when the checkpoint was created
+    pub message: String,
+}
+
+impl Checkpoint {
+    pub fn short_id(&self) -> &str {
+        &self.id[..self.id.len().min(8)]
+    }
+}
+
+/// Checkpoints for the git repository containing a working directory
+pub struct Checkpoints {
+    root: PathBuf,
+    ignore_patterns: Gitignore,
+}
+
+impl Checkpoints {
+    /// Open the checkpoint store for the repository containing `dir`.
+    ///
+    /// Fails if `dir` is not inside a git work tree or git is not installed. The .gooseignore
+    /// patterns that apply in `dir` decide which files are left out of checkpoints.
+    pub fn open(dir: &Path) -> Result<Self> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["rev-parse", "--show-toplevel"])
+            .output()
+            .context("Failed to run git, is it installed?")?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{} is not inside a git repository, checkpoints require git",
+                dir.display()
+            ));
+        }
+
+        let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok(Self {
+            root: PathBuf::from(root),
+            ignore_patterns: load_ignore_patterns(dir),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Snapshot the working tree as a new checkpoint.
+    ///
+    /// Returns `None` if the working tree is identical to the latest checkpoint, so calling
+    /// this at the start of every turn only records turns that follow a change.
+    pub fn create(&self, message: &str) -> Result<Option<Checkpoint>> {
+        let tree = self.snapshot_tree()?;
+        let parent = self.head()?;
+
+        if let Some(parent) = &parent {
+            if self.git(&["rev-parse", &format!("{}^{{tree}}", parent)])? == tree {
+                return Ok(None);
+            }
+        }
+
+        let mut args = vec!["commit-tree", tree.as_str(), "-m", message];
+        if let Some(parent) = &parent {
+            args.push("-p");
+            args.push(parent.as_str());
+        }
+        let id = self.git(&args)?;
+        self.git(&["update-ref", CHECKPOINT_REF, &id])?;
+
+        Ok(Some(self.get(&id)?))
+    }
+
+    /// List checkpoints, most recent first
+    pub fn list(&self, limit: usize) -> Result<Vec<Checkpoint>> {
+        if self.head()?.is_none() {
+            return Ok(Vec::new());
+        }
+
+        let output = self.git(&[
+            "log",
+            &format!("--max-count={}", limit),
+            "--format=%H%x1f%ct%x1f%s",
+            CHECKPOINT_REF,
+        ])?;
+
+        Ok(output.lines().filter_map(parse_log_line).collect())
+    }
+
+    /// Look up a checkpoint by full or abbreviated id
+    pub fn get(&self, id: &str) -> Result<Checkpoint> {
+        let id = self.resolve(id)?;
+        let output = self.git(&["log", "-1", "--format=%H%x1f%ct%x1f%s", &id])?;
+        parse_log_line(&output).ok_or_else(|| anyhow!("Checkpoint '{}' not found", id))
+    }
+
+    /// Diff between a checkpoint and the current working tree.
+    ///
+    /// With `stat` set only a per-file summary is returned.
+    pub fn diff(&self, id: &str, stat: bool) -> Result<String> {
+        let checkpoint = self.resolve(id)?;
+        let tree = self.snapshot_tree()?;
+
+        let mut args = vec!["diff", "--no-color", "--no-ext-diff"];
+        if stat {
+            args.push("--stat");
+        }
+        args.push(checkpoint.as_str());
+        args.push(tree.as_str());
+
+        let mut diff = self.git_raw(&args)?;
+        if diff.chars().count() > MAX_DIFF_CHARS {
+            diff = diff.chars().take(MAX_DIFF_CHARS).collect();
+            diff.push_str("\n... diff truncated, use a stat diff or narrow it with git directly");
+        }
+        Ok(diff)
+    }
+
+    /// Restore the working tree to a checkpoint.
+    ///
+    /// The current state is checkpointed first so a restore can itself be undone. Files that
+    /// were created after the checkpoint are removed, ignored files are left alone, and the
+    /// user's index and HEAD are not modified.
+    pub fn restore(&self, id: &str) -> Result<Checkpoint> {
+        let target = self.get(id)?;
+        self.create(&format!("Before restoring {}", target.short_id()))?;
+
+        let current = self.snapshot_tree()?;
+        let added = self.git_raw(&[
+            "diff",
+            "--name-only",
+            "--no-renames",
+            "--diff-filter=A",
+            "-z",
+            &target.id,
+            &current,
+        ])?;
+        for file in added.split('\0').filter(|f| !f.is_empty()) {
+            let path = self.root.join(file);
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+
+        let scratch = TempDir::new()?;
+        let index = scratch.path().join("index");
+        self.git_with_index(&index, &["read-tree", &target.id])?;
+        self.git_with_index(&index, &["checkout-index", "--all", "--force"])?;
+
+        Ok(target)
+    }
+
+    /// Write the working tree into a tree object without touching the real index
+    fn snapshot_tree(&self) -> Result<String> {
+        let scratch = TempDir::new()?;
+        let index = scratch.path().join("index");
+
+        // Seeding from the real index lets git reuse cached stat info instead of rehashing
+        let real_index = self
+            .root
+            .join(self.git(&["rev-parse", "--git-path", "index"])?);
+        if real_index.is_file() {
+            std::fs::copy(&real_index, &index)?;
+        }
+
+        // Stage path by path rather than with `git add --all`, which would hash files matched
+        // by .gooseignore into the object store before they could be dropped
+        let paths = self.git_raw_with_index(
+            &index,
+            &[
+                "ls-files",
+                "-z",
+                "--cached",
+                "--others",
+                "--exclude-standard",
+            ],
+        )?;
+        let (ignored, kept): (Vec<&str>, Vec<&str>) = paths
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .partition(|path| self.is_ignored(path));
+        self.update_index(&index, &["--add", "--remove"], &kept)?;
+        self.update_index(&index, &["--force-remove"], &ignored)?;
+        self.git_with_index(&index, &["write-tree"])
+    }
+
+    /// Whether a path relative to the repository root, or one of its parents, is in .gooseignore
+    fn is_ignored(&self, path: &str) -> bool {
+        let path = self.root.join(path);
+        path.ancestors()
+            .take_while(|ancestor| *ancestor != self.root)
+            .enumerate()
+            .any(|(depth, ancestor)| {
+                self.ignore_patterns
+                    .matched(ancestor, depth > 0)
+                    .is_ignore()
+            })
+    }
+
+    /// Run `git update-index` on `paths`, passing them on stdin so any number fits
+    fn update_index(&self, index: &Path, flags: &[&str], paths: &[&str]) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+        let mut command = self.command();
+        command
+            .env("GIT_INDEX_FILE", index)
+            .arg("update-index")
+            .args(flags)
+            .args(["-z", "--stdin"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+        let mut child = command.spawn().context("Failed to run git")?;
+        let mut stdin = child.stdin.take().context("Failed to open git stdin")?;
+        for path in paths {
+            stdin.write_all(path.as_bytes())?;
+            stdin.write_all(b"\0")?;
+        }
+        drop(stdin);
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "git update-index failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    fn head(&self) -> Result<Option<String>> {
+        let output = self
+            .command()
+            .args(["rev-parse", "--verify", "--quiet", CHECKPOINT_REF])
+            .output()?;
+        if output.status.success() {
+            Ok(Some(
+                String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn resolve(&self, id: &str) -> Result<String> {
+        let id = id.trim();
+        if id.is_empty() || id.starts_with('-') {
+            return Err(anyhow!("Invalid checkpoint id '{}'", id));
+        }
+
+        let commit = self
+            .git(&[
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                &format!("{}^{{commit}}", id),
+            ])
+            .map_err(|_| anyhow!("Checkpoint '{}' not found", id))?;
+
+        // Only accept commits that are part of the checkpoint chain
+        let is_checkpoint = self
+            .command()
+            .args(["merge-base", "--is-ancestor", &commit, CHECKPOINT_REF])
+            .status()?
+            .success();
+        if !is_checkpoint {
+            return Err(anyhow!("'{}' is not a goose checkpoint", id));
+        }
+
+        Ok(commit)
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new("git");
+        command
+            .current_dir(&self.root)
+            .env("GIT_AUTHOR_NAME", "goose")
+            .env("GIT_AUTHOR_EMAIL", "goose@localhost")
+            .env("GIT_COMMITTER_NAME", "goose")
+            .env("GIT_COMMITTER_EMAIL", "goose@localhost");
+        command
+    }
+
+    fn git_with_index(&self, index: &Path, args: &[&str]) -> Result<String> {
+        self.git_raw_with_index(index, args)
+            .map(|out| out.trim().to_string())
+    }
+
+    fn git_raw_with_index(&self, index: &Path, args: &[&str]) -> Result<String> {
+        let mut command = self.command();
+        command.env("GIT_INDEX_FILE", index).args(args);
+        run(command, args)
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String> {
+        self.git_raw(args).map(|out| out.trim().to_string())
+    }
+
+    fn git_raw(&self, args: &[&str]) -> Result<String> {
+        let mut command = self.command();
+        command.args(args);
+        run(command, args)
+    }
+}
+
+fn run(mut command: Command, args: &[&str]) -> Result<String> {
+    let output = command.output().context("Failed to run git")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn parse_log_line(line: &str) -> Option<Checkpoint> {
+    let mut parts = line.trim().splitn(3, '\x1f');
+    Some(Checkpoint {
+        id: parts.next()?.to_string(),
+        created: parts.next()?.parse().ok()?,
+        message: parts.next().unwrap_or_default().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn init_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let status = Command::new("git")
+            .arg("init")
+            .arg("--quiet")
+            .current_dir(dir.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        dir
+    }
+
+    #[test]
+    fn test_not_a_repo() {
+        let dir = TempDir::new().unwrap();
+        assert!(Checkpoints::open(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_create_skips_unchanged_tree() {
+        let dir = init_repo();
+        fs::write(dir.path().join("a.txt"), "one").unwrap();
+
+        let checkpoints = Checkpoints::open(dir.path()).unwrap();
+        assert!(checkpoints.create("first").unwrap().is_some());
+        assert!(checkpoints.create("second").unwrap().is_none());
+
+        fs::write(dir.path().join("a.txt"), "two").unwrap();
+        assert!(checkpoints.create("third").unwrap().is_some());
+
+        let list = checkpoints.list(10).unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].message, "third");
+        assert_eq!(list[1].message, "first");
+    }
+
+    #[test]
+    fn test_diff_and_restore() {
+        let dir = init_repo();
+        fs::write(dir.path().join(".gitignore"), "ignored.txt\n").unwrap();
+        fs::write(dir.path().join("a.txt"), "original").unwrap();
+
+        let checkpoints = Checkpoints::open(dir.path()).unwrap();
+        let checkpoint = checkpoints.create("before turn").unwrap().unwrap();
+
+        fs::write(dir.path().join("a.txt"), "changed").unwrap();
+        fs::write(dir.path().join("new.txt"), "new file").unwrap();
+        fs::write(dir.path().join("ignored.txt"), "keep me").unwrap();
+
+        let diff = checkpoints.diff(checkpoint.short_id(), false).unwrap();
+        assert!(diff.contains("+changed"));
+        assert!(diff.contains("new.txt"));
+        assert!(!diff.contains("ignored.txt"));
+
+        checkpoints.restore(checkpoint.short_id()).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "original"
+        );
+        assert!(!dir.path().join("new.txt").exists());
+        assert!(dir.path().join("ignored.txt").exists());
+
+        // The pre-restore state was checkpointed, so the restore can be undone
+        let list = checkpoints.list(10).unwrap();
+        assert!(list[0].message.starts_with("Before restoring"));
+        checkpoints.restore(&list[0].id).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "changed"
+        );
+    }
+
+    #[test]
+    fn test_gooseignored_files_stay_out_of_checkpoints() {
+        let dir = init_repo();
+        fs::write(dir.path().join(".gooseignore"), "secret.txt\nprivate/\n").unwrap();
+        fs::write(dir.path().join("a.txt"), "one").unwrap();
+        fs::write(dir.path().join("secret.txt"), "hunter2").unwrap();
+        fs::create_dir(dir.path().join("private")).unwrap();
+        fs::write(dir.path().join("private/key.pem"), "private key").unwrap();
+
+        let checkpoints = Checkpoints::open(dir.path()).unwrap();
+        let checkpoint = checkpoints.create("first").unwrap().unwrap();
+        let files = checkpoints
+            .git(&["ls-tree", "-r", "--name-only", &checkpoint.id])
+            .unwrap();
+        assert_eq!(files.lines().collect::<Vec<_>>(), [".gooseignore", "a.txt"]);
+
+        // The contents were never hashed into the object store
+        for file in ["secret.txt", "private/key.pem"] {
+            let blob = checkpoints.git(&["hash-object", file]).unwrap();
+            assert!(checkpoints.git(&["cat-file", "-e", &blob]).is_err());
+        }
+
+        // Restoring leaves them alone
+        fs::write(dir.path().join("secret.txt"), "changed").unwrap();
+        checkpoints.restore(checkpoint.short_id()).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("secret.txt")).unwrap(),
+            "changed"
+        );
+    }
+
+    #[test]
+    fn test_rejects_non_checkpoint_commits() {
+        let dir = init_repo();
+        fs::write(dir.path().join("a.txt"), "one").unwrap();
+        let checkpoints = Checkpoints::open(dir.path()).unwrap();
+        checkpoints.create("first").unwrap();
+
+        assert!(checkpoints.get("--all").is_err());
+        assert!(checkpoints.get("deadbeef").is_err());
+    }
+}
@@ -0,0 +1,424 @@
+//! Discovery and rendering of .goosehints files.
+//!
+//! Hints are collected from the global config directory and from every directory between the
+//! repository root and the working directory. Hints in subdirectories are picked up later, the
+//! first time a file below them is touched. Hint files can pull in other files with
+//! `@include <path>` and use a few `{{ variable }}` placeholders. Everything shares a token
+//! budget (`GOOSE_HINTS_TOKEN_BUDGET`) so hints cannot crowd out the rest of the context.
+
+use ignore::gitignore::Gitignore;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+pub const HINTS_FILENAME: &str = ".goosehints";
+
+const DEFAULT_TOKEN_BUDGET: usize = 8000;
+/// Includes nested deeper than this are skipped
+const MAX_INCLUDE_DEPTH: usize = 5;
+
+static VARIABLE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{\s*([a-z_]+)\s*\}\}").expect("valid regex"));
+
+/// Rough token count, hints are plain prose so four characters per token is close enough
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// The closest ancestor of `dir` containing a .git entry
+pub fn find_repo_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|ancestor| ancestor.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+struct Section {
+    heading: String,
+    body: String,
+}
+
+pub struct HintsLoader {
+    cwd: PathBuf,
+    /// Hints are only collected at or below this directory
+    root: PathBuf,
+    global_path: Option<PathBuf>,
+    ignore_patterns: Arc<Gitignore>,
+    budget: usize,
+    used: Mutex<usize>,
+    /// Directories already checked for hints
+    loaded: Mutex<HashSet<PathBuf>>,
+}
+
+impl HintsLoader {
+    pub fn new(
+        cwd: PathBuf,
+        global_path: Option<PathBuf>,
+        ignore_patterns: Arc<Gitignore>,
+        budget: usize,
+    ) -> Self {
+        let root = find_repo_root(&cwd).unwrap_or_else(|| cwd.clone());
+        Self {
+            cwd,
+            root,
+            global_path,
+            ignore_patterns,
+            budget,
+            used: Mutex::new(0),
+            loaded: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn from_env(cwd: PathBuf, global_path: Option<PathBuf>, ignore: Arc<Gitignore>) -> Self {
+        let budget = std::env::var("GOOSE_HINTS_TOKEN_BUDGET")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_TOKEN_BUDGET);
+        Self::new(cwd, global_path, ignore, budget)
+    }
+
+    /// Global hints and the hints from the repository root down to the working directory
+    pub fn initial_hints(&self) -> String {
+        let mut sections = Vec::new();
+
+        if let Some(global_path) = self.global_path.as_ref().filter(|p| p.is_file()) {
+            sections.push(Section {
+                heading: "### Global Hints\nThe developer extension includes some global hints that apply to all projects & directories.\n".to_string(),
+                body: self.render(global_path),
+            });
+        }
+
+        let dirs: Vec<PathBuf> = self
+            .cwd
+            .ancestors()
+            .take_while(|dir| dir.starts_with(&self.root))
+            .map(Path::to_path_buf)
+            .collect();
+        self.loaded.lock().unwrap().extend(dirs.iter().cloned());
+
+        for dir in dirs.iter().rev() {
+            let path = dir.join(HINTS_FILENAME);
+            if !path.is_file() {
+                continue;
+            }
+            let heading = if *dir == self.cwd {
+                "### Project Hints\nThe developer extension includes some hints for working on the project in this directory.\n".to_string()
+            } else {
+                dir_heading(dir)
+            };
+            sections.push(Section {
+                heading,
+                body: self.render(&path),
+            });
+        }
+
+        self.fit(sections)
+    }
+
+    /// Hints from directories between the root and `path` that have not been loaded yet.
+    ///
+    /// Called when the agent touches a file, so hints in subdirectories are only added once
+    /// the agent works there.
+    pub fn discover(&self, path: &Path) -> Option<String> {
+        let dir = if path.is_dir() { path } else { path.parent()? };
+        if !dir.starts_with(&self.root) {
+            return None;
+        }
+
+        let new_dirs: Vec<PathBuf> = {
+            let mut loaded = self.loaded.lock().unwrap();
+            dir.ancestors()
+                .take_while(|d| d.starts_with(&self.root))
+                .filter(|d| loaded.insert(d.to_path_buf()))
+                .map(Path::to_path_buf)
+                .collect()
+        };
+
+        let sections: Vec<Section> = new_dirs
+            .iter()
+            .rev()
+            .map(|dir| dir.join(HINTS_FILENAME))
+            .filter(|path| path.is_file() && !self.is_ignored(path))
+            .map(|path| Section {
+                heading: dir_heading(path.parent().unwrap_or(&path)),
+                body: self.render(&path),
+            })
+            .collect();
+
+        let hints = self.fit(sections);
+        (!hints.is_empty()).then_some(hints)
+    }
+
+    fn is_ignored(&self, path: &Path) -> bool {
+        self.ignore_patterns.matched(path, false).is_ignore()
+    }
+
+    /// Join sections within the remaining budget. The most specific sections come last and
+    /// are given the budget first, since they are the most relevant to the work at hand.
+    fn fit(&self, sections: Vec<Section>) -> String {
+        let mut used = self.used.lock().unwrap();
+        let mut parts = Vec::new();
+
+        for section in sections.into_iter().rev() {
+            let remaining = self.budget.saturating_sub(*used);
+            let tokens = estimate_tokens(&section.heading) + estimate_tokens(&section.body);
+            let body = if tokens <= remaining {
+                *used += tokens;
+                section.body
+            } else if remaining > estimate_tokens(&section.heading) + 50 {
+                let chars = (remaining - estimate_tokens(&section.heading) - 20) * 4;
+                *used = self.budget;
+                let clipped: String = section.body.chars().take(chars).collect();
+                format!("{}\n[Hints truncated to fit the token budget]", clipped)
+            } else {
+                tracing::warn!("Skipping hints, the token budget is exhausted");
+                continue;
+            };
+            parts.push(format!("{}{}", section.heading, body));
+        }
+
+        parts.reverse();
+        parts.join("\n\n")
+    }
+
+    /// Render a hints file and its includes
+    ///
+    /// Includes must stay inside the directory the hints belong to: the repository root for
+    /// project hints, the goose config directory for global hints. A cloned repository could
+    /// otherwise pull files like `~/.ssh/id_rsa` into the prompt.
+    fn render(&self, path: &Path) -> String {
+        let global = self.global_path.as_deref() == Some(path);
+        let base = if global {
+            path.parent().unwrap_or(path)
+        } else {
+            &self.root
+        };
+        let Ok(base) = base.canonicalize() else {
+            tracing::warn!("Failed to resolve {}", base.display());
+            return String::new();
+        };
+        let mut visited = Vec::new();
+        self.render_file(path, &base, global, &mut visited)
+    }
+
+    fn render_file(
+        &self,
+        path: &Path,
+        base: &Path,
+        global: bool,
+        stack: &mut Vec<PathBuf>,
+    ) -> String {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if stack.contains(&canonical) {
+            tracing::warn!("Skipping recursive include of {}", path.display());
+            return String::new();
+        }
+        let Ok(content) = std::fs::read_to_string(path) else {
+            tracing::warn!("Failed to read hints from {}", path.display());
+            return String::new();
+        };
+        let dir = path.parent().unwrap_or(Path::new("/"));
+
+        stack.push(canonical);
+        let mut rendered = Vec::new();
+        for line in content.lines() {
+            let Some(include) = line.trim().strip_prefix("@include ") else {
+                rendered.push(self.substitute(line, dir));
+                continue;
+            };
+
+            let include = include.trim();
+            if !global && (include.starts_with('~') || Path::new(include).is_absolute()) {
+                tracing::warn!(
+                    "Skipping {}, project hints can only include files in the project",
+                    include
+                );
+                continue;
+            }
+            let target = dir.join(shellexpand::tilde(include).as_ref());
+            if stack.len() > MAX_INCLUDE_DEPTH {
+                tracing::warn!(
+                    "Skipping {}, includes are nested too deep",
+                    target.display()
+                );
+                continue;
+            }
+            let Ok(resolved) = target.canonicalize() else {
+                tracing::warn!("Included hints file {} does not exist", target.display());
+                continue;
+            };
+            if !resolved.starts_with(base) {
+                tracing::warn!(
+                    "Skipping {}, it is outside {}",
+                    target.display(),
+                    base.display()
+                );
+            } else if self.is_ignored(&target) || self.is_ignored(&resolved) {
+                tracing::warn!("Skipping {}, restricted by .gooseignore", target.display());
+            } else if resolved.is_file() {
+                rendered.push(self.render_file(&resolved, base, global, stack));
+            } else {
+                tracing::warn!("Included hints file {} is not a file", target.display());
+            }
+        }
+        stack.pop();
+
+        rendered.join("\n")
+    }
+
+    /// Replace the known `{{ variable }}` placeholders, leaving anything else untouched
+    fn substitute(&self, line: &str, dir: &Path) -> String {
+        VARIABLE
+            .replace_all(line, |captures: &regex::Captures| match &captures[1] {
+                "cwd" => self.cwd.display().to_string(),
+                "repo_root" => self.root.display().to_string(),
+                "dir" => dir.display().to_string(),
+                "os" => std::env::consts::OS.to_string(),
+                "arch" => std::env::consts::ARCH.to_string(),
+                "date" => chrono::Local::now().format("%Y-%m-%d").to_string(),
+                _ => captures[0].to_string(),
+            })
+            .into_owned()
+    }
+}
+
+fn dir_heading(dir: &Path) -> String {
+    format!(
+        "### Hints for {}\nThese hints apply to files in {} and its subdirectories.\n",
+        dir.display(),
+        dir.display()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ignore::gitignore::GitignoreBuilder;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("packages/api/src")).unwrap();
+        fs::create_dir_all(root.join("packages/web")).unwrap();
+        fs::write(root.join(HINTS_FILENAME), "Root hints").unwrap();
+        fs::write(root.join("packages/api/.goosehints"), "Api hints").unwrap();
+        fs::write(root.join("packages/web/.goosehints"), "Web hints").unwrap();
+        dir
+    }
+
+    fn loader(cwd: PathBuf, budget: usize) -> HintsLoader {
+        let mut builder = GitignoreBuilder::new(&cwd);
+        builder.add_line(None, "**/.env").unwrap();
+        HintsLoader::new(cwd, None, Arc::new(builder.build().unwrap()), budget)
+    }
+
+    #[test]
+    fn test_ancestor_hints() {
+        let dir = repo();
+        let cwd = dir.path().join("packages/api");
+        let hints = loader(cwd, 1000).initial_hints();
+
+        let root = hints.find("Root hints").unwrap();
+        let api = hints.find("Api hints").unwrap();
+        assert!(root < api);
+        assert!(hints.contains("### Project Hints"));
+        assert!(!hints.contains("Web hints"));
+    }
+
+    #[test]
+    fn test_discover_subdirectories_once() {
+        let dir = repo();
+        let loader = loader(dir.path().to_path_buf(), 1000);
+        assert!(loader.initial_hints().contains("Root hints"));
+
+        let hints = loader
+            .discover(&dir.path().join("packages/api/src/main.rs"))
+            .unwrap();
+        assert!(hints.contains("Api hints"));
+        assert!(!hints.contains("Root hints"));
+
+        assert!(loader
+            .discover(&dir.path().join("packages/api/src/lib.rs"))
+            .is_none());
+        assert!(loader.discover(Path::new("/elsewhere/file.rs")).is_none());
+    }
+
+    #[test]
+    fn test_includes_and_templating() {
+        let dir = repo();
+        let root = dir.path();
+        fs::write(
+            root.join(HINTS_FILENAME),
+            "Start in {{ repo_root }} on {{os}}\n@include docs/style.md\n@include .env\n{{ unknown }}",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        // Includes are relative to the including file, and cycles are cut
+        fs::write(root.join("docs/style.md"), "Use tabs\n@include style.md").unwrap();
+        fs::write(root.join(".env"), "SECRET=1").unwrap();
+
+        let hints = loader(root.to_path_buf(), 1000).initial_hints();
+        assert!(hints.contains(&format!(
+            "Start in {} on {}",
+            root.display(),
+            std::env::consts::OS
+        )));
+        assert_eq!(hints.matches("Use tabs").count(), 1);
+        assert!(!hints.contains("SECRET"));
+        assert!(hints.contains("{{ unknown }}"));
+    }
+
+    #[test]
+    fn test_includes_stay_inside_their_root() {
+        let dir = repo();
+        let root = dir.path().join("repo");
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(dir.path().join("secret.txt"), "SECRET=1").unwrap();
+        fs::write(
+            root.join(HINTS_FILENAME),
+            format!(
+                "@include ../secret.txt\n@include {}\n@include ~/.ssh/id_rsa",
+                dir.path().join("secret.txt").display()
+            ),
+        )
+        .unwrap();
+        assert!(!loader(root.clone(), 1000)
+            .initial_hints()
+            .contains("SECRET"));
+
+        // Global hints may include files next to them, but not outside the config dir
+        let config = dir.path().join("config");
+        fs::create_dir_all(&config).unwrap();
+        fs::write(config.join("shared.md"), "Shared hints").unwrap();
+        fs::write(
+            config.join(HINTS_FILENAME),
+            "@include shared.md\n@include ../secret.txt",
+        )
+        .unwrap();
+        let loader = HintsLoader::new(
+            root,
+            Some(config.join(HINTS_FILENAME)),
+            Arc::new(GitignoreBuilder::new(dir.path()).build().unwrap()),
+            1000,
+        );
+        let hints = loader.initial_hints();
+        assert!(hints.contains("Shared hints"));
+        assert!(!hints.contains("SECRET"));
+    }
+
+    #[test]
+    fn test_token_budget_prefers_specific_hints() {
+        let dir = repo();
+        let root = dir.path();
+        fs::write(root.join(HINTS_FILENAME), "x".repeat(4000)).unwrap();
+
+        let hints = loader(root.join("packages/api"), 300).initial_hints();
+        assert!(hints.contains("Api hints"));
+        assert!(hints.contains("[Hints truncated to fit the token budget]"));
+        assert!(estimate_tokens(&hints) <= 300);
+    }
+}
@@ -35,3 +35,18 @@ pub fn get_language_identifier(path: &Path) -> &'static str {
         _ => "",
     }
 }
+
+/// Get the tree-sitter grammar for a file extension, if one is bundled
+pub fn get_tree_sitter_language(path: &Path) -> Option<tree_sitter::Language> {
+    let language = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("rs") => tree_sitter_rust::LANGUAGE,
+        Some("py") => tree_sitter_python::LANGUAGE,
+        Some("js") | Some("jsx") | Some("mjs") | Some("cjs") => tree_sitter_javascript::LANGUAGE,
+        Some("ts") | Some("mts") | Some("cts") => tree_sitter_typescript::LANGUAGE_TYPESCRIPT,
+        Some("tsx") => tree_sitter_typescript::LANGUAGE_TSX,
+        Some("go") => tree_sitter_go::LANGUAGE,
+        Some("java") => tree_sitter_java::LANGUAGE,
+        _ => return None,
+    };
+    Some(language.into())
+}
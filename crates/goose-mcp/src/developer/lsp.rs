@@ -0,0 +1,909 @@
+//! Optional language server integration for the developer extension.
+//!
+//! Language servers are started lazily, one per server command, the first time a file in
+//! their language is edited or queried. After an edit the file is synced to the server and
+//! the diagnostics it publishes are returned, so the model sees compile errors right away.
+//!
+//! Configured through environment variables:
+//! - `GOOSE_LSP`: `true` to enable the default servers, or a comma separated list of
+//!   languages (`rust,python,typescript,go`) to enable only those
+//! - `GOOSE_LSP_SERVERS`: JSON object overriding the command per language, for example
+//!   `{"python": "pylsp"}`
+//! - `GOOSE_LSP_TIMEOUT`: seconds to wait for diagnostics after an edit, default 10
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{oneshot, Mutex, Notify};
+use url::Url;
+
+/// Requests to the language server fail after this long
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// At most this many diagnostics are reported per file
+const MAX_DIAGNOSTICS: usize = 50;
+
+/// The language server commands used when GOOSE_LSP is enabled without overrides
+const DEFAULT_SERVERS: &[(&str, &str)] = &[
+    ("rust", "rust-analyzer"),
+    ("python", "pyright-langserver --stdio"),
+    ("typescript", "typescript-language-server --stdio"),
+    ("go", "gopls"),
+];
+
+/// The LSP language id for a file, and the language whose server handles it
+fn language_for(path: &Path) -> Option<(&'static str, &'static str)> {
+    Some(match path.extension().and_then(|ext| ext.to_str())? {
+        "rs" => ("rust", "rust"),
+        "py" => ("python", "python"),
+        "ts" | "mts" | "cts" => ("typescript", "typescript"),
+        "tsx" => ("typescriptreact", "typescript"),
+        "js" | "mjs" | "cjs" => ("javascript", "typescript"),
+        "jsx" => ("javascriptreact", "typescript"),
+        "go" => ("go", "go"),
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LspConfig {
+    /// Server command line per language, only for enabled languages
+    pub servers: HashMap<String, Vec<String>>,
+    pub diagnostics_timeout: Duration,
+}
+
+impl LspConfig {
+    pub fn from_env() -> Self {
+        let enabled = std::env::var("GOOSE_LSP").unwrap_or_default();
+        let overrides = std::env::var("GOOSE_LSP_SERVERS").ok();
+        let timeout = std::env::var("GOOSE_LSP_TIMEOUT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(10);
+        Self::parse(&enabled, overrides.as_deref(), timeout)
+    }
+
+    fn parse(enabled: &str, overrides: Option<&str>, timeout_secs: u64) -> Self {
+        let enabled = enabled.trim().to_lowercase();
+        let languages: Vec<String> = match enabled.as_str() {
+            "" | "false" | "off" => Vec::new(),
+            "true" | "on" | "all" => DEFAULT_SERVERS.iter().map(|(l, _)| l.to_string()).collect(),
+            list => list.split(',').map(|l| l.trim().to_string()).collect(),
+        };
+
+        let overrides: HashMap<String, String> = overrides
+            .and_then(|o| match serde_json::from_str(o) {
+                Ok(map) => Some(map),
+                Err(e) => {
+                    tracing::warn!("Ignoring invalid GOOSE_LSP_SERVERS: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        let servers = languages
+            .into_iter()
+            .filter_map(|language| {
+                let command = overrides.get(&language).cloned().or_else(|| {
+                    DEFAULT_SERVERS
+                        .iter()
+                        .find(|(l, _)| *l == language)
+                        .map(|(_, c)| c.to_string())
+                })?;
+                let argv: Vec<String> = command.split_whitespace().map(String::from).collect();
+                (!argv.is_empty()).then_some((language, argv))
+            })
+            .collect();
+
+        Self {
+            servers,
+            diagnostics_timeout: Duration::from_secs(timeout_secs),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.servers.is_empty()
+    }
+}
+
+/// A location in a file, with 1-based line and column for display
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub path: PathBuf,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Starts language servers on demand and routes files to them
+pub struct LspManager {
+    config: LspConfig,
+    root: PathBuf,
+    clients: Mutex<HashMap<String, Arc<LspClient>>>,
+}
+
+impl LspManager {
+    pub fn new(config: LspConfig, root: PathBuf) -> Self {
+        Self {
+            config,
+            root,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_enabled()
+    }
+
+    /// Whether an enabled server handles this file
+    pub fn supports(&self, path: &Path) -> bool {
+        language_for(path).is_some_and(|(_, server)| self.config.servers.contains_key(server))
+    }
+
+    async fn client(&self, path: &Path) -> Result<(Arc<LspClient>, &'static str)> {
+        let (language_id, server) = language_for(path)
+            .ok_or_else(|| anyhow!("No language server for {}", path.display()))?;
+        let command = self.config.servers.get(server).ok_or_else(|| {
+            anyhow!(
+                "The {} language server is not enabled, add it to GOOSE_LSP",
+                server
+            )
+        })?;
+
+        let mut clients = self.clients.lock().await;
+        if let Some(client) = clients.get(server) {
+            if client.is_alive().await {
+                return Ok((Arc::clone(client), language_id));
+            }
+        }
+
+        let client = Arc::new(LspClient::start(command, &self.root).await?);
+        clients.insert(server.to_string(), Arc::clone(&client));
+        Ok((client, language_id))
+    }
+
+    /// Sync a file's current content to its server and wait for the resulting diagnostics.
+    ///
+    /// Returns a human readable report, or None when the file has no diagnostics.
+    pub async fn diagnostics_after_edit(&self, path: &Path) -> Result<Option<String>> {
+        let (client, language_id) = self.client(path).await?;
+        let text = tokio::fs::read_to_string(path).await?;
+        let uri = file_uri(path)?;
+
+        let generation = client.diagnostics_generation(&uri).await;
+        client.sync(&uri, language_id, &text).await?;
+
+        let diagnostics = client
+            .wait_for_diagnostics(&uri, generation, self.config.diagnostics_timeout)
+            .await
+            .ok_or_else(|| {
+                anyhow!(
+                    "No diagnostics received within {}s, the language server may still be indexing",
+                    self.config.diagnostics_timeout.as_secs()
+                )
+            })?;
+
+        Ok(format_diagnostics(path, &diagnostics, &text))
+    }
+
+    pub async fn definition(&self, path: &Path, line: u32, column: u32) -> Result<Vec<Location>> {
+        self.locations("textDocument/definition", path, line, column, json!({}))
+            .await
+    }
+
+    pub async fn references(&self, path: &Path, line: u32, column: u32) -> Result<Vec<Location>> {
+        self.locations(
+            "textDocument/references",
+            path,
+            line,
+            column,
+            json!({"context": {"includeDeclaration": true}}),
+        )
+        .await
+    }
+
+    /// Ask the server for a rename and return the edits per file, without applying them
+    pub async fn rename(
+        &self,
+        path: &Path,
+        line: u32,
+        column: u32,
+        new_name: &str,
+    ) -> Result<HashMap<PathBuf, Vec<TextEdit>>> {
+        let result = self
+            .position_request(
+                "textDocument/rename",
+                path,
+                line,
+                column,
+                json!({"newName": new_name}),
+            )
+            .await?;
+        parse_workspace_edit(&result)
+    }
+
+    /// Notify the server that files changed on disk outside of an edit it saw
+    pub async fn resync(&self, paths: &[PathBuf]) -> Result<()> {
+        for path in paths {
+            let (client, language_id) = self.client(path).await?;
+            let text = tokio::fs::read_to_string(path).await?;
+            client.sync(&file_uri(path)?, language_id, &text).await?;
+        }
+        Ok(())
+    }
+
+    async fn position_request(
+        &self,
+        method: &str,
+        path: &Path,
+        line: u32,
+        column: u32,
+        extra: Value,
+    ) -> Result<Value> {
+        let (client, language_id) = self.client(path).await?;
+        let text = tokio::fs::read_to_string(path).await?;
+        let uri = file_uri(path)?;
+        client.sync(&uri, language_id, &text).await?;
+
+        let line_index = line.saturating_sub(1) as usize;
+        let line_text = text.lines().nth(line_index).unwrap_or_default();
+        let character = utf16_column(line_text, column.saturating_sub(1) as usize);
+
+        let mut params = json!({
+            "textDocument": {"uri": uri},
+            "position": {"line": line_index, "character": character},
+        });
+        if let (Some(params), Some(extra)) = (params.as_object_mut(), extra.as_object()) {
+            params.extend(extra.clone());
+        }
+
+        client.request(method, params).await
+    }
+
+    async fn locations(
+        &self,
+        method: &str,
+        path: &Path,
+        line: u32,
+        column: u32,
+        extra: Value,
+    ) -> Result<Vec<Location>> {
+        let result = self
+            .position_request(method, path, line, column, extra)
+            .await?;
+        Ok(parse_locations(&result))
+    }
+}
+
+type Pending = Arc<Mutex<HashMap<i64, oneshot::Sender<Result<Value>>>>>;
+
+#[derive(Default)]
+struct DiagnosticsState {
+    /// Incremented every time the server publishes diagnostics for a document
+    generation: HashMap<String, u64>,
+    latest: HashMap<String, Vec<Value>>,
+}
+
+/// A running language server speaking JSON-RPC over stdio
+struct LspClient {
+    child: Mutex<Child>,
+    stdin: Arc<Mutex<ChildStdin>>,
+    next_id: AtomicI64,
+    pending: Pending,
+    diagnostics: Arc<Mutex<DiagnosticsState>>,
+    diagnostics_updated: Arc<Notify>,
+    versions: Mutex<HashMap<String, i64>>,
+}
+
+impl LspClient {
+    async fn start(command: &[String], root: &Path) -> Result<Self> {
+        let mut child = Command::new(&command[0])
+            .args(&command[1..])
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start language server '{}'", command[0]))?;
+
+        let stdin = Arc::new(Mutex::new(child.stdin.take().expect("stdin is piped")));
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+
+        let client = Self {
+            child: Mutex::new(child),
+            stdin: Arc::clone(&stdin),
+            next_id: AtomicI64::new(1),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            diagnostics: Arc::new(Mutex::new(DiagnosticsState::default())),
+            diagnostics_updated: Arc::new(Notify::new()),
+            versions: Mutex::new(HashMap::new()),
+        };
+
+        tokio::spawn(read_loop(
+            stdout,
+            stdin,
+            Arc::clone(&client.pending),
+            Arc::clone(&client.diagnostics),
+            Arc::clone(&client.diagnostics_updated),
+        ));
+
+        let root_uri = file_uri(root)?;
+        client
+            .request(
+                "initialize",
+                json!({
+                    "processId": std::process::id(),
+                    "rootUri": root_uri,
+                    "workspaceFolders": [{"uri": root_uri, "name": "workspace"}],
+                    "capabilities": {
+                        "general": {"positionEncodings": ["utf-16"]},
+                        "textDocument": {
+                            "synchronization": {"didSave": false},
+                            "publishDiagnostics": {"versionSupport": true},
+                            "definition": {"linkSupport": true},
+                            "references": {},
+                            "rename": {"prepareSupport": false}
+                        },
+                        "workspace": {
+                            "workspaceEdit": {"documentChanges": true},
+                            "configuration": true,
+                            "workspaceFolders": true
+                        }
+                    }
+                }),
+            )
+            .await?;
+        client.notify("initialized", json!({})).await?;
+
+        Ok(client)
+    }
+
+    async fn is_alive(&self) -> bool {
+        matches!(self.child.lock().await.try_wait(), Ok(None))
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().await.insert(id, tx);
+
+        let message = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        write_message(&mut *self.stdin.lock().await, &message).await?;
+
+        match tokio::time::timeout(REQUEST_TIMEOUT, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(anyhow!("The language server exited")),
+            Err(_) => {
+                self.pending.lock().await.remove(&id);
+                Err(anyhow!("The language server did not answer {}", method))
+            }
+        }
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        let message = json!({"jsonrpc": "2.0", "method": method, "params": params});
+        write_message(&mut *self.stdin.lock().await, &message).await
+    }
+
+    /// Open the document, or send its full new content if it is already open
+    async fn sync(&self, uri: &str, language_id: &str, text: &str) -> Result<()> {
+        let mut versions = self.versions.lock().await;
+        match versions.get_mut(uri) {
+            Some(version) => {
+                *version += 1;
+                self.notify(
+                    "textDocument/didChange",
+                    json!({
+                        "textDocument": {"uri": uri, "version": *version},
+                        "contentChanges": [{"text": text}]
+                    }),
+                )
+                .await
+            }
+            None => {
+                versions.insert(uri.to_string(), 1);
+                self.notify(
+                    "textDocument/didOpen",
+                    json!({
+                        "textDocument": {
+                            "uri": uri,
+                            "languageId": language_id,
+                            "version": 1,
+                            "text": text
+                        }
+                    }),
+                )
+                .await
+            }
+        }
+    }
+
+    async fn diagnostics_generation(&self, uri: &str) -> u64 {
+        let state = self.diagnostics.lock().await;
+        state.generation.get(uri).copied().unwrap_or(0)
+    }
+
+    /// Wait until the server publishes diagnostics newer than `after` for the document
+    async fn wait_for_diagnostics(
+        &self,
+        uri: &str,
+        after: u64,
+        timeout: Duration,
+    ) -> Option<Vec<Value>> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let notified = self.diagnostics_updated.notified();
+            {
+                let state = self.diagnostics.lock().await;
+                if state.generation.get(uri).copied().unwrap_or(0) > after {
+                    return Some(state.latest.get(uri).cloned().unwrap_or_default());
+                }
+            }
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return None;
+            }
+        }
+    }
+}
+
+async fn read_loop<R: AsyncBufRead + Unpin>(
+    mut stdout: R,
+    stdin: Arc<Mutex<ChildStdin>>,
+    pending: Pending,
+    diagnostics: Arc<Mutex<DiagnosticsState>>,
+    diagnostics_updated: Arc<Notify>,
+) {
+    loop {
+        let message = match read_message(&mut stdout).await {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                tracing::warn!("Failed to read from language server: {}", e);
+                break;
+            }
+        };
+
+        let id = message.get("id").cloned();
+        let method = message.get("method").and_then(|m| m.as_str());
+        match (id, method) {
+            // A request from the server; answer with an empty result so it does not stall
+            (Some(id), Some(_)) => {
+                let response = json!({"jsonrpc": "2.0", "id": id, "result": Value::Null});
+                if write_message(&mut *stdin.lock().await, &response)
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+            (Some(id), None) => {
+                let Some(id) = id.as_i64() else { continue };
+                if let Some(tx) = pending.lock().await.remove(&id) {
+                    let result = match message.get("error") {
+                        Some(error) => Err(anyhow!(
+                            "Language server error: {}",
+                            error
+                                .get("message")
+                                .and_then(|m| m.as_str())
+                                .unwrap_or("unknown error")
+                        )),
+                        None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                    };
+                    let _ = tx.send(result);
+                }
+            }
+            (None, Some("textDocument/publishDiagnostics")) => {
+                let params = message.get("params").cloned().unwrap_or_default();
+                let Some(uri) = params.get("uri").and_then(|u| u.as_str()) else {
+                    continue;
+                };
+                let items = params
+                    .get("diagnostics")
+                    .and_then(|d| d.as_array())
+                    .cloned()
+                    .unwrap_or_default();
+
+                let mut state = diagnostics.lock().await;
+                *state.generation.entry(uri.to_string()).or_default() += 1;
+                state.latest.insert(uri.to_string(), items);
+                drop(state);
+                diagnostics_updated.notify_waiters();
+            }
+            _ => {}
+        }
+    }
+
+    // Fail anything still waiting, the server is gone
+    pending.lock().await.clear();
+}
+
+async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Value) -> Result<()> {
+    let body = serde_json::to_vec(message)?;
+    writer
+        .write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes())
+        .await?;
+    writer.write_all(&body).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Read one framed message, or None at end of stream
+async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+    }
+
+    let length = content_length.ok_or_else(|| anyhow!("Message without Content-Length"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+fn file_uri(path: &Path) -> Result<String> {
+    Url::from_file_path(path)
+        .map(|u| u.to_string())
+        .map_err(|_| anyhow!("Invalid file path {}", path.display()))
+}
+
+/// Convert a character column to the UTF-16 offset LSP positions use
+fn utf16_column(line: &str, column: usize) -> usize {
+    line.chars().take(column).map(char::len_utf16).sum()
+}
+
+/// Convert an LSP position to a byte offset in `text`
+fn byte_offset(text: &str, line: usize, character: usize) -> usize {
+    let mut offset = 0;
+    for (index, line_text) in text.split_inclusive('\n').enumerate() {
+        if index == line {
+            let mut units = 0;
+            for (byte, c) in line_text.char_indices() {
+                if units >= character || c == '\n' || c == '\r' {
+                    return offset + byte;
+                }
+                units += c.len_utf16();
+            }
+            return offset + line_text.trim_end_matches(['\n', '\r']).len();
+        }
+        offset += line_text.len();
+    }
+    text.len()
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+fn parse_locations(result: &Value) -> Vec<Location> {
+    let items = match result {
+        Value::Array(items) => items.clone(),
+        Value::Null => Vec::new(),
+        single => vec![single.clone()],
+    };
+
+    items
+        .iter()
+        .filter_map(|item| {
+            // Location has uri/range, LocationLink has targetUri/targetSelectionRange
+            let uri = item
+                .get("uri")
+                .or_else(|| item.get("targetUri"))?
+                .as_str()?;
+            let start = item
+                .get("range")
+                .or_else(|| item.get("targetSelectionRange"))?
+                .get("start")?;
+            Some(Location {
+                path: uri_to_path(uri)?,
+                line: start.get("line")?.as_u64()? as u32 + 1,
+                column: start.get("character")?.as_u64()? as u32 + 1,
+            })
+        })
+        .collect()
+}
+
+/// A replacement of a range of a document, in LSP (line, UTF-16 character) coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub new_text: String,
+}
+
+fn parse_text_edit(edit: &Value) -> Option<TextEdit> {
+    let range = edit.get("range")?;
+    let position = |p: &Value| -> Option<(usize, usize)> {
+        Some((
+            p.get("line")?.as_u64()? as usize,
+            p.get("character")?.as_u64()? as usize,
+        ))
+    };
+    Some(TextEdit {
+        start: position(range.get("start")?)?,
+        end: position(range.get("end")?)?,
+        new_text: edit.get("newText")?.as_str()?.to_string(),
+    })
+}
+
+fn parse_workspace_edit(edit: &Value) -> Result<HashMap<PathBuf, Vec<TextEdit>>> {
+    if edit.is_null() {
+        return Err(anyhow!("The language server cannot rename this symbol"));
+    }
+
+    let mut files: HashMap<PathBuf, Vec<TextEdit>> = HashMap::new();
+    let mut add = |uri: &str, edits: &Value| -> Result<()> {
+        let path = uri_to_path(uri).ok_or_else(|| anyhow!("Unsupported uri {}", uri))?;
+        let edits = edits
+            .as_array()
+            .ok_or_else(|| anyhow!("Malformed edits for {}", uri))?
+            .iter()
+            .map(|e| parse_text_edit(e).ok_or_else(|| anyhow!("Malformed edit for {}", uri)))
+            .collect::<Result<Vec<_>>>()?;
+        files.entry(path).or_default().extend(edits);
+        Ok(())
+    };
+
+    if let Some(changes) = edit.get("documentChanges").and_then(|c| c.as_array()) {
+        for change in changes {
+            let uri = change
+                .get("textDocument")
+                .and_then(|d| d.get("uri"))
+                .and_then(|u| u.as_str())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "The rename needs to create, move or delete files, which is not supported"
+                    )
+                })?;
+            add(uri, change.get("edits").unwrap_or(&Value::Null))?;
+        }
+    } else if let Some(changes) = edit.get("changes").and_then(|c| c.as_object()) {
+        for (uri, edits) in changes {
+            add(uri, edits)?;
+        }
+    }
+
+    Ok(files)
+}
+
+/// Apply edits to a document's text. Edits must not overlap.
+pub fn apply_edits(text: &str, edits: &[TextEdit]) -> String {
+    let mut edits: Vec<_> = edits
+        .iter()
+        .map(|e| {
+            (
+                byte_offset(text, e.start.0, e.start.1),
+                byte_offset(text, e.end.0, e.end.1),
+                e.new_text.as_str(),
+            )
+        })
+        .collect();
+    // Apply from the end so earlier offsets stay valid
+    edits.sort_by_key(|e| std::cmp::Reverse(e.0));
+
+    let mut result = text.to_string();
+    for (start, end, new_text) in edits {
+        result.replace_range(start..end.max(start), new_text);
+    }
+    result
+}
+
+fn format_diagnostics(path: &Path, diagnostics: &[Value], text: &str) -> Option<String> {
+    if diagnostics.is_empty() {
+        return None;
+    }
+
+    let lines: Vec<&str> = text.lines().collect();
+    let mut sorted: Vec<&Value> = diagnostics.iter().collect();
+    // Severity 1 is error, missing severity sorts last
+    sorted.sort_by_key(|d| d.get("severity").and_then(|s| s.as_u64()).unwrap_or(5));
+
+    let mut report = format!("Language server diagnostics for {}:\n", path.display());
+    for diagnostic in sorted.iter().take(MAX_DIAGNOSTICS) {
+        let severity = match diagnostic.get("severity").and_then(|s| s.as_u64()) {
+            Some(1) => "error",
+            Some(2) => "warning",
+            Some(3) => "info",
+            Some(4) => "hint",
+            _ => "diagnostic",
+        };
+        let start = diagnostic.get("range").and_then(|r| r.get("start"));
+        let line = start
+            .and_then(|s| s.get("line"))
+            .and_then(|l| l.as_u64())
+            .unwrap_or(0) as usize;
+        let character = start
+            .and_then(|s| s.get("character"))
+            .and_then(|c| c.as_u64())
+            .unwrap_or(0);
+        let message = diagnostic
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or_default()
+            .replace('\n', " ");
+
+        report.push_str(&format!(
+            "- {} at line {}, column {}: {}\n",
+            severity,
+            line + 1,
+            character + 1,
+            message
+        ));
+        if let Some(source_line) = lines.get(line) {
+            report.push_str(&format!("    {}\n", source_line.trim()));
+        }
+    }
+    if diagnostics.len() > MAX_DIAGNOSTICS {
+        report.push_str(&format!(
+            "... and {} more\n",
+            diagnostics.len() - MAX_DIAGNOSTICS
+        ));
+    }
+    Some(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_parsing() {
+        assert!(!LspConfig::parse("", None, 10).is_enabled());
+
+        let config = LspConfig::parse("true", None, 10);
+        assert_eq!(config.servers.len(), DEFAULT_SERVERS.len());
+        assert_eq!(config.servers["rust"], vec!["rust-analyzer"]);
+
+        let config = LspConfig::parse("python, go", Some(r#"{"python": "pylsp -v"}"#), 3);
+        assert_eq!(config.servers.len(), 2);
+        assert_eq!(config.servers["python"], vec!["pylsp", "-v"]);
+        assert_eq!(config.servers["go"], vec!["gopls"]);
+        assert_eq!(config.diagnostics_timeout, Duration::from_secs(3));
+
+        // Languages without a default need an explicit command
+        let config = LspConfig::parse("zig", None, 10);
+        assert!(!config.is_enabled());
+    }
+
+    #[test]
+    fn test_manager_supports() {
+        let manager = LspManager::new(LspConfig::parse("typescript", None, 10), PathBuf::new());
+        assert!(manager.supports(Path::new("/a/b.tsx")));
+        assert!(manager.supports(Path::new("/a/b.js")));
+        assert!(!manager.supports(Path::new("/a/b.rs")));
+        assert!(!manager.supports(Path::new("/a/b.txt")));
+    }
+
+    #[tokio::test]
+    async fn test_message_framing() {
+        let message = json!({"jsonrpc": "2.0", "id": 1, "result": {"ok": "é"}});
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &message).await.unwrap();
+        write_message(&mut buffer, &message).await.unwrap();
+
+        let mut reader = BufReader::new(buffer.as_slice());
+        assert_eq!(
+            read_message(&mut reader).await.unwrap(),
+            Some(message.clone())
+        );
+        assert_eq!(read_message(&mut reader).await.unwrap(), Some(message));
+        assert_eq!(read_message(&mut reader).await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_utf16_positions() {
+        // 😀 is two UTF-16 code units and four bytes
+        let text = "let a = \"😀\";\nlet b = 1;\n";
+        assert_eq!(utf16_column("\"😀\"x", 3), 4);
+        assert_eq!(byte_offset(text, 0, 11), 13);
+        assert_eq!(byte_offset(text, 1, 4), 20);
+        // Positions past the end of a line clamp to the line end
+        assert_eq!(byte_offset(text, 1, 100), 26);
+    }
+
+    #[test]
+    fn test_apply_edits() {
+        let text = "fn foo() {}\nfn main() { foo(); }\n";
+        let edits = vec![
+            TextEdit {
+                start: (0, 3),
+                end: (0, 6),
+                new_text: "bar".into(),
+            },
+            TextEdit {
+                start: (1, 12),
+                end: (1, 15),
+                new_text: "bar".into(),
+            },
+        ];
+        assert_eq!(
+            apply_edits(text, &edits),
+            "fn bar() {}\nfn main() { bar(); }\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_workspace_edit() {
+        let edit = json!({
+            "documentChanges": [{
+                "textDocument": {"uri": "file:///tmp/a.rs", "version": 1},
+                "edits": [{
+                    "range": {"start": {"line": 0, "character": 3}, "end": {"line": 0, "character": 6}},
+                    "newText": "bar"
+                }]
+            }]
+        });
+        let files = parse_workspace_edit(&edit).unwrap();
+        assert_eq!(files[Path::new("/tmp/a.rs")].len(), 1);
+
+        let edit = json!({"documentChanges": [{"kind": "create", "uri": "file:///tmp/b.rs"}]});
+        assert!(parse_workspace_edit(&edit).is_err());
+        assert!(parse_workspace_edit(&Value::Null).is_err());
+    }
+
+    #[test]
+    fn test_parse_locations() {
+        let result = json!([
+            {"uri": "file:///tmp/a.rs", "range": {"start": {"line": 4, "character": 2}, "end": {"line": 4, "character": 5}}},
+            {"targetUri": "file:///tmp/b.rs", "targetSelectionRange": {"start": {"line": 0, "character": 0}, "end": {"line": 0, "character": 1}}}
+        ]);
+        let locations = parse_locations(&result);
+        assert_eq!(
+            locations,
+            vec![
+                Location {
+                    path: PathBuf::from("/tmp/a.rs"),
+                    line: 5,
+                    column: 3
+                },
+                Location {
+                    path: PathBuf::from("/tmp/b.rs"),
+                    line: 1,
+                    column: 1
+                },
+            ]
+        );
+        assert!(parse_locations(&Value::Null).is_empty());
+    }
+
+    #[test]
+    fn test_format_diagnostics() {
+        let diagnostics = vec![
+            json!({"severity": 2, "message": "unused variable", "range": {"start": {"line": 0, "character": 4}}}),
+            json!({"severity": 1, "message": "mismatched types", "range": {"start": {"line": 1, "character": 0}}}),
+        ];
+        let report = format_diagnostics(
+            Path::new("/tmp/a.rs"),
+            &diagnostics,
+            "let x = 1;\nfoo(1);\n",
+        )
+        .unwrap();
+        let error = report
+            .find("error at line 2, column 1: mismatched types")
+            .unwrap();
+        let warning = report.find("warning at line 1, column 5").unwrap();
+        assert!(error < warning);
+        assert!(report.contains("    foo(1);"));
+
+        assert!(format_diagnostics(Path::new("/tmp/a.rs"), &[], "").is_none());
+    }
+}
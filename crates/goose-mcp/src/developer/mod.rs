@@ -1,4 +1,10 @@
+pub mod checkpoint;
+mod hints;
 mod lang;
+mod lsp;
+mod outline;
+mod sandbox;
+mod search;
 mod shell;
 
 use anyhow::Result;
@@ -30,6 +36,11 @@ use mcp_server::Router;
 use mcp_core::content::Content;
 use mcp_core::role::Role;
 
+use self::checkpoint::Checkpoints;
+use self::hints::HintsLoader;
+use self::lsp::{LspConfig, LspManager};
+use self::sandbox::{MaskCache, SandboxConfig};
+use self::search::SearchOptions;
 use self::shell::{
     expand_path, format_command_for_platform, get_shell_config, is_absolute_path,
     normalize_line_endings,
@@ -93,6 +104,10 @@ pub struct DeveloperRouter {
     instructions: String,
     file_history: Arc<Mutex<HashMap<PathBuf, Vec<String>>>>,
     ignore_patterns: Arc<Gitignore>,
+    hints: Arc<HintsLoader>,
+    sandbox: SandboxConfig,
+    sandbox_masks: Arc<MaskCache>,
+    lsp: Arc<LspManager>,
 }
 
 impl Default for DeveloperRouter {
@@ -101,11 +116,52 @@ impl Default for DeveloperRouter {
     }
 }
 
+/// The .gooseignore patterns for a working directory: the global file in the goose config
+/// directory and the one in `cwd`, or defaults covering common secrets if neither exists
+pub(crate) fn load_ignore_patterns(cwd: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(cwd);
+    let mut has_ignore_file = false;
+    // Initialize ignore patterns
+    // - macOS/Linux: ~/.config/goose/
+    // - Windows:     ~\AppData\Roaming\Block\goose\config\
+    let global_ignore_path = choose_app_strategy(crate::APP_STRATEGY.clone())
+        .map(|strategy| strategy.in_config_dir(".gooseignore"))
+        .unwrap_or_else(|_| {
+            PathBuf::from(shellexpand::tilde("~/.config/goose/.gooseignore").to_string())
+        });
+
+    // Create the directory if it doesn't exist
+    let _ = std::fs::create_dir_all(global_ignore_path.parent().unwrap());
+
+    // Read global ignores if they exist
+    if global_ignore_path.is_file() {
+        let _ = builder.add(global_ignore_path);
+        has_ignore_file = true;
+    }
+
+    // Check for local ignores in current directory
+    let local_ignore_path = cwd.join(".gooseignore");
+
+    // Read local ignores if they exist
+    if local_ignore_path.is_file() {
+        let _ = builder.add(local_ignore_path);
+        has_ignore_file = true;
+    }
+
+    // Only use default patterns if no .gooseignore files were found
+    // If the file is empty, we will not ignore any file
+    if !has_ignore_file {
+        // Add some sensible defaults
+        let _ = builder.add_line(None, "**/.env");
+        let _ = builder.add_line(None, "**/.env.*");
+        let _ = builder.add_line(None, "**/secrets.*");
+    }
+
+    builder.build().expect("Failed to build ignore patterns")
+}
+
 impl DeveloperRouter {
     pub fn new() -> Self {
-        // TODO consider rust native search tools, we could use
-        // https://docs.rs/ignore/latest/ignore/
-
         // Get OS-specific shell tool description
         let shell_tool_desc = match std::env::consts::OS {
             "windows" => indoc! {r#"
@@ -117,7 +173,8 @@ impl DeveloperRouter {
 
                 Avoid commands that produce a large amount of output, and consider piping those outputs to files.
 
-                **Important**: For searching files and code:
+                **Important**: For searching files and code, prefer the `code_search`, `find_files` and
+                `file_outline` tools. If you do need the shell for this:
 
                 Preferred: Use ripgrep (`rg`) when available - it respects .gitignore and is fast:
                   - To locate a file by name: `rg --files | rg example.py`
@@ -144,7 +201,8 @@ impl DeveloperRouter {
                 sourcing files do not persist between tool calls. So you may need to repeat them each time by
                 stringing together commands, e.g. `cd example && ls` or `source env/bin/activate && pip install numpy`
 
-                **Important**: Use ripgrep - `rg` - when you need to locate a file or a code reference, other solutions
+                **Important**: Prefer the `code_search`, `find_files` and `file_outline` tools to locate files and
+                code references. If you do need the shell for this, use ripgrep - `rg` - as other solutions
                 may show ignored or hidden files. For example *do not* use `find` or `ls -r`
                   - List files by name: `rg --files | rg <filename>`
                   - List files that contain a regex: `rg '<regex>' -l`
@@ -265,6 +323,187 @@ impl DeveloperRouter {
             }),
         );
 
+        let code_search_tool = Tool::new(
+            "code_search",
+            indoc! {r#"
+                Search file contents for a regular expression, like ripgrep.
+
+                Respects .gitignore and .gooseignore and skips hidden, binary and very large files.
+                Returns a JSON object with the matching lines as `path`, `line` and `text`, and
+                `truncated` set when more matches exist than `max_results`. Narrow the search
+                with a more specific `pattern`, a `path` or a `glob` rather than raising the limit.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["pattern"],
+                "properties": {
+                    "pattern": {
+                        "type": "string",
+                        "description": "Regular expression (Rust regex syntax) to search for"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path of the directory to search, defaults to the current directory"
+                    },
+                    "glob": {
+                        "type": "string",
+                        "description": "Only search files matching this glob, e.g. `*.rs` or `src/**/*.ts`"
+                    },
+                    "case_sensitive": {
+                        "type": "boolean",
+                        "default": true
+                    },
+                    "max_results": {
+                        "type": "integer",
+                        "default": 100
+                    }
+                }
+            }),
+        );
+
+        let find_files_tool = Tool::new(
+            "find_files",
+            indoc! {r#"
+                List files whose path matches a glob, e.g. `**/*.py` or `src/**/mod.rs`.
+
+                Respects .gitignore and .gooseignore and skips hidden files. Returns a JSON object with
+                the matching `files` and `truncated` set when more files exist than `max_results`.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["glob"],
+                "properties": {
+                    "glob": {
+                        "type": "string",
+                        "description": "Glob matched against paths relative to `path`"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path of the directory to list, defaults to the current directory"
+                    },
+                    "max_results": {
+                        "type": "integer",
+                        "default": 200
+                    }
+                }
+            }),
+        );
+
+        let file_outline_tool = Tool::new(
+            "file_outline",
+            indoc! {r#"
+                List the symbols (functions, types, classes, methods, modules...) defined in a source file,
+                with their line ranges and nesting depth.
+
+                Use this to understand the structure of a large file before viewing it. Supports Rust,
+                Python, JavaScript, TypeScript, Go and Java.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["path"],
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to the source file"
+                    }
+                }
+            }),
+        );
+
+        let checkpoint_tool = Tool::new(
+            "checkpoint",
+            indoc! {r#"
+                Manage git checkpoints of the working tree.
+
+                A checkpoint is a snapshot of every file in the repository (respecting .gitignore) stored on a
+                hidden git ref, so it never touches branches, the index or the stash. Checkpoints are created
+                automatically at the start of each turn when the working tree has changed.
+
+                The `command` parameter specifies the operation to perform. Allowed options are:
+                - `create`: Snapshot the working tree now, with an optional `message`.
+                - `list`: List recent checkpoints, most recent first.
+                - `diff`: Show the changes between checkpoint `id` and the current working tree.
+                - `restore`: Restore the working tree to checkpoint `id`. The current state is checkpointed
+                  first, so a restore can be undone by restoring that checkpoint.
+
+                Only restore a checkpoint when the user asks for it.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["command"],
+                "properties": {
+                    "command": {
+                        "type": "string",
+                        "enum": ["create", "list", "diff", "restore"],
+                        "description": "Allowed options are: `create`, `list`, `diff`, `restore`."
+                    },
+                    "id": {
+                        "type": "string",
+                        "description": "Checkpoint id (full or abbreviated), required for `diff` and `restore`"
+                    },
+                    "message": {
+                        "type": "string",
+                        "description": "Optional label for `create`"
+                    },
+                    "stat": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "For `diff`, only summarize the changed files"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "default": 20,
+                        "description": "For `list`, the maximum number of checkpoints to return"
+                    }
+                }
+            }),
+        );
+
+        let lsp_tool = Tool::new(
+            "lsp",
+            indoc! {r#"
+                Query the language server for a source file.
+
+                The `command` parameter specifies the operation to perform. Allowed options are:
+                - `diagnostics`: List the errors and warnings the language server reports for `path`.
+                - `definition`: Find where the symbol at `line` and `column` in `path` is defined.
+                - `references`: Find every reference to the symbol at `line` and `column` in `path`.
+                - `rename`: Rename the symbol at `line` and `column` in `path` to `new_name`, updating
+                  every file that refers to it. Each changed file can be reverted with `undo` in the
+                  text_editor tool.
+
+                Lines and columns are 1-based, as shown by the text_editor view. Prefer this over
+                searching with regular expressions when you need precise definitions or references.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["command", "path"],
+                "properties": {
+                    "command": {
+                        "type": "string",
+                        "enum": ["diagnostics", "definition", "references", "rename"],
+                        "description": "Allowed options are: `diagnostics`, `definition`, `references`, `rename`."
+                    },
+                    "path": {
+                        "description": "Absolute path to the source file",
+                        "type": "string"
+                    },
+                    "line": {
+                        "type": "integer",
+                        "description": "1-based line of the symbol, required for `definition`, `references` and `rename`"
+                    },
+                    "column": {
+                        "type": "integer",
+                        "description": "1-based column of the symbol, required for `definition`, `references` and `rename`"
+                    },
+                    "new_name": {
+                        "type": "string",
+                        "description": "The new name, required for `rename`"
+                    }
+                }
+            }),
+        );
+
         // Get base instructions and working directory
         let cwd = std::env::current_dir().expect("should have a current working dir");
         let os = std::env::consts::OS;
@@ -308,6 +547,8 @@ impl DeveloperRouter {
             },
         };
 
+        let ignore_patterns = load_ignore_patterns(&cwd);
+
         // choose_app_strategy().config_dir()
         // - macOS/Linux: ~/.config/goose/
         // - Windows:     ~\AppData\Roaming\Block\goose\config\
@@ -321,28 +562,21 @@ impl DeveloperRouter {
         // Create the directory if it doesn't exist
         let _ = std::fs::create_dir_all(global_hints_path.parent().unwrap());
 
-        // Check for local hints in current directory
-        let local_hints_path = cwd.join(".goosehints");
-
-        // Read global hints if they exist
-        let mut hints = String::new();
-        if global_hints_path.is_file() {
-            if let Ok(global_hints) = std::fs::read_to_string(&global_hints_path) {
-                hints.push_str("\n### Global Hints\nThe developer extension includes some global hints that apply to all projects & directories.\n");
-                hints.push_str(&global_hints);
-            }
-        }
+        // Collect hints from the global config and from the repository root down to cwd,
+        // hints in subdirectories are added as files there are touched
+        let ignore_patterns = Arc::new(ignore_patterns);
+        let hints_loader = HintsLoader::from_env(
+            cwd.clone(),
+            Some(global_hints_path),
+            Arc::clone(&ignore_patterns),
+        );
+        let hints = hints_loader.initial_hints();
 
-        // Read local hints if they exist
-        if local_hints_path.is_file() {
-            if let Ok(local_hints) = std::fs::read_to_string(&local_hints_path) {
-                if !hints.is_empty() {
-                    hints.push_str("\n\n");
-                }
-                hints.push_str("### Project Hints\nThe developer extension includes some hints for working on the project in this directory.\n");
-                hints.push_str(&local_hints);
-            }
-        }
+        let sandbox = SandboxConfig::from_env();
+        let base_instructions = match sandbox.describe() {
+            Some(description) => format!("{base_instructions}{description}\n"),
+            None => base_instructions,
+        };
 
         // Return base instructions directly when no hints are found
         let instructions = if hints.is_empty() {
@@ -351,58 +585,33 @@ impl DeveloperRouter {
             format!("{base_instructions}\n{hints}")
         };
 
-        let mut builder = GitignoreBuilder::new(cwd.clone());
-        let mut has_ignore_file = false;
-        // Initialize ignore patterns
-        // - macOS/Linux: ~/.config/goose/
-        // - Windows:     ~\AppData\Roaming\Block\goose\config\
-        let global_ignore_path = choose_app_strategy(crate::APP_STRATEGY.clone())
-            .map(|strategy| strategy.in_config_dir(".gooseignore"))
-            .unwrap_or_else(|_| {
-                PathBuf::from(shellexpand::tilde("~/.config/goose/.gooseignore").to_string())
-            });
-
-        // Create the directory if it doesn't exist
-        let _ = std::fs::create_dir_all(global_ignore_path.parent().unwrap());
-
-        // Read global ignores if they exist
-        if global_ignore_path.is_file() {
-            let _ = builder.add(global_ignore_path);
-            has_ignore_file = true;
-        }
-
-        // Check for local ignores in current directory
-        let local_ignore_path = cwd.join(".gooseignore");
-
-        // Read local ignores if they exist
-        if local_ignore_path.is_file() {
-            let _ = builder.add(local_ignore_path);
-            has_ignore_file = true;
+        // The lsp tool is only offered when a language server is configured
+        let lsp = LspManager::new(LspConfig::from_env(), cwd.clone());
+        let mut tools = vec![
+            bash_tool,
+            text_editor_tool,
+            list_windows_tool,
+            screen_capture_tool,
+            image_processor_tool,
+            code_search_tool,
+            find_files_tool,
+            file_outline_tool,
+            checkpoint_tool,
+        ];
+        if lsp.is_enabled() {
+            tools.push(lsp_tool);
         }
 
-        // Only use default patterns if no .gooseignore files were found
-        // If the file is empty, we will not ignore any file
-        if !has_ignore_file {
-            // Add some sensible defaults
-            let _ = builder.add_line(None, "**/.env");
-            let _ = builder.add_line(None, "**/.env.*");
-            let _ = builder.add_line(None, "**/secrets.*");
-        }
-
-        let ignore_patterns = builder.build().expect("Failed to build ignore patterns");
-
         Self {
-            tools: vec![
-                bash_tool,
-                text_editor_tool,
-                list_windows_tool,
-                screen_capture_tool,
-                image_processor_tool,
-            ],
+            tools,
             prompts: Arc::new(load_prompt_files()),
             instructions,
             file_history: Arc::new(Mutex::new(HashMap::new())),
-            ignore_patterns: Arc::new(ignore_patterns),
+            ignore_patterns,
+            hints: Arc::new(hints_loader),
+            sandbox,
+            sandbox_masks: Arc::new(MaskCache::default()),
+            lsp: Arc::new(lsp),
         }
     }
 
@@ -464,14 +673,41 @@ impl DeveloperRouter {
         let shell_config = get_shell_config();
         let cmd_with_redirect = format_command_for_platform(command);
 
-        // Execute the command using platform-specific shell
-        let child = Command::new(&shell_config.executable)
+        // Execute the command using platform-specific shell, inside the sandbox if configured
+        let mut cmd = if self.sandbox.is_enabled() {
+            sandbox::check_available(&self.sandbox).map_err(ToolError::ExecutionError)?;
+
+            let cwd = std::env::current_dir().expect("should have a current working dir");
+            let ignore_patterns = Arc::clone(&self.ignore_patterns);
+            let masks = Arc::clone(&self.sandbox_masks);
+            let masked_root = cwd.clone();
+            let masked = tokio::task::spawn_blocking(move || {
+                masks.masked_paths(&masked_root, &ignore_patterns)
+            })
+            .await
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?
+            .map_err(ToolError::ExecutionError)?;
+
+            let mut cmd = Command::new("bwrap");
+            cmd.args(sandbox::bwrap_args(
+                &self.sandbox,
+                &cwd,
+                &masked,
+                &shell_config.executable,
+                &[shell_config.arg.clone(), cmd_with_redirect],
+            ));
+            cmd
+        } else {
+            let mut cmd = Command::new(&shell_config.executable);
+            cmd.arg(&shell_config.arg).arg(cmd_with_redirect);
+            cmd
+        };
+
+        let child = cmd
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .stdin(Stdio::null())
             .kill_on_drop(true)
-            .arg(&shell_config.arg)
-            .arg(cmd_with_redirect)
             .spawn()
             .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
 
@@ -526,7 +762,7 @@ impl DeveloperRouter {
             )));
         }
 
-        match command {
+        let mut result = match command {
             "view" => self.text_editor_view(&path).await,
             "write" => {
                 let file_text = params
@@ -559,7 +795,14 @@ impl DeveloperRouter {
                 "Unknown command '{}'",
                 command
             ))),
+        }?;
+
+        // Pick up .goosehints from directories the agent has not worked in before
+        if let Some(hints) = self.hints.discover(&path) {
+            result.push(Content::text(hints).with_audience(vec![Role::Assistant]));
         }
+
+        Ok(result)
     }
 
     async fn text_editor_view(&self, path: &PathBuf) -> Result<Vec<Content>, ToolError> {
@@ -644,9 +887,14 @@ impl DeveloperRouter {
 
         // The assistant output does not show the file again because the content is already in the tool request
         // but we do show it to the user here
+        let mut message = format!("Successfully wrote to {}", path.display());
+        if let Some(diagnostics) = self.lsp_diagnostics(path).await {
+            message.push_str("\n\n");
+            message.push_str(&diagnostics);
+        }
+
         Ok(vec![
-            Content::text(format!("Successfully wrote to {}", path.display()))
-                .with_audience(vec![Role::Assistant]),
+            Content::text(message).with_audience(vec![Role::Assistant]),
             Content::text(formatdoc! {r#"
                 ### {path}
                 ```{language}
@@ -739,7 +987,7 @@ impl DeveloperRouter {
             snippet=snippet
         };
 
-        let success_message = formatdoc! {r#"
+        let mut success_message = formatdoc! {r#"
             The file {} has been edited, and the section now reads:
             {}
             Review the changes above for errors. Undo and edit the file again if necessary!
@@ -747,6 +995,10 @@ impl DeveloperRouter {
             path.display(),
             output
         };
+        if let Some(diagnostics) = self.lsp_diagnostics(path).await {
+            success_message.push('\n');
+            success_message.push_str(&diagnostics);
+        }
 
         Ok(vec![
             Content::text(success_message).with_audience(vec![Role::Assistant]),
@@ -789,6 +1041,439 @@ impl DeveloperRouter {
         Ok(())
     }
 
+    // Diagnostics from the language server after an edit, when one is configured for the file
+    async fn lsp_diagnostics(&self, path: &Path) -> Option<String> {
+        if !self.lsp.supports(path) {
+            return None;
+        }
+        match self.lsp.diagnostics_after_edit(path).await {
+            Ok(report) => report,
+            Err(e) => Some(format!("Language server diagnostics unavailable: {}", e)),
+        }
+    }
+
+    async fn lsp(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let command = params
+            .get("command")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'command' parameter".into()))?;
+        let path_str = params
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'path' parameter".into()))?;
+        let path = self.resolve_path(path_str)?;
+
+        if self.is_ignored(&path) {
+            return Err(ToolError::ExecutionError(format!(
+                "Access to '{}' is restricted by .gooseignore",
+                path.display()
+            )));
+        }
+        if !path.is_file() {
+            return Err(ToolError::ExecutionError(format!(
+                "The path '{}' does not exist or is not a file.",
+                path.display()
+            )));
+        }
+
+        let position = || -> Result<(u32, u32), ToolError> {
+            let get = |name: &str| {
+                params
+                    .get(name)
+                    .and_then(|v| v.as_u64())
+                    .filter(|v| *v > 0)
+                    .map(|v| v as u32)
+                    .ok_or_else(|| {
+                        ToolError::InvalidParameters(format!(
+                            "Missing '{}' parameter, a 1-based integer",
+                            name
+                        ))
+                    })
+            };
+            Ok((get("line")?, get("column")?))
+        };
+
+        let text = match command {
+            "diagnostics" => self
+                .lsp
+                .diagnostics_after_edit(&path)
+                .await
+                .map_err(|e| ToolError::ExecutionError(e.to_string()))?
+                .unwrap_or_else(|| format!("No diagnostics for {}", path.display())),
+            "definition" | "references" => {
+                let (line, column) = position()?;
+                let locations = if command == "definition" {
+                    self.lsp.definition(&path, line, column).await
+                } else {
+                    self.lsp.references(&path, line, column).await
+                }
+                .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+                if locations.is_empty() {
+                    format!("No {} found", command)
+                } else {
+                    locations
+                        .iter()
+                        .map(|location| {
+                            let source = std::fs::read_to_string(&location.path)
+                                .ok()
+                                .and_then(|text| {
+                                    text.lines()
+                                        .nth(location.line as usize - 1)
+                                        .map(|l| l.trim().to_string())
+                                })
+                                .unwrap_or_default();
+                            format!(
+                                "{}:{}:{}: {}",
+                                location.path.display(),
+                                location.line,
+                                location.column,
+                                source
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }
+            }
+            "rename" => {
+                let (line, column) = position()?;
+                let new_name =
+                    params
+                        .get("new_name")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| {
+                            ToolError::InvalidParameters("Missing 'new_name' parameter".into())
+                        })?;
+                let files = self
+                    .lsp
+                    .rename(&path, line, column, new_name)
+                    .await
+                    .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+                // Check every file before changing any of them
+                let mut updates = Vec::new();
+                for (file, edits) in &files {
+                    if self.is_ignored(file) {
+                        return Err(ToolError::ExecutionError(format!(
+                            "The rename would change '{}', which is restricted by .gooseignore",
+                            file.display()
+                        )));
+                    }
+                    let content = std::fs::read_to_string(file).map_err(|e| {
+                        ToolError::ExecutionError(format!("Failed to read file: {}", e))
+                    })?;
+                    updates.push((file.clone(), lsp::apply_edits(&content, edits), edits.len()));
+                }
+                updates.sort();
+
+                let mut summary = Vec::new();
+                for (file, content, count) in &updates {
+                    self.save_file_history(file)?;
+                    std::fs::write(file, content).map_err(|e| {
+                        ToolError::ExecutionError(format!("Failed to write file: {}", e))
+                    })?;
+                    summary.push(format!("{} ({} edits)", file.display(), count));
+                }
+                let changed: Vec<PathBuf> = updates.into_iter().map(|(file, ..)| file).collect();
+                self.lsp
+                    .resync(&changed)
+                    .await
+                    .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+                if summary.is_empty() {
+                    "The rename did not change any files".to_string()
+                } else {
+                    format!(
+                        "Renamed to '{}' in {} files:\n{}",
+                        new_name,
+                        summary.len(),
+                        summary.join("\n")
+                    )
+                }
+            }
+            _ => {
+                return Err(ToolError::InvalidParameters(format!(
+                    "Unknown command '{}'",
+                    command
+                )));
+            }
+        };
+
+        Ok(vec![
+            Content::text(text.clone()).with_audience(vec![Role::Assistant]),
+            Content::text(text)
+                .with_audience(vec![Role::User])
+                .with_priority(0.0),
+        ])
+    }
+
+    // Resolve an optional directory parameter, defaulting to the current directory
+    fn resolve_search_root(&self, params: &Value) -> Result<PathBuf, ToolError> {
+        let root = match params.get("path").and_then(|v| v.as_str()) {
+            Some(path_str) => self.resolve_path(path_str)?,
+            None => std::env::current_dir().expect("should have a current working dir"),
+        };
+
+        if !root.is_dir() {
+            return Err(ToolError::InvalidParameters(format!(
+                "The path '{}' is not a directory",
+                root.display()
+            )));
+        }
+        if self.is_ignored(&root) {
+            return Err(ToolError::ExecutionError(format!(
+                "Access to '{}' is restricted by .gooseignore",
+                root.display()
+            )));
+        }
+        Ok(root)
+    }
+
+    async fn code_search(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let pattern = params
+            .get("pattern")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'pattern' parameter".into()))?
+            .to_string();
+        let glob = params
+            .get("glob")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        let case_sensitive = params
+            .get("case_sensitive")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+        let max_results = params
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(100) as usize;
+        let root = self.resolve_search_root(&params)?;
+        let ignore_patterns = Arc::clone(&self.ignore_patterns);
+
+        let results = tokio::task::spawn_blocking(move || {
+            search::search_code(
+                SearchOptions {
+                    pattern: &pattern,
+                    root: &root,
+                    glob: glob.as_deref(),
+                    case_sensitive,
+                    max_results,
+                },
+                &ignore_patterns,
+            )
+        })
+        .await
+        .map_err(|e| ToolError::ExecutionError(e.to_string()))?
+        .map_err(ToolError::InvalidParameters)?;
+
+        let summary = format!(
+            "Found {} matches in {} files searched{}",
+            results.matches.len(),
+            results.files_searched,
+            if results.truncated {
+                " (truncated)"
+            } else {
+                ""
+            }
+        );
+        let json = serde_json::to_string_pretty(&results)
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+        Ok(vec![
+            Content::text(json).with_audience(vec![Role::Assistant]),
+            Content::text(summary)
+                .with_audience(vec![Role::User])
+                .with_priority(0.0),
+        ])
+    }
+
+    async fn find_files(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let glob = params
+            .get("glob")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'glob' parameter".into()))?
+            .to_string();
+        let max_results = params
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(200) as usize;
+        let root = self.resolve_search_root(&params)?;
+        let ignore_patterns = Arc::clone(&self.ignore_patterns);
+
+        let list = tokio::task::spawn_blocking(move || {
+            search::find_files(&root, &glob, max_results, &ignore_patterns)
+        })
+        .await
+        .map_err(|e| ToolError::ExecutionError(e.to_string()))?
+        .map_err(ToolError::InvalidParameters)?;
+
+        let summary = format!(
+            "Found {} files{}",
+            list.files.len(),
+            if list.truncated { " (truncated)" } else { "" }
+        );
+        let json = serde_json::to_string_pretty(&list)
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+        Ok(vec![
+            Content::text(json).with_audience(vec![Role::Assistant]),
+            Content::text(summary)
+                .with_audience(vec![Role::User])
+                .with_priority(0.0),
+        ])
+    }
+
+    async fn file_outline(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let path_str = params
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'path' parameter".into()))?;
+        let path = self.resolve_path(path_str)?;
+
+        if self.is_ignored(&path) {
+            return Err(ToolError::ExecutionError(format!(
+                "Access to '{}' is restricted by .gooseignore",
+                path.display()
+            )));
+        }
+        if !path.is_file() {
+            return Err(ToolError::ExecutionError(format!(
+                "The path '{}' does not exist or is not a file.",
+                path.display()
+            )));
+        }
+
+        let source = std::fs::read_to_string(&path)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to read file: {}", e)))?;
+        let outline = outline::outline(&path, &source).map_err(ToolError::ExecutionError)?;
+
+        let text = outline
+            .symbols
+            .iter()
+            .map(|s| {
+                format!(
+                    "{}{} {} (lines {}-{})",
+                    "  ".repeat(s.depth),
+                    s.kind,
+                    s.name,
+                    s.start_line,
+                    s.end_line
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let text = match (text.is_empty(), outline.truncated) {
+            (true, _) => format!("No symbols found in {}", path.display()),
+            (false, true) => format!("{}\n... outline truncated", text),
+            (false, false) => text,
+        };
+
+        Ok(vec![
+            Content::text(text.clone()).with_audience(vec![Role::Assistant]),
+            Content::text(text)
+                .with_audience(vec![Role::User])
+                .with_priority(0.0),
+        ])
+    }
+
+    async fn checkpoint(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let command = params
+            .get("command")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'command' parameter".to_string()))?
+            .to_string();
+        if !matches!(command.as_str(), "create" | "list" | "diff" | "restore") {
+            return Err(ToolError::InvalidParameters(format!(
+                "Unknown command '{}'",
+                command
+            )));
+        }
+        let id = params.get("id").and_then(|v| v.as_str()).map(String::from);
+        if id.is_none() && matches!(command.as_str(), "diff" | "restore") {
+            return Err(ToolError::InvalidParameters(
+                "Missing 'id' parameter".into(),
+            ));
+        }
+        let message = params
+            .get("message")
+            .and_then(|v| v.as_str())
+            .unwrap_or("Manual checkpoint")
+            .to_string();
+        let limit = params.get("limit").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+        let stat = params
+            .get("stat")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let cwd = std::env::current_dir().expect("should have a current working dir");
+        let file_history = Arc::clone(&self.file_history);
+        // Snapshots hash and copy the whole working tree through git
+        let output = tokio::task::spawn_blocking(move || -> Result<String, ToolError> {
+            let checkpoints =
+                Checkpoints::open(&cwd).map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+            let id = id.as_deref().unwrap_or_default();
+
+            Ok(match command.as_str() {
+                "create" => match checkpoints
+                    .create(&message)
+                    .map_err(|e| ToolError::ExecutionError(e.to_string()))?
+                {
+                    Some(checkpoint) => format!("Created checkpoint {}", checkpoint.short_id()),
+                    None => "The working tree has not changed since the latest checkpoint".into(),
+                },
+                "list" => {
+                    let list = checkpoints
+                        .list(limit)
+                        .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+                    if list.is_empty() {
+                        "No checkpoints found".to_string()
+                    } else {
+                        list.iter()
+                            .map(|c| {
+                                let created = chrono::DateTime::from_timestamp(c.created, 0)
+                                    .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                                    .unwrap_or_default();
+                                format!("{} - {} - {}", c.short_id(), created, c.message)
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    }
+                }
+                "diff" => {
+                    let diff = checkpoints
+                        .diff(id, stat)
+                        .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+                    if diff.trim().is_empty() {
+                        "No changes since this checkpoint".to_string()
+                    } else {
+                        diff
+                    }
+                }
+                _ => {
+                    let checkpoint = checkpoints
+                        .restore(id)
+                        .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+                    // Edit history refers to content that may no longer be on disk
+                    file_history.lock().unwrap().clear();
+                    format!(
+                        "Restored the working tree to checkpoint {} ({})",
+                        checkpoint.short_id(),
+                        checkpoint.message
+                    )
+                }
+            })
+        })
+        .await
+        .map_err(|e| ToolError::ExecutionError(e.to_string()))??;
+
+        Ok(vec![
+            Content::text(output.clone()).with_audience(vec![Role::Assistant]),
+            Content::text(output)
+                .with_audience(vec![Role::User])
+                .with_priority(0.0),
+        ])
+    }
+
     async fn list_windows(&self, _params: Value) -> Result<Vec<Content>, ToolError> {
         let windows = Window::all()
             .map_err(|_| ToolError::ExecutionError("Failed to list windows".into()))?;
@@ -1031,6 +1716,11 @@ impl Router for DeveloperRouter {
                 "list_windows" => this.list_windows(arguments).await,
                 "screen_capture" => this.screen_capture(arguments).await,
                 "image_processor" => this.image_processor(arguments).await,
+                "code_search" => this.code_search(arguments).await,
+                "find_files" => this.find_files(arguments).await,
+                "file_outline" => this.file_outline(arguments).await,
+                "checkpoint" => this.checkpoint(arguments).await,
+                "lsp" => this.lsp(arguments).await,
                 _ => Err(ToolError::NotFound(format!("Tool {} not found", tool_name))),
             }
         })
@@ -1088,6 +1778,10 @@ impl Clone for DeveloperRouter {
             instructions: self.instructions.clone(),
             file_history: Arc::clone(&self.file_history),
             ignore_patterns: Arc::clone(&self.ignore_patterns),
+            hints: Arc::clone(&self.hints),
+            sandbox: self.sandbox.clone(),
+            sandbox_masks: Arc::clone(&self.sandbox_masks),
+            lsp: Arc::clone(&self.lsp),
         }
     }
 }
@@ -1495,6 +2189,15 @@ mod tests {
             instructions: String::new(),
             file_history: Arc::new(Mutex::new(HashMap::new())),
             ignore_patterns: Arc::new(ignore_patterns),
+            hints: Arc::new(HintsLoader::new(
+                PathBuf::new(),
+                None,
+                Arc::new(Gitignore::empty()),
+                0,
+            )),
+            sandbox: SandboxConfig::default(),
+            sandbox_masks: Arc::new(MaskCache::default()),
+            lsp: Arc::new(LspManager::new(LspConfig::default(), PathBuf::new())),
         };
 
         // Test basic file matching
@@ -1545,6 +2248,15 @@ mod tests {
             instructions: String::new(),
             file_history: Arc::new(Mutex::new(HashMap::new())),
             ignore_patterns: Arc::new(ignore_patterns),
+            hints: Arc::new(HintsLoader::new(
+                PathBuf::new(),
+                None,
+                Arc::new(Gitignore::empty()),
+                0,
+            )),
+            sandbox: SandboxConfig::default(),
+            sandbox_masks: Arc::new(MaskCache::default()),
+            lsp: Arc::new(LspManager::new(LspConfig::default(), PathBuf::new())),
         };
 
         // Try to write to an ignored file
@@ -1602,6 +2314,15 @@ mod tests {
             instructions: String::new(),
             file_history: Arc::new(Mutex::new(HashMap::new())),
             ignore_patterns: Arc::new(ignore_patterns),
+            hints: Arc::new(HintsLoader::new(
+                PathBuf::new(),
+                None,
+                Arc::new(Gitignore::empty()),
+                0,
+            )),
+            sandbox: SandboxConfig::default(),
+            sandbox_masks: Arc::new(MaskCache::default()),
+            lsp: Arc::new(LspManager::new(LspConfig::default(), PathBuf::new())),
         };
 
         // Create an ignored file
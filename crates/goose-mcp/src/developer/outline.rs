@@ -0,0 +1,178 @@
+//! Symbol outlines of source files using tree-sitter grammars.
+
+use serde::Serialize;
+use std::path::Path;
+use tree_sitter::{Node, Parser};
+
+use super::lang;
+
+/// Outlines stop after this many symbols
+const MAX_SYMBOLS: usize = 500;
+
+#[derive(Debug, Serialize)]
+pub struct Symbol {
+    pub kind: String,
+    pub name: String,
+    /// 1-based first line of the symbol
+    pub start_line: usize,
+    /// 1-based last line of the symbol
+    pub end_line: usize,
+    /// Nesting depth, 0 for top-level symbols
+    pub depth: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Outline {
+    pub symbols: Vec<Symbol>,
+    pub truncated: bool,
+}
+
+/// Map a syntax node kind to a symbol kind, for the kinds worth listing in an outline
+fn symbol_kind(node_kind: &str) -> Option<&'static str> {
+    Some(match node_kind {
+        "function_item" | "function_definition" | "function_declaration" => "function",
+        "method_definition" | "method_declaration" => "method",
+        "constructor_declaration" => "constructor",
+        "struct_item" => "struct",
+        "enum_item" | "enum_declaration" => "enum",
+        "trait_item" => "trait",
+        "impl_item" => "impl",
+        "mod_item" => "module",
+        "const_item" => "const",
+        "static_item" => "static",
+        "type_item" | "type_alias_declaration" | "type_spec" => "type",
+        "macro_definition" => "macro",
+        "class_definition" | "class_declaration" | "class" => "class",
+        "interface_declaration" => "interface",
+        _ => return None,
+    })
+}
+
+/// The text naming a symbol node, which for impl blocks is the implemented type
+fn symbol_name(node: Node, source: &[u8]) -> Option<String> {
+    let name = match node.kind() {
+        "impl_item" => {
+            let ty = node.child_by_field_name("type")?.utf8_text(source).ok()?;
+            match node.child_by_field_name("trait") {
+                Some(tr) => format!("{} for {}", tr.utf8_text(source).ok()?, ty),
+                None => ty.to_string(),
+            }
+        }
+        _ => node
+            .child_by_field_name("name")?
+            .utf8_text(source)
+            .ok()?
+            .to_string(),
+    };
+    Some(name)
+}
+
+/// Parse a file and list the symbols it defines, in source order
+pub fn outline(path: &Path, source: &str) -> Result<Outline, String> {
+    let language = lang::get_tree_sitter_language(path).ok_or_else(|| {
+        format!(
+            "No symbol outline support for '{}', supported extensions are rs, py, js, jsx, ts, tsx, go and java",
+            path.display()
+        )
+    })?;
+
+    let mut parser = Parser::new();
+    parser
+        .set_language(&language)
+        .map_err(|e| format!("Failed to load grammar: {}", e))?;
+    let tree = parser
+        .parse(source, None)
+        .ok_or_else(|| "Failed to parse file".to_string())?;
+
+    let mut outline = Outline {
+        symbols: Vec::new(),
+        truncated: false,
+    };
+    collect(tree.root_node(), source.as_bytes(), 0, &mut outline);
+    Ok(outline)
+}
+
+fn collect(node: Node, source: &[u8], depth: usize, outline: &mut Outline) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if outline.truncated {
+            return;
+        }
+
+        let symbol = symbol_kind(child.kind())
+            .and_then(|kind| symbol_name(child, source).map(|name| (kind, name)));
+
+        match symbol {
+            Some((kind, name)) => {
+                if outline.symbols.len() == MAX_SYMBOLS {
+                    outline.truncated = true;
+                    return;
+                }
+                outline.symbols.push(Symbol {
+                    kind: kind.to_string(),
+                    name,
+                    start_line: child.start_position().row + 1,
+                    end_line: child.end_position().row + 1,
+                    depth,
+                });
+                collect(child, source, depth + 1, outline);
+            }
+            None => collect(child, source, depth, outline),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rust_outline() {
+        let source = indoc::indoc! {r#"
+            struct Point {
+                x: i32,
+            }
+
+            impl Display for Point {
+                fn fmt(&self) {}
+            }
+
+            mod inner {
+                pub fn helper() {}
+            }
+        "#};
+        let outline = outline(Path::new("lib.rs"), source).unwrap();
+        let names: Vec<_> = outline
+            .symbols
+            .iter()
+            .map(|s| (s.kind.as_str(), s.name.as_str(), s.depth))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("struct", "Point", 0),
+                ("impl", "Display for Point", 0),
+                ("function", "fmt", 1),
+                ("module", "inner", 0),
+                ("function", "helper", 1),
+            ]
+        );
+        assert_eq!(outline.symbols[0].start_line, 1);
+        assert_eq!(outline.symbols[0].end_line, 3);
+    }
+
+    #[test]
+    fn test_python_outline() {
+        let source =
+            "class Greeter:\n    def greet(self):\n        pass\n\ndef main():\n    pass\n";
+        let outline = outline(Path::new("app.py"), source).unwrap();
+        let names: Vec<_> = outline.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Greeter", "greet", "main"]);
+        assert_eq!(outline.symbols[1].depth, 1);
+    }
+
+    #[test]
+    fn test_unsupported_extension() {
+        assert!(outline(Path::new("notes.txt"), "hello").is_err());
+    }
+}
@@ -0,0 +1,395 @@
+//! Optional sandbox for shell commands, built on bubblewrap (`bwrap`).
+//!
+//! When enabled the whole filesystem is mounted read-only, with only the working directory, a
+//! private /tmp and explicitly allowlisted paths writable. Files and directories matched by
+//! .gooseignore are masked with empty mounts, so no command can read them however it is
+//! spelled, and the network is unshared unless it is allowed.
+//!
+//! Configured through environment variables:
+//! - `GOOSE_SANDBOX`: `bwrap` (or `true`, `on`, `yes`, `1`) to enable, `off` (default) to
+//!   disable. Any other value enables the sandbox, so a typo never turns it off.
+//! - `GOOSE_SANDBOX_NETWORK`: `true` to keep network access inside the sandbox
+//! - `GOOSE_SANDBOX_WRITABLE_PATHS`: extra writable paths, separated like `PATH`
+
+use ignore::gitignore::Gitignore;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use super::shell::expand_path;
+
+/// Refuse to run sandboxed commands in trees with more directory entries than this, since
+/// ignored files past the limit could not be masked
+const MAX_WALK_ENTRIES: usize = 200_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxMode {
+    Off,
+    Bubblewrap,
+}
+
+impl SandboxMode {
+    /// Parse a `GOOSE_SANDBOX` value. A security switch must not fail open, so values that
+    /// are not recognised enable the sandbox.
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "bwrap" | "bubblewrap" | "true" | "on" | "yes" | "1" => SandboxMode::Bubblewrap,
+            "" | "off" | "false" | "no" | "0" => SandboxMode::Off,
+            other => {
+                tracing::warn!(
+                    "Unknown GOOSE_SANDBOX mode '{}', using the bubblewrap sandbox",
+                    other
+                );
+                SandboxMode::Bubblewrap
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SandboxConfig {
+    pub mode: SandboxMode,
+    pub allow_network: bool,
+    pub writable_paths: Vec<PathBuf>,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            mode: SandboxMode::Off,
+            allow_network: false,
+            writable_paths: Vec::new(),
+        }
+    }
+}
+
+impl SandboxConfig {
+    pub fn from_env() -> Self {
+        let mode = SandboxMode::parse(&std::env::var("GOOSE_SANDBOX").unwrap_or_default());
+
+        let allow_network = std::env::var("GOOSE_SANDBOX_NETWORK")
+            .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+            .unwrap_or(false);
+
+        let writable_paths = std::env::var_os("GOOSE_SANDBOX_WRITABLE_PATHS")
+            .map(|paths| {
+                std::env::split_paths(&paths)
+                    .map(|p| PathBuf::from(expand_path(&p.to_string_lossy())))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            mode,
+            allow_network,
+            writable_paths,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.mode != SandboxMode::Off
+    }
+
+    /// A short description of the restrictions, for the extension instructions
+    pub fn describe(&self) -> Option<String> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let mut description = "Shell commands run in a sandbox: only the current directory and /tmp are writable, and files restricted by .gooseignore are hidden.".to_string();
+        if !self.writable_paths.is_empty() {
+            let paths: Vec<_> = self
+                .writable_paths
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect();
+            description.push_str(&format!(
+                " These paths are also writable: {}.",
+                paths.join(", ")
+            ));
+        }
+        if !self.allow_network {
+            description.push_str(" There is no network access.");
+        }
+        Some(description)
+    }
+}
+
+/// Paths under the working directory that must be hidden from sandboxed commands
+#[derive(Debug, Default, PartialEq)]
+pub struct MaskedPaths {
+    pub files: Vec<PathBuf>,
+    pub dirs: Vec<PathBuf>,
+}
+
+/// A walk of the working directory, with the modification times of the directories it read
+struct Walk {
+    masked: Arc<MaskedPaths>,
+    dirs: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl Walk {
+    /// Whether no walked directory has gained, lost or renamed an entry since the walk
+    fn is_current(&self) -> bool {
+        self.dirs.iter().all(|(dir, modified)| {
+            std::fs::metadata(dir).and_then(|m| m.modified()).ok() == *modified
+        })
+    }
+}
+
+/// The masked paths of each working directory, walked again only when the tree changes
+#[derive(Default)]
+pub struct MaskCache {
+    walks: Mutex<HashMap<PathBuf, Walk>>,
+}
+
+impl MaskCache {
+    pub fn masked_paths(
+        &self,
+        root: &Path,
+        ignore_patterns: &Gitignore,
+    ) -> Result<Arc<MaskedPaths>, String> {
+        if let Some(walk) = self.walks.lock().unwrap().get(root) {
+            if walk.is_current() {
+                return Ok(Arc::clone(&walk.masked));
+            }
+        }
+        let walk = walk(root, ignore_patterns, MAX_WALK_ENTRIES)?;
+        let masked = Arc::clone(&walk.masked);
+        self.walks.lock().unwrap().insert(root.to_path_buf(), walk);
+        Ok(masked)
+    }
+}
+
+/// Find everything under `root` matched by the ignore patterns.
+///
+/// Unlike the code search walk this includes hidden and gitignored files, as those are
+/// exactly where secrets live. Ignored directories are masked whole without descending.
+/// Fails rather than returning a partial mask when the tree has more than `limit` entries.
+fn walk(root: &Path, ignore_patterns: &Gitignore, limit: usize) -> Result<Walk, String> {
+    let mut masked = MaskedPaths::default();
+    let mut dirs = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    let mut entries = 0;
+
+    while let Some(dir) = pending.pop() {
+        // Taken before reading so a change during the walk invalidates it
+        let modified = std::fs::metadata(&dir).and_then(|m| m.modified()).ok();
+        let Ok(read_dir) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in read_dir.flatten() {
+            entries += 1;
+            if entries > limit {
+                return Err(format!(
+                    "The working directory has more than {} entries, too many to find every file restricted by .gooseignore. Refusing to run the command in the sandbox; run goose from a smaller directory.",
+                    limit
+                ));
+            }
+
+            let path = entry.path();
+            // Symlinks are not followed, they are matched like files
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+
+            if ignore_patterns.matched(&path, is_dir).is_ignore() {
+                if is_dir {
+                    masked.dirs.push(path);
+                } else {
+                    masked.files.push(path);
+                }
+            } else if is_dir && entry.file_name() != ".git" {
+                pending.push(path);
+            }
+        }
+        dirs.push((dir, modified));
+    }
+
+    masked.files.sort();
+    masked.dirs.sort();
+    Ok(Walk {
+        masked: Arc::new(masked),
+        dirs,
+    })
+}
+
+/// Build the bubblewrap arguments that run `program args...` inside the sandbox
+pub fn bwrap_args(
+    config: &SandboxConfig,
+    cwd: &Path,
+    masked: &MaskedPaths,
+    program: &str,
+    args: &[String],
+) -> Vec<OsString> {
+    let mut bwrap: Vec<OsString> = Vec::new();
+    let mut push = |items: &[&dyn AsRef<std::ffi::OsStr>]| {
+        bwrap.extend(items.iter().map(|i| i.as_ref().to_os_string()));
+    };
+
+    push(&[&"--ro-bind", &"/", &"/"]);
+    push(&[&"--dev", &"/dev"]);
+    push(&[&"--proc", &"/proc"]);
+    push(&[&"--tmpfs", &"/tmp"]);
+    push(&[&"--bind", &cwd, &cwd]);
+    for path in config.writable_paths.iter().filter(|p| p.exists()) {
+        push(&[&"--bind", path, path]);
+    }
+
+    // Masks come after the binds so they are mounted on top of them
+    for dir in &masked.dirs {
+        push(&[&"--tmpfs", dir, &"--remount-ro", dir]);
+    }
+    for file in &masked.files {
+        push(&[&"--ro-bind", &"/dev/null", file]);
+    }
+
+    push(&[&"--unshare-pid", &"--unshare-ipc", &"--unshare-uts"]);
+    if !config.allow_network {
+        push(&[&"--unshare-net"]);
+    }
+    push(&[&"--die-with-parent", &"--chdir", &cwd, &"--"]);
+    push(&[&program]);
+    for arg in args {
+        push(&[arg]);
+    }
+
+    bwrap
+}
+
+/// Check that the configured sandbox can run on this machine
+pub fn check_available(config: &SandboxConfig) -> Result<(), String> {
+    match config.mode {
+        SandboxMode::Off => Ok(()),
+        SandboxMode::Bubblewrap => {
+            if !cfg!(target_os = "linux") {
+                return Err("The bubblewrap sandbox is only supported on Linux".to_string());
+            }
+            let available = std::process::Command::new("bwrap")
+                .arg("--version")
+                .output()
+                .is_ok_and(|output| output.status.success());
+            if available {
+                Ok(())
+            } else {
+                Err("GOOSE_SANDBOX is set to bwrap but bubblewrap (bwrap) is not installed. Install it or unset GOOSE_SANDBOX.".to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ignore::gitignore::GitignoreBuilder;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn strings(args: &[OsString]) -> Vec<String> {
+        args.iter()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_find_masked_paths() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::write(root.join(".env"), "KEY=1").unwrap();
+        fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+        fs::create_dir_all(root.join("secrets/nested")).unwrap();
+        fs::write(root.join("secrets/nested/key.pem"), "key").unwrap();
+        // .gitignore must not hide files from the mask walk
+        fs::write(root.join(".gitignore"), ".env\n").unwrap();
+
+        let mut builder = GitignoreBuilder::new(root);
+        builder.add_line(None, "**/.env").unwrap();
+        builder.add_line(None, "secrets/").unwrap();
+        let ignore = builder.build().unwrap();
+
+        let cache = MaskCache::default();
+        let masked = cache.masked_paths(root, &ignore).unwrap();
+        assert_eq!(masked.files, vec![root.join(".env")]);
+        assert_eq!(masked.dirs, vec![root.join("secrets")]);
+
+        // The walk is reused until the tree changes
+        assert!(Arc::ptr_eq(
+            &masked,
+            &cache.masked_paths(root, &ignore).unwrap()
+        ));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/.env"), "KEY=2").unwrap();
+        let masked = cache.masked_paths(root, &ignore).unwrap();
+        assert_eq!(masked.files, vec![root.join(".env"), root.join("src/.env")]);
+
+        // A tree too large to walk is an error, not a partial mask
+        assert!(walk(root, &ignore, 3).is_err());
+    }
+
+    #[test]
+    fn test_bwrap_args() {
+        let config = SandboxConfig {
+            mode: SandboxMode::Bubblewrap,
+            allow_network: false,
+            writable_paths: vec![std::env::temp_dir()],
+        };
+        let masked = MaskedPaths {
+            files: vec![PathBuf::from("/work/.env")],
+            dirs: vec![PathBuf::from("/work/secrets")],
+        };
+        let args = strings(&bwrap_args(
+            &config,
+            Path::new("/work"),
+            &masked,
+            "bash",
+            &["-c".to_string(), "cat .env".to_string()],
+        ));
+        let joined = args.join(" ");
+
+        assert!(joined.starts_with("--ro-bind / /"));
+        assert!(joined.contains("--bind /work /work"));
+        let temp = std::env::temp_dir().display().to_string();
+        assert!(joined.contains(&format!("--bind {} {}", temp, temp)));
+        assert!(joined.contains("--ro-bind /dev/null /work/.env"));
+        assert!(joined.contains("--tmpfs /work/secrets --remount-ro /work/secrets"));
+        assert!(joined.contains("--unshare-net"));
+        assert!(joined.ends_with("-- bash -c cat .env"));
+
+        // Masks are mounted after the working directory bind so they take precedence
+        let bind = args.iter().position(|a| a == "/work").unwrap();
+        let mask = args.iter().position(|a| a == "/work/.env").unwrap();
+        assert!(mask > bind);
+
+        let config = SandboxConfig {
+            allow_network: true,
+            ..config
+        };
+        let args = strings(&bwrap_args(
+            &config,
+            Path::new("/work"),
+            &MaskedPaths::default(),
+            "bash",
+            &[],
+        ));
+        assert!(!args.contains(&"--unshare-net".to_string()));
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let config = SandboxConfig::default();
+        assert!(!config.is_enabled());
+        assert!(config.describe().is_none());
+        assert!(check_available(&config).is_ok());
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        for value in ["bwrap", "ON", "yes", "1", "true"] {
+            assert_eq!(SandboxMode::parse(value), SandboxMode::Bubblewrap);
+        }
+        for value in ["", "off", "False", "no", "0"] {
+            assert_eq!(SandboxMode::parse(value), SandboxMode::Off);
+        }
+        // Typos keep the sandbox on
+        assert_eq!(SandboxMode::parse("bwarp"), SandboxMode::Bubblewrap);
+    }
+}
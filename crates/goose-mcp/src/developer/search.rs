@@ -0,0 +1,279 @@
+//! Code search and file listing that respect .gitignore and .gooseignore.
+//!
+//! Everything here is bounded: walks stop once enough results are collected, large and
+//! binary files are skipped, and long lines are clipped, so results fit in the context.
+
+use ignore::gitignore::Gitignore;
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use regex::RegexBuilder;
+use serde::Serialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Files larger than this are not searched
+const MAX_SEARCH_FILE_SIZE: u64 = 1024 * 1024;
+/// Matched lines are clipped to this many characters
+const MAX_LINE_CHARS: usize = 300;
+/// Upper bound for any caller-supplied result limit
+pub const MAX_RESULTS: usize = 1000;
+
+#[derive(Debug, Serialize)]
+pub struct SearchMatch {
+    pub path: PathBuf,
+    pub line: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResults {
+    pub matches: Vec<SearchMatch>,
+    pub files_searched: usize,
+    /// True when the limit was hit and more matches may exist
+    pub truncated: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileList {
+    pub files: Vec<PathBuf>,
+    pub truncated: bool,
+}
+
+/// Options for [`search_code`]
+pub struct SearchOptions<'a> {
+    pub pattern: &'a str,
+    pub root: &'a Path,
+    pub glob: Option<&'a str>,
+    pub case_sensitive: bool,
+    pub max_results: usize,
+}
+
+fn walker(root: &Path, glob: Option<&str>) -> Result<ignore::Walk, String> {
+    let mut builder = WalkBuilder::new(root);
+    builder.sort_by_file_path(|a, b| a.cmp(b));
+
+    if let Some(glob) = glob {
+        let mut overrides = OverrideBuilder::new(root);
+        overrides
+            .add(glob)
+            .map_err(|e| format!("Invalid glob '{}': {}", glob, e))?;
+        builder.overrides(
+            overrides
+                .build()
+                .map_err(|e| format!("Invalid glob '{}': {}", glob, e))?,
+        );
+    }
+
+    Ok(builder.build())
+}
+
+/// Walk the files under `root`, skipping anything matched by the ignore patterns
+fn files<'a>(
+    root: &Path,
+    glob: Option<&str>,
+    ignore_patterns: &'a Gitignore,
+) -> Result<impl Iterator<Item = PathBuf> + 'a, String> {
+    Ok(walker(root, glob)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(|entry| entry.into_path())
+        .filter(move |path| !is_ignored(ignore_patterns, path)))
+}
+
+fn is_ignored(ignore_patterns: &Gitignore, path: &Path) -> bool {
+    // Parent matching panics for paths outside the matcher's root
+    if path.starts_with(ignore_patterns.path()) {
+        ignore_patterns
+            .matched_path_or_any_parents(path, false)
+            .is_ignore()
+    } else {
+        ignore_patterns.matched(path, false).is_ignore()
+    }
+}
+
+/// Search file contents under a directory for a regular expression
+pub fn search_code(
+    options: SearchOptions,
+    ignore_patterns: &Gitignore,
+) -> Result<SearchResults, String> {
+    let regex = RegexBuilder::new(options.pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| format!("Invalid regex '{}': {}", options.pattern, e))?;
+    let max_results = options.max_results.clamp(1, MAX_RESULTS);
+
+    let mut results = SearchResults {
+        matches: Vec::new(),
+        files_searched: 0,
+        truncated: false,
+    };
+
+    for path in files(options.root, options.glob, ignore_patterns)? {
+        let Some(content) = read_text(&path) else {
+            continue;
+        };
+        results.files_searched += 1;
+
+        for (index, line) in content.lines().enumerate() {
+            if !regex.is_match(line) {
+                continue;
+            }
+            if results.matches.len() == max_results {
+                results.truncated = true;
+                return Ok(results);
+            }
+            results.matches.push(SearchMatch {
+                path: path.clone(),
+                line: index + 1,
+                text: clip(line.trim_end()),
+            });
+        }
+    }
+
+    Ok(results)
+}
+
+/// List files under a directory whose path matches a glob
+pub fn find_files(
+    root: &Path,
+    glob: &str,
+    max_results: usize,
+    ignore_patterns: &Gitignore,
+) -> Result<FileList, String> {
+    let max_results = max_results.clamp(1, MAX_RESULTS);
+    let mut list = FileList {
+        files: Vec::new(),
+        truncated: false,
+    };
+
+    for path in files(root, Some(glob), ignore_patterns)? {
+        if list.files.len() == max_results {
+            list.truncated = true;
+            break;
+        }
+        list.files.push(path);
+    }
+
+    Ok(list)
+}
+
+/// Read a file as text, returning None for large or binary files
+fn read_text(path: &Path) -> Option<String> {
+    let file = std::fs::File::open(path).ok()?;
+    if file.metadata().ok()?.len() > MAX_SEARCH_FILE_SIZE {
+        return None;
+    }
+
+    let mut bytes = Vec::new();
+    file.take(MAX_SEARCH_FILE_SIZE)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    if bytes.iter().take(8192).any(|b| *b == 0) {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn clip(line: &str) -> String {
+    if line.chars().count() <= MAX_LINE_CHARS {
+        line.to_string()
+    } else {
+        let mut clipped: String = line.chars().take(MAX_LINE_CHARS).collect();
+        clipped.push_str("...");
+        clipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ignore::gitignore::GitignoreBuilder;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn fixture() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(
+            dir.path().join("src/main.rs"),
+            "fn main() {\n    println!(\"hello\");\n}\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("src/lib.py"), "def hello():\n    pass\n").unwrap();
+        fs::create_dir_all(dir.path().join("private")).unwrap();
+        fs::write(dir.path().join("private/notes.txt"), "hello secret\n").unwrap();
+        dir
+    }
+
+    fn ignore(dir: &TempDir) -> Gitignore {
+        let mut builder = GitignoreBuilder::new(dir.path());
+        builder.add_line(None, "private/").unwrap();
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn test_search_respects_ignore_and_glob() {
+        let dir = fixture();
+        let ignore = ignore(&dir);
+
+        let results = search_code(
+            SearchOptions {
+                pattern: "hello",
+                root: dir.path(),
+                glob: None,
+                case_sensitive: false,
+                max_results: 100,
+            },
+            &ignore,
+        )
+        .unwrap();
+        assert_eq!(results.matches.len(), 2);
+        assert!(results
+            .matches
+            .iter()
+            .all(|m| !m.path.starts_with(dir.path().join("private"))));
+
+        let results = search_code(
+            SearchOptions {
+                pattern: "hello",
+                root: dir.path(),
+                glob: Some("*.rs"),
+                case_sensitive: true,
+                max_results: 100,
+            },
+            &ignore,
+        )
+        .unwrap();
+        assert_eq!(results.matches.len(), 1);
+        assert_eq!(results.matches[0].line, 2);
+    }
+
+    #[test]
+    fn test_search_is_bounded() {
+        let dir = fixture();
+        let results = search_code(
+            SearchOptions {
+                pattern: ".",
+                root: dir.path(),
+                glob: None,
+                case_sensitive: true,
+                max_results: 2,
+            },
+            &ignore(&dir),
+        )
+        .unwrap();
+        assert_eq!(results.matches.len(), 2);
+        assert!(results.truncated);
+    }
+
+    #[test]
+    fn test_find_files() {
+        let dir = fixture();
+        let list = find_files(dir.path(), "**/*.py", 10, &ignore(&dir)).unwrap();
+        assert_eq!(list.files, vec![dir.path().join("src/lib.py")]);
+        assert!(!list.truncated);
+
+        assert!(find_files(dir.path(), "[", 10, &ignore(&dir)).is_err());
+    }
+}
@@ -0,0 +1,910 @@
+//! Structured Google Docs edits through the Docs API batchUpdate, which keeps the formatting
+//! and comments that replacing the whole file would lose.
+//!
+//! Edits target the section under a heading or a piece of anchor text. The Docs API indexes
+//! content in UTF-16 code units, so every index here is a UTF-16 offset.
+
+use mcp_core::handler::ToolError;
+use serde_json::{json, Value};
+
+const DOCS_API_URL: &str = "https://docs.googleapis.com/v1/";
+const NAMED_STYLES: [&str; 9] = [
+    "NORMAL_TEXT",
+    "TITLE",
+    "SUBTITLE",
+    "HEADING_1",
+    "HEADING_2",
+    "HEADING_3",
+    "HEADING_4",
+    "HEADING_5",
+    "HEADING_6",
+];
+/// Longest excerpt of removed text shown in a preview
+const PREVIEW_CHARS: usize = 2000;
+
+#[derive(Clone)]
+pub struct DocsApi {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl DocsApi {
+    pub fn new() -> Self {
+        Self::with_base_url(DOCS_API_URL)
+    }
+
+    pub fn with_base_url(base_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: format!("{}/", base_url.trim_end_matches('/')),
+        }
+    }
+
+    pub async fn get(&self, token: &str, document_id: &str) -> Result<Document, ToolError> {
+        let response = self
+            .client
+            .get(format!("{}documents/{}", self.base_url, document_id))
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(|e| {
+                ToolError::ExecutionError(format!(
+                    "Failed to fetch document {}, {}.",
+                    document_id, e
+                ))
+            })?;
+        let value = Self::json(response, document_id).await?;
+        Document::parse(&value)
+    }
+
+    /// Apply requests to the document, failing if it changed since the given revision.
+    /// Returns the new revision ID.
+    pub async fn batch_update(
+        &self,
+        token: &str,
+        document_id: &str,
+        requests: &[Value],
+        revision_id: &str,
+    ) -> Result<String, ToolError> {
+        let response = self
+            .client
+            .post(format!(
+                "{}documents/{}:batchUpdate",
+                self.base_url, document_id
+            ))
+            .bearer_auth(token)
+            .json(&json!({
+                "requests": requests,
+                "writeControl": {"requiredRevisionId": revision_id},
+            }))
+            .send()
+            .await
+            .map_err(|e| {
+                ToolError::ExecutionError(format!(
+                    "Failed to update document {}, {}.",
+                    document_id, e
+                ))
+            })?;
+        let value = Self::json(response, document_id).await?;
+        Ok(value["writeControl"]["requiredRevisionId"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string())
+    }
+
+    async fn json(response: reqwest::Response, document_id: &str) -> Result<Value, ToolError> {
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(ToolError::ExecutionError(format!(
+                "Google Docs request for {} failed with {}: {}",
+                document_id, status, body
+            )));
+        }
+        response.json().await.map_err(|e| {
+            ToolError::ExecutionError(format!(
+                "Failed to parse the Google Docs response for {}, {}.",
+                document_id, e
+            ))
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Paragraph {
+    pub start: i64,
+    pub end: i64,
+    /// Paragraph text including its trailing newline. Non-text elements such as images are
+    /// replaced with U+FFFC so offsets into the text still line up with document indices.
+    pub text: String,
+    pub style: String,
+}
+
+impl Paragraph {
+    /// 0 for the title, 1-6 for headings, None for other paragraphs
+    fn heading_level(&self) -> Option<usize> {
+        match self.style.as_str() {
+            "TITLE" => Some(0),
+            style => style.strip_prefix("HEADING_")?.parse().ok(),
+        }
+    }
+
+    fn label(&self) -> &str {
+        self.text.trim_end_matches('\n').trim()
+    }
+}
+
+#[derive(Debug)]
+pub struct Document {
+    pub title: String,
+    pub revision_id: String,
+    /// Top level body paragraphs, paragraphs inside tables are not targets
+    pub paragraphs: Vec<Paragraph>,
+    /// End index of the body, one past its final newline
+    pub end: i64,
+}
+
+impl Document {
+    pub fn parse(value: &Value) -> Result<Self, ToolError> {
+        let content = value["body"]["content"].as_array().ok_or_else(|| {
+            ToolError::ExecutionError("The document has no body content.".to_string())
+        })?;
+
+        let mut paragraphs = Vec::new();
+        for element in content {
+            let Some(paragraph) = element.get("paragraph") else {
+                continue;
+            };
+            let start = element["startIndex"].as_i64().unwrap_or_default();
+            let end = element["endIndex"].as_i64().unwrap_or(start);
+            let mut text = String::new();
+            for part in paragraph["elements"].as_array().into_iter().flatten() {
+                match part["textRun"]["content"].as_str() {
+                    Some(run) => text.push_str(run),
+                    None => {
+                        let width = part["endIndex"].as_i64().unwrap_or_default()
+                            - part["startIndex"].as_i64().unwrap_or_default();
+                        text.extend(std::iter::repeat_n('\u{FFFC}', width.max(0) as usize));
+                    }
+                }
+            }
+            let style = paragraph["paragraphStyle"]["namedStyleType"]
+                .as_str()
+                .unwrap_or("NORMAL_TEXT")
+                .to_string();
+            paragraphs.push(Paragraph {
+                start,
+                end,
+                text,
+                style,
+            });
+        }
+
+        Ok(Self {
+            title: value["title"].as_str().unwrap_or_default().to_string(),
+            revision_id: value["revisionId"].as_str().unwrap_or_default().to_string(),
+            paragraphs,
+            end: content
+                .last()
+                .and_then(|element| element["endIndex"].as_i64())
+                .unwrap_or(1),
+        })
+    }
+
+    /// The headings of the document, indented by level
+    pub fn outline(&self) -> String {
+        let headings: Vec<String> = self
+            .paragraphs
+            .iter()
+            .filter_map(|p| {
+                let level = p.heading_level()?;
+                Some(format!(
+                    "{}- {} ({}, index {})",
+                    "  ".repeat(level.saturating_sub(1)),
+                    p.label(),
+                    p.style,
+                    p.start
+                ))
+            })
+            .collect();
+        if headings.is_empty() {
+            "The document has no headings.".to_string()
+        } else {
+            headings.join("\n")
+        }
+    }
+
+    fn find_heading(&self, heading: &str) -> Result<&Paragraph, ToolError> {
+        let wanted = heading.trim().to_lowercase();
+        self.paragraphs
+            .iter()
+            .find(|p| p.heading_level().is_some() && p.label().to_lowercase() == wanted)
+            .ok_or_else(|| {
+                ToolError::InvalidParameters(format!(
+                    "Heading '{}' not found. The document headings are:\n{}",
+                    heading,
+                    self.outline()
+                ))
+            })
+    }
+
+    /// Where the section under a heading ends: the next heading at the same or a higher
+    /// level, or the end of the document
+    fn section_end(&self, heading: &Paragraph) -> i64 {
+        let level = heading.heading_level().unwrap_or_default();
+        self.paragraphs
+            .iter()
+            .filter(|p| p.start > heading.start)
+            .find(|p| p.heading_level().is_some_and(|l| l <= level))
+            .map(|p| p.start)
+            .unwrap_or(self.end)
+    }
+
+    /// The range of the nth occurrence (1-based) of some text, along with its paragraph
+    fn find_anchor(
+        &self,
+        anchor: &str,
+        occurrence: usize,
+    ) -> Result<(i64, i64, &Paragraph), ToolError> {
+        let mut seen = 0;
+        for paragraph in &self.paragraphs {
+            for (offset, _) in paragraph.text.match_indices(anchor) {
+                seen += 1;
+                if seen == occurrence {
+                    let start = paragraph.start + len16(&paragraph.text[..offset]);
+                    return Ok((start, start + len16(anchor), paragraph));
+                }
+            }
+        }
+        Err(ToolError::InvalidParameters(if seen == 0 {
+            format!("Anchor text '{}' not found in the document.", anchor)
+        } else {
+            format!(
+                "Anchor text '{}' occurs {} times, occurrence {} does not exist.",
+                anchor, seen, occurrence
+            )
+        }))
+    }
+
+    /// The text between two indices, for previews
+    fn text_in(&self, start: i64, end: i64) -> String {
+        let mut text = String::new();
+        for p in &self.paragraphs {
+            if p.end <= start || p.start >= end {
+                continue;
+            }
+            let units: Vec<u16> = p.text.encode_utf16().collect();
+            let from = (start - p.start).max(0) as usize;
+            let to = ((end - p.start) as usize).min(units.len());
+            text.push_str(&String::from_utf16_lossy(&units[from.min(to)..to]));
+        }
+        text
+    }
+}
+
+fn len16(text: &str) -> i64 {
+    text.encode_utf16().count() as i64
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Heading(String),
+    Anchor { text: String, occurrence: usize },
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Position {
+    Before,
+    After,
+    EndOfSection,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    Insert {
+        target: Target,
+        position: Position,
+        text: String,
+        style: Option<String>,
+    },
+    Replace {
+        target: Target,
+        text: String,
+        style: Option<String>,
+    },
+    Delete {
+        target: Target,
+    },
+    ApplyStyle {
+        target: Target,
+        style: String,
+    },
+    InsertTable {
+        target: Target,
+        position: Position,
+        rows: Vec<Vec<String>>,
+    },
+}
+
+impl Edit {
+    pub fn from_params(params: &Value) -> Result<Self, ToolError> {
+        let operation = params["operation"].as_str().ok_or_else(|| {
+            ToolError::InvalidParameters("The operation param is required".to_string())
+        })?;
+        let target = match (params["heading"].as_str(), params["anchor"].as_str()) {
+            (Some(_), Some(_)) => {
+                return Err(ToolError::InvalidParameters(
+                    "Use either heading or anchor, not both".to_string(),
+                ))
+            }
+            (Some(heading), None) => Target::Heading(heading.to_string()),
+            (None, Some(anchor)) if !anchor.is_empty() => Target::Anchor {
+                text: anchor.to_string(),
+                occurrence: params["occurrence"].as_u64().unwrap_or(1).max(1) as usize,
+            },
+            (None, Some(_)) => {
+                return Err(ToolError::InvalidParameters(
+                    "The anchor param can't be empty".to_string(),
+                ))
+            }
+            (None, None) => Target::End,
+        };
+        let position = match params["position"].as_str().unwrap_or("after") {
+            "before" => Position::Before,
+            "after" => Position::After,
+            "end_of_section" => Position::EndOfSection,
+            other => {
+                return Err(ToolError::InvalidParameters(format!(
+                    "position must be 'before', 'after' or 'end_of_section', got {}",
+                    other
+                )))
+            }
+        };
+        let style = match params["style"].as_str() {
+            Some(style) if NAMED_STYLES.contains(&style) => Some(style.to_string()),
+            Some(style) => {
+                return Err(ToolError::InvalidParameters(format!(
+                    "style must be one of {}, got {}",
+                    NAMED_STYLES.join(", "),
+                    style
+                )))
+            }
+            None => None,
+        };
+        let text = || -> Result<String, ToolError> {
+            match params["text"].as_str() {
+                Some(text) if !text.is_empty() => Ok(text.to_string()),
+                _ => Err(ToolError::InvalidParameters(format!(
+                    "The text param is required for {}",
+                    operation
+                ))),
+            }
+        };
+        let needs_target = |target: Target| -> Result<Target, ToolError> {
+            if target == Target::End {
+                Err(ToolError::InvalidParameters(format!(
+                    "A heading or anchor is required for {}",
+                    operation
+                )))
+            } else {
+                Ok(target)
+            }
+        };
+
+        match operation {
+            "insert" => Ok(Edit::Insert {
+                target,
+                position,
+                text: text()?,
+                style,
+            }),
+            "replace" => Ok(Edit::Replace {
+                target: needs_target(target)?,
+                text: text()?,
+                style,
+            }),
+            "delete" => Ok(Edit::Delete {
+                target: needs_target(target)?,
+            }),
+            "apply_style" => Ok(Edit::ApplyStyle {
+                target: needs_target(target)?,
+                style: style.ok_or_else(|| {
+                    ToolError::InvalidParameters(
+                        "The style param is required for apply_style".to_string(),
+                    )
+                })?,
+            }),
+            "insert_table" => {
+                let rows: Vec<Vec<String>> = params["rows"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|row| {
+                        row.as_array()
+                            .into_iter()
+                            .flatten()
+                            .map(|cell| match cell {
+                                Value::String(s) => s.clone(),
+                                other => other.to_string(),
+                            })
+                            .collect()
+                    })
+                    .collect();
+                if rows.is_empty() || rows.iter().all(|row| row.is_empty()) {
+                    return Err(ToolError::InvalidParameters(
+                        "The rows param is required for insert_table, as an array of rows of cell text"
+                            .to_string(),
+                    ));
+                }
+                Ok(Edit::InsertTable {
+                    target,
+                    position,
+                    rows,
+                })
+            }
+            other => Err(ToolError::InvalidParameters(format!(
+                "Unknown operation {}, use outline, insert, replace, delete, apply_style or insert_table",
+                other
+            ))),
+        }
+    }
+}
+
+/// The requests for an edit, with what it removes and inserts for the preview
+#[derive(Debug)]
+pub struct Plan {
+    pub target: String,
+    pub removed: String,
+    pub inserted: String,
+    pub requests: Vec<Value>,
+}
+
+impl Plan {
+    fn new(target: String) -> Self {
+        Self {
+            target,
+            removed: String::new(),
+            inserted: String::new(),
+            requests: Vec::new(),
+        }
+    }
+
+    fn delete(&mut self, doc: &Document, start: i64, end: i64) {
+        if start < end {
+            self.removed.push_str(&doc.text_in(start, end));
+            self.requests.push(json!({"deleteContentRange": {
+                "range": {"startIndex": start, "endIndex": end}
+            }}));
+        }
+    }
+
+    fn insert_text(&mut self, index: i64, text: &str) {
+        self.inserted.push_str(text);
+        self.requests.push(json!({"insertText": {
+            "location": {"index": index},
+            "text": text,
+        }}));
+    }
+
+    fn paragraph_style(&mut self, start: i64, end: i64, style: &str) {
+        self.requests.push(json!({"updateParagraphStyle": {
+            "range": {"startIndex": start, "endIndex": end},
+            "paragraphStyle": {"namedStyleType": style},
+            "fields": "namedStyleType",
+        }}));
+    }
+
+    /// Insert text as new paragraphs at a paragraph boundary. The final newline of the body
+    /// can't be moved, so appending goes before it instead.
+    fn insert_paragraphs(&mut self, doc: &Document, index: i64, text: &str, style: &str) {
+        let text = text.trim_end_matches('\n');
+        if index < doc.end {
+            self.insert_text(index, &format!("{}\n", text));
+            self.paragraph_style(index, index + len16(text) + 1, style);
+        } else {
+            self.insert_text(doc.end - 1, &format!("\n{}", text));
+            self.paragraph_style(doc.end, doc.end + len16(text), style);
+        }
+    }
+}
+
+/// Work out the batchUpdate requests for an edit against the current document
+pub fn plan(doc: &Document, edit: &Edit) -> Result<Plan, ToolError> {
+    match edit {
+        Edit::Insert {
+            target,
+            position,
+            text,
+            style,
+        } => {
+            let style = style.as_deref().unwrap_or("NORMAL_TEXT");
+            if let Target::Anchor {
+                text: anchor,
+                occurrence,
+            } = target
+            {
+                let (start, end, _) = doc.find_anchor(anchor, *occurrence)?;
+                let mut plan = Plan::new(format!("text '{}'", anchor));
+                match position {
+                    Position::Before => plan.insert_text(start, text),
+                    Position::After => plan.insert_text(end, text),
+                    Position::EndOfSection => {
+                        return Err(ToolError::InvalidParameters(
+                            "end_of_section only applies to a heading".to_string(),
+                        ))
+                    }
+                }
+                return Ok(plan);
+            }
+            let (index, description) = boundary(doc, target, *position)?;
+            let mut plan = Plan::new(description);
+            plan.insert_paragraphs(doc, index, text, style);
+            Ok(plan)
+        }
+        Edit::Replace {
+            target,
+            text,
+            style,
+        } => match target {
+            Target::Anchor {
+                text: anchor,
+                occurrence,
+            } => {
+                let (start, end, _) = doc.find_anchor(anchor, *occurrence)?;
+                let mut plan = Plan::new(format!("text '{}'", anchor));
+                plan.delete(doc, start, end);
+                plan.insert_text(start, text);
+                Ok(plan)
+            }
+            Target::Heading(heading) => {
+                let heading = doc.find_heading(heading)?;
+                let (start, end) = (heading.end, doc.section_end(heading));
+                let style = style.as_deref().unwrap_or("NORMAL_TEXT");
+                let mut plan = Plan::new(format!("the section under '{}'", heading.label()));
+                if start >= doc.end {
+                    plan.insert_paragraphs(doc, doc.end, text, style);
+                } else if end >= doc.end {
+                    // The section runs to the end of the body, so its final newline stays as
+                    // the paragraph the new text goes into
+                    let text = text.trim_end_matches('\n');
+                    plan.delete(doc, start, doc.end - 1);
+                    plan.insert_text(start, text);
+                    plan.paragraph_style(start, start + len16(text), style);
+                } else {
+                    plan.delete(doc, start, end);
+                    plan.insert_paragraphs(doc, start, text, style);
+                }
+                Ok(plan)
+            }
+            Target::End => unreachable!("replace always has a target"),
+        },
+        Edit::Delete { target } => {
+            let (start, end, description) = match target {
+                Target::Anchor { text, occurrence } => {
+                    let (start, end, _) = doc.find_anchor(text, *occurrence)?;
+                    (start, end, format!("text '{}'", text))
+                }
+                Target::Heading(heading) => {
+                    let heading = doc.find_heading(heading)?;
+                    let end = doc.section_end(heading).min(doc.end - 1);
+                    (
+                        heading.start,
+                        end,
+                        format!("heading '{}' and its section", heading.label()),
+                    )
+                }
+                Target::End => unreachable!("delete always has a target"),
+            };
+            if start >= end {
+                return Err(ToolError::InvalidParameters(format!(
+                    "There is nothing to delete for {}",
+                    description
+                )));
+            }
+            let mut plan = Plan::new(description);
+            plan.delete(doc, start, end);
+            Ok(plan)
+        }
+        Edit::ApplyStyle { target, style } => {
+            let (start, end, description) = match target {
+                Target::Anchor { text, occurrence } => {
+                    let (start, end, _) = doc.find_anchor(text, *occurrence)?;
+                    (start, end, format!("the paragraph containing '{}'", text))
+                }
+                Target::Heading(heading) => {
+                    let heading = doc.find_heading(heading)?;
+                    (
+                        heading.start,
+                        heading.end,
+                        format!("heading '{}'", heading.label()),
+                    )
+                }
+                Target::End => unreachable!("apply_style always has a target"),
+            };
+            let mut plan = Plan::new(format!("{} becomes {}", description, style));
+            plan.paragraph_style(start, end, style);
+            Ok(plan)
+        }
+        Edit::InsertTable {
+            target,
+            position,
+            rows,
+        } => {
+            let (index, description) = match target {
+                Target::Anchor { text, occurrence } => {
+                    let (_, _, paragraph) = doc.find_anchor(text, *occurrence)?;
+                    (
+                        paragraph.end,
+                        format!("after the paragraph containing '{}'", text),
+                    )
+                }
+                _ => boundary(doc, target, *position)?,
+            };
+            let mut plan = Plan::new(description);
+            insert_table(&mut plan, index.min(doc.end), rows);
+            Ok(plan)
+        }
+    }
+}
+
+/// The paragraph boundary an insert goes to
+fn boundary(
+    doc: &Document,
+    target: &Target,
+    position: Position,
+) -> Result<(i64, String), ToolError> {
+    match target {
+        Target::Heading(heading) => {
+            let heading = doc.find_heading(heading)?;
+            let label = heading.label();
+            Ok(match position {
+                Position::Before => (heading.start, format!("before heading '{}'", label)),
+                Position::After => (heading.end, format!("after heading '{}'", label)),
+                Position::EndOfSection => (
+                    doc.section_end(heading),
+                    format!("at the end of the section under '{}'", label),
+                ),
+            })
+        }
+        _ => Ok((doc.end, "at the end of the document".to_string())),
+    }
+}
+
+/// Insert a table at a paragraph boundary and fill in its cells. The table goes in at the
+/// newline ending the previous paragraph, after which the first cell's paragraph is 4 indices
+/// in, and each row takes one index plus two for each cell. Cells are filled last to first so
+/// earlier indices don't shift.
+fn insert_table(plan: &mut Plan, boundary: i64, rows: &[Vec<String>]) {
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(1).max(1);
+    let index = (boundary - 1).max(1);
+    plan.requests.push(json!({"insertTable": {
+        "rows": rows.len(),
+        "columns": columns,
+        "location": {"index": index},
+    }}));
+
+    let row_width = 2 * columns as i64 + 1;
+    for (r, row) in rows.iter().enumerate().rev() {
+        for (c, cell) in row.iter().enumerate().rev() {
+            if cell.is_empty() {
+                continue;
+            }
+            let cell_index = index + 4 + r as i64 * row_width + 2 * c as i64;
+            plan.requests.push(json!({"insertText": {
+                "location": {"index": cell_index},
+                "text": cell,
+            }}));
+        }
+    }
+    plan.inserted = rows
+        .iter()
+        .map(|row| format!("| {} |", row.join(" | ")))
+        .collect::<Vec<_>>()
+        .join("\n");
+}
+
+/// Describe a planned edit without applying it
+pub fn preview(doc: &Document, operation: &str, plan: &Plan) -> String {
+    let mut out = format!(
+        "Preview of {} in '{}' (revision {}), targeting {}.\n",
+        operation, doc.title, doc.revision_id, plan.target
+    );
+    if !plan.removed.is_empty() {
+        let mut removed: String = plan.removed.chars().take(PREVIEW_CHARS).collect();
+        if removed.len() < plan.removed.len() {
+            removed.push_str("\n[...]");
+        }
+        out.push_str(&format!("\nRemoves:\n{}\n", removed.trim_end()));
+    }
+    if !plan.inserted.is_empty() {
+        out.push_str(&format!("\nInserts:\n{}\n", plan.inserted.trim_end()));
+    }
+    out.push_str(&format!(
+        "\nRequests:\n{}\n\nNothing has been changed yet. To apply this edit, call edit_doc again with the same parameters, dryRun false and revisionId {}.",
+        serde_json::to_string_pretty(&plan.requests).unwrap_or_default(),
+        doc.revision_id
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn paragraph(start: i64, text: &str, style: &str) -> Value {
+        let end = start + len16(text);
+        json!({
+            "startIndex": start,
+            "endIndex": end,
+            "paragraph": {
+                "elements": [{"startIndex": start, "endIndex": end, "textRun": {"content": text}}],
+                "paragraphStyle": {"namedStyleType": style},
+            }
+        })
+    }
+
+    /// Title, an intro with an emoji before the anchor, then two sections, the last running
+    /// to the end of the body
+    fn sample() -> Document {
+        let value = json!({
+            "title": "Launch",
+            "revisionId": "rev1",
+            "body": {"content": [
+                {"endIndex": 1, "sectionBreak": {}},
+                paragraph(1, "Launch\n", "TITLE"),
+                paragraph(8, "Intro\n", "HEADING_1"),
+                paragraph(14, "🚀 Ship TODO soon\n", "NORMAL_TEXT"),
+                paragraph(32, "Risks\n", "HEADING_1"),
+                paragraph(38, "None yet\n", "NORMAL_TEXT"),
+            ]}
+        });
+        Document::parse(&value).unwrap()
+    }
+
+    fn edit(params: Value) -> Edit {
+        Edit::from_params(&params).unwrap()
+    }
+
+    #[test]
+    fn test_locates_headings_sections_and_anchors() {
+        let doc = sample();
+        assert_eq!(doc.end, 47);
+        assert!(doc.outline().contains("- Intro (HEADING_1, index 8)"));
+
+        let intro = doc.find_heading("intro").unwrap();
+        assert_eq!((intro.start, intro.end), (8, 14));
+        assert_eq!(doc.section_end(intro), 32);
+        // The title is the top level heading, so its section is the whole document
+        assert_eq!(doc.section_end(doc.find_heading("Launch").unwrap()), 47);
+
+        // The emoji is two UTF-16 units
+        let (start, end, _) = doc.find_anchor("TODO", 1).unwrap();
+        assert_eq!((start, end), (22, 26));
+        assert_eq!(doc.text_in(start, end), "TODO");
+        assert!(doc.find_anchor("TODO", 2).is_err());
+        assert!(doc.find_heading("Missing").is_err());
+    }
+
+    #[test]
+    fn test_plans_section_and_anchor_edits() {
+        let doc = sample();
+
+        // Replacing a section in the middle removes its body and inserts whole paragraphs
+        let plan = super::plan(
+            &doc,
+            &edit(json!({"operation": "replace", "heading": "Intro", "text": "New intro"})),
+        )
+        .unwrap();
+        assert_eq!(plan.removed, "🚀 Ship TODO soon\n");
+        assert_eq!(
+            plan.requests,
+            vec![
+                json!({"deleteContentRange": {"range": {"startIndex": 14, "endIndex": 32}}}),
+                json!({"insertText": {"location": {"index": 14}, "text": "New intro\n"}}),
+                json!({"updateParagraphStyle": {
+                    "range": {"startIndex": 14, "endIndex": 24},
+                    "paragraphStyle": {"namedStyleType": "NORMAL_TEXT"},
+                    "fields": "namedStyleType",
+                }}),
+            ]
+        );
+
+        // The last section keeps the body's final newline
+        let plan = super::plan(
+            &doc,
+            &edit(json!({"operation": "replace", "heading": "Risks", "text": "Many"})),
+        )
+        .unwrap();
+        assert_eq!(
+            plan.requests[0],
+            json!({"deleteContentRange": {"range": {"startIndex": 38, "endIndex": 46}}})
+        );
+        assert_eq!(
+            plan.requests[1],
+            json!({"insertText": {"location": {"index": 38}, "text": "Many"}})
+        );
+
+        // Appending goes before the final newline
+        let plan =
+            super::plan(&doc, &edit(json!({"operation": "insert", "text": "Done"}))).unwrap();
+        assert_eq!(
+            plan.requests[0],
+            json!({"insertText": {"location": {"index": 46}, "text": "\nDone"}})
+        );
+
+        let plan = super::plan(
+            &doc,
+            &edit(json!({"operation": "replace", "anchor": "TODO", "text": "today"})),
+        )
+        .unwrap();
+        assert_eq!(plan.removed, "TODO");
+        assert_eq!(
+            plan.requests[1],
+            json!({"insertText": {"location": {"index": 22}, "text": "today"}})
+        );
+
+        let plan = super::plan(
+            &doc,
+            &edit(json!({"operation": "insert_table", "heading": "Intro", "position": "end_of_section",
+                         "rows": [["Name", "Owner"], ["API", "Sam"]]})),
+        )
+        .unwrap();
+        assert_eq!(
+            plan.requests[0],
+            json!({"insertTable": {"rows": 2, "columns": 2, "location": {"index": 31}}})
+        );
+        // Filled from the last cell back: row 1 starts 5 indices after row 0
+        let cells: Vec<(i64, &str)> = plan.requests[1..]
+            .iter()
+            .map(|r| {
+                (
+                    r["insertText"]["location"]["index"].as_i64().unwrap(),
+                    r["insertText"]["text"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            cells,
+            vec![(42, "Sam"), (40, "API"), (37, "Owner"), (35, "Name")]
+        );
+
+        assert!(Edit::from_params(&json!({"operation": "delete"})).is_err());
+        assert!(Edit::from_params(
+            &json!({"operation": "apply_style", "heading": "Intro", "style": "BIG"})
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_applies_with_the_previewed_revision() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/documents/doc1:batchUpdate"))
+            .and(body_partial_json(json!({
+                "writeControl": {"requiredRevisionId": "rev1"},
+                "requests": [{"updateParagraphStyle": {"paragraphStyle": {"namedStyleType": "HEADING_2"}}}],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "documentId": "doc1",
+                "writeControl": {"requiredRevisionId": "rev2"},
+            })))
+            .mount(&server)
+            .await;
+
+        let doc = sample();
+        let edit =
+            edit(json!({"operation": "apply_style", "heading": "Risks", "style": "HEADING_2"}));
+        let plan = super::plan(&doc, &edit).unwrap();
+        assert!(preview(&doc, "apply_style", &plan).contains("revisionId rev1"));
+
+        let api = DocsApi::with_base_url(&server.uri());
+        let revision = api
+            .batch_update("token", "doc1", &plan.requests, &doc.revision_id)
+            .await
+            .unwrap();
+        assert_eq!(revision, "rev2");
+    }
+}
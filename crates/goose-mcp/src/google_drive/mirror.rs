@@ -0,0 +1,835 @@
+//! A local mirror of selected Drive folders and shared drives.
+//!
+//! The first sync lists every file under the mirrored roots and exports it into the mirror
+//! directory. Later syncs read the Drive changes feed from the saved page token, so only
+//! files that changed since the last sync are exported again.
+//!
+//! Configured with environment variables:
+//! - `GOOGLE_DRIVE_MIRROR_FOLDERS`: comma separated IDs of folders to mirror
+//! - `GOOGLE_DRIVE_MIRROR_DRIVES`: comma separated IDs of shared drives to mirror
+//! - `GOOGLE_DRIVE_MIRROR_DIR`: where to keep the mirror, defaults to the goose cache directory
+//! - `GOOGLE_DRIVE_MIRROR_INTERVAL`: seconds between background syncs, default 300, 0 disables
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    env, fmt, fs, io,
+    path::PathBuf,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use etcetera::{choose_app_strategy, AppStrategy};
+use google_drive3::{
+    api::{Change, File},
+    hyper_rustls::HttpsConnector,
+    hyper_util::client::legacy::connect::HttpConnector,
+    DriveHub,
+};
+use http_body_util::BodyExt;
+use mcp_core::handler::ToolError;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::GOOGLE_DRIVE_SCOPES;
+
+type Hub = DriveHub<HttpsConnector<HttpConnector>>;
+
+const FOLDER_MIME_TYPE: &str = "application/vnd.google-apps.folder";
+const FILE_FIELDS: &str = "id, name, mimeType, modifiedTime, parents, driveId, trashed";
+const STATE_FILE: &str = "state.json";
+/// Page token key for the folders in My Drive, shared drives use their ID
+const USER_CORPUS: &str = "user";
+const DEFAULT_INTERVAL_SECS: u64 = 300;
+
+#[derive(Debug, Clone)]
+pub struct MirrorConfig {
+    pub folders: Vec<String>,
+    pub drives: Vec<String>,
+    pub dir: PathBuf,
+    /// How often to sync in the background, None to only sync on request
+    pub interval: Option<Duration>,
+}
+
+impl MirrorConfig {
+    /// Read the mirror settings, or None if no folders or drives are configured
+    pub fn from_env() -> Option<Self> {
+        let ids = |name: &str| -> Vec<String> {
+            env::var(name)
+                .unwrap_or_default()
+                .split(',')
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty())
+                .collect()
+        };
+        let folders = ids("GOOGLE_DRIVE_MIRROR_FOLDERS");
+        let drives = ids("GOOGLE_DRIVE_MIRROR_DRIVES");
+        if folders.is_empty() && drives.is_empty() {
+            return None;
+        }
+
+        let dir = match env::var("GOOGLE_DRIVE_MIRROR_DIR") {
+            Ok(dir) => PathBuf::from(shellexpand::tilde(&dir).as_ref()),
+            Err(_) => choose_app_strategy(crate::APP_STRATEGY.clone())
+                .map(|strategy| strategy.in_cache_dir("google_drive"))
+                .unwrap_or_else(|_| env::temp_dir().join("goose_google_drive")),
+        };
+        let interval = env::var("GOOGLE_DRIVE_MIRROR_INTERVAL")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_INTERVAL_SECS);
+
+        Some(Self {
+            folders,
+            drives,
+            dir,
+            interval: (interval > 0).then(|| Duration::from_secs(interval)),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirroredFile {
+    pub name: String,
+    pub mime_type: String,
+    pub modified_time: DateTime<Utc>,
+    /// Name of the exported copy inside the mirror directory
+    pub file: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct State {
+    /// Changes page token for each mirrored corpus
+    page_tokens: HashMap<String, String>,
+    /// Mirrored folders in My Drive, including subfolders of the configured roots
+    folders: HashSet<String>,
+    files: HashMap<String, MirroredFile>,
+    /// Files whose download failed, retried on every sync since the changes feed has moved
+    /// past them
+    #[serde(default)]
+    failed: HashSet<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub updated: usize,
+    pub removed: usize,
+    pub skipped: usize,
+    pub failed: Vec<String>,
+}
+
+impl fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} updated, {} removed, {} skipped (unsupported type)",
+            self.updated, self.removed, self.skipped
+        )?;
+        if !self.failed.is_empty() {
+            write!(f, ", {} failed:", self.failed.len())?;
+            for failure in &self.failed {
+                write!(f, "\n- {}", failure)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The mirror is shared between the background sync and tool calls. A sync works on its own
+/// copy of the state and only locks to publish it, so reads never wait on Drive.
+pub struct Mirror {
+    config: MirrorConfig,
+    state: Mutex<State>,
+    /// Held for the whole of a sync so that two syncs never run at once
+    syncing: tokio::sync::Mutex<()>,
+}
+
+impl Mirror {
+    /// Open the mirror directory, picking up the state of the previous sync if there is one
+    pub fn open(config: MirrorConfig) -> io::Result<Self> {
+        fs::create_dir_all(&config.dir)?;
+        let mut state: State = fs::read_to_string(config.dir.join(STATE_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        // Files deleted locally are exported again on the next change
+        state
+            .files
+            .retain(|_, entry| config.dir.join(&entry.file).exists());
+        Ok(Self {
+            config,
+            state: Mutex::new(state),
+            syncing: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    pub fn config(&self) -> &MirrorConfig {
+        &self.config
+    }
+
+    pub fn files(&self) -> Vec<(String, MirroredFile)> {
+        self.state()
+            .files
+            .iter()
+            .map(|(id, entry)| (id.clone(), entry.clone()))
+            .collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<MirroredFile> {
+        self.state().files.get(id).cloned()
+    }
+
+    pub fn path_of(&self, entry: &MirroredFile) -> PathBuf {
+        self.config.dir.join(&entry.file)
+    }
+
+    /// The mirrored content of a file, if it is in the mirror
+    pub fn read(&self, id: &str) -> Option<String> {
+        let entry = self.get(id)?;
+        fs::read_to_string(self.path_of(&entry)).ok()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Bring the mirror up to date, with a full listing for corpora that were never synced
+    /// and the changes feed for the rest
+    pub async fn sync(&self, hub: &Hub) -> Result<SyncReport, ToolError> {
+        let _syncing = self.syncing.lock().await;
+        let mut pass = SyncPass {
+            config: &self.config,
+            state: self.state().clone(),
+        };
+        let mut report = SyncReport::default();
+
+        if !pass.state.failed.is_empty() {
+            pass.retry_failed(hub, &mut report).await;
+            self.publish(&pass.state)?;
+        }
+
+        let mut corpora: Vec<Option<String>> = Vec::new();
+        if !self.config.folders.is_empty() {
+            corpora.push(None);
+        }
+        corpora.extend(self.config.drives.iter().cloned().map(Some));
+
+        for drive in corpora {
+            let key = drive.clone().unwrap_or_else(|| USER_CORPUS.to_string());
+            let token = match pass.state.page_tokens.get(&key).cloned() {
+                Some(token) => {
+                    pass.apply_changes(hub, drive.as_deref(), token, &mut report)
+                        .await?
+                }
+                None => {
+                    // Take the token before listing, so edits made during the listing
+                    // show up in the next sync
+                    let token = start_page_token(hub, drive.as_deref()).await?;
+                    pass.full_sync(hub, drive.as_deref(), &mut report).await?;
+                    token
+                }
+            };
+            pass.state.page_tokens.insert(key, token);
+            self.publish(&pass.state)?;
+        }
+        Ok(report)
+    }
+
+    /// Make the state of a sync visible to readers and save it for the next start
+    fn publish(&self, state: &State) -> Result<(), ToolError> {
+        *self.state() = state.clone();
+        let path = self.config.dir.join(STATE_FILE);
+        let temp = path.with_extension("json.tmp");
+        serde_json::to_string_pretty(state)
+            .map_err(io::Error::from)
+            .and_then(|content| fs::write(&temp, content))
+            .and_then(|_| fs::rename(temp, path))
+            .map_err(|e| {
+                ToolError::ExecutionError(format!("Failed to save the mirror state, {}.", e))
+            })
+    }
+}
+
+/// A sync in progress, which updates its own copy of the mirror state
+struct SyncPass<'a> {
+    config: &'a MirrorConfig,
+    state: State,
+}
+
+impl SyncPass<'_> {
+    async fn full_sync(
+        &mut self,
+        hub: &Hub,
+        drive: Option<&str>,
+        report: &mut SyncReport,
+    ) -> Result<(), ToolError> {
+        match drive {
+            Some(drive) => {
+                for file in list_files(hub, "trashed = false", Some(drive)).await? {
+                    if file.mime_type.as_deref() != Some(FOLDER_MIME_TYPE) {
+                        self.fetch(hub, file, report).await;
+                    }
+                }
+                Ok(())
+            }
+            None => {
+                for folder in self.config.folders.clone() {
+                    self.sync_folder(hub, folder, report).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Mirror everything under a folder in My Drive
+    async fn sync_folder(
+        &mut self,
+        hub: &Hub,
+        folder: String,
+        report: &mut SyncReport,
+    ) -> Result<(), ToolError> {
+        let mut queue = VecDeque::from([folder]);
+        while let Some(folder) = queue.pop_front() {
+            self.state.folders.insert(folder.clone());
+            let query = format!("'{}' in parents and trashed = false", folder);
+            for file in list_files(hub, &query, None).await? {
+                if file.mime_type.as_deref() == Some(FOLDER_MIME_TYPE) {
+                    if let Some(id) = file.id {
+                        if !self.state.folders.contains(&id) {
+                            queue.push_back(id);
+                        }
+                    }
+                } else {
+                    self.fetch(hub, file, report).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply every change since the page token, returning the token for the next sync
+    async fn apply_changes(
+        &mut self,
+        hub: &Hub,
+        drive: Option<&str>,
+        mut page_token: String,
+        report: &mut SyncReport,
+    ) -> Result<String, ToolError> {
+        loop {
+            let mut call = hub
+                .changes()
+                .list(&page_token)
+                .param(
+                    "fields",
+                    &format!(
+                        "nextPageToken, newStartPageToken, changes(fileId, removed, file({}))",
+                        FILE_FIELDS
+                    ),
+                )
+                .page_size(1000)
+                .include_removed(true)
+                .supports_all_drives(true)
+                .include_items_from_all_drives(true)
+                .clear_scopes()
+                .add_scope(GOOGLE_DRIVE_SCOPES);
+            if let Some(drive) = drive {
+                call = call.drive_id(drive);
+            }
+            let (_, list) = call.doit().await.map_err(|e| {
+                ToolError::ExecutionError(format!("Failed to list google drive changes, {}.", e))
+            })?;
+
+            for change in list.changes.unwrap_or_default() {
+                self.apply_change(hub, drive, change, report).await?;
+            }
+
+            match (list.next_page_token, list.new_start_page_token) {
+                (Some(next), _) => page_token = next,
+                (None, Some(new_start)) => return Ok(new_start),
+                (None, None) => {
+                    return Err(ToolError::ExecutionError(
+                        "Google drive changes list returned no page token.".to_string(),
+                    ))
+                }
+            }
+        }
+    }
+
+    async fn apply_change(
+        &mut self,
+        hub: &Hub,
+        drive: Option<&str>,
+        change: Change,
+        report: &mut SyncReport,
+    ) -> Result<(), ToolError> {
+        let Some(id) = change.file_id else {
+            return Ok(());
+        };
+        let file = change
+            .file
+            .filter(|file| !change.removed.unwrap_or(false) && !file.trashed.unwrap_or(false))
+            .filter(|file| self.in_scope(file, drive));
+
+        match file {
+            Some(file) if file.mime_type.as_deref() == Some(FOLDER_MIME_TYPE) => {
+                // A folder created in or moved into a mirrored folder
+                if drive.is_none() && !self.state.folders.contains(&id) {
+                    self.sync_folder(hub, id, report).await?;
+                }
+            }
+            Some(file) => self.fetch(hub, file, report).await,
+            None => self.remove(&id, report),
+        }
+        Ok(())
+    }
+
+    /// Download the files that failed in an earlier sync again, or drop them if they were
+    /// deleted or moved out of the mirror since
+    async fn retry_failed(&mut self, hub: &Hub, report: &mut SyncReport) {
+        for id in self.state.failed.clone() {
+            let result = hub
+                .files()
+                .get(&id)
+                .param("fields", FILE_FIELDS)
+                .supports_all_drives(true)
+                .clear_scopes()
+                .add_scope(GOOGLE_DRIVE_SCOPES)
+                .doit()
+                .await;
+            match result {
+                Ok((_, file)) => {
+                    let drive = file.drive_id.clone();
+                    let mirrored = !file.trashed.unwrap_or(false)
+                        && match drive.as_deref() {
+                            Some(drive) => self.config.drives.iter().any(|d| d == drive),
+                            None => self.in_scope(&file, None),
+                        };
+                    if mirrored {
+                        self.fetch(hub, file, report).await;
+                    } else {
+                        self.remove(&id, report);
+                    }
+                }
+                Err(e) if is_not_found(&e) => self.remove(&id, report),
+                Err(e) => report.failed.push(format!("{}: {}", id, e)),
+            }
+        }
+    }
+
+    fn in_scope(&self, file: &File, drive: Option<&str>) -> bool {
+        match drive {
+            Some(drive) => file.drive_id.as_deref() == Some(drive),
+            None => file
+                .parents
+                .iter()
+                .flatten()
+                .any(|parent| self.state.folders.contains(parent)),
+        }
+    }
+
+    fn remove(&mut self, id: &str, report: &mut SyncReport) {
+        self.state.folders.remove(id);
+        self.state.failed.remove(id);
+        if let Some(entry) = self.state.files.remove(id) {
+            let _ = fs::remove_file(self.config.dir.join(&entry.file));
+            report.removed += 1;
+        }
+    }
+
+    /// Export a file into the mirror unless the mirrored copy is already current. Failures
+    /// are recorded in the report and retried on the next sync rather than stopping this one.
+    async fn fetch(&mut self, hub: &Hub, file: File, report: &mut SyncReport) {
+        let (Some(id), Some(mime_type)) = (file.id, file.mime_type) else {
+            return;
+        };
+        let name = file.name.unwrap_or_default();
+        let modified_time = file.modified_time.unwrap_or_else(Utc::now);
+        if self
+            .state
+            .files
+            .get(&id)
+            .is_some_and(|entry| entry.modified_time == modified_time)
+        {
+            self.state.failed.remove(&id);
+            return;
+        }
+        let Some((export_type, extension)) = local_format(&mime_type) else {
+            self.state.failed.remove(&id);
+            report.skipped += 1;
+            return;
+        };
+
+        let entry = MirroredFile {
+            name,
+            mime_type,
+            modified_time,
+            file: format!("{}.{}", id, extension),
+        };
+        let written = match download(hub, &id, export_type).await {
+            Ok(content) => fs::write(self.config.dir.join(&entry.file), strip_images(&content))
+                .map_err(|e| ToolError::ExecutionError(e.to_string())),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            tracing::warn!("Failed to mirror google drive file {}: {}", id, e);
+            report
+                .failed
+                .push(format!("{} ({}): {}", entry.name, id, e));
+            self.state.failed.insert(id);
+            return;
+        }
+        self.state.failed.remove(&id);
+        self.state.files.insert(id, entry);
+        report.updated += 1;
+    }
+}
+
+/// The export format for Google Workspace files, or None to download other text files as is,
+/// along with the extension of the mirrored copy. Binary files are not mirrored.
+fn local_format(mime_type: &str) -> Option<(Option<&'static str>, &'static str)> {
+    match mime_type {
+        "application/vnd.google-apps.document" => Some((Some("text/markdown"), "md")),
+        "application/vnd.google-apps.spreadsheet" => Some((Some("text/csv"), "csv")),
+        "application/vnd.google-apps.presentation" => Some((Some("text/plain"), "txt")),
+        "text/markdown" => Some((None, "md")),
+        "text/csv" => Some((None, "csv")),
+        "application/json" => Some((None, "json")),
+        m if m.starts_with("text/") => Some((None, "txt")),
+        _ => None,
+    }
+}
+
+fn is_not_found(error: &google_drive3::Error) -> bool {
+    match error {
+        google_drive3::Error::BadRequest(body) => body["error"]["code"] == 404,
+        google_drive3::Error::Failure(response) => response.status() == 404,
+        _ => false,
+    }
+}
+
+/// Exported docs embed images as base64, which would dwarf the text
+fn strip_images(content: &str) -> String {
+    let image_regex = Regex::new(r"<data:image/[a-zA-Z0-9.+-]+;base64,[^>]+>").unwrap();
+    image_regex.replace_all(content, "").to_string()
+}
+
+async fn start_page_token(hub: &Hub, drive: Option<&str>) -> Result<String, ToolError> {
+    let mut call = hub
+        .changes()
+        .get_start_page_token()
+        .supports_all_drives(true)
+        .clear_scopes()
+        .add_scope(GOOGLE_DRIVE_SCOPES);
+    if let Some(drive) = drive {
+        call = call.drive_id(drive);
+    }
+    call.doit()
+        .await
+        .map_err(|e| {
+            ToolError::ExecutionError(format!(
+                "Failed to get google drive start page token, {}.",
+                e
+            ))
+        })?
+        .1
+        .start_page_token
+        .ok_or_else(|| {
+            ToolError::ExecutionError("Google drive returned no start page token.".to_string())
+        })
+}
+
+async fn list_files(hub: &Hub, query: &str, drive: Option<&str>) -> Result<Vec<File>, ToolError> {
+    let mut files = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let mut call = hub
+            .files()
+            .list()
+            .q(query)
+            .param("fields", &format!("nextPageToken, files({})", FILE_FIELDS))
+            .page_size(1000)
+            .supports_all_drives(true)
+            .include_items_from_all_drives(true)
+            .clear_scopes()
+            .add_scope(GOOGLE_DRIVE_SCOPES);
+        if let Some(drive) = drive {
+            call = call.corpora("drive").drive_id(drive);
+        }
+        if let Some(token) = &page_token {
+            call = call.page_token(token);
+        }
+        let (_, list) = call.doit().await.map_err(|e| {
+            ToolError::ExecutionError(format!(
+                "Failed to execute google drive list query '{}', {}.",
+                query, e
+            ))
+        })?;
+        files.extend(list.files.unwrap_or_default());
+        match list.next_page_token {
+            Some(token) => page_token = Some(token),
+            None => return Ok(files),
+        }
+    }
+}
+
+async fn download(hub: &Hub, id: &str, export_type: Option<&str>) -> Result<String, ToolError> {
+    let response = match export_type {
+        Some(export_type) => {
+            hub.files()
+                .export(id, export_type)
+                .param("alt", "media")
+                .clear_scopes()
+                .add_scope(GOOGLE_DRIVE_SCOPES)
+                .doit()
+                .await
+        }
+        None => hub
+            .files()
+            .get(id)
+            .param("alt", "media")
+            .supports_all_drives(true)
+            .clear_scopes()
+            .add_scope(GOOGLE_DRIVE_SCOPES)
+            .doit()
+            .await
+            .map(|r| r.0),
+    }
+    .map_err(|e| ToolError::ExecutionError(format!("Failed to download, {}.", e)))?;
+
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to read the download, {}.", e)))?
+        .to_bytes();
+    String::from_utf8(body.to_vec())
+        .map_err(|_| ToolError::ExecutionError("The file is not valid UTF-8.".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use google_drive3::{common::NoToken, hyper_rustls, hyper_util};
+    use serde_json::{json, Value};
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const DOC: &str = "application/vnd.google-apps.document";
+
+    fn test_hub(server: &MockServer) -> Hub {
+        let client =
+            hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
+                .build(
+                    hyper_rustls::HttpsConnectorBuilder::new()
+                        .with_native_roots()
+                        .unwrap()
+                        .https_or_http()
+                        .enable_http1()
+                        .build(),
+                );
+        let mut hub = DriveHub::new(client, NoToken);
+        hub.base_url(format!("{}/drive/v3/", server.uri()));
+        hub
+    }
+
+    fn test_config(dir: &tempfile::TempDir) -> MirrorConfig {
+        MirrorConfig {
+            folders: vec!["root".to_string()],
+            drives: vec![],
+            dir: dir.path().to_path_buf(),
+            interval: None,
+        }
+    }
+
+    async fn mock_json(server: &MockServer, route: &str, query: (&str, &str), body: Value) {
+        Mock::given(method("GET"))
+            .and(path(route))
+            .and(query_param(query.0, query.1))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(server)
+            .await;
+    }
+
+    async fn mock_export(server: &MockServer, id: &str, body: &str) {
+        Mock::given(method("GET"))
+            .and(path(format!("/drive/v3/files/{}/export", id)))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(server)
+            .await;
+    }
+
+    /// A folder with a doc, a binary file and a subfolder holding a plain text file
+    async fn mock_initial_tree(server: &MockServer) {
+        mock_json(
+            server,
+            "/drive/v3/changes/startPageToken",
+            ("supportsAllDrives", "true"),
+            json!({"startPageToken": "10"}),
+        )
+        .await;
+        mock_json(
+            server,
+            "/drive/v3/files",
+            ("q", "'root' in parents and trashed = false"),
+            json!({"files": [
+                {"id": "doc1", "name": "Plan", "mimeType": DOC,
+                 "modifiedTime": "2025-01-01T00:00:00Z", "parents": ["root"]},
+                {"id": "img1", "name": "logo.png", "mimeType": "image/png",
+                 "modifiedTime": "2025-01-01T00:00:00Z", "parents": ["root"]},
+                {"id": "sub", "name": "Notes", "mimeType": FOLDER_MIME_TYPE, "parents": ["root"]},
+            ]}),
+        )
+        .await;
+        mock_json(
+            server,
+            "/drive/v3/files",
+            ("q", "'sub' in parents and trashed = false"),
+            json!({"files": [
+                {"id": "txt1", "name": "todo.txt", "mimeType": "text/plain",
+                 "modifiedTime": "2025-01-02T00:00:00Z", "parents": ["sub"]},
+            ]}),
+        )
+        .await;
+        mock_export(
+            server,
+            "doc1",
+            "# Plan\n<data:image/png;base64,AAAA>\nShip it",
+        )
+        .await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v3/files/txt1"))
+            .and(query_param("alt", "media"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("buy milk"))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_initial_sync_mirrors_folder_tree() {
+        let server = MockServer::start().await;
+        mock_initial_tree(&server).await;
+        let dir = tempfile::tempdir().unwrap();
+
+        let mirror = Mirror::open(test_config(&dir)).unwrap();
+        let report = mirror.sync(&test_hub(&server)).await.unwrap();
+        assert_eq!(report.updated, 2);
+        assert_eq!(report.skipped, 1);
+        assert!(report.failed.is_empty(), "{}", report);
+
+        assert_eq!(mirror.read("doc1").unwrap(), "# Plan\n\nShip it");
+        assert_eq!(mirror.read("txt1").unwrap(), "buy milk");
+        let doc = mirror.get("doc1").unwrap();
+        assert_eq!(doc.file, "doc1.md");
+        assert_eq!(
+            doc.modified_time,
+            "2025-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+
+        // The page token and mirrored files survive a restart
+        let reopened = Mirror::open(test_config(&dir)).unwrap();
+        assert_eq!(reopened.state().page_tokens[USER_CORPUS], "10");
+        assert_eq!(reopened.files().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_incremental_sync_applies_changes() {
+        let server = MockServer::start().await;
+        mock_initial_tree(&server).await;
+        let dir = tempfile::tempdir().unwrap();
+        let hub = test_hub(&server);
+        let mirror = Mirror::open(test_config(&dir)).unwrap();
+        mirror.sync(&hub).await.unwrap();
+
+        // Two pages of changes: the doc is edited and an unrelated file changes, then the
+        // text file is deleted. Resetting drops the old export of the doc.
+        server.reset().await;
+        mock_json(
+            &server,
+            "/drive/v3/changes",
+            ("pageToken", "10"),
+            json!({"nextPageToken": "11", "changes": [
+                {"fileId": "doc1", "removed": false, "file":
+                    {"id": "doc1", "name": "Plan v2", "mimeType": DOC,
+                     "modifiedTime": "2025-02-01T00:00:00Z", "parents": ["root"]}},
+                {"fileId": "other", "removed": false, "file":
+                    {"id": "other", "name": "Elsewhere", "mimeType": DOC,
+                     "modifiedTime": "2025-02-01T00:00:00Z", "parents": ["unrelated"]}},
+            ]}),
+        )
+        .await;
+        mock_json(
+            &server,
+            "/drive/v3/changes",
+            ("pageToken", "11"),
+            json!({"newStartPageToken": "12", "changes": [
+                {"fileId": "txt1", "removed": true},
+            ]}),
+        )
+        .await;
+        mock_export(&server, "doc1", "# Plan v2").await;
+
+        let report = mirror.sync(&hub).await.unwrap();
+        assert_eq!((report.updated, report.removed), (1, 1));
+        assert_eq!(mirror.read("doc1").unwrap(), "# Plan v2");
+        assert_eq!(mirror.get("doc1").unwrap().name, "Plan v2");
+        assert!(mirror.get("txt1").is_none());
+        assert!(mirror.get("other").is_none());
+        assert!(!dir.path().join("txt1.txt").exists());
+        assert_eq!(mirror.state().page_tokens[USER_CORPUS], "12");
+
+        // Nothing changed since, so nothing is exported again
+        mock_json(
+            &server,
+            "/drive/v3/changes",
+            ("pageToken", "12"),
+            json!({"newStartPageToken": "12", "changes": []}),
+        )
+        .await;
+        let report = mirror.sync(&hub).await.unwrap();
+        assert_eq!((report.updated, report.removed), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_failed_downloads_are_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v3/files/txt1"))
+            .and(query_param("alt", "media"))
+            .respond_with(ResponseTemplate::new(500))
+            .with_priority(1)
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        mock_initial_tree(&server).await;
+        let dir = tempfile::tempdir().unwrap();
+        let hub = test_hub(&server);
+        let mirror = Mirror::open(test_config(&dir)).unwrap();
+
+        let report = mirror.sync(&hub).await.unwrap();
+        assert_eq!(report.failed.len(), 1, "{}", report);
+        assert!(mirror.get("txt1").is_none());
+        let reopened = Mirror::open(test_config(&dir)).unwrap();
+        assert!(reopened.state().failed.contains("txt1"));
+
+        // The changes feed has moved past the file, but it is downloaded again
+        mock_json(
+            &server,
+            "/drive/v3/changes",
+            ("pageToken", "10"),
+            json!({"newStartPageToken": "10", "changes": []}),
+        )
+        .await;
+        mock_json(
+            &server,
+            "/drive/v3/files/txt1",
+            ("fields", FILE_FIELDS),
+            json!({"id": "txt1", "name": "todo.txt", "mimeType": "text/plain",
+                   "modifiedTime": "2025-01-02T00:00:00Z", "parents": ["sub"]}),
+        )
+        .await;
+        let report = mirror.sync(&hub).await.unwrap();
+        assert_eq!(report.updated, 1, "{}", report);
+        assert_eq!(mirror.read("txt1").unwrap(), "buy milk");
+        assert!(mirror.state().failed.is_empty());
+    }
+}
@@ -1,9 +1,12 @@
+mod docs;
+mod mirror;
 mod oauth_pkce;
 pub mod storage;
 
 use anyhow::{Context, Error};
 use base64::Engine;
 use indoc::indoc;
+use mirror::{Mirror, MirrorConfig};
 use oauth_pkce::PkceOAuth2Client;
 use regex::Regex;
 use serde_json::{json, Value};
@@ -58,6 +61,8 @@ pub struct GoogleDriveRouter {
     drive: DriveHub<HttpsConnector<HttpConnector>>,
     sheets: Sheets<HttpsConnector<HttpConnector>>,
     credentials_manager: Arc<CredentialsManager>,
+    docs: docs::DocsApi,
+    mirror: Option<Arc<Mirror>>,
 }
 
 impl GoogleDriveRouter {
@@ -540,6 +545,78 @@ impl GoogleDriveRouter {
             }),
         );
 
+        let edit_doc_tool = Tool::new(
+            "edit_doc".to_string(),
+            indoc! {r#"
+                Edit a Google Doc in place, keeping its formatting and comments.
+                Edits target the section under a heading or a piece of anchor text:
+                - outline: list the document's headings
+                - insert: insert text before or after a heading or anchor, or at the end of a heading's section
+                  (without a heading or anchor, text is appended to the document)
+                - replace: replace the anchor text, or the content of the section under a heading
+                - delete: delete the anchor text, or a heading and its section
+                - apply_style: apply a named style to a heading or the paragraph containing an anchor
+                - insert_table: insert a table with the given rows of cell text
+                By default this returns a preview of the change without applying it. Show the preview to the
+                user, then call again with dryRun false and the revisionId from the preview to apply it.
+            "#}
+            .to_string(),
+            json!({
+              "type": "object",
+              "properties": {
+                "documentId": {
+                    "type": "string",
+                    "description": "ID of the Google Doc to edit",
+                },
+                "operation": {
+                    "type": "string",
+                    "enum": ["outline", "insert", "replace", "delete", "apply_style", "insert_table"],
+                    "description": "The edit to make",
+                },
+                "heading": {
+                    "type": "string",
+                    "description": "Text of the heading whose section to target",
+                },
+                "anchor": {
+                    "type": "string",
+                    "description": "Text to target, must be within a single paragraph",
+                },
+                "occurrence": {
+                    "type": "number",
+                    "description": "Which occurrence of the anchor text to target, default 1",
+                },
+                "position": {
+                    "type": "string",
+                    "enum": ["before", "after", "end_of_section"],
+                    "description": "Where to insert relative to the target, default after. end_of_section only applies to headings.",
+                },
+                "text": {
+                    "type": "string",
+                    "description": "Text to insert or replace with. Newlines start new paragraphs.",
+                },
+                "style": {
+                    "type": "string",
+                    "enum": ["NORMAL_TEXT", "TITLE", "SUBTITLE", "HEADING_1", "HEADING_2", "HEADING_3", "HEADING_4", "HEADING_5", "HEADING_6"],
+                    "description": "Named style for apply_style, or for paragraphs added by insert and replace (default NORMAL_TEXT)",
+                },
+                "rows": {
+                    "type": "array",
+                    "items": {"type": "array", "items": {"type": "string"}},
+                    "description": "Table rows for insert_table, each an array of cell text",
+                },
+                "dryRun": {
+                    "type": "boolean",
+                    "description": "Only preview the change, defaults to true",
+                },
+                "revisionId": {
+                    "type": "string",
+                    "description": "Revision from the preview, required when dryRun is false. The edit fails if the document changed since.",
+                },
+              },
+              "required": ["documentId", "operation"],
+            }),
+        );
+
         let list_drives_tool = Tool::new(
             "list_drives".to_string(),
             indoc! {r#"
@@ -557,7 +634,7 @@ impl GoogleDriveRouter {
             }),
         );
 
-        let instructions = indoc::formatdoc! {r#"
+        let mut instructions = indoc::formatdoc! {r#"
             Google Drive MCP Server Instructions
 
             ## Overview
@@ -567,6 +644,7 @@ impl GoogleDriveRouter {
             3. sheets_tool - Work with Google Sheets data using various operations
             4. create_file - Create Google Workspace files (Docs, Sheets, or Slides)
             5. update_file - Update existing Google Workspace files
+            6. edit_doc - Edit parts of a Google Doc in place
 
             ## Available Tools
 
@@ -613,6 +691,13 @@ impl GoogleDriveRouter {
             - For Google Sheets: Updates with new CSV text
             - For Google Slides: Updates with a new PowerPoint file (requires a path to the powerpoint file)
                 - Note: This functionally is an overwrite to the slides, warn the user before using this tool.
+            Updating a Google Doc replaces all of it and loses its formatting and comments, use edit_doc instead.
+
+            ### 6. Edit Doc Tool
+            Edit part of a Google Doc without replacing the rest of it: insert, replace or delete text at a heading
+            or anchor text, apply named styles and insert tables. Use the outline operation to see the headings.
+            Edits are previewed by default. Show the user the preview before applying an edit to a shared doc, then
+            apply it with dryRun false and the revisionId from the preview.
 
             Parameters:
             - spreadsheetId: The ID of the spreadsheet (can be obtained from search results)
@@ -655,28 +740,108 @@ impl GoogleDriveRouter {
             Remember: Always use the tools in sequence - search first to get the file URI, then read to access the contents.
         "#};
 
+        let mirror = MirrorConfig::from_env().and_then(|config| {
+            let dir = config.dir.clone();
+            Mirror::open(config)
+                .map_err(|e| {
+                    tracing::error!(
+                        "Failed to open the google drive mirror at {}: {}",
+                        dir.display(),
+                        e
+                    )
+                })
+                .ok()
+                .map(Arc::new)
+        });
+
+        let mut tools = vec![
+            search_tool,
+            read_tool,
+            upload_tool,
+            create_file_tool,
+            move_file_tool,
+            update_tool,
+            update_file_tool,
+            sheets_tool,
+            get_comments_tool,
+            create_comment_tool,
+            reply_tool,
+            list_drives_tool,
+            edit_doc_tool,
+        ];
+
+        if let Some(mirror) = &mirror {
+            tools.push(Tool::new(
+                "sync_mirror".to_string(),
+                indoc! {r#"
+                    Sync the local mirror of Google Drive folders and shared drives.
+                    Only files changed since the last sync are downloaded again.
+                "#}
+                .to_string(),
+                json!({
+                  "type": "object",
+                  "properties": {},
+                }),
+            ));
+            instructions.push_str(indoc! {r#"
+
+                ## Local Mirror
+                Selected folders and shared drives are mirrored locally and listed as resources, with
+                their modification time. Reading a mirrored file returns the local copy without
+                exporting it again. The mirror syncs in the background; use sync_mirror to pick up
+                changes made since the last sync.
+            "#});
+
+            if let Some(interval) = mirror.config().interval {
+                Self::watch_changes(drive.clone(), mirror.clone(), interval);
+            }
+        }
+
         Self {
-            tools: vec![
-                search_tool,
-                read_tool,
-                upload_tool,
-                create_file_tool,
-                move_file_tool,
-                update_tool,
-                update_file_tool,
-                sheets_tool,
-                get_comments_tool,
-                create_comment_tool,
-                reply_tool,
-                list_drives_tool,
-            ],
+            tools,
             instructions,
             drive,
             sheets,
             credentials_manager,
+            docs: docs::DocsApi::new(),
+            mirror,
         }
     }
 
+    /// Sync the mirror now and then every interval, for as long as the server runs
+    fn watch_changes(
+        drive: DriveHub<HttpsConnector<HttpConnector>>,
+        mirror: Arc<Mirror>,
+        interval: std::time::Duration,
+    ) {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                match mirror.sync(&drive).await {
+                    Ok(report) => tracing::info!("Synced google drive mirror: {}", report),
+                    Err(e) => tracing::warn!("Failed to sync google drive mirror: {}", e),
+                }
+            }
+        });
+    }
+
+    async fn sync_mirror(&self) -> Result<Vec<Content>, ToolError> {
+        let mirror = self.mirror.as_ref().ok_or_else(|| {
+            ToolError::ExecutionError(
+                "No mirror is configured, set GOOGLE_DRIVE_MIRROR_FOLDERS or GOOGLE_DRIVE_MIRROR_DRIVES.".to_string(),
+            )
+        })?;
+        let report = mirror.sync(&self.drive).await?;
+        Ok(vec![Content::text(format!(
+            "Synced the mirror at {}: {}. {} files are mirrored.",
+            mirror.config().dir.display(),
+            report,
+            mirror.files().len()
+        ))])
+    }
+
     // Implement search tool functionality
     async fn search(&self, params: Value) -> Result<Vec<Content>, ToolError> {
         let name = params.get("name").and_then(|q| q.as_str());
@@ -1025,6 +1190,13 @@ impl GoogleDriveRouter {
             .and_then(|i| i.as_bool())
             .unwrap_or(false);
 
+        // Mirrored files are kept current by the changes feed, so skip the export
+        if let (Some(mirror), false) = (&self.mirror, include_images) {
+            if let Some(content) = mirror.read(&drive_uri) {
+                return Ok(vec![Content::text(content).with_priority(0.1)]);
+            }
+        }
+
         let metadata = self.fetch_file_metadata(&drive_uri).await?;
         let mime_type = metadata.mime_type.ok_or_else(|| {
             ToolError::ExecutionError(format!("Missing mime type in file metadata for {}.", uri))
@@ -1488,6 +1660,31 @@ impl GoogleDriveRouter {
         }
     }
 
+    fn list_mirror_resources(mirror: &Mirror) -> Vec<Resource> {
+        mirror
+            .files()
+            .into_iter()
+            .filter_map(|(id, entry)| {
+                let mut resource = Resource::new(
+                    format!("gdrive:///{}", id),
+                    Some("text".to_string()),
+                    Some(entry.name.clone()),
+                )
+                .ok()?
+                .with_description(format!(
+                    "{} mirrored at {}",
+                    entry.mime_type,
+                    mirror.path_of(&entry).display()
+                ));
+                resource.annotations = Some(mcp_core::Annotations::for_resource(
+                    0.0,
+                    entry.modified_time,
+                ));
+                Some(resource)
+            })
+            .collect()
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn upload_to_drive(
         &self,
@@ -1827,6 +2024,70 @@ impl GoogleDriveRouter {
         .await
     }
 
+    async fn edit_doc(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let document_id = params
+            .get("documentId")
+            .and_then(|q| q.as_str())
+            .map(|id| id.trim_start_matches("gdrive:///"))
+            .ok_or(ToolError::InvalidParameters(
+                "The documentId param is required".to_string(),
+            ))?;
+        let operation = params.get("operation").and_then(|q| q.as_str()).ok_or(
+            ToolError::InvalidParameters("The operation param is required".to_string()),
+        )?;
+
+        // The Docs API accepts the Drive scope, so the Drive token works for it
+        let token = self
+            .drive
+            .auth
+            .get_token(&[GOOGLE_DRIVE_SCOPES.as_ref()])
+            .await
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to get a token, {}.", e)))?
+            .unwrap_or_default();
+        let doc = self.docs.get(&token, document_id).await?;
+        if operation == "outline" {
+            return Ok(vec![Content::text(format!(
+                "{} (revision {})\n{}",
+                doc.title,
+                doc.revision_id,
+                doc.outline()
+            ))]);
+        }
+
+        let edit = docs::Edit::from_params(&params)?;
+        let plan = docs::plan(&doc, &edit)?;
+        if params
+            .get("dryRun")
+            .and_then(|q| q.as_bool())
+            .unwrap_or(true)
+        {
+            return Ok(vec![Content::text(docs::preview(&doc, operation, &plan))]);
+        }
+
+        // Apply against the previewed revision, so an edit planned on an older version of
+        // the document is never applied to a newer one
+        let revision_id = params
+            .get("revisionId")
+            .and_then(|q| q.as_str())
+            .ok_or(ToolError::InvalidParameters(
+                "The revisionId param is required to apply an edit, preview it with dryRun true and pass the revisionId from the preview".to_string(),
+            ))?;
+        if revision_id != doc.revision_id {
+            return Err(ToolError::ExecutionError(format!(
+                "The document changed since revision {}, it is now at revision {}. Preview the edit again.",
+                revision_id, doc.revision_id
+            )));
+        }
+        let new_revision = self
+            .docs
+            .batch_update(&token, document_id, &plan.requests, revision_id)
+            .await?;
+        Ok(vec![Content::text(format!(
+            "Applied {} to {} in '{}', the document is now at revision {}.",
+            operation, plan.target, doc.title, new_revision
+        ))])
+    }
+
     async fn update_file(&self, params: Value) -> Result<Vec<Content>, ToolError> {
         // Extract common parameters
         let file_id =
@@ -2185,12 +2446,17 @@ impl Router for GoogleDriveRouter {
                 "get_comments" => this.get_comments(arguments).await,
                 "reply" => this.reply(arguments).await,
                 "list_drives" => this.list_drives(arguments).await,
+                "sync_mirror" => this.sync_mirror().await,
+                "edit_doc" => this.edit_doc(arguments).await,
                 _ => Err(ToolError::NotFound(format!("Tool {} not found", tool_name))),
             }
         })
     }
 
     fn list_resources(&self) -> Vec<Resource> {
+        if let Some(mirror) = &self.mirror {
+            return Self::list_mirror_resources(mirror);
+        }
         tokio::task::block_in_place(|| {
             tokio::runtime::Handle::current()
                 .block_on(async { self.list_google_resources(json!({})).await })
@@ -2232,6 +2498,8 @@ impl Clone for GoogleDriveRouter {
             drive: self.drive.clone(),
             sheets: self.sheets.clone(),
             credentials_manager: self.credentials_manager.clone(),
+            docs: self.docs.clone(),
+            mirror: self.mirror.clone(),
         }
     }
 }
@@ -1,6 +1,7 @@
 mod proxy;
 
 use anyhow::Result;
+use indoc::indoc;
 use mcp_core::{
     content::Content,
     handler::{PromptError, ResourceError, ToolError},
@@ -12,7 +13,7 @@ use mcp_core::{
 };
 use mcp_server::router::CapabilitiesBuilder;
 use mcp_server::Router;
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -38,7 +39,15 @@ impl JetBrainsRouter {
     pub fn new() -> Self {
         let tools = Arc::new(Mutex::new(Vec::new()));
         let proxy = Arc::new(JetBrainsProxy::new());
-        let instructions = "JetBrains IDE integration".to_string();
+        let instructions = indoc! {r#"
+            JetBrains IDE integration
+
+            When several IDEs are open, tools go to the IDE whose project contains the working
+            directory. The tools of the other IDEs are available with their instance prefix, such
+            as `myproject__get_open_in_editor_file_text`. Use select_ide to list the running IDEs
+            or to send unprefixed tools to a different one.
+        "#}
+        .to_string();
 
         // Initialize the proxy
         let proxy_clone = Arc::clone(&proxy);
@@ -97,6 +106,64 @@ impl JetBrainsRouter {
         Ok(contents)
     }
 
+    fn select_ide_tool() -> Tool {
+        Tool::new(
+            "select_ide",
+            indoc! {r#"
+                List the running JetBrains IDEs, or choose which one unprefixed tools go to.
+                Without an instance, lists the IDEs with their ports, projects and tool prefixes.
+                Pass an instance's prefix, port or product name to select it, or "auto" to go back
+                to choosing the IDE whose project contains the working directory.
+            "#},
+            json!({
+                "type": "object",
+                "properties": {
+                    "instance": {
+                        "type": "string",
+                        "description": "Prefix, port or product name of the IDE to use, or \"auto\""
+                    }
+                }
+            }),
+        )
+    }
+
+    async fn select_ide(&self, arguments: Value) -> Result<Vec<Content>, ToolError> {
+        if let Some(name) = arguments.get("instance").and_then(|v| v.as_str()) {
+            let name = (name != "auto").then(|| name.to_string());
+            self.proxy
+                .select(name)
+                .await
+                .map_err(|e| ToolError::InvalidParameters(e.to_string()))?;
+            // Refresh now so the unprefixed tools match the selection
+            if let Ok(tools) = self.proxy.list_tools().await {
+                *self.tools.lock().await = tools;
+            }
+        }
+
+        let instances = self.proxy.instances().await;
+        if instances.is_empty() {
+            return Err(ToolError::ExecutionError(
+                "No JetBrains IDE is running with the MCP plugin.".to_string(),
+            ));
+        }
+        let routed = self.proxy.routed_instance().await.map(|i| i.port);
+        let lines: Vec<String> = instances
+            .iter()
+            .map(|instance| {
+                let marker = if Some(instance.port) == routed {
+                    " [unprefixed tools]"
+                } else {
+                    ""
+                };
+                format!("- {}{}", instance.describe(), marker)
+            })
+            .collect();
+        Ok(vec![Content::text(format!(
+            "Running IDEs:\n{}",
+            lines.join("\n")
+        ))])
+    }
+
     async fn ensure_tools(&self) -> Result<(), ToolError> {
         let mut retry_count = 0;
         let max_retries = 50; // 5 second total wait time
@@ -139,7 +206,7 @@ impl Router for JetBrainsRouter {
                 .unwrap();
             rt.block_on(async {
                 let tools = self.tools.lock().await;
-                if tools.is_empty() {
+                let mut tools = if tools.is_empty() {
                     drop(tools);
                     if let Err(e) = self.ensure_tools().await {
                         error!("Failed to ensure tools: {}", e);
@@ -149,7 +216,9 @@ impl Router for JetBrainsRouter {
                     }
                 } else {
                     tools.clone()
-                }
+                };
+                tools.push(Self::select_ide_tool());
+                tools
             })
         })
     }
@@ -162,6 +231,9 @@ impl Router for JetBrainsRouter {
         let this = self.clone();
         let tool_name = tool_name.to_string();
         Box::pin(async move {
+            if tool_name == "select_ide" {
+                return this.select_ide(arguments).await;
+            }
             this.ensure_tools().await?;
             this.call_proxy_tool(tool_name, arguments).await
         })
@@ -2,8 +2,9 @@ use anyhow::{anyhow, Result};
 use mcp_core::{Content, Tool};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::env;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
@@ -12,6 +13,12 @@ use tracing::{debug, error, info};
 const PORT_RANGE_START: u16 = 63342;
 const PORT_RANGE_END: u16 = 63352;
 const ENDPOINT_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// Limit for the requests that discover IDEs, tool calls run as long as the IDE needs
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Separates an instance prefix from the IDE's own tool name
+pub const PREFIX_SEPARATOR: &str = "__";
+/// Plugin tools that report the project root, used when the IDE lists one of them
+const PROJECT_ROOT_TOOLS: [&str; 2] = ["get_project_root", "get_project_root_path"];
 
 #[derive(Debug, Serialize, Deserialize)]
 struct IDEResponseOk {
@@ -31,250 +38,261 @@ pub struct CallToolResult {
     pub is_error: bool,
 }
 
+/// A running IDE with the MCP plugin
+#[derive(Debug, Clone)]
+pub struct IdeInstance {
+    pub port: u16,
+    pub endpoint: String,
+    /// Product name, such as "IntelliJ IDEA"
+    pub product: String,
+    /// Root of the open project, if the IDE reports it
+    pub project: Option<PathBuf>,
+    /// Prefix for this instance's tools, unique among the running instances
+    pub prefix: String,
+    pub tools: Vec<Tool>,
+}
+
+impl IdeInstance {
+    pub fn describe(&self) -> String {
+        format!(
+            "{} (port {}, prefix {}, project {})",
+            self.product,
+            self.port,
+            self.prefix,
+            self.project
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "unknown".to_string())
+        )
+    }
+
+    /// Whether an override names this instance, by prefix, port or product name
+    fn matches(&self, name: &str) -> bool {
+        self.prefix == name
+            || self.port.to_string() == name
+            || self.product.eq_ignore_ascii_case(name)
+    }
+}
+
 #[derive(Debug)]
 pub struct JetBrainsProxy {
-    cached_endpoint: Arc<RwLock<Option<String>>>,
-    previous_response: Arc<RwLock<Option<String>>>,
+    instances: Arc<RwLock<Vec<IdeInstance>>>,
+    /// Instance chosen with select_ide or JETBRAINS_IDE, instead of routing by project
+    selected: Arc<RwLock<Option<String>>>,
+    previous_tools: Arc<RwLock<Option<Vec<String>>>>,
+    working_dir: PathBuf,
     client: Client,
 }
 
 impl JetBrainsProxy {
     pub fn new() -> Self {
         Self {
-            cached_endpoint: Arc::new(RwLock::new(None)),
-            previous_response: Arc::new(RwLock::new(None)),
+            instances: Arc::new(RwLock::new(Vec::new())),
+            selected: Arc::new(RwLock::new(env::var("JETBRAINS_IDE").ok())),
+            previous_tools: Arc::new(RwLock::new(None)),
+            working_dir: env::current_dir().unwrap_or_default(),
             client: Client::new(),
         }
     }
 
-    async fn test_list_tools(&self, endpoint: &str) -> Result<bool> {
-        debug!("Sending test request to {}/mcp/list_tools", endpoint);
+    /// Ports to look for IDEs on, IDE_PORT may list several separated by commas
+    fn candidate_ports() -> Result<Vec<u16>> {
+        match env::var("IDE_PORT") {
+            Ok(ports) => {
+                debug!("Found IDE_PORT environment variable: {}", ports);
+                ports
+                    .split(',')
+                    .map(|p| {
+                        p.trim()
+                            .parse()
+                            .map_err(|_| anyhow!("Invalid port in IDE_PORT: {}", p))
+                    })
+                    .collect()
+            }
+            Err(_) => Ok((PORT_RANGE_START..=PORT_RANGE_END).collect()),
+        }
+    }
 
+    /// Check whether an IDE answers at an endpoint, and if so what it is and which project
+    /// it has open
+    async fn probe(&self, port: u16, endpoint: &str) -> Option<IdeInstance> {
+        debug!("Sending test request to {}/mcp/list_tools", endpoint);
         let response = match self
             .client
             .get(format!("{}/mcp/list_tools", endpoint))
+            .timeout(PROBE_TIMEOUT)
             .send()
             .await
         {
+            Ok(resp) if resp.status().is_success() => resp,
             Ok(resp) => {
-                debug!("Got response with status: {}", resp.status());
-                resp
+                debug!("Test request failed with status {}", resp.status());
+                return None;
             }
             Err(e) => {
                 debug!("Error testing endpoint {}: {}", endpoint, e);
-                return Ok(false);
+                return None;
+            }
+        };
+        let tools = match response.json::<Value>().await {
+            Ok(value @ Value::Array(_)) => parse_tools(&value),
+            _ => {
+                debug!("Response is not a valid JSON array of tools");
+                return None;
             }
         };
 
-        if !response.status().is_success() {
-            debug!("Test request failed with status {}", response.status());
-            return Ok(false);
-        }
-
-        let current_response = response.text().await?;
-        debug!("Received response: {}", current_response);
-
-        // Try to parse as JSON array to validate format
-        if serde_json::from_str::<Vec<Value>>(&current_response).is_err() {
-            debug!("Response is not a valid JSON array of tools");
-            return Ok(false);
-        }
-
-        let mut prev_response = self.previous_response.write().await;
-        if let Some(prev) = prev_response.as_ref() {
-            if prev != &current_response {
-                debug!("Response changed since last check");
-                self.send_tools_changed().await;
+        let about = match self
+            .client
+            .get(format!("{}/about", endpoint))
+            .timeout(PROBE_TIMEOUT)
+            .send()
+            .await
+        {
+            Ok(resp) => resp.json::<Value>().await.ok(),
+            Err(_) => None,
+        };
+        let product = about
+            .and_then(|about| {
+                about["productName"]
+                    .as_str()
+                    .or_else(|| about["name"].as_str())
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| "JetBrains IDE".to_string());
+
+        let mut project = None;
+        for tool in PROJECT_ROOT_TOOLS {
+            if tools.iter().any(|t| t.name == tool) {
+                let result = self
+                    .post_tool(endpoint, tool, json!({}), Some(PROBE_TIMEOUT))
+                    .await;
+                if let Ok(result) = result {
+                    project = project_root(&result);
+                    break;
+                }
             }
         }
-        *prev_response = Some(current_response);
 
-        Ok(true)
+        Some(IdeInstance {
+            port,
+            endpoint: endpoint.to_string(),
+            product,
+            project,
+            prefix: String::new(),
+            tools,
+        })
     }
 
-    async fn find_working_ide_endpoint(&self) -> Result<String> {
-        debug!("Attempting to find working IDE endpoint...");
+    async fn find_ide_instances(&self) -> Result<Vec<IdeInstance>> {
+        let ports = Self::candidate_ports()?;
+        debug!("Scanning ports {:?} for IDE instances", ports);
 
-        // Check IDE_PORT environment variable first
-        if let Ok(port) = env::var("IDE_PORT") {
-            debug!("Found IDE_PORT environment variable: {}", port);
-            let test_endpoint = format!("http://127.0.0.1:{}/api", port);
-            if self.test_list_tools(&test_endpoint).await? {
-                debug!("IDE_PORT {} is working", port);
-                return Ok(test_endpoint);
+        let mut instances = Vec::new();
+        for port in ports.iter().copied() {
+            let endpoint = format!("http://127.0.0.1:{}/api", port);
+            if let Some(instance) = self.probe(port, &endpoint).await {
+                instances.push(instance);
             }
-            debug!("IDE_PORT {} is not responding correctly", port);
+        }
+        if instances.is_empty() {
             return Err(anyhow!(
-                "Specified IDE_PORT={} is not responding correctly",
-                port
+                "No working IDE endpoint found on ports {:?}",
+                ports
             ));
         }
-
-        debug!(
-            "No IDE_PORT environment variable, scanning port range {}-{}",
-            PORT_RANGE_START, PORT_RANGE_END
-        );
-
-        // Scan port range
-        for port in PORT_RANGE_START..=PORT_RANGE_END {
-            let candidate_endpoint = format!("http://127.0.0.1:{}/api", port);
-            debug!("Testing port {}...", port);
-
-            if self.test_list_tools(&candidate_endpoint).await? {
-                debug!("Found working IDE endpoint at {}", candidate_endpoint);
-                return Ok(candidate_endpoint);
-            }
-        }
-
-        debug!("No working IDE endpoint found in port range");
-        Err(anyhow!(
-            "No working IDE endpoint found in range {}-{}",
-            PORT_RANGE_START,
-            PORT_RANGE_END
-        ))
+        assign_prefixes(&mut instances);
+        Ok(instances)
     }
 
-    async fn update_ide_endpoint(&self) {
-        debug!("Updating IDE endpoint...");
-        match self.find_working_ide_endpoint().await {
-            Ok(endpoint) => {
-                let mut cached = self.cached_endpoint.write().await;
-                *cached = Some(endpoint.clone());
-                debug!("Updated cached endpoint to: {}", endpoint);
+    async fn update_ide_instances(&self) {
+        debug!("Updating IDE instances...");
+        match self.find_ide_instances().await {
+            Ok(instances) => {
+                for instance in &instances {
+                    debug!("Found IDE {}", instance.describe());
+                }
+                *self.instances.write().await = instances;
             }
             Err(e) => {
-                debug!("Failed to update IDE endpoint: {}", e);
-                error!("Failed to update IDE endpoint: {}", e);
+                error!("Failed to update IDE instances: {}", e);
+                self.instances.write().await.clear();
             }
         }
     }
 
-    pub async fn list_tools(&self) -> Result<Vec<Tool>> {
-        debug!("Listing tools...");
-        let endpoint = {
-            let cached = self.cached_endpoint.read().await;
-            match cached.as_ref() {
-                Some(ep) => {
-                    debug!("Using cached endpoint: {}", ep);
-                    ep.clone()
-                }
-                None => {
-                    debug!("No cached endpoint available");
-                    return Ok(vec![]);
-                }
-            }
-        };
+    pub async fn instances(&self) -> Vec<IdeInstance> {
+        self.instances.read().await.clone()
+    }
 
-        debug!("Sending list_tools request to {}/mcp/list_tools", endpoint);
-        let response = match self
-            .client
-            .get(format!("{}/mcp/list_tools", endpoint))
-            .send()
-            .await
-        {
-            Ok(resp) => {
-                debug!("Got response with status: {}", resp.status());
-                resp
-            }
-            Err(e) => {
-                debug!("Failed to send request: {}", e);
-                return Err(anyhow!("Failed to send request: {}", e));
-            }
+    /// Route to an instance by name, or pass None to route by the working directory again
+    pub async fn select(&self, name: Option<String>) -> Result<Option<IdeInstance>> {
+        let instance = match &name {
+            Some(name) => Some(
+                self.instances
+                    .read()
+                    .await
+                    .iter()
+                    .find(|i| i.matches(name))
+                    .cloned()
+                    .ok_or_else(|| anyhow!("No running IDE matches '{}'", name))?,
+            ),
+            None => None,
         };
+        *self.selected.write().await = name;
+        Ok(instance)
+    }
 
-        if !response.status().is_success() {
-            debug!("Request failed with status: {}", response.status());
-            return Err(anyhow!(
-                "Failed to fetch tools with status {}",
-                response.status()
-            ));
+    /// The instance unprefixed tools go to
+    pub async fn routed_instance(&self) -> Option<IdeInstance> {
+        let instances = self.instances.read().await;
+        let selected = self.selected.read().await;
+        route(&instances, &self.working_dir, selected.as_deref()).cloned()
+    }
+
+    pub async fn list_tools(&self) -> Result<Vec<Tool>> {
+        debug!("Listing tools...");
+        let instances = self.instances.read().await.clone();
+        let routed = self.routed_instance().await;
+
+        let mut tools = Vec::new();
+        for instance in &instances {
+            if routed.as_ref().is_some_and(|r| r.port == instance.port) {
+                tools.extend(instance.tools.iter().cloned());
+            } else {
+                tools.extend(instance.tools.iter().map(|tool| Tool {
+                    name: format!("{}{}{}", instance.prefix, PREFIX_SEPARATOR, tool.name),
+                    description: format!("[{}] {}", instance.product, tool.description),
+                    input_schema: tool.input_schema.clone(),
+                }));
+            }
         }
 
-        let response_text = response.text().await?;
-        debug!("Got response text: {}", response_text);
-
-        let tools_response: Value = serde_json::from_str(&response_text).map_err(|e| {
-            debug!("Failed to parse response as JSON: {}", e);
-            anyhow!("Failed to parse response as JSON: {}", e)
-        })?;
-
-        debug!("Parsed JSON response: {:?}", tools_response);
-
-        let tools: Vec<Tool> = tools_response
-            .as_array()
-            .ok_or_else(|| {
-                debug!("Response is not a JSON array");
-                anyhow!("Invalid tools response format: not an array")
-            })?
-            .iter()
-            .filter_map(|t| {
-                if let (Some(name), Some(description)) =
-                    (t["name"].as_str(), t["description"].as_str())
-                {
-                    // Get just the first sentence of the description
-                    let first_sentence = description
-                        .split('.')
-                        .next()
-                        .unwrap_or(description)
-                        .trim()
-                        .to_string()
-                        + ".";
-
-                    // Handle input_schema as either a string or an object
-                    let input_schema = match &t["inputSchema"] {
-                        Value::String(s) => Value::String(s.clone()),
-                        Value::Object(o) => Value::Object(o.clone()),
-                        _ => {
-                            debug!(
-                                "Invalid inputSchema format for tool {}: {:?}",
-                                name, t["inputSchema"]
-                            );
-                            return None;
-                        }
-                    };
-
-                    Some(Tool {
-                        name: name.to_string(),
-                        description: first_sentence,
-                        input_schema,
-                    })
-                } else {
-                    debug!("Skipping invalid tool entry: {:?}", t);
-                    None
-                }
-            })
-            .collect();
+        let names: Vec<String> = tools.iter().map(|t| t.name.clone()).collect();
+        let mut previous = self.previous_tools.write().await;
+        if previous.as_ref().is_some_and(|p| p != &names) {
+            debug!("Tools changed since last check");
+            self.send_tools_changed().await;
+        }
+        *previous = Some(names);
 
         debug!("Collected {} tools", tools.len());
         Ok(tools)
     }
 
     pub async fn call_tool(&self, name: &str, args: Value) -> Result<CallToolResult> {
-        let endpoint = self
-            .cached_endpoint
-            .read()
-            .await
-            .clone()
-            .ok_or_else(|| anyhow!("No working IDE endpoint available"))?;
+        let instances = self.instances.read().await.clone();
+        let (instance, tool) = match split_prefixed(&instances, name) {
+            Some((instance, tool)) => (Some(instance.clone()), tool),
+            None => (self.routed_instance().await, name),
+        };
+        let instance = instance.ok_or_else(|| anyhow!("No working IDE endpoint available"))?;
 
         debug!(
             "ENDPOINT: {} | Tool name: {} | args: {}",
-            endpoint, name, args
+            instance.endpoint, tool, args
         );
-
-        let response = self
-            .client
-            .post(format!("{}/mcp/{}", endpoint, name))
-            .json(&args)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            debug!("Response failed with status: {}", response.status());
-            return Err(anyhow!("Response failed: {}", response.status()));
-        }
-
-        let ide_response: Value = response.json().await?;
+        let ide_response = self.post_tool(&instance.endpoint, tool, args, None).await?;
         let (is_error, text) = match ide_response {
             Value::Object(map) => {
                 let status = map.get("status").and_then(|v| v.as_str());
@@ -301,6 +319,29 @@ impl JetBrainsProxy {
         })
     }
 
+    async fn post_tool(
+        &self,
+        endpoint: &str,
+        name: &str,
+        args: Value,
+        timeout: Option<Duration>,
+    ) -> Result<Value> {
+        let mut request = self
+            .client
+            .post(format!("{}/mcp/{}", endpoint, name))
+            .json(&args);
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send().await?;
+
+        if !response.status().is_success() {
+            debug!("Response failed with status: {}", response.status());
+            return Err(anyhow!("Response failed: {}", response.status()));
+        }
+        Ok(response.json().await?)
+    }
+
     async fn send_tools_changed(&self) {
         debug!("Sending tools changed notification");
         // TODO: Implement notification mechanism when needed
@@ -312,7 +353,7 @@ impl JetBrainsProxy {
 
         // Initial endpoint check
         debug!("Performing initial endpoint check...");
-        self.update_ide_endpoint().await;
+        self.update_ide_instances().await;
 
         // Schedule periodic endpoint checks
         let proxy = self.clone();
@@ -320,7 +361,7 @@ impl JetBrainsProxy {
             loop {
                 tokio::time::sleep(ENDPOINT_CHECK_INTERVAL).await;
                 debug!("Performing periodic endpoint check...");
-                proxy.update_ide_endpoint().await;
+                proxy.update_ide_instances().await;
             }
         });
 
@@ -333,9 +374,284 @@ impl JetBrainsProxy {
 impl Clone for JetBrainsProxy {
     fn clone(&self) -> Self {
         Self {
-            cached_endpoint: Arc::clone(&self.cached_endpoint),
-            previous_response: Arc::clone(&self.previous_response),
-            client: Client::new(),
+            instances: Arc::clone(&self.instances),
+            selected: Arc::clone(&self.selected),
+            previous_tools: Arc::clone(&self.previous_tools),
+            working_dir: self.working_dir.clone(),
+            client: self.client.clone(),
         }
     }
 }
+
+/// Parse the IDE's list_tools response
+fn parse_tools(tools_response: &Value) -> Vec<Tool> {
+    tools_response
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|t| {
+            if let (Some(name), Some(description)) = (t["name"].as_str(), t["description"].as_str())
+            {
+                // Get just the first sentence of the description
+                let first_sentence = description
+                    .split('.')
+                    .next()
+                    .unwrap_or(description)
+                    .trim()
+                    .to_string()
+                    + ".";
+
+                // Handle input_schema as either a string or an object
+                let input_schema = match &t["inputSchema"] {
+                    Value::String(s) => Value::String(s.clone()),
+                    Value::Object(o) => Value::Object(o.clone()),
+                    _ => {
+                        debug!(
+                            "Invalid inputSchema format for tool {}: {:?}",
+                            name, t["inputSchema"]
+                        );
+                        return None;
+                    }
+                };
+
+                Some(Tool {
+                    name: name.to_string(),
+                    description: first_sentence,
+                    input_schema,
+                })
+            } else {
+                debug!("Skipping invalid tool entry: {:?}", t);
+                None
+            }
+        })
+        .collect()
+}
+
+/// The project root from a plugin tool response, which is an absolute path in its status
+fn project_root(response: &Value) -> Option<PathBuf> {
+    let path = PathBuf::from(response["status"].as_str()?.trim());
+    path.is_absolute().then_some(path)
+}
+
+/// Give each instance a prefix from its project directory, or its product name when the
+/// project is unknown, adding the port where two would clash
+fn assign_prefixes(instances: &mut [IdeInstance]) {
+    // Runs of other characters become a single '_', so a prefix never holds PREFIX_SEPARATOR
+    let slug = |name: &str| -> String {
+        let mut slug = String::new();
+        for c in name.to_lowercase().chars() {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c);
+            } else if !slug.ends_with('_') {
+                slug.push('_');
+            }
+        }
+        let slug = slug.trim_matches('_').to_string();
+        if slug.is_empty() {
+            "ide".to_string()
+        } else {
+            slug
+        }
+    };
+    let bases: Vec<String> = instances
+        .iter()
+        .map(|i| {
+            let name = i
+                .project
+                .as_ref()
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| i.product.clone());
+            slug(&name)
+        })
+        .collect();
+    for (index, instance) in instances.iter_mut().enumerate() {
+        let base = &bases[index];
+        instance.prefix = if bases.iter().filter(|b| *b == base).count() > 1 {
+            format!("{}_{}", base, instance.port)
+        } else {
+            base.clone()
+        };
+    }
+}
+
+/// The instance a prefixed tool name belongs to and the IDE's own tool name, matching the
+/// longest prefix so one prefix that starts another never takes its tools
+fn split_prefixed<'a, 'n>(
+    instances: &'a [IdeInstance],
+    name: &'n str,
+) -> Option<(&'a IdeInstance, &'n str)> {
+    instances
+        .iter()
+        .filter_map(|instance| {
+            let tool = name
+                .strip_prefix(instance.prefix.as_str())?
+                .strip_prefix(PREFIX_SEPARATOR)?;
+            Some((instance, tool))
+        })
+        .max_by_key(|(instance, _)| instance.prefix.len())
+}
+
+/// Pick the instance for unprefixed tools: the selected one, else the one whose project
+/// contains the working directory (the deepest if projects nest), else the first
+fn route<'a>(
+    instances: &'a [IdeInstance],
+    working_dir: &Path,
+    selected: Option<&str>,
+) -> Option<&'a IdeInstance> {
+    if let Some(selected) = selected {
+        if let Some(instance) = instances.iter().find(|i| i.matches(selected)) {
+            return Some(instance);
+        }
+        debug!("Selected IDE '{}' is not running", selected);
+    }
+    instances
+        .iter()
+        .filter_map(|i| {
+            let project = i.project.as_ref()?;
+            working_dir
+                .starts_with(project)
+                .then(|| (project.components().count(), i))
+        })
+        .max_by_key(|(depth, _)| *depth)
+        .map(|(_, i)| i)
+        .or_else(|| instances.first())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn instance(port: u16, product: &str, project: Option<&str>) -> IdeInstance {
+        IdeInstance {
+            port,
+            endpoint: format!("http://127.0.0.1:{}/api", port),
+            product: product.to_string(),
+            project: project.map(PathBuf::from),
+            prefix: String::new(),
+            tools: vec![],
+        }
+    }
+
+    #[test]
+    fn test_routes_by_project_and_selection() {
+        let mut instances = vec![
+            instance(63342, "IntelliJ IDEA", Some("/work/backend")),
+            instance(63343, "PyCharm", Some("/work/backend/scripts")),
+            instance(63344, "WebStorm", None),
+        ];
+        assign_prefixes(&mut instances);
+        assert_eq!(instances[0].prefix, "backend");
+        assert_eq!(instances[1].prefix, "scripts");
+        assert_eq!(instances[2].prefix, "webstorm");
+
+        let route_port = |dir: &str, selected: Option<&str>| {
+            route(&instances, Path::new(dir), selected).map(|i| i.port)
+        };
+        assert_eq!(route_port("/work/backend/src", None), Some(63342));
+        // Nested projects route to the deepest one
+        assert_eq!(route_port("/work/backend/scripts/db", None), Some(63343));
+        // Outside every project falls back to the first instance
+        assert_eq!(route_port("/elsewhere", None), Some(63342));
+        // A selection wins by prefix, port or product, unless it is not running
+        assert_eq!(route_port("/work/backend", Some("webstorm")), Some(63344));
+        assert_eq!(route_port("/work/backend", Some("63343")), Some(63343));
+        assert_eq!(route_port("/work/backend", Some("gone")), Some(63342));
+        assert_eq!(route(&[], Path::new("/"), None).map(|i| i.port), None);
+    }
+
+    #[test]
+    fn test_prefixes_are_unique() {
+        let mut instances = vec![
+            instance(63342, "IntelliJ IDEA", Some("/a/app")),
+            instance(63343, "IntelliJ IDEA", Some("/b/app")),
+        ];
+        assign_prefixes(&mut instances);
+        assert_eq!(instances[0].prefix, "app_63342");
+        assert_eq!(instances[1].prefix, "app_63343");
+    }
+
+    #[test]
+    fn test_prefixes_never_hold_the_separator() {
+        let mut instances = vec![
+            instance(63342, "IntelliJ IDEA", Some("/work/my--app")),
+            instance(63343, "PyCharm", Some("/work/a__b")),
+            instance(63344, "WebStorm", Some("/work/a")),
+        ];
+        assign_prefixes(&mut instances);
+        assert_eq!(instances[0].prefix, "my_app");
+        assert_eq!(instances[1].prefix, "a_b");
+
+        let split = |name| split_prefixed(&instances, name).map(|(i, tool)| (i.port, tool));
+        assert_eq!(split("my_app__get_status"), Some((63342, "get_status")));
+        assert_eq!(split("a_b__get_status"), Some((63343, "get_status")));
+        assert_eq!(split("a__b__get_status"), Some((63344, "b__get_status")));
+        assert_eq!(split("get_status"), None);
+    }
+
+    #[tokio::test]
+    async fn test_probe_reads_tools_product_and_project() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/mcp/list_tools"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"name": "get_project_root", "description": "Project root. More text.",
+                 "inputSchema": {"type": "object"}},
+                {"name": "broken", "description": "No schema"},
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/about"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"productName": "RustRover"})),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/mcp/get_project_root"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"status": "/work/crate"})),
+            )
+            .mount(&server)
+            .await;
+
+        let proxy = JetBrainsProxy::new();
+        let endpoint = format!("{}/api", server.uri());
+        let found = proxy
+            .probe(server.address().port(), &endpoint)
+            .await
+            .unwrap();
+        assert_eq!(found.product, "RustRover");
+        assert_eq!(found.project, Some(PathBuf::from("/work/crate")));
+        assert_eq!(found.tools.len(), 1);
+        assert_eq!(found.tools[0].description, "Project root.");
+
+        assert!(proxy.probe(1, "http://127.0.0.1:1/api").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_outlast_the_probe_timeout() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/mcp/build_project"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"status": "Build finished"}))
+                    .set_delay(PROBE_TIMEOUT + Duration::from_millis(500)),
+            )
+            .mount(&server)
+            .await;
+
+        let proxy = JetBrainsProxy::new();
+        let mut ide = instance(server.address().port(), "IntelliJ IDEA", None);
+        ide.endpoint = format!("{}/api", server.uri());
+        *proxy.instances.write().await = vec![ide];
+
+        let result = proxy.call_tool("build_project", json!({})).await.unwrap();
+        assert!(!result.is_error);
+        assert_eq!(result.content[0].as_text(), Some("Build finished"));
+    }
+}
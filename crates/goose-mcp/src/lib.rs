@@ -15,6 +15,7 @@ mod memory;
 mod tutorial;
 
 pub use computercontroller::ComputerControllerRouter;
+pub use developer::checkpoint::{Checkpoint, Checkpoints};
 pub use developer::DeveloperRouter;
 pub use google_drive::GoogleDriveRouter;
 pub use jetbrains::JetBrainsRouter;
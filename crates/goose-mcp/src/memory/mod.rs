@@ -3,12 +3,13 @@ use etcetera::{choose_app_strategy, AppStrategy};
 use indoc::formatdoc;
 use serde_json::{json, Value};
 use std::{
-    collections::HashMap,
-    fs,
+    collections::{HashMap, HashSet},
     future::Future,
-    io::{self, Read, Write},
+    io,
     path::PathBuf,
     pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use mcp_core::{
@@ -22,13 +23,36 @@ use mcp_core::{
 use mcp_server::router::CapabilitiesBuilder;
 use mcp_server::Router;
 
+mod store;
+
+pub use store::{MemoryEntry, MemoryStore, MemoryUpdate, SearchMode};
+
+/// Memories unused for this many days are archived, unless GOOSE_MEMORY_MAX_AGE_DAYS is set
+const DEFAULT_MAX_AGE_DAYS: i64 = 90;
+/// Token budget for relevant memories when the agent does not give one
+const DEFAULT_RELEVANT_BUDGET: usize = 1000;
+/// At most this many memories are considered for injection
+const MAX_RELEVANT: usize = 20;
+/// The resource that returns the memories relevant to a message
+const RELEVANT_MEMORIES_URI: &str = "memory://relevant";
+/// How long usage of injected memories is collected before it is saved in one write
+const USAGE_FLUSH_DELAY: Duration = Duration::from_secs(10);
+
+/// Ids of injected memories whose use has not been saved yet, by scope
+#[derive(Default)]
+struct PendingUsage {
+    ids: HashMap<bool, HashSet<String>>,
+    flush_scheduled: bool,
+}
+
 // MemoryRouter implementation
 #[derive(Clone)]
 pub struct MemoryRouter {
     tools: Vec<Tool>,
     instructions: String,
-    global_memory_dir: PathBuf,
-    local_memory_dir: PathBuf,
+    global: Arc<MemoryStore>,
+    local: Arc<MemoryStore>,
+    pending_usage: Arc<Mutex<PendingUsage>>,
 }
 
 impl Default for MemoryRouter {
@@ -80,17 +104,52 @@ impl MemoryRouter {
             }),
         );
 
-        let remove_specific_memory = Tool::new(
-            "remove_specific_memory",
-            "Removes a specific memory within a specified category",
+        let search_memories = Tool::new(
+            "search_memories",
+            "Searches memories across categories by relevance to a query, optionally filtered by tags, category and scope",
             json!({
                 "type": "object",
                 "properties": {
+                    "query": {"type": "string"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
                     "category": {"type": "string"},
-                    "memory_content": {"type": "string"},
-                    "is_global": {"type": "boolean"}
+                    "is_global": {"type": "boolean", "description": "Search only global or only local memories, both when omitted"},
+                    "mode": {
+                        "type": "string",
+                        "enum": ["keyword", "semantic", "hybrid"],
+                        "description": "keyword matches words, semantic also matches related word forms, hybrid combines both. Defaults to keyword."
+                    },
+                    "limit": {"type": "integer", "default": 10},
+                    "include_archived": {"type": "boolean", "default": false, "description": "Also search memories archived after a long time unused"}
                 },
-                "required": ["category", "memory_content", "is_global"]
+                "required": ["query"]
+            }),
+        );
+
+        let update_memory = Tool::new(
+            "update_memory",
+            "Updates the content, tags or category of a memory by its id",
+            json!({
+                "type": "object",
+                "properties": {
+                    "id": {"type": "string"},
+                    "data": {"type": "string"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "category": {"type": "string"}
+                },
+                "required": ["id"]
+            }),
+        );
+
+        let remove_memory = Tool::new(
+            "remove_memory",
+            "Removes a single memory by its id",
+            json!({
+                "type": "object",
+                "properties": {
+                    "id": {"type": "string"}
+                },
+                "required": ["id"]
             }),
         );
 
@@ -101,7 +160,8 @@ impl MemoryRouter {
              1. Store information in categories with optional tags for context-based retrieval.
              2. Search memories by content or specific tags to find relevant information.
              3. List all available memory categories for easy navigation.
-             4. Remove entire categories of memories when they are no longer needed.
+             4. Update or remove individual memories by their id.
+             5. Remove entire categories of memories when they are no longer needed.
              When to call memory tools:
              - These are examples where the assistant should proactively call the memory tool because the user is providing recurring preferences, project details, or workflow habits that they may expect to be remembered.
              - Preferred Development Tools & Conventions
@@ -167,9 +227,13 @@ impl MemoryRouter {
                - Use: `retrieve_memories(category="development", is_global=False)`
                - Note: If you want to retrieve all local memories, use `retrieve_memories(category="*", is_global=False)`
                - Note: If you want to retrieve all global memories, use `retrieve_memories(category="*", is_global=True)`
-             - **Filter by Tags**:
-               - Enables targeted retrieval based on specific tags.
-               - Use: Provide tag filters to refine search.
+             - **Search**:
+               - Finds the most relevant memories across all categories, optionally filtered by tags.
+               - Use: `search_memories(query="code formatting", tags=["formatting"])`
+               - Note: use mode="semantic" to also match related word forms, like "deploy" for "deployment"
+             Every memory has an id, shown in brackets. Use it to change a memory precisely:
+             - `update_memory(id="3f2a9c1e", data="We use ruff for formatting")`
+             - `remove_memory(id="3f2a9c1e")`
             To remove a memory, use the following protocol:
             - **Remove by Category**:
               - Removes all memories within the specified category.
@@ -212,63 +276,50 @@ impl MemoryRouter {
             .map(|strategy| strategy.in_config_dir("memory"))
             .unwrap_or_else(|_| PathBuf::from(".config/goose/memory"));
 
-        fs::create_dir_all(&global_memory_dir).unwrap();
-        fs::create_dir_all(&local_memory_dir).unwrap();
+        // Opening the stores migrates memories saved in the old text format
+        let global = MemoryStore::open(global_memory_dir);
+        let local = MemoryStore::open(local_memory_dir);
+
+        // Memories that have not been used for a long time are archived so they stop being
+        // injected, they can still be found with search_memories
+        let max_age_days = std::env::var("GOOSE_MEMORY_MAX_AGE_DAYS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_MAX_AGE_DAYS);
+        // Zero or less turns archiving off, and so does an age too long for a Duration
+        let max_age = Some(max_age_days)
+            .filter(|days| *days > 0)
+            .and_then(chrono::TimeDelta::try_days);
+        if let Some(max_age) = max_age {
+            for store in [&global, &local] {
+                if let Err(e) = store.archive_stale(max_age) {
+                    tracing::warn!("Failed to archive stale memories: {}", e);
+                }
+            }
+        }
+
+        let memories_follow_up_instructions = formatdoc! {r#"
+            **Saved memories:**
+            Memories relevant to each user message are provided automatically with the message, under
+            "Relevant memories". Do not bring up memories unless relevant. Use search_memories to look
+            for anything else, including archived memories that have not been used for a long time.
+            "#};
 
-        let mut memory_router = Self {
+        let memory_router = Self {
             tools: vec![
                 remember_memory,
                 retrieve_memories,
+                search_memories,
+                update_memory,
+                remove_memory,
                 remove_memory_category,
-                remove_specific_memory,
             ],
-            instructions: instructions.clone(),
-            global_memory_dir,
-            local_memory_dir,
+            instructions: format!("{}\n\n{}", instructions, memories_follow_up_instructions),
+            global: Arc::new(global),
+            local: Arc::new(local),
+            pending_usage: Arc::default(),
         };
 
-        let retrieved_global_memories = memory_router.retrieve_all(true);
-        let retrieved_local_memories = memory_router.retrieve_all(false);
-
-        let mut updated_instructions = instructions;
-
-        let memories_follow_up_instructions = formatdoc! {r#"
-            **Here are the user's currently saved memories:**
-            Please keep this information in mind when answering future questions.
-            Do not bring up memories unless relevant.
-            Note: if the user has not saved any memories, this section will be empty.
-            Note: if the user removes a memory that was previously loaded into the system, please remove it from the system instructions.
-            "#};
-
-        updated_instructions.push_str("\n\n");
-        updated_instructions.push_str(&memories_follow_up_instructions);
-
-        if let Ok(global_memories) = retrieved_global_memories {
-            if !global_memories.is_empty() {
-                updated_instructions.push_str("\n\nGlobal Memories:\n");
-                for (category, memories) in global_memories {
-                    updated_instructions.push_str(&format!("\nCategory: {}\n", category));
-                    for memory in memories {
-                        updated_instructions.push_str(&format!("- {}\n", memory));
-                    }
-                }
-            }
-        }
-
-        if let Ok(local_memories) = retrieved_local_memories {
-            if !local_memories.is_empty() {
-                updated_instructions.push_str("\n\nLocal Memories:\n");
-                for (category, memories) in local_memories {
-                    updated_instructions.push_str(&format!("\nCategory: {}\n", category));
-                    for memory in memories {
-                        updated_instructions.push_str(&format!("- {}\n", memory));
-                    }
-                }
-            }
-        }
-
-        memory_router.set_instructions(updated_instructions);
-
         memory_router
     }
 
@@ -281,144 +332,171 @@ impl MemoryRouter {
         &self.instructions
     }
 
-    fn get_memory_file(&self, category: &str, is_global: bool) -> PathBuf {
+    fn store(&self, is_global: bool) -> &MemoryStore {
         // Defaults to local memory if no is_global flag is provided
-        let base_dir = if is_global {
-            &self.global_memory_dir
+        if is_global {
+            &self.global
         } else {
-            &self.local_memory_dir
-        };
-        base_dir.join(format!("{}.txt", category))
+            &self.local
+        }
     }
 
-    pub fn retrieve_all(&self, is_global: bool) -> io::Result<HashMap<String, Vec<String>>> {
-        let base_dir = if is_global {
-            &self.global_memory_dir
-        } else {
-            &self.local_memory_dir
-        };
-        let mut memories = HashMap::new();
-        if base_dir.exists() {
-            for entry in fs::read_dir(base_dir)? {
-                let entry = entry?;
-                if entry.file_type()?.is_file() {
-                    let category = entry.file_name().to_string_lossy().replace(".txt", "");
-                    let category_memories = self.retrieve(&category, is_global)?;
-                    memories.insert(
-                        category,
-                        category_memories.into_iter().flat_map(|(_, v)| v).collect(),
-                    );
-                }
-            }
-        }
-        Ok(memories)
+    pub fn retrieve_all(&self, is_global: bool) -> io::Result<Vec<MemoryEntry>> {
+        self.store(is_global).list(None)
     }
 
     pub fn remember(
         &self,
-        _context: &str,
+        source: &str,
         category: &str,
         data: &str,
         tags: &[&str],
         is_global: bool,
-    ) -> io::Result<()> {
-        let memory_file_path = self.get_memory_file(category, is_global);
-
-        let mut file = fs::OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open(&memory_file_path)?;
-        if !tags.is_empty() {
-            writeln!(file, "# {}", tags.join(" "))?;
-        }
-        writeln!(file, "{}\n", data)?;
+    ) -> io::Result<MemoryEntry> {
+        self.store(is_global).add(category, data, tags, source)
+    }
 
-        Ok(())
+    pub fn retrieve(&self, category: &str, is_global: bool) -> io::Result<Vec<MemoryEntry>> {
+        self.store(is_global).list(Some(category))
     }
 
-    pub fn retrieve(
+    /// Search one scope, or both when `is_global` is None
+    pub fn search(
         &self,
-        category: &str,
-        is_global: bool,
-    ) -> io::Result<HashMap<String, Vec<String>>> {
-        let memory_file_path = self.get_memory_file(category, is_global);
-        if !memory_file_path.exists() {
-            return Ok(HashMap::new());
-        }
+        query: &str,
+        filter: &SearchFilter,
+        mode: SearchMode,
+        limit: usize,
+    ) -> io::Result<Vec<(f32, MemoryEntry, bool)>> {
+        let scopes = match filter.is_global {
+            Some(is_global) => vec![is_global],
+            None => vec![false, true],
+        };
 
-        let mut file = fs::File::open(memory_file_path)?;
-        let mut content = String::new();
-        file.read_to_string(&mut content)?;
-
-        let mut memories = HashMap::new();
-        for entry in content.split("\n\n") {
-            let mut lines = entry.lines();
-            if let Some(first_line) = lines.next() {
-                if let Some(stripped) = first_line.strip_prefix('#') {
-                    let tags = stripped
-                        .split_whitespace()
-                        .map(String::from)
-                        .collect::<Vec<_>>();
-                    memories.insert(tags.join(" "), lines.map(String::from).collect());
-                } else {
-                    let entry_data: Vec<String> = std::iter::once(first_line.to_string())
-                        .chain(lines.map(String::from))
-                        .collect();
-                    memories
-                        .entry("untagged".to_string())
-                        .or_insert_with(Vec::new)
-                        .extend(entry_data);
+        let mut candidates = Vec::new();
+        let mut scope_of = HashMap::new();
+        for is_global in scopes {
+            for entry in self.store(is_global).list(filter.category.as_deref())? {
+                if (filter.include_archived || !entry.archived)
+                    && filter.tags.iter().all(|tag| entry.tags.contains(tag))
+                {
+                    scope_of.insert(entry.id.clone(), is_global);
+                    candidates.push(entry);
                 }
             }
         }
 
-        Ok(memories)
+        Ok(store::search(&candidates, query, mode, limit)
+            .into_iter()
+            .map(|(score, entry)| (score, entry.clone(), scope_of[&entry.id]))
+            .collect())
     }
 
-    pub fn remove_specific_memory(
+    /// The memories most relevant to a user message that fit in `budget` tokens, formatted for
+    /// the model, along with the ids and scopes of the memories included
+    pub fn relevant_memories(
         &self,
-        category: &str,
-        memory_content: &str,
-        is_global: bool,
-    ) -> io::Result<()> {
-        let memory_file_path = self.get_memory_file(category, is_global);
-        if !memory_file_path.exists() {
-            return Ok(());
+        query: &str,
+        budget: usize,
+    ) -> io::Result<(String, Vec<(String, bool)>)> {
+        let results = self.search(
+            query,
+            &SearchFilter::default(),
+            SearchMode::Hybrid,
+            MAX_RELEVANT,
+        )?;
+
+        let mut text = String::new();
+        let mut used_tokens = 0;
+        let mut used = Vec::new();
+        for (_, entry, is_global) in results {
+            let line = format!("- {}\n", format_entry(&entry));
+            // Rough token count, memories are short prose
+            let tokens = line.len().div_ceil(4);
+            if used_tokens + tokens > budget {
+                break;
+            }
+            used_tokens += tokens;
+            text.push_str(&line);
+            used.push((entry.id, is_global));
         }
 
-        let mut file = fs::File::open(&memory_file_path)?;
-        let mut content = String::new();
-        file.read_to_string(&mut content)?;
-
-        let memories: Vec<&str> = content.split("\n\n").collect();
-        let new_content: Vec<String> = memories
-            .into_iter()
-            .filter(|entry| !entry.contains(memory_content))
-            .map(|s| s.to_string())
-            .collect();
+        if text.is_empty() {
+            return Ok((String::new(), used));
+        }
+        let text = format!(
+            "Relevant memories (saved by the user earlier, use them only if they apply):\n{}",
+            text
+        );
+        Ok((text, used))
+    }
 
-        fs::write(memory_file_path, new_content.join("\n\n"))?;
+    /// Note that memories were injected, returning true if a flush has to be scheduled
+    ///
+    /// Usage is only kept in memory here, so reading relevant memories never writes the stores.
+    fn record_usage(&self, used: Vec<(String, bool)>) -> bool {
+        if used.is_empty() {
+            return false;
+        }
+        let mut pending = self.pending_usage.lock().unwrap();
+        for (id, is_global) in used {
+            pending.ids.entry(is_global).or_default().insert(id);
+        }
+        !std::mem::replace(&mut pending.flush_scheduled, true)
+    }
 
-        Ok(())
+    /// Save the collected usage, one write per scope, so used memories do not age out
+    fn flush_usage(&self) {
+        let ids = {
+            let mut pending = self.pending_usage.lock().unwrap();
+            pending.flush_scheduled = false;
+            std::mem::take(&mut pending.ids)
+        };
+        for (is_global, ids) in ids {
+            let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+            if let Err(e) = self.store(is_global).mark_used(&ids) {
+                tracing::warn!("Failed to record memory usage: {}", e);
+            }
+        }
     }
 
-    pub fn clear_memory(&self, category: &str, is_global: bool) -> io::Result<()> {
-        let memory_file_path = self.get_memory_file(category, is_global);
-        if memory_file_path.exists() {
-            fs::remove_file(memory_file_path)?;
+    /// Find which scope holds a memory id
+    fn locate(&self, id: &str) -> io::Result<&MemoryStore> {
+        for store in [&self.local, &self.global] {
+            if store.get(id)?.is_some() {
+                return Ok(store);
+            }
         }
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No memory with id '{}'", id),
+        ))
+    }
 
-        Ok(())
+    pub fn update_memory(&self, id: &str, update: MemoryUpdate) -> io::Result<MemoryEntry> {
+        self.locate(id)?.update(id, update)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No memory with id '{}'", id),
+            )
+        })
     }
 
-    pub fn clear_all_global_or_local_memories(&self, is_global: bool) -> io::Result<()> {
-        let base_dir = if is_global {
-            &self.global_memory_dir
-        } else {
-            &self.local_memory_dir
-        };
-        fs::remove_dir_all(base_dir)?;
-        Ok(())
+    pub fn remove_memory(&self, id: &str) -> io::Result<MemoryEntry> {
+        self.locate(id)?.remove(id)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No memory with id '{}'", id),
+            )
+        })
+    }
+
+    pub fn clear_memory(&self, category: &str, is_global: bool) -> io::Result<usize> {
+        self.store(is_global).clear_category(category)
+    }
+
+    pub fn clear_all_global_or_local_memories(&self, is_global: bool) -> io::Result<usize> {
+        self.store(is_global).clear()
     }
 
     async fn execute_tool_call(&self, tool_call: ToolCall) -> Result<String, io::Error> {
@@ -431,8 +509,17 @@ impl MemoryRouter {
                         "Data must exist when remembering a memory",
                     )
                 })?;
-                self.remember("context", args.category, data, &args.tags, args.is_global)?;
-                Ok(format!("Stored memory in category: {}", args.category))
+                let entry = self.remember(
+                    "remember_memory",
+                    args.category,
+                    data,
+                    &args.tags,
+                    args.is_global,
+                )?;
+                Ok(format!(
+                    "Stored memory {} in category: {}",
+                    entry.id, args.category
+                ))
             }
             "retrieve_memories" => {
                 let args = MemoryArgs::from_value(&tool_call.arguments)?;
@@ -441,7 +528,67 @@ impl MemoryRouter {
                 } else {
                     self.retrieve(args.category, args.is_global)?
                 };
-                Ok(format!("Retrieved memories: {:?}", memories))
+                if memories.is_empty() {
+                    Ok("No memories found".to_string())
+                } else {
+                    Ok(format_by_category(&memories))
+                }
+            }
+            "search_memories" => {
+                let args = &tool_call.arguments;
+                let query = args["query"].as_str().unwrap_or_default();
+                let filter = SearchFilter::from_value(args)?;
+                let mode = match args.get("mode").and_then(|m| m.as_str()) {
+                    Some(mode) => SearchMode::parse(mode).ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "mode must be one of keyword, semantic or hybrid",
+                        )
+                    })?,
+                    None => SearchMode::default(),
+                };
+                let limit = args.get("limit").and_then(|l| l.as_u64()).unwrap_or(10) as usize;
+
+                let results = self.search(query, &filter, mode, limit)?;
+                if results.is_empty() {
+                    return Ok(format!("No memories match '{}'", query));
+                }
+                Ok(results
+                    .iter()
+                    .map(|(score, entry, is_global)| {
+                        format!(
+                            "{} (score {:.2}, {} memory)",
+                            format_entry(entry),
+                            score,
+                            if *is_global { "global" } else { "local" }
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            "update_memory" => {
+                let args = &tool_call.arguments;
+                let id = required_str(args, "id")?;
+                let update = MemoryUpdate {
+                    category: args
+                        .get("category")
+                        .and_then(|c| c.as_str())
+                        .filter(|c| !c.is_empty())
+                        .map(String::from),
+                    content: args
+                        .get("data")
+                        .and_then(|d| d.as_str())
+                        .filter(|d| !d.is_empty())
+                        .map(String::from),
+                    tags: args.get("tags").map(|_| string_list(&args["tags"])),
+                };
+                let entry = self.update_memory(id, update)?;
+                Ok(format!("Updated memory {}", format_entry(&entry)))
+            }
+            "remove_memory" => {
+                let id = required_str(&tool_call.arguments, "id")?;
+                let entry = self.remove_memory(id)?;
+                Ok(format!("Removed memory {}", format_entry(&entry)))
             }
             "remove_memory_category" => {
                 let args = MemoryArgs::from_value(&tool_call.arguments)?;
@@ -456,20 +603,76 @@ impl MemoryRouter {
                     Ok(format!("Cleared memories in category: {}", args.category))
                 }
             }
-            "remove_specific_memory" => {
-                let args = MemoryArgs::from_value(&tool_call.arguments)?;
-                let memory_content = tool_call.arguments["memory_content"].as_str().unwrap();
-                self.remove_specific_memory(args.category, memory_content, args.is_global)?;
-                Ok(format!(
-                    "Removed specific memory from category: {}",
-                    args.category
-                ))
-            }
             _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "Unknown tool")),
         }
     }
 }
 
+/// One memory per line, with its id so it can be updated or removed
+fn format_entry(entry: &MemoryEntry) -> String {
+    let tags = if entry.tags.is_empty() {
+        String::new()
+    } else {
+        format!(
+            " {}",
+            entry
+                .tags
+                .iter()
+                .map(|t| format!("#{}", t))
+                .collect::<Vec<_>>()
+                .join(" ")
+        )
+    };
+    format!(
+        "[{}] ({}) {}{} (saved {}{})",
+        entry.id,
+        entry.category,
+        entry.content.replace('\n', " "),
+        tags,
+        entry.updated_at.format("%Y-%m-%d"),
+        if entry.archived { ", archived" } else { "" }
+    )
+}
+
+fn format_by_category(entries: &[MemoryEntry]) -> String {
+    let mut categories: Vec<&str> = entries.iter().map(|e| e.category.as_str()).collect();
+    categories.sort();
+    categories.dedup();
+
+    let mut text = String::new();
+    for category in categories {
+        text.push_str(&format!("\nCategory: {}\n", category));
+        for entry in entries.iter().filter(|e| e.category == category) {
+            text.push_str(&format!("- {}\n", format_entry(entry)));
+        }
+    }
+    text
+}
+
+fn required_str<'a>(args: &'a Value, name: &str) -> Result<&'a str, io::Error> {
+    args.get(name)
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} must be a string", name),
+            )
+        })
+}
+
+fn string_list(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(arr) => arr
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(String::from)
+            .collect(),
+        Value::String(s) => s.split_whitespace().map(String::from).collect(),
+        _ => Vec::new(),
+    }
+}
+
 #[async_trait]
 impl Router for MemoryRouter {
     fn name(&self) -> String {
@@ -481,7 +684,10 @@ impl Router for MemoryRouter {
     }
 
     fn capabilities(&self) -> ServerCapabilities {
-        CapabilitiesBuilder::new().with_tools(false).build()
+        CapabilitiesBuilder::new()
+            .with_tools(false)
+            .with_resources(false, false)
+            .build()
     }
 
     fn list_tools(&self) -> Vec<Tool> {
@@ -509,14 +715,60 @@ impl Router for MemoryRouter {
     }
 
     fn list_resources(&self) -> Vec<Resource> {
-        Vec::new()
+        // Listed so the agent can tell this server provides relevant memories, it is not active
+        // because reading it needs a query
+        Resource::with_uri(
+            RELEVANT_MEMORIES_URI,
+            "relevant memories",
+            0.0,
+            Some("text".to_string()),
+        )
+        .into_iter()
+        .collect()
     }
 
+    /// `memory://relevant?query=<message>&budget=<tokens>` returns the memories relevant to a
+    /// message. The agent reads it for each user message instead of loading every memory
+    /// into the system prompt. Reading it does not write the stores, usage of the returned
+    /// memories is saved in a batch a little later.
     fn read_resource(
         &self,
-        _uri: &str,
+        uri: &str,
     ) -> Pin<Box<dyn Future<Output = Result<String, ResourceError>> + Send + 'static>> {
-        Box::pin(async move { Ok("".to_string()) })
+        let this = self.clone();
+        let uri = uri.to_string();
+        Box::pin(async move {
+            let url = url::Url::parse(&uri)
+                .map_err(|e| ResourceError::NotFound(format!("Invalid uri {}: {}", uri, e)))?;
+            if url.scheme() != "memory" || url.host_str() != Some("relevant") {
+                return Err(ResourceError::NotFound(format!(
+                    "Resource {} not found",
+                    uri
+                )));
+            }
+
+            let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+            let query = params.get("query").cloned().unwrap_or_default();
+            let budget = params
+                .get("budget")
+                .and_then(|b| b.parse().ok())
+                .unwrap_or(DEFAULT_RELEVANT_BUDGET);
+
+            let router = this.clone();
+            let (text, used) =
+                tokio::task::spawn_blocking(move || router.relevant_memories(&query, budget))
+                    .await
+                    .map_err(|e| ResourceError::ExecutionError(e.to_string()))?
+                    .map_err(|e| ResourceError::ExecutionError(e.to_string()))?;
+
+            if this.record_usage(used) {
+                tokio::spawn(async move {
+                    tokio::time::sleep(USAGE_FLUSH_DELAY).await;
+                    let _ = tokio::task::spawn_blocking(move || this.flush_usage()).await;
+                });
+            }
+            Ok(text)
+        })
     }
     fn list_prompts(&self) -> Vec<Prompt> {
         vec![]
@@ -587,3 +839,46 @@ impl<'a> MemoryArgs<'a> {
         })
     }
 }
+
+/// Which memories a search considers
+#[derive(Debug, Default)]
+pub struct SearchFilter {
+    pub tags: Vec<String>,
+    pub category: Option<String>,
+    /// Only global or only local memories, both when None
+    pub is_global: Option<bool>,
+    pub include_archived: bool,
+}
+
+impl SearchFilter {
+    fn from_value(args: &Value) -> Result<Self, io::Error> {
+        let is_global = match args.get("is_global") {
+            Some(Value::Bool(b)) => Some(*b),
+            Some(Value::String(s)) => Some(s.to_lowercase() == "true"),
+            None | Some(Value::Null) => None,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "is_global must be a boolean or string 'true'/'false'",
+                ))
+            }
+        };
+
+        Ok(Self {
+            tags: string_list(&args["tags"])
+                .into_iter()
+                .map(|t| t.trim_start_matches('#').to_lowercase())
+                .collect(),
+            category: args
+                .get("category")
+                .and_then(|c| c.as_str())
+                .filter(|c| !c.is_empty() && *c != "*")
+                .map(String::from),
+            is_global,
+            include_archived: args
+                .get("include_archived")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+        })
+    }
+}
@@ -0,0 +1,643 @@
+//! Structured storage and search for memories.
+//!
+//! Each scope (global or local) keeps its memories in a single `memories.json` file, written
+//! atomically. Memories have stable ids so they can be updated or removed precisely.
+//! Category files from the previous plain text format are migrated on first open.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+const STORE_FILENAME: &str = "memories.json";
+/// Dimensions of the hashed n-gram vectors used for similarity search
+const EMBEDDING_DIMS: usize = 512;
+/// Semantic matches below this cosine similarity are dropped
+const MIN_SIMILARITY: f32 = 0.15;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MemoryEntry {
+    pub id: String,
+    pub category: String,
+    pub content: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Where the memory came from, such as the tool that stored it or the file it was migrated from
+    pub source: String,
+    /// When the memory was last injected into a conversation
+    #[serde(default)]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub use_count: u32,
+    /// Archived memories are no longer injected automatically but can still be searched
+    #[serde(default)]
+    pub archived: bool,
+}
+
+impl MemoryEntry {
+    /// The last time the memory was stored, changed or used
+    pub fn last_active(&self) -> DateTime<Utc> {
+        self.last_used_at
+            .map_or(self.updated_at, |used| used.max(self.updated_at))
+    }
+
+    fn searchable_text(&self) -> String {
+        format!("{} {} {}", self.category, self.tags.join(" "), self.content)
+    }
+}
+
+/// Changes to apply to a memory, fields left as None are kept
+#[derive(Debug, Default)]
+pub struct MemoryUpdate {
+    pub category: Option<String>,
+    pub content: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+pub struct MemoryStore {
+    dir: PathBuf,
+}
+
+impl MemoryStore {
+    /// Open the store in `dir`, migrating any legacy category files found there
+    ///
+    /// A failed migration is logged and leaves the legacy files in place, so the memories that
+    /// can be read are still available and the migration is retried on the next open.
+    pub fn open(dir: PathBuf) -> Self {
+        let store = Self { dir };
+        if let Err(e) = store.migrate_legacy() {
+            tracing::warn!(
+                "Failed to migrate legacy memories in {}: {}",
+                store.dir.display(),
+                e
+            );
+        }
+        store
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(STORE_FILENAME)
+    }
+
+    pub fn load(&self) -> io::Result<Vec<MemoryEntry>> {
+        match fs::read_to_string(self.path()) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write all entries, through a temporary file so a crash never leaves a partial store
+    fn save(&self, entries: &[MemoryEntry]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let json = serde_json::to_string_pretty(entries)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let tmp = self.dir.join(format!("{}.tmp", STORE_FILENAME));
+        fs::write(&tmp, json)?;
+        fs::rename(tmp, self.path())
+    }
+
+    pub fn add(
+        &self,
+        category: &str,
+        content: &str,
+        tags: &[&str],
+        source: &str,
+    ) -> io::Result<MemoryEntry> {
+        let mut entries = self.load()?;
+        let now = Utc::now();
+        let entry = MemoryEntry {
+            id: new_id(&entries),
+            category: category.to_string(),
+            content: content.to_string(),
+            tags: normalize_tags(tags.iter().copied()),
+            created_at: now,
+            updated_at: now,
+            source: source.to_string(),
+            last_used_at: None,
+            use_count: 0,
+            archived: false,
+        };
+        entries.push(entry.clone());
+        self.save(&entries)?;
+        Ok(entry)
+    }
+
+    pub fn get(&self, id: &str) -> io::Result<Option<MemoryEntry>> {
+        Ok(self.load()?.into_iter().find(|e| e.id == id))
+    }
+
+    pub fn update(&self, id: &str, update: MemoryUpdate) -> io::Result<Option<MemoryEntry>> {
+        let mut entries = self.load()?;
+        let Some(entry) = entries.iter_mut().find(|e| e.id == id) else {
+            return Ok(None);
+        };
+
+        if let Some(category) = update.category {
+            entry.category = category;
+        }
+        if let Some(content) = update.content {
+            entry.content = content;
+        }
+        if let Some(tags) = update.tags {
+            entry.tags = normalize_tags(tags.iter().map(String::as_str));
+        }
+        // Changing a memory shows it is still relevant
+        entry.updated_at = Utc::now();
+        entry.archived = false;
+
+        let updated = entry.clone();
+        self.save(&entries)?;
+        Ok(Some(updated))
+    }
+
+    pub fn remove(&self, id: &str) -> io::Result<Option<MemoryEntry>> {
+        let mut entries = self.load()?;
+        let Some(index) = entries.iter().position(|e| e.id == id) else {
+            return Ok(None);
+        };
+        let removed = entries.remove(index);
+        self.save(&entries)?;
+        Ok(Some(removed))
+    }
+
+    /// Memories in a category, or all memories when the category is None
+    pub fn list(&self, category: Option<&str>) -> io::Result<Vec<MemoryEntry>> {
+        let mut entries = self.load()?;
+        if let Some(category) = category {
+            entries.retain(|e| e.category == category);
+        }
+        Ok(entries)
+    }
+
+    /// Record that memories were used in a conversation
+    pub fn mark_used(&self, ids: &[&str]) -> io::Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let mut entries = self.load()?;
+        let now = Utc::now();
+        for entry in entries.iter_mut().filter(|e| ids.contains(&e.id.as_str())) {
+            entry.last_used_at = Some(now);
+            entry.use_count += 1;
+        }
+        self.save(&entries)
+    }
+
+    /// Archive memories that have not been stored, changed or used within `max_age`,
+    /// returning how many were archived. An age reaching past the earliest representable
+    /// time archives nothing.
+    pub fn archive_stale(&self, max_age: chrono::Duration) -> io::Result<usize> {
+        let Some(cutoff) = Utc::now().checked_sub_signed(max_age) else {
+            return Ok(0);
+        };
+        let mut entries = self.load()?;
+        let mut archived = 0;
+        for entry in entries
+            .iter_mut()
+            .filter(|e| !e.archived && e.last_active() < cutoff)
+        {
+            entry.archived = true;
+            archived += 1;
+        }
+        if archived > 0 {
+            self.save(&entries)?;
+        }
+        Ok(archived)
+    }
+
+    /// Remove every memory in a category, returning how many were removed
+    pub fn clear_category(&self, category: &str) -> io::Result<usize> {
+        let mut entries = self.load()?;
+        let before = entries.len();
+        entries.retain(|e| e.category != category);
+        self.save(&entries)?;
+        Ok(before - entries.len())
+    }
+
+    pub fn clear(&self) -> io::Result<usize> {
+        let count = self.load()?.len();
+        self.save(&[])?;
+        Ok(count)
+    }
+
+    /// Import `<category>.txt` files written by earlier versions, then rename them so they are
+    /// only migrated once
+    fn migrate_legacy(&self) -> io::Result<()> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let mut legacy: Vec<PathBuf> = dir
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "txt"))
+            .collect();
+        if legacy.is_empty() {
+            return Ok(());
+        }
+        legacy.sort();
+
+        let mut entries = self.load()?;
+        for path in &legacy {
+            let category = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            let created_at = fs::metadata(path)
+                .and_then(|m| m.modified())
+                .map(DateTime::<Utc>::from)
+                .unwrap_or_else(|_| Utc::now());
+
+            for (tags, content) in parse_legacy(&fs::read_to_string(path)?) {
+                entries.push(MemoryEntry {
+                    id: new_id(&entries),
+                    category: category.clone(),
+                    content,
+                    tags,
+                    created_at,
+                    updated_at: created_at,
+                    source: format!("migrated from {}", file_name),
+                    last_used_at: None,
+                    use_count: 0,
+                    archived: false,
+                });
+            }
+        }
+        self.save(&entries)?;
+
+        for path in legacy {
+            let mut migrated = path.clone().into_os_string();
+            migrated.push(".migrated");
+            fs::rename(&path, migrated)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse the legacy format: entries separated by blank lines, with an optional `# tag tag`
+/// first line
+fn parse_legacy(content: &str) -> Vec<(Vec<String>, String)> {
+    content
+        .split("\n\n")
+        .filter_map(|block| {
+            let mut lines = block.lines().peekable();
+            let tags = match lines.peek().and_then(|l| l.strip_prefix('#')) {
+                Some(tags) => {
+                    let tags = normalize_tags(tags.split_whitespace());
+                    lines.next();
+                    tags
+                }
+                None => Vec::new(),
+            };
+            let text = lines.collect::<Vec<_>>().join("\n").trim().to_string();
+            (!text.is_empty()).then_some((tags, text))
+        })
+        .collect()
+}
+
+fn normalize_tags<'a>(tags: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    tags.map(|t| t.trim().trim_start_matches('#').to_lowercase())
+        .filter(|t| !t.is_empty() && seen.insert(t.clone()))
+        .collect()
+}
+
+fn new_id(existing: &[MemoryEntry]) -> String {
+    loop {
+        let id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+        if !existing.iter().any(|e| e.id == id) {
+            return id;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    /// Ranks by shared words, weighted by how rare they are
+    #[default]
+    Keyword,
+    /// Ranks by similarity of hashed character n-grams, which also matches word variants
+    Semantic,
+    /// Averages the keyword and semantic scores
+    Hybrid,
+}
+
+impl SearchMode {
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "keyword" => Some(Self::Keyword),
+            "semantic" => Some(Self::Semantic),
+            "hybrid" => Some(Self::Hybrid),
+            _ => None,
+        }
+    }
+}
+
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "with", "that", "this", "are", "was", "use", "you", "our", "not", "but",
+    "from", "have", "has", "what", "when", "how", "which", "into", "its",
+];
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() > 1)
+        .map(|w| w.to_lowercase())
+        .filter(|w| !STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
+
+/// A local embedding: character trigrams of every word hashed into a fixed size vector.
+/// This needs no model, and captures shared stems and spelling variants.
+fn embed(text: &str) -> Vec<f32> {
+    let mut vector = vec![0f32; EMBEDDING_DIMS];
+    for word in tokenize(text) {
+        let padded: Vec<char> = format!("^{}$", word).chars().collect();
+        for gram in padded.windows(3) {
+            let hash = gram.iter().fold(0xcbf29ce484222325u64, |h, c| {
+                (h ^ *c as u64).wrapping_mul(0x100000001b3)
+            });
+            vector[(hash % EMBEDDING_DIMS as u64) as usize] += 1.0;
+        }
+    }
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// BM25 scores of each document against the query terms
+fn keyword_scores(documents: &[Vec<String>], query: &[String]) -> Vec<f32> {
+    const K1: f32 = 1.2;
+    const B: f32 = 0.75;
+
+    let count = documents.len() as f32;
+    let average_length = documents.iter().map(|d| d.len()).sum::<usize>() as f32 / count.max(1.0);
+    let unique_terms: HashSet<&String> = query.iter().collect();
+
+    let document_frequency: HashMap<&String, f32> = unique_terms
+        .iter()
+        .map(|term| {
+            let frequency = documents.iter().filter(|d| d.contains(term)).count() as f32;
+            (*term, frequency)
+        })
+        .collect();
+
+    documents
+        .iter()
+        .map(|document| {
+            unique_terms
+                .iter()
+                .map(|term| {
+                    let frequency = document.iter().filter(|w| w == term).count() as f32;
+                    if frequency == 0.0 {
+                        return 0.0;
+                    }
+                    let df = document_frequency[term];
+                    let idf = ((count - df + 0.5) / (df + 0.5) + 1.0).ln();
+                    idf * frequency * (K1 + 1.0)
+                        / (frequency
+                            + K1 * (1.0 - B + B * document.len() as f32 / average_length.max(1.0)))
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// Rank memories against a query, best first, dropping those that do not match at all
+pub fn search<'a>(
+    entries: &'a [MemoryEntry],
+    query: &str,
+    mode: SearchMode,
+    limit: usize,
+) -> Vec<(f32, &'a MemoryEntry)> {
+    let query_terms = tokenize(query);
+    let keyword = match mode {
+        SearchMode::Semantic => None,
+        _ => {
+            let documents: Vec<Vec<String>> = entries
+                .iter()
+                .map(|e| tokenize(&e.searchable_text()))
+                .collect();
+            Some(keyword_scores(&documents, &query_terms))
+        }
+    };
+    let semantic = match mode {
+        SearchMode::Keyword => None,
+        _ => {
+            let query_vector = embed(query);
+            Some(
+                entries
+                    .iter()
+                    .map(|e| cosine(&query_vector, &embed(&e.searchable_text())))
+                    .collect::<Vec<_>>(),
+            )
+        }
+    };
+
+    let max_keyword = keyword
+        .as_ref()
+        .map(|scores| scores.iter().cloned().fold(0.0, f32::max))
+        .unwrap_or(0.0);
+
+    let mut results: Vec<(f32, &MemoryEntry)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entry)| {
+            let keyword = keyword.as_ref().map(|s| s[i]);
+            let semantic = semantic.as_ref().map(|s| s[i]);
+            let score = match (keyword, semantic) {
+                (Some(k), None) => (k > 0.0).then_some(k),
+                (None, Some(s)) => (s >= MIN_SIMILARITY).then_some(s),
+                (Some(k), Some(s)) => {
+                    let k = if max_keyword > 0.0 {
+                        k / max_keyword
+                    } else {
+                        0.0
+                    };
+                    (k > 0.0 || s >= MIN_SIMILARITY).then_some((k + s) / 2.0)
+                }
+                (None, None) => None,
+            }?;
+            Some((score, entry))
+        })
+        .collect();
+
+    results.sort_by(|a, b| b.0.total_cmp(&a.0));
+    results.truncate(limit);
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(content: &str, tags: &[&str]) -> MemoryEntry {
+        MemoryEntry {
+            id: content.chars().take(4).collect(),
+            category: "development".to_string(),
+            content: content.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            source: "test".to_string(),
+            last_used_at: None,
+            use_count: 0,
+            archived: false,
+        }
+    }
+
+    #[test]
+    fn test_add_update_remove_by_id() {
+        let dir = TempDir::new().unwrap();
+        let store = MemoryStore::open(dir.path().to_path_buf());
+
+        let first = store
+            .add(
+                "development",
+                "Use black for formatting",
+                &["#Formatting", "tools"],
+                "remember_memory",
+            )
+            .unwrap();
+        let second = store
+            .add(
+                "development",
+                "Use black for formatting notebooks",
+                &[],
+                "remember_memory",
+            )
+            .unwrap();
+        assert_ne!(first.id, second.id);
+        assert_eq!(first.tags, vec!["formatting", "tools"]);
+
+        let updated = store
+            .update(
+                &first.id,
+                MemoryUpdate {
+                    content: Some("Use ruff for formatting".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.content, "Use ruff for formatting");
+        assert_eq!(updated.tags, first.tags);
+        assert!(updated.updated_at >= first.updated_at);
+
+        // Removing by id leaves the similar memory alone
+        assert!(store.remove(&first.id).unwrap().is_some());
+        assert!(store.remove(&first.id).unwrap().is_none());
+        assert_eq!(store.list(None).unwrap(), vec![second]);
+    }
+
+    #[test]
+    fn test_migrates_legacy_files() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("development.txt"),
+            "# formatting tools\nUse black\n\nRun tests with pytest\n\n",
+        )
+        .unwrap();
+
+        let store = MemoryStore::open(dir.path().to_path_buf());
+        let entries = store.list(Some("development")).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].content, "Use black");
+        assert_eq!(entries[0].tags, vec!["formatting", "tools"]);
+        assert_eq!(entries[1].content, "Run tests with pytest");
+        assert!(entries[1].tags.is_empty());
+        assert_eq!(entries[1].source, "migrated from development.txt");
+
+        assert!(!dir.path().join("development.txt").exists());
+        assert!(dir.path().join("development.txt.migrated").exists());
+
+        // Opening again does not import twice
+        let store = MemoryStore::open(dir.path().to_path_buf());
+        assert_eq!(store.list(None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_failed_migration_keeps_legacy_files() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("development.txt"), b"Use black\xff\n").unwrap();
+
+        // The store still opens, and the file is left to be migrated once it can be read
+        let store = MemoryStore::open(dir.path().to_path_buf());
+        assert!(store.list(None).unwrap().is_empty());
+        assert!(dir.path().join("development.txt").exists());
+
+        fs::write(dir.path().join(STORE_FILENAME), "not json").unwrap();
+        fs::write(dir.path().join("development.txt"), "Use black\n").unwrap();
+        let store = MemoryStore::open(dir.path().to_path_buf());
+        assert!(store.list(None).is_err());
+        assert!(dir.path().join("development.txt").exists());
+    }
+
+    #[test]
+    fn test_search_modes() {
+        let entries = vec![
+            entry("We format Python code with black", &["formatting"]),
+            entry("Deploy with kubectl apply", &["deployment"]),
+            entry("The staging database is postgres", &[]),
+        ];
+
+        let results = search(&entries, "python formatting", SearchMode::Keyword, 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1.content, entries[0].content);
+
+        // Semantic search matches word variants that share no exact keyword
+        assert!(search(&entries, "deploying", SearchMode::Keyword, 10).is_empty());
+        let results = search(&entries, "deploying", SearchMode::Semantic, 10);
+        assert_eq!(results[0].1.content, entries[1].content);
+
+        let results = search(&entries, "postgres deployment", SearchMode::Hybrid, 1);
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn test_archive_stale_memories() {
+        let dir = TempDir::new().unwrap();
+        let store = MemoryStore::open(dir.path().to_path_buf());
+        let old = store.add("notes", "Old memory", &[], "test").unwrap();
+        let used = store.add("notes", "Used memory", &[], "test").unwrap();
+
+        // Backdate both, then use one of them
+        let mut entries = store.load().unwrap();
+        for entry in entries.iter_mut() {
+            entry.updated_at -= chrono::Duration::days(120);
+        }
+        store.save(&entries).unwrap();
+        store.mark_used(&[used.id.as_str()]).unwrap();
+
+        assert_eq!(store.archive_stale(chrono::Duration::days(90)).unwrap(), 1);
+        let old = store.get(&old.id).unwrap().unwrap();
+        assert!(old.archived);
+        let used = store.get(&used.id).unwrap().unwrap();
+        assert!(!used.archived);
+        assert_eq!(used.use_count, 1);
+
+        // Updating an archived memory brings it back
+        let restored = store
+            .update(&old.id, MemoryUpdate::default())
+            .unwrap()
+            .unwrap();
+        assert!(!restored.archived);
+
+        // An age too long to subtract from now archives nothing
+        assert_eq!(store.archive_stale(chrono::Duration::MAX).unwrap(), 0);
+    }
+}
@@ -0,0 +1,319 @@
+//! Finding tutorials and reading their front matter.
+//!
+//! Tutorials are markdown files, optionally starting with YAML front matter between `---`
+//! lines. They are loaded from, in increasing order of precedence:
+//! - the tutorials built into goose
+//! - `tutorials` directories of installed extensions, `<goose config>/extensions/<name>/tutorials`
+//! - team directories listed in `GOOSE_TUTORIAL_PATH`, separated like `PATH`
+//! - the user's own `<goose config>/tutorials`
+//!
+//! so a team or user tutorial with the same name as a built-in one replaces it.
+
+use etcetera::{choose_app_strategy, AppStrategy};
+use include_dir::{include_dir, Dir};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+static TUTORIALS_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/src/tutorial/tutorials");
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Tutorials to complete first, or other things the user needs
+    pub prerequisites: Vec<String>,
+    /// Extensions the tutorial uses
+    pub extensions: Vec<String>,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Step {
+    pub id: String,
+    pub title: String,
+    pub verify: Option<Verify>,
+}
+
+/// How to check that a step was done before checkpointing it, written as
+/// `verify: {command: ...}` or `verify: {file_exists: ...}`
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(try_from = "VerifySpec")]
+pub enum Verify {
+    /// A shell command that exits successfully once the step is done
+    Command(String),
+    /// A file that exists once the step is done, relative to the working directory
+    FileExists(String),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VerifySpec {
+    command: Option<String>,
+    file_exists: Option<String>,
+}
+
+impl TryFrom<VerifySpec> for Verify {
+    type Error = String;
+
+    fn try_from(spec: VerifySpec) -> Result<Self, Self::Error> {
+        match (spec.command, spec.file_exists) {
+            (Some(command), None) => Ok(Verify::Command(command)),
+            (None, Some(path)) => Ok(Verify::FileExists(path)),
+            _ => Err("verify needs exactly one of command or file_exists".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Tutorial {
+    pub name: String,
+    /// Where the tutorial came from, such as "built-in" or a directory
+    pub source: String,
+    pub front_matter: FrontMatter,
+    /// The markdown after the front matter
+    pub body: String,
+}
+
+impl Tutorial {
+    pub fn parse(name: &str, source: &str, content: &str) -> Result<Self, String> {
+        let (front_matter, body) = split_front_matter(content);
+        let front_matter: FrontMatter = match front_matter {
+            Some(yaml) => serde_yaml::from_str(yaml)
+                .map_err(|e| format!("Invalid front matter in tutorial '{}': {}", name, e))?,
+            None => FrontMatter::default(),
+        };
+        if let Some(step) = front_matter.steps.iter().find(|s| s.id.is_empty()) {
+            return Err(format!(
+                "Step '{}' in tutorial '{}' has no id",
+                step.title, name
+            ));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            source: source.to_string(),
+            front_matter,
+            body: body.to_string(),
+        })
+    }
+
+    /// One line summary: the description, else the first line of the body
+    pub fn summary(&self) -> String {
+        self.front_matter
+            .description
+            .clone()
+            .or_else(|| self.front_matter.title.clone())
+            .unwrap_or_else(|| {
+                self.body
+                    .lines()
+                    .find(|line| !line.trim().is_empty())
+                    .unwrap_or_default()
+                    .to_string()
+            })
+    }
+
+    pub fn step(&self, id: &str) -> Option<&Step> {
+        self.front_matter.steps.iter().find(|s| s.id == id)
+    }
+}
+
+fn split_front_matter(content: &str) -> (Option<&str>, &str) {
+    let content = content.trim_start_matches('\u{feff}');
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return (None, content);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, content)
+}
+
+/// All available tutorials by name
+pub struct Catalog {
+    tutorials: BTreeMap<String, Tutorial>,
+    /// Files that could not be loaded, with why
+    pub errors: Vec<String>,
+}
+
+impl Catalog {
+    pub fn load() -> Self {
+        let config_dir = choose_app_strategy(crate::APP_STRATEGY.clone())
+            .map(|strategy| strategy.config_dir())
+            .ok();
+
+        let mut dirs: Vec<(PathBuf, String)> = Vec::new();
+        if let Some(config_dir) = &config_dir {
+            if let Ok(extensions) = fs::read_dir(config_dir.join("extensions")) {
+                let mut extensions: Vec<PathBuf> = extensions
+                    .filter_map(|entry| entry.ok().map(|e| e.path()))
+                    .collect();
+                extensions.sort();
+                for extension in extensions {
+                    let name = extension
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    dirs.push((extension.join("tutorials"), format!("extension {}", name)));
+                }
+            }
+        }
+        if let Some(path) = env::var_os("GOOSE_TUTORIAL_PATH") {
+            for dir in env::split_paths(&path) {
+                let source = dir.display().to_string();
+                dirs.push((dir, source));
+            }
+        }
+        if let Some(config_dir) = &config_dir {
+            let dir = config_dir.join("tutorials");
+            let source = dir.display().to_string();
+            dirs.push((dir, source));
+        }
+        Self::load_from(&dirs)
+    }
+
+    /// Load the built-in tutorials, then each directory in turn
+    pub fn load_from(dirs: &[(PathBuf, String)]) -> Self {
+        let mut catalog = Self {
+            tutorials: BTreeMap::new(),
+            errors: Vec::new(),
+        };
+        for file in TUTORIALS_DIR.files() {
+            if let (Some(name), Some(content)) = (file.path().file_stem(), file.contents_utf8()) {
+                catalog.add(&name.to_string_lossy(), "built-in", content);
+            }
+        }
+        for (dir, source) in dirs {
+            catalog.add_dir(dir, source);
+        }
+        catalog
+    }
+
+    fn add_dir(&mut self, dir: &Path, source: &str) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let Some(name) = path.file_stem().map(|n| n.to_string_lossy().into_owned()) else {
+                continue;
+            };
+            match fs::read_to_string(&path) {
+                Ok(content) => self.add(&name, source, &content),
+                Err(e) => self
+                    .errors
+                    .push(format!("Could not read {}: {}", path.display(), e)),
+            }
+        }
+    }
+
+    fn add(&mut self, name: &str, source: &str, content: &str) {
+        match Tutorial::parse(name, source, content) {
+            Ok(tutorial) => {
+                self.tutorials.insert(name.to_string(), tutorial);
+            }
+            Err(e) => self.errors.push(e),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Tutorial> {
+        self.tutorials.get(name)
+    }
+
+    pub fn tutorials(&self) -> impl Iterator<Item = &Tutorial> {
+        self.tutorials.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TUTORIAL: &str = "---
+title: Onboarding
+description: Set up the team repo
+prerequisites: [first-game, a GitHub account]
+extensions: [developer]
+steps:
+  - id: clone
+    title: Clone the repo
+    verify:
+      file_exists: repo/README.md
+  - id: build
+    title: Build it
+    verify:
+      command: make build
+  - id: explore
+    title: Look around
+---
+# Onboarding
+
+Welcome!
+";
+
+    #[test]
+    fn test_parses_front_matter() {
+        let tutorial = Tutorial::parse("onboarding", "team", TUTORIAL).unwrap();
+        let fm = &tutorial.front_matter;
+        assert_eq!(fm.title.as_deref(), Some("Onboarding"));
+        assert_eq!(fm.prerequisites, vec!["first-game", "a GitHub account"]);
+        assert_eq!(fm.extensions, vec!["developer"]);
+        assert_eq!(fm.steps.len(), 3);
+        assert_eq!(
+            tutorial.step("clone").unwrap().verify,
+            Some(Verify::FileExists("repo/README.md".to_string()))
+        );
+        assert_eq!(
+            tutorial.step("build").unwrap().verify,
+            Some(Verify::Command("make build".to_string()))
+        );
+        assert_eq!(tutorial.step("explore").unwrap().verify, None);
+        assert!(tutorial.body.starts_with("# Onboarding"));
+        assert_eq!(tutorial.summary(), "Set up the team repo");
+
+        // Without front matter, the first line describes the tutorial
+        let plain = Tutorial::parse("plain", "team", "# Plain\n---\ntext").unwrap();
+        assert_eq!(plain.summary(), "# Plain");
+        assert!(plain.front_matter.steps.is_empty());
+
+        assert!(Tutorial::parse("bad", "team", "---\nsteps: [{title: x}]\n---\n").is_err());
+    }
+
+    #[test]
+    fn test_later_directories_take_precedence() {
+        let team = tempfile::tempdir().unwrap();
+        let user = tempfile::tempdir().unwrap();
+        fs::write(team.path().join("onboarding.md"), TUTORIAL).unwrap();
+        fs::write(team.path().join("first-game.md"), "# Team game").unwrap();
+        fs::write(team.path().join("broken.md"), "---\nsteps: 3\n---\n").unwrap();
+        fs::write(team.path().join("notes.txt"), "not a tutorial").unwrap();
+        fs::write(user.path().join("first-game.md"), "# My game").unwrap();
+
+        let catalog = Catalog::load_from(&[
+            (team.path().to_path_buf(), "team".to_string()),
+            (user.path().to_path_buf(), "user".to_string()),
+        ]);
+        assert_eq!(
+            catalog.get("build-mcp-extension").unwrap().source,
+            "built-in"
+        );
+        assert_eq!(catalog.get("onboarding").unwrap().source, "team");
+        assert_eq!(catalog.get("first-game").unwrap().body, "# My game");
+        assert!(catalog.get("notes").is_none());
+        assert_eq!(catalog.errors.len(), 1);
+    }
+}
@@ -1,8 +1,14 @@
+mod catalog;
+mod progress;
+
 use anyhow::Result;
-use include_dir::{include_dir, Dir};
-use indoc::formatdoc;
+use indoc::{formatdoc, indoc};
 use serde_json::{json, Value};
-use std::{future::Future, pin::Pin};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
 
 use mcp_core::{
     handler::{PromptError, ResourceError, ToolError},
@@ -17,11 +23,14 @@ use mcp_server::Router;
 
 use mcp_core::content::Content;
 
-static TUTORIALS_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/src/tutorial/tutorials");
+use self::catalog::{Catalog, Tutorial};
+use self::progress::ProgressStore;
 
 pub struct TutorialRouter {
     tools: Vec<Tool>,
     instructions: String,
+    catalog: Arc<Catalog>,
+    progress: Arc<Mutex<ProgressStore>>,
 }
 
 impl Default for TutorialRouter {
@@ -32,9 +41,20 @@ impl Default for TutorialRouter {
 
 impl TutorialRouter {
     pub fn new() -> Self {
+        Self::with_catalog(
+            Catalog::load(),
+            ProgressStore::open(ProgressStore::default_path()),
+        )
+    }
+
+    fn with_catalog(catalog: Catalog, progress: ProgressStore) -> Self {
+        for error in &catalog.errors {
+            tracing::warn!("Skipping tutorial: {}", error);
+        }
+
         let load_tutorial = Tool::new(
             "load_tutorial".to_string(),
-            "Load a specific tutorial by name. The tutorial will be returned as markdown content that provides step by step instructions.".to_string(),
+            "Load a specific tutorial by name. The tutorial will be returned as markdown content that provides step by step instructions, along with its prerequisites, required extensions and the user's progress.".to_string(),
             json!({
                 "type": "object",
                 "required": ["name"],
@@ -47,8 +67,40 @@ impl TutorialRouter {
             }),
         );
 
+        let tutorial_progress = Tool::new(
+            "tutorial_progress".to_string(),
+            indoc! {r#"
+                Check or checkpoint the user's progress through a tutorial.
+                - status: show which steps are done and which is next
+                - complete: checkpoint a step once the user has done it. Steps with a check are verified first,
+                  and are only checkpointed if the check passes. For a tutorial without steps, omit the step to
+                  mark the whole tutorial done.
+                - reset: forget the progress to start over
+            "#}
+            .to_string(),
+            json!({
+                "type": "object",
+                "required": ["name", "action"],
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "Name of the tutorial"
+                    },
+                    "action": {
+                        "type": "string",
+                        "enum": ["status", "complete", "reset"],
+                        "description": "What to do"
+                    },
+                    "step": {
+                        "type": "string",
+                        "description": "ID of the step to checkpoint, for complete"
+                    }
+                }
+            }),
+        );
+
         // Get base instructions and available tutorials
-        let available_tutorials = Self::get_available_tutorials();
+        let available_tutorials = Self::get_available_tutorials(&catalog, &progress);
 
         let instructions = formatdoc! {r#"
             Because the tutorial extension is enabled, be aware that the user may be new to using Goose
@@ -61,6 +113,10 @@ impl TutorialRouter {
             To run through a tutorial, make sure to be interactive with the user. Don't run more than
             a few related tool calls in a row. Make sure to prompt the user for understanding and participation.
 
+            Some tutorials have steps. Start from the next step that isn't done yet, so the user picks up where
+            they left off, and once the user finishes a step checkpoint it with tutorial_progress. If a step's
+            check fails, help the user finish it rather than moving on.
+
             **Important**: Make sure that you provide guidance or info *before* you run commands, as the command will
             run immediately for the user. For example while running a game tutorial, let the user know what to expect
             before you run a command to start the game itself.
@@ -69,36 +125,173 @@ impl TutorialRouter {
         };
 
         Self {
-            tools: vec![load_tutorial],
+            tools: vec![load_tutorial, tutorial_progress],
             instructions,
+            catalog: Arc::new(catalog),
+            progress: Arc::new(Mutex::new(progress)),
         }
     }
 
-    fn get_available_tutorials() -> String {
+    fn get_available_tutorials(catalog: &Catalog, store: &ProgressStore) -> String {
         let mut tutorials = String::new();
-        for file in TUTORIALS_DIR.files() {
-            // Use first line for additional context
-            let first_line = file
-                .contents_utf8()
-                .and_then(|s| s.lines().next().map(|line| line.to_string()))
-                .unwrap_or_else(String::new);
-
-            if let Some(name) = file.path().file_stem() {
-                tutorials.push_str(&format!("- {}: {}\n", name.to_string_lossy(), first_line));
-            }
+        for tutorial in catalog.tutorials() {
+            let progress = store.get(&tutorial.name);
+            let state = if progress::is_complete(tutorial, progress) {
+                " (completed)"
+            } else if progress.is_some() {
+                " (in progress)"
+            } else {
+                ""
+            };
+            tutorials.push_str(&format!(
+                "- {}: {}{}\n",
+                tutorial.name,
+                tutorial.summary(),
+                state
+            ));
         }
         tutorials
     }
 
-    async fn load_tutorial(&self, name: &str) -> Result<String, ToolError> {
-        let file_name = format!("{}.md", name);
-        let file = TUTORIALS_DIR
-            .get_file(&file_name)
+    fn find(&self, name: &str) -> Result<&Tutorial, ToolError> {
+        self.catalog
+            .get(name)
             .ok_or(ToolError::ExecutionError(format!(
                 "Could not locate tutorial '{}'",
                 name
-            )))?;
-        Ok(String::from_utf8_lossy(file.contents()).into_owned())
+            )))
+    }
+
+    fn progress_error(e: std::io::Error) -> ToolError {
+        ToolError::ExecutionError(format!("Failed to save tutorial progress: {}", e))
+    }
+
+    async fn load_tutorial(&self, name: &str) -> Result<String, ToolError> {
+        let tutorial = self.find(name)?;
+        let mut store = self.progress.lock().unwrap();
+        let resuming = store.get(name).is_some_and(|p| !p.completed.is_empty());
+        store.start(name).map_err(Self::progress_error)?;
+
+        let front_matter = &tutorial.front_matter;
+        let mut header = format!("Tutorial: {} (from {})\n", name, tutorial.source);
+        if !front_matter.prerequisites.is_empty() {
+            let prerequisites: Vec<String> = front_matter
+                .prerequisites
+                .iter()
+                .map(|prerequisite| match self.catalog.get(prerequisite) {
+                    Some(other) if progress::is_complete(other, store.get(prerequisite)) => {
+                        format!("{} (tutorial, completed)", prerequisite)
+                    }
+                    Some(_) => format!("{} (tutorial, not completed yet)", prerequisite),
+                    None => prerequisite.clone(),
+                })
+                .collect();
+            header.push_str(&format!(
+                "Prerequisites: {}. Check these with the user before starting.\n",
+                prerequisites.join(", ")
+            ));
+        }
+        if !front_matter.extensions.is_empty() {
+            header.push_str(&format!(
+                "Required extensions: {}. Make sure they are enabled before starting.\n",
+                front_matter.extensions.join(", ")
+            ));
+        }
+        if !front_matter.steps.is_empty() {
+            let progress = store.get(name);
+            header.push_str(&format!(
+                "\nSteps:\n{}\n",
+                progress::status(tutorial, progress)
+            ));
+            match progress::next_step(tutorial, progress) {
+                Some(step) if resuming => header.push_str(&format!(
+                    "The user already started this tutorial, resume at step '{}'.\n",
+                    step.title
+                )),
+                None => header.push_str("The user already completed every step.\n"),
+                _ => {}
+            }
+            header.push_str("Checkpoint each step with tutorial_progress once it is done.\n");
+        }
+
+        Ok(format!("{}\n---\n\n{}", header, tutorial.body))
+    }
+
+    async fn tutorial_progress(&self, arguments: Value) -> Result<String, ToolError> {
+        let name = arguments
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'name' parameter".to_string()))?;
+        let action = arguments
+            .get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| {
+                ToolError::InvalidParameters("Missing 'action' parameter".to_string())
+            })?;
+        let step_id = arguments.get("step").and_then(|v| v.as_str());
+        let tutorial = self.find(name)?;
+
+        match action {
+            "status" => {}
+            "reset" => {
+                self.progress
+                    .lock()
+                    .unwrap()
+                    .reset(name)
+                    .map_err(Self::progress_error)?;
+            }
+            "complete" => {
+                let step = match step_id {
+                    Some(id) => Some(tutorial.step(id).ok_or_else(|| {
+                        ToolError::InvalidParameters(format!(
+                            "Tutorial '{}' has no step '{}'",
+                            name, id
+                        ))
+                    })?),
+                    None if tutorial.front_matter.steps.is_empty() => None,
+                    None => {
+                        return Err(ToolError::InvalidParameters(
+                            "This tutorial has steps, give the step to complete".to_string(),
+                        ))
+                    }
+                };
+                if let Some(check) = step.and_then(|s| s.verify.as_ref()) {
+                    let dir = std::env::current_dir().unwrap_or_default();
+                    progress::verify(check, &dir).await.map_err(|reason| {
+                        ToolError::ExecutionError(format!(
+                            "Step '{}' isn't done yet: {}",
+                            step_id.unwrap_or_default(),
+                            reason
+                        ))
+                    })?;
+                }
+                self.progress
+                    .lock()
+                    .unwrap()
+                    .complete(name, step_id)
+                    .map_err(Self::progress_error)?;
+            }
+            other => {
+                return Err(ToolError::InvalidParameters(format!(
+                    "Unknown action '{}', use status, complete or reset",
+                    other
+                )))
+            }
+        }
+
+        let store = self.progress.lock().unwrap();
+        let progress = store.get(name);
+        let mut status = format!(
+            "Progress through '{}':\n{}",
+            name,
+            progress::status(tutorial, progress)
+        );
+        if progress::is_complete(tutorial, progress) {
+            status.push_str("\nThe tutorial is complete.");
+        } else if let Some(step) = progress::next_step(tutorial, progress) {
+            status.push_str(&format!("\nNext step: {}", step.title));
+        }
+        Ok(status)
     }
 }
 
@@ -142,6 +335,10 @@ impl Router for TutorialRouter {
                         Content::text(content).with_audience(vec![Role::Assistant])
                     ])
                 }
+                "tutorial_progress" => {
+                    let status = this.tutorial_progress(arguments).await?;
+                    Ok(vec![Content::text(status)])
+                }
                 _ => Err(ToolError::NotFound(format!("Tool {} not found", tool_name))),
             }
         })
@@ -181,6 +378,51 @@ impl Clone for TutorialRouter {
         Self {
             tools: self.tools.clone(),
             instructions: self.instructions.clone(),
+            catalog: Arc::clone(&self.catalog),
+            progress: Arc::clone(&self.progress),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[tokio::test]
+    async fn test_resumes_and_verifies_steps() {
+        let team = tempfile::tempdir().unwrap();
+        fs::write(
+            team.path().join("onboarding.md"),
+            "---\nprerequisites: [first-game]\nextensions: [developer]\nsteps:\n  - {id: read, title: Read the guide}\n  - id: missing\n    title: Create a file\n    verify: {file_exists: does-not-exist.txt}\n---\n# Onboarding\n",
+        )
+        .unwrap();
+        let progress_dir = tempfile::tempdir().unwrap();
+        let router = TutorialRouter::with_catalog(
+            Catalog::load_from(&[(team.path().to_path_buf(), "team".to_string())]),
+            ProgressStore::open(progress_dir.path().join("progress.json")),
+        );
+        assert!(router.instructions().contains("- onboarding: # Onboarding"));
+
+        let loaded = router.load_tutorial("onboarding").await.unwrap();
+        assert!(loaded.contains("first-game (tutorial, not completed yet)"));
+        assert!(loaded.contains("Required extensions: developer"));
+        assert!(loaded.ends_with("# Onboarding\n"));
+
+        let status = router
+            .tutorial_progress(json!({"name": "onboarding", "action": "complete", "step": "read"}))
+            .await
+            .unwrap();
+        assert!(status.contains("Next step: Create a file"));
+        // The check fails, so the step isn't checkpointed
+        assert!(router
+            .tutorial_progress(
+                json!({"name": "onboarding", "action": "complete", "step": "missing"})
+            )
+            .await
+            .is_err());
+
+        let loaded = router.load_tutorial("onboarding").await.unwrap();
+        assert!(loaded.contains("resume at step 'Create a file'"));
+    }
+}
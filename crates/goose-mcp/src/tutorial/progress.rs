@@ -0,0 +1,258 @@
+//! Checkpointed progress through tutorials, so a walkthrough can resume where the user left
+//! off in a later session. Progress is saved in `<goose data>/tutorials/progress.json`.
+
+use chrono::{DateTime, Utc};
+use etcetera::{choose_app_strategy, AppStrategy};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::catalog::{Step, Tutorial, Verify};
+
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(120);
+/// Most output from a failed verification command shown to the agent
+const VERIFY_OUTPUT_CHARS: usize = 2000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TutorialProgress {
+    /// IDs of the steps checkpointed so far
+    pub completed: Vec<String>,
+    /// Set when a tutorial without steps is marked done
+    #[serde(default)]
+    pub finished: bool,
+    pub started: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+}
+
+impl TutorialProgress {
+    fn new() -> Self {
+        Self {
+            completed: Vec::new(),
+            finished: false,
+            started: Utc::now(),
+            updated: Utc::now(),
+        }
+    }
+}
+
+pub struct ProgressStore {
+    path: PathBuf,
+    progress: BTreeMap<String, TutorialProgress>,
+}
+
+impl ProgressStore {
+    pub fn default_path() -> PathBuf {
+        choose_app_strategy(crate::APP_STRATEGY.clone())
+            .map(|strategy| strategy.in_data_dir("tutorials"))
+            .unwrap_or_else(|_| std::env::temp_dir().join("goose_tutorials"))
+            .join("progress.json")
+    }
+
+    pub fn open(path: PathBuf) -> Self {
+        let progress = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { path, progress }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TutorialProgress> {
+        self.progress.get(name)
+    }
+
+    /// Record that a tutorial was started, keeping any earlier progress
+    pub fn start(&mut self, name: &str) -> io::Result<()> {
+        if self.progress.contains_key(name) {
+            return Ok(());
+        }
+        self.progress
+            .insert(name.to_string(), TutorialProgress::new());
+        self.save()
+    }
+
+    /// Checkpoint a step, or finish a tutorial that has no steps
+    pub fn complete(&mut self, name: &str, step: Option<&str>) -> io::Result<()> {
+        let progress = self
+            .progress
+            .entry(name.to_string())
+            .or_insert_with(TutorialProgress::new);
+        match step {
+            Some(step) if !progress.completed.iter().any(|s| s == step) => {
+                progress.completed.push(step.to_string())
+            }
+            Some(_) => {}
+            None => progress.finished = true,
+        }
+        progress.updated = Utc::now();
+        self.save()
+    }
+
+    pub fn reset(&mut self, name: &str) -> io::Result<()> {
+        self.progress.remove(name);
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temp = self.path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_string_pretty(&self.progress)?)?;
+        fs::rename(temp, &self.path)
+    }
+}
+
+/// The first step not checkpointed yet
+pub fn next_step<'a>(
+    tutorial: &'a Tutorial,
+    progress: Option<&TutorialProgress>,
+) -> Option<&'a Step> {
+    tutorial
+        .front_matter
+        .steps
+        .iter()
+        .find(|step| !progress.is_some_and(|p| p.completed.contains(&step.id)))
+}
+
+pub fn is_complete(tutorial: &Tutorial, progress: Option<&TutorialProgress>) -> bool {
+    match progress {
+        Some(progress) if tutorial.front_matter.steps.is_empty() => progress.finished,
+        Some(_) => next_step(tutorial, progress).is_none(),
+        None => false,
+    }
+}
+
+/// A checklist of the tutorial's steps
+pub fn status(tutorial: &Tutorial, progress: Option<&TutorialProgress>) -> String {
+    let steps = &tutorial.front_matter.steps;
+    if steps.is_empty() {
+        return match progress {
+            Some(p) if p.finished => "Completed.".to_string(),
+            Some(_) => "In progress.".to_string(),
+            None => "Not started.".to_string(),
+        };
+    }
+    let next = next_step(tutorial, progress).map(|s| s.id.as_str());
+    let lines: Vec<String> = steps
+        .iter()
+        .map(|step| {
+            let done = progress.is_some_and(|p| p.completed.contains(&step.id));
+            let marker = if done { "x" } else { " " };
+            let current = if Some(step.id.as_str()) == next {
+                " <- next"
+            } else {
+                ""
+            };
+            format!("- [{}] {} ({}){}", marker, step.title, step.id, current)
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// Check that a step was done, returning why not if it wasn't
+pub async fn verify(verify: &Verify, dir: &Path) -> Result<(), String> {
+    match verify {
+        Verify::FileExists(path) => {
+            let expanded = shellexpand::tilde(path);
+            if dir.join(expanded.as_ref()).exists() {
+                Ok(())
+            } else {
+                Err(format!("{} does not exist yet", path))
+            }
+        }
+        Verify::Command(command) => {
+            let mut cmd = if cfg!(windows) {
+                let mut cmd = tokio::process::Command::new("cmd");
+                cmd.arg("/C");
+                cmd
+            } else {
+                let mut cmd = tokio::process::Command::new("sh");
+                cmd.arg("-c");
+                cmd
+            };
+            cmd.arg(command).current_dir(dir).kill_on_drop(true);
+            let output = tokio::time::timeout(VERIFY_TIMEOUT, cmd.output())
+                .await
+                .map_err(|_| format!("`{}` did not finish in time", command))?
+                .map_err(|e| format!("Could not run `{}`: {}", command, e))?;
+            if output.status.success() {
+                return Ok(());
+            }
+            let text = format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            let chars: Vec<char> = text.trim().chars().collect();
+            let tail: String = chars[chars.len().saturating_sub(VERIFY_OUTPUT_CHARS)..]
+                .iter()
+                .collect();
+            Err(format!(
+                "`{}` failed ({}):\n{}",
+                command, output.status, tail
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tutorial() -> Tutorial {
+        Tutorial::parse(
+            "setup",
+            "test",
+            "---\nsteps:\n  - {id: one, title: First}\n  - {id: two, title: Second}\n---\nBody",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_progress_resumes_after_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("progress.json");
+        let tutorial = tutorial();
+
+        let mut store = ProgressStore::open(path.clone());
+        assert_eq!(next_step(&tutorial, store.get("setup")).unwrap().id, "one");
+        store.start("setup").unwrap();
+        store.complete("setup", Some("one")).unwrap();
+        store.complete("setup", Some("one")).unwrap();
+
+        let mut store = ProgressStore::open(path);
+        let progress = store.get("setup");
+        assert_eq!(progress.unwrap().completed, vec!["one"]);
+        assert_eq!(next_step(&tutorial, progress).unwrap().id, "two");
+        assert_eq!(
+            status(&tutorial, progress),
+            "- [x] First (one)\n- [ ] Second (two) <- next"
+        );
+        assert!(!is_complete(&tutorial, progress));
+
+        store.complete("setup", Some("two")).unwrap();
+        assert!(is_complete(&tutorial, store.get("setup")));
+        store.reset("setup").unwrap();
+        assert!(store.get("setup").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_verifies_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = Verify::FileExists("done.txt".to_string());
+        assert!(verify(&file, dir.path()).await.is_err());
+        fs::write(dir.path().join("done.txt"), "").unwrap();
+        assert!(verify(&file, dir.path()).await.is_ok());
+
+        if cfg!(unix) {
+            let ok = Verify::Command("test -f done.txt".to_string());
+            assert!(verify(&ok, dir.path()).await.is_ok());
+            let failing = Verify::Command("echo not yet; exit 3".to_string());
+            let error = verify(&failing, dir.path()).await.unwrap_err();
+            assert!(error.contains("not yet"), "{}", error);
+        }
+    }
+}
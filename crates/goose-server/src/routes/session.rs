@@ -1,14 +1,21 @@
 use crate::state::AppState;
 use axum::{
-    extract::{Path, State},
-    http::{HeaderMap, StatusCode},
-    routing::get,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
     Json, Router,
 };
 use goose::message::Message;
 use goose::session;
 use goose::session::info::{get_session_info, SessionInfo};
-use serde::Serialize;
+use goose::session::manage::{
+    check_cut, export_session, fork_session, remove_session, rename_session, rewind_session,
+    validate_session_id, ExportFormat, SessionFilter,
+};
+use goose::session::search::{search_sessions, SearchHit};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 #[derive(Serialize)]
 struct SessionListResponse {
@@ -22,6 +29,107 @@ struct SessionHistoryResponse {
     messages: Vec<Message>,
 }
 
+#[derive(Deserialize)]
+struct RenameSessionRequest {
+    new_id: String,
+}
+
+/// The id of a renamed or forked session
+#[derive(Serialize)]
+struct SessionIdResponse {
+    session_id: String,
+}
+
+#[derive(Deserialize)]
+struct ForkSessionRequest {
+    /// The fork keeps the messages before this index
+    message_index: usize,
+    /// Id for the fork, named after the session if missing
+    new_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RewindSessionRequest {
+    /// The session keeps the messages before this index
+    message_index: usize,
+}
+
+#[derive(Serialize)]
+struct RewindSessionResponse {
+    message_count: usize,
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    #[serde(default = "default_export_format")]
+    format: String,
+}
+
+fn default_export_format() -> String {
+    "markdown".to_string()
+}
+
+#[derive(Deserialize)]
+struct RemoveSessionsRequest {
+    /// Regex over session ids and descriptions
+    pattern: Option<String>,
+    /// Age such as 30d, for sessions last used longer ago
+    older_than: Option<String>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Serialize)]
+struct RemoveSessionsResponse {
+    removed: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: String,
+    #[serde(default = "default_search_limit")]
+    limit: usize,
+}
+
+fn default_search_limit() -> usize {
+    20
+}
+
+#[derive(Serialize)]
+struct SearchResult {
+    #[serde(flatten)]
+    hit: SearchHit,
+    /// Where to fetch the session history to resume it
+    session_url: String,
+}
+
+#[derive(Serialize)]
+struct SearchResponse {
+    results: Vec<SearchResult>,
+}
+
+fn verify_secret_key(headers: &HeaderMap, state: &AppState) -> Result<(), StatusCode> {
+    let secret_key = headers
+        .get("X-Secret-Key")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if secret_key != state.secret_key {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(())
+}
+
+/// The path of an existing session
+fn existing_session(session_id: &str) -> Result<PathBuf, StatusCode> {
+    validate_session_id(session_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let path = session::get_path(session::Identifier::Name(session_id.to_string()));
+    if !path.exists() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(path)
+}
+
 // List all available sessions
 async fn list_sessions(
     State(state): State<AppState>,
@@ -78,10 +186,275 @@ async fn get_session_history(
     }))
 }
 
+// Remove a session
+async fn delete_session(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+    let path = existing_session(&session_id)?;
+    remove_session(&path).map_err(|e| {
+        tracing::error!("Failed to remove session {}: {:?}", session_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Give a session a new id
+async fn rename(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+    Json(request): Json<RenameSessionRequest>,
+) -> Result<Json<SessionIdResponse>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+    let path = existing_session(&session_id)?;
+    validate_session_id(&request.new_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    if path
+        .with_file_name(format!("{}.jsonl", request.new_id))
+        .exists()
+    {
+        return Err(StatusCode::CONFLICT);
+    }
+    rename_session(&path, &request.new_id).map_err(|e| {
+        tracing::error!("Failed to rename session {}: {:?}", session_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(SessionIdResponse {
+        session_id: request.new_id,
+    }))
+}
+
+/// Check that a session can be cut at `index`, answering 400 if not
+fn check_session_cut(path: &std::path::Path, index: usize) -> Result<(), StatusCode> {
+    let messages = session::read_messages(path).map_err(|e| {
+        tracing::error!("Failed to read session messages: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    check_cut(&messages, index).map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+// Copy the start of a session into a new session
+async fn fork(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+    Json(request): Json<ForkSessionRequest>,
+) -> Result<Json<SessionIdResponse>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+    let path = existing_session(&session_id)?;
+    if let Some(new_id) = &request.new_id {
+        validate_session_id(new_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+        if path.with_file_name(format!("{}.jsonl", new_id)).exists() {
+            return Err(StatusCode::CONFLICT);
+        }
+    }
+    check_session_cut(&path, request.message_index)?;
+    let (new_id, _) = fork_session(&path, request.message_index, request.new_id.as_deref())
+        .map_err(|e| {
+            tracing::error!("Failed to fork session {}: {:?}", session_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(Json(SessionIdResponse { session_id: new_id }))
+}
+
+// Drop the messages of a session from an index on
+async fn rewind(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+    Json(request): Json<RewindSessionRequest>,
+) -> Result<Json<RewindSessionResponse>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+    let path = existing_session(&session_id)?;
+    check_session_cut(&path, request.message_index)?;
+    let messages = rewind_session(&path, request.message_index).map_err(|e| {
+        tracing::error!("Failed to rewind session {}: {:?}", session_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(RewindSessionResponse {
+        message_count: messages.len(),
+    }))
+}
+
+// Export a session as markdown, json or html
+async fn export(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+    let format: ExportFormat = query.format.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let path = existing_session(&session_id)?;
+    let exported = export_session(&path, &session_id, format).map_err(|e| {
+        tracing::error!("Failed to export session {}: {:?}", session_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let content_type = match format {
+        ExportFormat::Markdown => "text/markdown; charset=utf-8",
+        ExportFormat::Json => "application/json",
+        ExportFormat::Html => "text/html; charset=utf-8",
+    };
+    Ok(([(header::CONTENT_TYPE, content_type)], exported))
+}
+
+// Remove the sessions matching a pattern or older than an age
+async fn remove_sessions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<RemoveSessionsRequest>,
+) -> Result<Json<RemoveSessionsResponse>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+    let filter = SessionFilter::new(request.pattern.as_deref(), request.older_than.as_deref())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    // Never remove every session by accident
+    if filter.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let sessions = session::list_sessions().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut removed = Vec::new();
+    for (id, path) in filter.apply(sessions) {
+        if !request.dry_run {
+            if let Err(e) = remove_session(&path) {
+                tracing::error!("Failed to remove session {}: {:?}", id, e);
+                continue;
+            }
+        }
+        removed.push(id);
+    }
+    Ok(Json(RemoveSessionsResponse { removed }))
+}
+
+// Search the text, tool calls and working directories of sessions
+async fn search(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<SearchResponse>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+    if query.q.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let hits =
+        search_sessions(&query.q, query.limit).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let results = hits
+        .into_iter()
+        .map(|hit| SearchResult {
+            session_url: format!("/sessions/{}", hit.session_id),
+            hit,
+        })
+        .collect();
+
+    Ok(Json(SearchResponse { results }))
+}
+
 // Configure routes for this module
 pub fn routes(state: AppState) -> Router {
     Router::new()
         .route("/sessions", get(list_sessions))
-        .route("/sessions/:session_id", get(get_session_history))
+        .route("/sessions/remove", post(remove_sessions))
+        .route("/sessions/search", get(search))
+        .route(
+            "/sessions/:session_id",
+            get(get_session_history).delete(delete_session),
+        )
+        .route("/sessions/:session_id/rename", post(rename))
+        .route("/sessions/:session_id/export", get(export))
+        .route("/sessions/:session_id/fork", post(fork))
+        .route("/sessions/:session_id/rewind", post(rewind))
         .with_state(state)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tokio::sync::{Mutex, RwLock};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        routes(AppState {
+            config: Arc::new(Mutex::new(HashMap::new())),
+            agent: Arc::new(RwLock::new(None)),
+            secret_key: "test-secret".to_string(),
+        })
+    }
+
+    async fn send(method: &str, uri: &str, secret: &str, body: &str) -> StatusCode {
+        let request = Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("content-type", "application/json")
+            .header("x-secret-key", secret)
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        app().oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_requests_before_touching_sessions() {
+        assert_eq!(
+            send("DELETE", "/sessions/project", "wrong", "").await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            send("DELETE", "/sessions/..project", "test-secret", "").await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            send(
+                "POST",
+                "/sessions/..project/rename",
+                "test-secret",
+                r#"{"new_id": "renamed"}"#
+            )
+            .await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            send(
+                "GET",
+                "/sessions/project/export?format=pdf",
+                "test-secret",
+                ""
+            )
+            .await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            send(
+                "POST",
+                "/sessions/..project/fork",
+                "test-secret",
+                r#"{"message_index": 0}"#
+            )
+            .await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            send("GET", "/sessions/search?q=%20", "test-secret", "").await,
+            StatusCode::BAD_REQUEST
+        );
+        // An empty filter would remove every session
+        assert_eq!(
+            send("POST", "/sessions/remove", "test-secret", "{}").await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            send(
+                "POST",
+                "/sessions/remove",
+                "test-secret",
+                r#"{"older_than": "soon"}"#
+            )
+            .await,
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
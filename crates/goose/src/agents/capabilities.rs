@@ -12,11 +12,12 @@ use tracing::{debug, instrument};
 
 use super::extension::{ExtensionConfig, ExtensionError, ExtensionInfo, ExtensionResult, ToolInfo};
 use crate::config::Config;
+use crate::message::Message;
 use crate::prompt_template;
 use crate::providers::base::Provider;
 use mcp_client::client::{ClientCapabilities, ClientInfo, McpClient, McpClientTrait};
 use mcp_client::transport::{SseTransport, StdioTransport, Transport};
-use mcp_core::{prompt::Prompt, Content, Tool, ToolCall, ToolError, ToolResult};
+use mcp_core::{prompt::Prompt, role::Role, Content, Tool, ToolCall, ToolError, ToolResult};
 use serde_json::Value;
 
 // By default, we set it to Jan 1, 2020 if the resource does not have a timestamp
@@ -26,11 +27,17 @@ static DEFAULT_TIMESTAMP: LazyLock<DateTime<Utc>> =
 
 type McpClientBox = Arc<Mutex<Box<dyn McpClientTrait>>>;
 
+/// Extensions that list this resource provide the memories relevant to each message
+const RELEVANT_MEMORIES_URI: &str = "memory://relevant";
+const DEFAULT_MEMORY_TOKEN_BUDGET: usize = 1000;
+const MAX_MEMORY_QUERY_CHARS: usize = 2000;
+
 /// Manages MCP clients and their interactions
 pub struct Capabilities {
     clients: HashMap<String, McpClientBox>,
     instructions: HashMap<String, String>,
     resource_capable_extensions: HashSet<String>,
+    memory_extension: Option<String>,
     provider: Arc<Box<dyn Provider>>,
     system_prompt_override: Option<String>,
     system_prompt_extensions: Vec<String>,
@@ -98,6 +105,7 @@ impl Capabilities {
             clients: HashMap::new(),
             instructions: HashMap::new(),
             resource_capable_extensions: HashSet::new(),
+            memory_extension: None,
             provider: Arc::new(provider),
             system_prompt_override: None,
             system_prompt_extensions: Vec::new(),
@@ -194,6 +202,16 @@ impl Capabilities {
         if init_result.capabilities.resources.is_some() {
             self.resource_capable_extensions
                 .insert(sanitized_name.clone());
+
+            let provides_memories = client.list_resources(None).await.is_ok_and(|result| {
+                result
+                    .resources
+                    .iter()
+                    .any(|resource| resource.uri == RELEVANT_MEMORIES_URI)
+            });
+            if provides_memories {
+                self.memory_extension = Some(sanitized_name.clone());
+            }
         }
 
         // Store the client using the provided name
@@ -225,6 +243,9 @@ impl Capabilities {
         self.clients.remove(&sanitized_name);
         self.instructions.remove(&sanitized_name);
         self.resource_capable_extensions.remove(&sanitized_name);
+        if self.memory_extension.as_ref() == Some(&sanitized_name) {
+            self.memory_extension = None;
+        }
         Ok(())
     }
 
@@ -363,6 +384,53 @@ impl Capabilities {
         }
     }
 
+    /// Memories relevant to the latest user message, from the extension that lists the
+    /// relevant memories resource when one is enabled.
+    ///
+    /// The memory extension ranks its memories against the message and returns those that fit in
+    /// the GOOSE_MEMORY_TOKEN_BUDGET, so the prompt does not grow with every memory saved.
+    pub async fn get_relevant_memories(&self, messages: &[Message]) -> Option<String> {
+        let client = self.clients.get(self.memory_extension.as_ref()?)?;
+
+        let query = messages
+            .iter()
+            .rev()
+            .filter(|m| m.role == Role::User)
+            .map(|m| m.as_concat_text())
+            .find(|text| !text.trim().is_empty())?;
+        // Long pastes rank poorly and make for huge uris, the start of the message is enough
+        let query: String = query.chars().take(MAX_MEMORY_QUERY_CHARS).collect();
+
+        let budget = Config::global()
+            .get_param::<usize>("GOOSE_MEMORY_TOKEN_BUDGET")
+            .unwrap_or(DEFAULT_MEMORY_TOKEN_BUDGET);
+        let uri = url::Url::parse_with_params(
+            RELEVANT_MEMORIES_URI,
+            &[("query", query), ("budget", budget.to_string())],
+        )
+        .ok()?;
+
+        let result = match client.lock().await.read_resource(uri.as_str()).await {
+            Ok(result) => result,
+            Err(e) => {
+                debug!("Failed to read relevant memories: {}", e);
+                return None;
+            }
+        };
+        let text: String = result
+            .contents
+            .into_iter()
+            .filter_map(|content| match content {
+                mcp_core::resource::ResourceContents::TextResourceContents { text, .. } => {
+                    Some(text)
+                }
+                _ => None,
+            })
+            .collect();
+
+        (!text.trim().is_empty()).then(|| format!("\n\n# Relevant Memories\n\n{}", text))
+    }
+
     /// Find and return a reference to the appropriate client for a tool call
     fn get_client_for_tool(&self, prefixed_name: &str) -> Option<(&str, McpClientBox)> {
         self.clients
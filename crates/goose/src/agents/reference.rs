@@ -127,7 +127,12 @@ impl Agent for ReferenceAgent {
             tools.push(list_resources_tool);
         }
 
-        let system_prompt = capabilities.get_system_prompt().await;
+        let mut system_prompt = capabilities.get_system_prompt().await;
+
+        // Memories relevant to this message are added for this reply only
+        if let Some(memories) = capabilities.get_relevant_memories(&messages).await {
+            system_prompt.push_str(&memories);
+        }
 
         // Set the user_message field in the span instead of creating a new event
         if let Some(content) = messages
@@ -155,6 +160,8 @@ impl Agent for ReferenceAgent {
                     let mut metadata = session::read_metadata(&session_file)?;
                     metadata.working_dir = session.working_dir;
                     metadata.total_tokens = usage.usage.total_tokens;
+                    metadata.input_tokens = usage.usage.input_tokens;
+                    metadata.output_tokens = usage.usage.output_tokens;
                     // The message count is the number of messages in the session + 1 for the response
                     // The message count does not include the tool response till next iteration
                     metadata.message_count = messages.len() + 1;
@@ -222,6 +222,11 @@ impl Agent for TruncateAgent {
 
         let config = capabilities.provider().get_model_config();
         let mut system_prompt = capabilities.get_system_prompt().await;
+
+        // Memories relevant to this message are added for this reply only
+        if let Some(memories) = capabilities.get_relevant_memories(&messages).await {
+            system_prompt.push_str(&memories);
+        }
         let mut toolshim_tools = vec![];
         if config.toolshim {
             // If tool interpretation is enabled, modify the system prompt to instruct to return JSON tool requests
@@ -265,6 +270,8 @@ impl Agent for TruncateAgent {
                             let mut metadata = session::read_metadata(&session_file)?;
                             metadata.working_dir = session.working_dir;
                             metadata.total_tokens = usage.usage.total_tokens;
+                            metadata.input_tokens = usage.usage.input_tokens;
+                            metadata.output_tokens = usage.usage.output_tokens;
                             // The message count is the number of messages in the session + 1 for the response
                             // The message count does not include the tool response till next iteration
                             metadata.message_count = messages.len() + 1;
@@ -5,6 +5,7 @@ pub mod message;
 pub mod model;
 pub mod prompt_template;
 pub mod providers;
+pub mod recipe;
 pub mod session;
 pub mod token_counter;
 pub mod tracing;
@@ -0,0 +1,480 @@
+//! Recipes: shareable agent tasks defined in YAML.
+//!
+//! A recipe packages a prompt template with the parameters it takes, the extensions it needs,
+//! the goose mode and model settings to run with, and optionally a JSON schema the final
+//! response has to match:
+//!
+//! ```yaml
+//! title: Dependency audit
+//! description: Check a package's dependencies for known vulnerabilities
+//! prompt: |
+//!   Audit the dependencies of {{ package }} and report anything above {{ severity }}.
+//! parameters:
+//!   - key: package
+//!     description: Path to the package to audit
+//!   - key: severity
+//!     default: medium
+//! extensions:
+//!   - type: builtin
+//!     name: developer
+//! goose_mode: auto
+//! settings:
+//!   goose_model: gpt-4o
+//!   temperature: 0.2
+//! response:
+//!   json_schema:
+//!     type: object
+//!     required: [findings]
+//! ```
+//!
+//! Recipes are found by name in the team directories listed in `GOOSE_RECIPE_PATH`, separated
+//! like `PATH`, and in `<goose config>/recipes`, which takes precedence.
+
+use crate::agents::ExtensionConfig;
+use crate::config::APP_STRATEGY;
+use crate::prompt_template;
+use anyhow::{anyhow, bail, Context, Result};
+use etcetera::{choose_app_strategy, AppStrategy};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const GOOSE_MODES: [&str; 4] = ["auto", "approve", "chat", "smart_approve"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recipe {
+    #[serde(default = "default_version")]
+    pub version: String,
+    pub title: String,
+    pub description: String,
+    /// Added to the system prompt while the recipe runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    /// Template for the first message, rendered with the parameters
+    pub prompt: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<RecipeParameter>,
+    /// Extensions to add on top of the ones the user has enabled
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<ExtensionConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub goose_mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<Settings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<Response>,
+}
+
+fn default_version() -> String {
+    "1.0.0".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeParameter {
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Used when the parameter is not given. Parameters without a default are required.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+/// Model settings that override the configured ones
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Settings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub goose_provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub goose_model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response {
+    /// The JSON schema the final response has to match
+    pub json_schema: Value,
+}
+
+impl Recipe {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read recipe {}", path.display()))?;
+        Self::from_yaml(&content).with_context(|| format!("Invalid recipe {}", path.display()))
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Self> {
+        let recipe: Recipe = serde_yaml::from_str(content)?;
+        recipe.validate()?;
+        Ok(recipe)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(mode) = &self.goose_mode {
+            if !GOOSE_MODES.contains(&mode.as_str()) {
+                bail!(
+                    "Invalid goose_mode '{}', must be one of: {}",
+                    mode,
+                    GOOSE_MODES.join(", ")
+                );
+            }
+        }
+
+        let mut seen = Vec::new();
+        for parameter in &self.parameters {
+            if seen.contains(&parameter.key.as_str()) {
+                bail!("Parameter '{}' is declared more than once", parameter.key);
+            }
+            seen.push(&parameter.key);
+        }
+
+        // Catch typos in the template rather than rendering them as empty strings
+        let template_env = minijinja::Environment::new();
+        let template = template_env
+            .template_from_str(&self.prompt)
+            .map_err(|e| anyhow!("Invalid prompt template: {}", e))?;
+        let mut undeclared: Vec<String> = template
+            .undeclared_variables(false)
+            .into_iter()
+            .filter(|name| !seen.contains(&name.as_str()))
+            .filter(|name| template_env.globals().all(|(global, _)| global != name))
+            .collect();
+        if !undeclared.is_empty() {
+            undeclared.sort();
+            bail!(
+                "The prompt uses parameters that are not declared: {}",
+                undeclared.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Render the prompt with the given parameters, filling in defaults
+    pub fn render_prompt(&self, params: &HashMap<String, String>) -> Result<String> {
+        if let Some(unknown) = params
+            .keys()
+            .find(|key| !self.parameters.iter().any(|p| &p.key == *key))
+        {
+            bail!(
+                "Unknown parameter '{}' for recipe '{}'",
+                unknown,
+                self.title
+            );
+        }
+
+        let mut context = BTreeMap::new();
+        let mut missing = Vec::new();
+        for parameter in &self.parameters {
+            match params.get(&parameter.key).or(parameter.default.as_ref()) {
+                Some(value) => {
+                    context.insert(parameter.key.clone(), value.clone());
+                }
+                None => missing.push(parameter.key.as_str()),
+            }
+        }
+        if !missing.is_empty() {
+            bail!(
+                "Missing required parameters for recipe '{}': {}",
+                self.title,
+                missing.join(", ")
+            );
+        }
+
+        prompt_template::render_inline_once(&self.prompt, &context)
+            .map_err(|e| anyhow!("Failed to render the recipe prompt: {}", e))
+    }
+
+    /// The recipe's addition to the system prompt, including how to format the response
+    pub fn system_prompt(&self) -> Option<String> {
+        let response = self.response.as_ref().map(|response| {
+            format!(
+                "When the task is done, reply with only a JSON document, without any other text, \
+                 that matches this JSON schema:\n{}",
+                serde_json::to_string_pretty(&response.json_schema).unwrap_or_default()
+            )
+        });
+        match (&self.instructions, response) {
+            (Some(instructions), Some(response)) => {
+                Some(format!("{}\n\n{}", instructions, response))
+            }
+            (Some(instructions), None) => Some(instructions.clone()),
+            (None, response) => response,
+        }
+    }
+
+    /// Parse the final response and check it against the response schema
+    pub fn check_response(&self, text: &str) -> Result<Option<Value>> {
+        let Some(response) = &self.response else {
+            return Ok(None);
+        };
+        let json = strip_code_fence(text.trim());
+        let value: Value =
+            serde_json::from_str(json).context("The response is not a JSON document")?;
+        check_schema(&response.json_schema, &value, "$").map_err(|e| anyhow!(e))?;
+        Ok(Some(value))
+    }
+}
+
+fn strip_code_fence(text: &str) -> &str {
+    text.strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(|rest| rest.split_once('\n').map_or(rest, |(_, body)| body))
+        .unwrap_or(text)
+}
+
+/// Check the parts of JSON schema that describe a response's shape: `type`, `enum`,
+/// `required`, `properties` and `items`
+fn check_schema(schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        let matches = |t: &str| match t {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "number" => value.is_number(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            _ => true,
+        };
+        if !types.is_empty() && !types.iter().any(|t| matches(t)) {
+            return Err(format!("{} should be of type {}", path, types.join(" or ")));
+        }
+    }
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            return Err(format!(
+                "{} should be one of {}",
+                path,
+                Value::from(allowed.clone())
+            ));
+        }
+    }
+    if let Value::Object(object) = value {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(|k| k.as_str()) {
+                if !object.contains_key(key) {
+                    return Err(format!(
+                        "{} is missing the required property '{}'",
+                        path, key
+                    ));
+                }
+            }
+        }
+        if let Some(Value::Object(properties)) = schema.get("properties") {
+            for (key, property) in properties {
+                if let Some(field) = object.get(key) {
+                    check_schema(property, field, &format!("{}.{}", path, key))?;
+                }
+            }
+        }
+    }
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            check_schema(item_schema, item, &format!("{}[{}]", path, i))?;
+        }
+    }
+    Ok(())
+}
+
+/// Directories recipes are found in, in increasing order of precedence
+pub fn recipe_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(path) = env::var_os("GOOSE_RECIPE_PATH") {
+        dirs.extend(env::split_paths(&path));
+    }
+    if let Ok(strategy) = choose_app_strategy(APP_STRATEGY.clone()) {
+        dirs.push(strategy.in_config_dir("recipes"));
+    }
+    dirs
+}
+
+/// A recipe file found in one of the recipe directories
+pub struct RecipeFile {
+    pub name: String,
+    pub path: PathBuf,
+    pub recipe: Result<Recipe>,
+}
+
+/// All recipes in `dirs` by name, where a later directory's recipe replaces an earlier one's
+pub fn list_recipes(dirs: &[PathBuf]) -> Vec<RecipeFile> {
+    let mut recipes: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for path in entries.filter_map(|entry| entry.ok().map(|e| e.path())) {
+            if !path
+                .extension()
+                .is_some_and(|ext| ext == "yaml" || ext == "yml")
+            {
+                continue;
+            }
+            if let Some(name) = path.file_stem() {
+                recipes.insert(name.to_string_lossy().into_owned(), path);
+            }
+        }
+    }
+    recipes
+        .into_iter()
+        .map(|(name, path)| RecipeFile {
+            name,
+            recipe: Recipe::from_file(&path),
+            path,
+        })
+        .collect()
+}
+
+/// Load a recipe from a path, or by name from the recipe directories
+pub fn load_recipe(name_or_path: &str, dirs: &[PathBuf]) -> Result<(PathBuf, Recipe)> {
+    let path = Path::new(name_or_path);
+    if path.is_file() {
+        return Ok((path.to_path_buf(), Recipe::from_file(path)?));
+    }
+    let found = dirs.iter().rev().find_map(|dir| {
+        ["yaml", "yml"]
+            .iter()
+            .map(|ext| dir.join(format!("{}.{}", name_or_path, ext)))
+            .find(|candidate| candidate.is_file())
+    });
+    match found {
+        Some(path) => {
+            let recipe = Recipe::from_file(&path)?;
+            Ok((path, recipe))
+        }
+        None => bail!(
+            "No recipe file or recipe named '{}' was found. Recipes are looked up in: {}",
+            name_or_path,
+            dirs.iter()
+                .map(|dir| dir.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const RECIPE: &str = r#"
+title: Dependency audit
+description: Check dependencies for known vulnerabilities
+instructions: Only report, do not change any files.
+prompt: |
+  Audit {{ package }}{% if severity %} at {{ severity }} and above{% endif %}.
+parameters:
+  - key: package
+    description: Path to the package
+  - key: severity
+    default: medium
+extensions:
+  - type: builtin
+    name: developer
+goose_mode: approve
+settings:
+  goose_model: gpt-4o
+  temperature: 0.2
+response:
+  json_schema:
+    type: object
+    required: [findings]
+    properties:
+      findings:
+        type: array
+        items: {type: string}
+"#;
+
+    #[test]
+    fn test_parse_and_render() {
+        let recipe = Recipe::from_yaml(RECIPE).unwrap();
+        assert_eq!(recipe.version, "1.0.0");
+        assert_eq!(recipe.goose_mode.as_deref(), Some("approve"));
+        assert!(matches!(
+            &recipe.extensions[0],
+            ExtensionConfig::Builtin { name, .. } if name == "developer"
+        ));
+        assert_eq!(recipe.settings.as_ref().unwrap().temperature, Some(0.2));
+
+        let params = HashMap::from([("package".to_string(), "./api".to_string())]);
+        assert_eq!(
+            recipe.render_prompt(&params).unwrap(),
+            "Audit ./api at medium and above."
+        );
+
+        let missing = recipe.render_prompt(&HashMap::new()).unwrap_err();
+        assert!(missing.to_string().contains("package"), "{}", missing);
+        let unknown = HashMap::from([
+            ("package".to_string(), "./api".to_string()),
+            ("pakage".to_string(), "./api".to_string()),
+        ]);
+        assert!(recipe.render_prompt(&unknown).is_err());
+
+        let system_prompt = recipe.system_prompt().unwrap();
+        assert!(system_prompt.starts_with("Only report"));
+        assert!(system_prompt.contains("\"findings\""));
+    }
+
+    #[test]
+    fn test_invalid_recipes() {
+        let undeclared = RECIPE.replace("{{ package }}", "{{ pakage }}");
+        let error = Recipe::from_yaml(&undeclared).unwrap_err();
+        assert!(error.to_string().contains("pakage"), "{}", error);
+
+        let mode = RECIPE.replace("goose_mode: approve", "goose_mode: yolo");
+        assert!(Recipe::from_yaml(&mode).is_err());
+    }
+
+    #[test]
+    fn test_check_response() {
+        let recipe = Recipe::from_yaml(RECIPE).unwrap();
+        assert_eq!(
+            recipe
+                .check_response("```json\n{\"findings\": [\"CVE-1\"]}\n```")
+                .unwrap(),
+            Some(json!({"findings": ["CVE-1"]}))
+        );
+        assert!(recipe.check_response("{\"issues\": []}").is_err());
+        assert!(recipe.check_response("{\"findings\": [1]}").is_err());
+        assert!(recipe.check_response("No findings").is_err());
+    }
+
+    #[test]
+    fn test_find_recipes() {
+        let team = tempfile::tempdir().unwrap();
+        let user = tempfile::tempdir().unwrap();
+        fs::write(team.path().join("audit.yaml"), RECIPE).unwrap();
+        fs::write(team.path().join("broken.yml"), "title: [").unwrap();
+        fs::write(team.path().join("notes.md"), "not a recipe").unwrap();
+        fs::write(
+            user.path().join("audit.yml"),
+            RECIPE.replace("Dependency audit", "My audit"),
+        )
+        .unwrap();
+        let dirs = vec![team.path().to_path_buf(), user.path().to_path_buf()];
+
+        let recipes = list_recipes(&dirs);
+        let names: Vec<&str> = recipes.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["audit", "broken"]);
+        assert_eq!(recipes[0].recipe.as_ref().unwrap().title, "My audit");
+        assert!(recipes[1].recipe.is_err());
+
+        let (path, recipe) = load_recipe("audit", &dirs).unwrap();
+        assert!(path.starts_with(user.path()));
+        assert_eq!(recipe.title, "My audit");
+        let direct = team.path().join("audit.yaml");
+        let (_, recipe) = load_recipe(direct.to_str().unwrap(), &dirs).unwrap();
+        assert_eq!(recipe.title, "Dependency audit");
+        assert!(load_recipe("missing", &dirs).is_err());
+    }
+}
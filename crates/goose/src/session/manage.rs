@@ -0,0 +1,613 @@
+//! Managing stored sessions: finding them by pattern and age, removing, renaming, forking,
+//! rewinding and exporting.
+
+use anyhow::{anyhow, bail, Result};
+use regex::Regex;
+use serde_json::json;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use crate::message::{Message, MessageContent};
+use crate::session::storage::save_messages_with_metadata;
+use crate::session::{read_messages, read_metadata, SessionLineage, SessionMetadata};
+use mcp_core::role::Role;
+
+/// Selects sessions by a regex over their id and description, and by age
+#[derive(Debug, Default)]
+pub struct SessionFilter {
+    pub pattern: Option<Regex>,
+    /// Only sessions last modified longer ago than this
+    pub older_than: Option<Duration>,
+}
+
+impl SessionFilter {
+    pub fn new(pattern: Option<&str>, older_than: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            pattern: pattern
+                .map(Regex::new)
+                .transpose()
+                .map_err(|e| anyhow!("Invalid pattern: {}", e))?,
+            older_than: older_than.map(parse_age).transpose()?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pattern.is_none() && self.older_than.is_none()
+    }
+
+    pub fn matches(&self, id: &str, path: &Path) -> bool {
+        if let Some(pattern) = &self.pattern {
+            let description = read_metadata(path)
+                .map(|metadata| metadata.description)
+                .unwrap_or_default();
+            if !pattern.is_match(id) && !pattern.is_match(&description) {
+                return false;
+            }
+        }
+        if let Some(older_than) = self.older_than {
+            let age = path
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .unwrap_or_default();
+            if age < older_than {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// The sessions from `sessions` that match, sorted by id
+    pub fn apply(&self, sessions: Vec<(String, PathBuf)>) -> Vec<(String, PathBuf)> {
+        let mut matching: Vec<(String, PathBuf)> = sessions
+            .into_iter()
+            .filter(|(id, path)| self.matches(id, path))
+            .collect();
+        matching.sort();
+        matching
+    }
+}
+
+/// Parse an age such as `90m`, `12h`, `30d` or `2w`
+pub fn parse_age(age: &str) -> Result<Duration> {
+    let age = age.trim();
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let (number, unit) = age.split_at(split);
+    let number: u64 = number.parse().map_err(|_| {
+        anyhow!(
+            "Invalid age '{}', expected a number and a unit like 30d",
+            age
+        )
+    })?;
+    let seconds = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" | "" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        unit => bail!(
+            "Invalid age unit '{}', expected one of s, m, h, d or w",
+            unit
+        ),
+    };
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| anyhow!("Invalid age '{}', it is too large", age))
+}
+
+/// Check that a session id can be used as a file name in the session directory
+pub fn validate_session_id(id: &str) -> Result<()> {
+    let valid = !id.is_empty()
+        && !id.starts_with('.')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        bail!(
+            "Invalid session id '{}': use letters, numbers, '_', '-' and '.'",
+            id
+        )
+    }
+}
+
+pub fn remove_session(path: &Path) -> Result<()> {
+    if !path.exists() {
+        bail!("Session {} does not exist", path.display());
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Give a session a new id, returning its new path
+pub fn rename_session(path: &Path, new_id: &str) -> Result<PathBuf> {
+    validate_session_id(new_id)?;
+    if !path.exists() {
+        bail!("Session {} does not exist", path.display());
+    }
+    let new_path = path.with_file_name(format!("{}.jsonl", new_id));
+    if new_path.exists() {
+        bail!("A session named '{}' already exists", new_id);
+    }
+    fs::rename(path, &new_path)?;
+    Ok(new_path)
+}
+
+/// Check that a session can be cut so that it keeps the messages before `index`
+///
+/// A cut must not separate a tool call from its result, since the conversation could not be
+/// continued from a tool call without an answer.
+pub fn check_cut(messages: &[Message], index: usize) -> Result<()> {
+    if index > messages.len() {
+        bail!(
+            "Message index {} is past the end of the session, which has {} messages",
+            index,
+            messages.len()
+        );
+    }
+    let answers_tool_call = messages.get(index).is_some_and(|message| {
+        message
+            .content
+            .iter()
+            .any(|content| matches!(content, MessageContent::ToolResponse(_)))
+    });
+    if answers_tool_call {
+        bail!(
+            "Message {} is the result of a tool call, pick the message before the call or after the result",
+            index
+        );
+    }
+    Ok(())
+}
+
+/// The indices of the messages the user typed, as opposed to tool results
+pub fn prompt_indices(messages: &[Message]) -> Vec<usize> {
+    messages
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| {
+            message.role == Role::User
+                && message
+                    .content
+                    .iter()
+                    .any(|content| matches!(content, MessageContent::Text(_)))
+                && !message
+                    .content
+                    .iter()
+                    .any(|content| matches!(content, MessageContent::ToolResponse(_)))
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Copy the messages before `index` of a session into a new session, returning its id and path
+///
+/// Without a `new_id`, the fork is named after its parent, like `<id>-fork-1`.
+pub fn fork_session(path: &Path, index: usize, new_id: Option<&str>) -> Result<(String, PathBuf)> {
+    if !path.exists() {
+        bail!("Session {} does not exist", path.display());
+    }
+    let parent_id = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let new_id = match new_id {
+        Some(new_id) => new_id.to_string(),
+        None => (1..)
+            .map(|n| format!("{}-fork-{}", parent_id, n))
+            .find(|id| !path.with_file_name(format!("{}.jsonl", id)).exists())
+            .expect("an unused fork id"),
+    };
+    validate_session_id(&new_id)?;
+    let new_path = path.with_file_name(format!("{}.jsonl", new_id));
+    if new_path.exists() {
+        bail!("A session named '{}' already exists", new_id);
+    }
+
+    let messages = read_messages(path)?;
+    check_cut(&messages, index)?;
+    let metadata = SessionMetadata {
+        message_count: index,
+        total_tokens: None,
+        input_tokens: None,
+        output_tokens: None,
+        forked_from: Some(SessionLineage {
+            session_id: parent_id,
+            message_index: index,
+        }),
+        ..read_metadata(path)?
+    };
+    save_messages_with_metadata(&new_path, &metadata, &messages[..index])?;
+    Ok((new_id, new_path))
+}
+
+/// Drop the messages of a session from `index` on, returning the messages it keeps
+pub fn rewind_session(path: &Path, index: usize) -> Result<Vec<Message>> {
+    if !path.exists() {
+        bail!("Session {} does not exist", path.display());
+    }
+    let mut messages = read_messages(path)?;
+    check_cut(&messages, index)?;
+    messages.truncate(index);
+    let metadata = SessionMetadata {
+        message_count: index,
+        ..read_metadata(path)?
+    };
+    save_messages_with_metadata(path, &metadata, &messages)?;
+    Ok(messages)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            "json" => Ok(ExportFormat::Json),
+            "html" => Ok(ExportFormat::Html),
+            _ => bail!(
+                "Unknown export format '{}', expected markdown, json or html",
+                s
+            ),
+        }
+    }
+}
+
+/// Render a session as a document
+pub fn export_session(path: &Path, id: &str, format: ExportFormat) -> Result<String> {
+    if !path.exists() {
+        bail!("Session {} does not exist", path.display());
+    }
+    let metadata = read_metadata(path)?;
+    let messages = read_messages(path)?;
+    Ok(match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&json!({
+            "id": id,
+            "metadata": metadata,
+            "messages": messages,
+        }))?,
+        ExportFormat::Markdown => export_markdown(id, &metadata, &messages),
+        ExportFormat::Html => export_html(id, &metadata, &messages),
+    })
+}
+
+fn title(id: &str, metadata: &SessionMetadata) -> String {
+    if metadata.description.trim().is_empty() {
+        id.to_string()
+    } else {
+        metadata.description.trim().to_string()
+    }
+}
+
+fn speaker(message: &Message) -> &'static str {
+    match message.role {
+        Role::User => "User",
+        Role::Assistant => "Goose",
+    }
+}
+
+/// The parts of a message worth exporting: text, tool calls and tool results
+enum Part {
+    Text(String),
+    ToolCall { name: String, arguments: String },
+    ToolResult { output: String, is_error: bool },
+}
+
+fn parts(message: &Message) -> Vec<Part> {
+    message
+        .content
+        .iter()
+        .filter_map(|content| match content {
+            MessageContent::Text(text) if !text.text.trim().is_empty() => {
+                Some(Part::Text(text.text.clone()))
+            }
+            MessageContent::ToolRequest(request) => Some(match &request.tool_call {
+                Ok(call) => Part::ToolCall {
+                    name: call.name.clone(),
+                    arguments: serde_json::to_string_pretty(&call.arguments).unwrap_or_default(),
+                },
+                Err(e) => Part::ToolResult {
+                    output: e.to_string(),
+                    is_error: true,
+                },
+            }),
+            MessageContent::ToolResponse(response) => Some(match &response.tool_result {
+                Ok(contents) => Part::ToolResult {
+                    output: contents
+                        .iter()
+                        .filter_map(|c| c.as_text())
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    is_error: false,
+                },
+                Err(e) => Part::ToolResult {
+                    output: e.to_string(),
+                    is_error: true,
+                },
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn export_markdown(id: &str, metadata: &SessionMetadata, messages: &[Message]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}\n", title(id, metadata));
+    let _ = writeln!(out, "- Session: `{}`", id);
+    let _ = writeln!(
+        out,
+        "- Working directory: `{}`",
+        metadata.working_dir.display()
+    );
+    let _ = writeln!(out, "- Messages: {}", messages.len());
+
+    for message in messages {
+        let parts = parts(message);
+        if parts.is_empty() {
+            continue;
+        }
+        let _ = writeln!(out, "\n## {}\n", speaker(message));
+        for part in parts {
+            match part {
+                Part::Text(text) => {
+                    let _ = writeln!(out, "{}\n", text.trim());
+                }
+                Part::ToolCall { name, arguments } => {
+                    let _ = writeln!(out, "**Tool call:** `{}`\n", name);
+                    let _ = writeln!(out, "```json\n{}\n```\n", arguments);
+                }
+                Part::ToolResult { output, is_error } => {
+                    let label = if is_error {
+                        "Tool error"
+                    } else {
+                        "Tool result"
+                    };
+                    let _ = writeln!(out, "**{}:**\n", label);
+                    let _ = writeln!(out, "```\n{}\n```\n", output.trim_end());
+                }
+            }
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn export_html(id: &str, metadata: &SessionMetadata, messages: &[Message]) -> String {
+    let title = escape_html(&title(id, metadata));
+    let mut body = String::new();
+    for message in messages {
+        let parts = parts(message);
+        if parts.is_empty() {
+            continue;
+        }
+        let role = speaker(message);
+        let _ = writeln!(
+            body,
+            "<section class=\"{}\">\n<h2>{}</h2>",
+            role.to_lowercase(),
+            role
+        );
+        for part in parts {
+            match part {
+                Part::Text(text) => {
+                    let _ = writeln!(body, "<div class=\"text\">{}</div>", escape_html(&text));
+                }
+                Part::ToolCall { name, arguments } => {
+                    let _ = writeln!(
+                        body,
+                        "<details class=\"tool-call\"><summary>Tool call: <code>{}</code></summary><pre>{}</pre></details>",
+                        escape_html(&name),
+                        escape_html(&arguments)
+                    );
+                }
+                Part::ToolResult { output, is_error } => {
+                    let label = if is_error {
+                        "Tool error"
+                    } else {
+                        "Tool result"
+                    };
+                    let _ = writeln!(
+                        body,
+                        "<details class=\"tool-result\"><summary>{}</summary><pre>{}</pre></details>",
+                        label,
+                        escape_html(&output)
+                    );
+                }
+            }
+        }
+        let _ = writeln!(body, "</section>");
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; max-width: 50rem; margin: 2rem auto; padding: 0 1rem; }}
+section {{ border-top: 1px solid #ddd; padding: 0.5rem 0; }}
+.text {{ white-space: pre-wrap; }}
+pre {{ background: #f5f5f5; padding: 0.5rem; overflow-x: auto; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<p>Session <code>{id}</code> in <code>{dir}</code>, {count} messages</p>
+{body}</body>
+</html>
+"#,
+        title = title,
+        id = escape_html(id),
+        dir = escape_html(&metadata.working_dir.display().to_string()),
+        count = messages.len(),
+        body = body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::storage::save_messages_with_metadata;
+    use mcp_core::content::Content;
+    use mcp_core::tool::ToolCall;
+    use tempfile::tempdir;
+
+    fn write_session(dir: &Path, id: &str, description: &str) -> PathBuf {
+        let path = dir.join(format!("{}.jsonl", id));
+        let mut metadata = SessionMetadata::new(dir.to_path_buf());
+        metadata.description = description.to_string();
+        let messages = vec![
+            Message::user().with_text("Fix the <migration>"),
+            Message::assistant()
+                .with_text("Looking")
+                .with_tool_request("1", Ok(ToolCall::new("shell", json!({"command": "ls"})))),
+            Message::user().with_tool_response("1", Ok(vec![Content::text("migrate.sql")])),
+        ];
+        save_messages_with_metadata(&path, &metadata, &messages).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_filter_sessions() {
+        let dir = tempdir().unwrap();
+        let sessions = vec![
+            (
+                "20250101_120000".to_string(),
+                write_session(dir.path(), "20250101_120000", "Database migration fix"),
+            ),
+            (
+                "20250102_120000".to_string(),
+                write_session(dir.path(), "20250102_120000", "Release notes"),
+            ),
+            (
+                "project-x".to_string(),
+                write_session(dir.path(), "project-x", "Project X"),
+            ),
+        ];
+
+        let filter = SessionFilter::new(Some("^2025"), None).unwrap();
+        assert_eq!(filter.apply(sessions.clone()).len(), 2);
+        let filter = SessionFilter::new(Some("(?i)migration"), None).unwrap();
+        let matched = filter.apply(sessions.clone());
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].0, "20250101_120000");
+
+        // Everything was just written, so nothing is a day old
+        let filter = SessionFilter::new(None, Some("1d")).unwrap();
+        assert!(filter.apply(sessions.clone()).is_empty());
+        let filter = SessionFilter::new(None, Some("0s")).unwrap();
+        assert_eq!(filter.apply(sessions).len(), 3);
+
+        assert!(SessionFilter::new(Some("("), None).is_err());
+        assert!(SessionFilter::default().is_empty());
+    }
+
+    #[test]
+    fn test_fork_and_rewind() {
+        let dir = tempdir().unwrap();
+        let path = write_session(dir.path(), "parent", "Migration");
+
+        let (id, fork) = fork_session(&path, 1, None).unwrap();
+        assert_eq!(id, "parent-fork-1");
+        assert_eq!(read_messages(&fork).unwrap().len(), 1);
+        let metadata = read_metadata(&fork).unwrap();
+        assert_eq!(metadata.description, "Migration");
+        assert_eq!(metadata.message_count, 1);
+        assert_eq!(
+            metadata.forked_from,
+            Some(SessionLineage {
+                session_id: "parent".to_string(),
+                message_index: 1,
+            })
+        );
+        assert_eq!(fork_session(&path, 3, None).unwrap().0, "parent-fork-2");
+        assert!(fork_session(&path, 0, Some("parent-fork-1")).is_err());
+
+        // Cutting between a tool call and its result, or past the end, is refused
+        assert!(fork_session(&path, 2, Some("broken")).is_err());
+        assert!(rewind_session(&path, 4).is_err());
+        assert_eq!(read_messages(&path).unwrap().len(), 3);
+
+        assert_eq!(prompt_indices(&read_messages(&path).unwrap()), vec![0]);
+        assert!(rewind_session(&path, 0).unwrap().is_empty());
+        assert!(read_messages(&path).unwrap().is_empty());
+        assert_eq!(read_metadata(&path).unwrap().description, "Migration");
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("90m").unwrap(), Duration::from_secs(90 * 60));
+        assert_eq!(parse_age("30d").unwrap(), Duration::from_secs(30 * 86400));
+        assert_eq!(parse_age("2w").unwrap(), Duration::from_secs(14 * 86400));
+        assert_eq!(parse_age("7").unwrap(), Duration::from_secs(7 * 86400));
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("3y").is_err());
+        assert!(parse_age("18446744073709551615w").is_err());
+    }
+
+    #[test]
+    fn test_remove_and_rename() {
+        let dir = tempdir().unwrap();
+        let path = write_session(dir.path(), "old", "Old");
+
+        assert!(rename_session(&path, "../escape").is_err());
+        let renamed = rename_session(&path, "new").unwrap();
+        assert_eq!(renamed, dir.path().join("new.jsonl"));
+        assert!(!path.exists());
+
+        write_session(dir.path(), "other", "Other");
+        assert!(rename_session(&renamed, "other").is_err());
+
+        remove_session(&renamed).unwrap();
+        assert!(!renamed.exists());
+        assert!(remove_session(&renamed).is_err());
+    }
+
+    #[test]
+    fn test_export() {
+        let dir = tempdir().unwrap();
+        let path = write_session(dir.path(), "fix", "Migration fix");
+
+        let markdown = export_session(&path, "fix", ExportFormat::Markdown).unwrap();
+        assert!(markdown.starts_with("# Migration fix\n"));
+        assert!(markdown.contains("## User\n\nFix the <migration>"));
+        assert!(markdown.contains("**Tool call:** `shell`"));
+        assert!(markdown.contains("```\nmigrate.sql\n```"));
+
+        let html = export_session(&path, "fix", ExportFormat::Html).unwrap();
+        assert!(html.contains("Fix the &lt;migration&gt;"));
+        assert!(html.contains("<code>shell</code>"));
+
+        let exported: serde_json::Value =
+            serde_json::from_str(&export_session(&path, "fix", ExportFormat::Json).unwrap())
+                .unwrap();
+        assert_eq!(exported["metadata"]["description"], "Migration fix");
+        assert_eq!(exported["messages"].as_array().unwrap().len(), 3);
+
+        assert_eq!(
+            "md".parse::<ExportFormat>().unwrap(),
+            ExportFormat::Markdown
+        );
+        assert!("pdf".parse::<ExportFormat>().is_err());
+    }
+}
@@ -0,0 +1,500 @@
+//! Full-text search over stored sessions.
+//!
+//! The index lives next to the sessions and is refreshed incrementally: only sessions whose
+//! file changed since the last search are read again, and removed sessions are dropped.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::message::{Message, MessageContent};
+use crate::session::{ensure_session_dir, list_sessions, read_messages, read_metadata};
+
+/// Bump when the layout of the index changes so old indexes are rebuilt
+const INDEX_VERSION: u32 = 1;
+const INDEX_FILE: &str = "search-index.json";
+
+/// Characters of context around the first match in a snippet
+const SNIPPET_CONTEXT: usize = 80;
+
+// BM25 parameters
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// Matches in the description, tool names or working directory count more than in message text
+const DESCRIPTION_WEIGHT: u32 = 3;
+const TOOL_WEIGHT: u32 = 2;
+const WORKING_DIR_WEIGHT: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedMessage {
+    index: usize,
+    text: String,
+    tools: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedSession {
+    /// Modification time in milliseconds and size of the file when it was indexed
+    modified: u64,
+    size: u64,
+    description: String,
+    working_dir: String,
+    messages: Vec<IndexedMessage>,
+    /// Weighted term frequencies over the whole session
+    terms: HashMap<String, u32>,
+    length: u32,
+}
+
+impl IndexedSession {
+    fn build(path: &Path, modified: u64, size: u64) -> Result<Self> {
+        let metadata = read_metadata(path)?;
+        let messages: Vec<IndexedMessage> = read_messages(path)?
+            .iter()
+            .enumerate()
+            .filter_map(|(index, message)| index_message(index, message))
+            .collect();
+
+        let mut terms = HashMap::new();
+        let mut add = |text: &str, weight: u32| {
+            for token in tokenize(text) {
+                *terms.entry(token).or_insert(0) += weight;
+            }
+        };
+        add(&metadata.description, DESCRIPTION_WEIGHT);
+        add(&metadata.working_dir.to_string_lossy(), WORKING_DIR_WEIGHT);
+        for message in &messages {
+            add(&message.text, 1);
+            for tool in &message.tools {
+                add(tool, TOOL_WEIGHT);
+            }
+        }
+        let length = terms.values().sum();
+
+        Ok(Self {
+            modified,
+            size,
+            description: metadata.description,
+            working_dir: metadata.working_dir.to_string_lossy().to_string(),
+            messages,
+            terms,
+            length,
+        })
+    }
+
+    /// Weighted frequency of the terms that start with `term`
+    fn frequency(&self, term: &str) -> u32 {
+        if let Some(frequency) = self.terms.get(term) {
+            return *frequency;
+        }
+        self.terms
+            .iter()
+            .filter(|(token, _)| token.starts_with(term))
+            .map(|(_, frequency)| frequency)
+            .sum()
+    }
+}
+
+fn index_message(index: usize, message: &Message) -> Option<IndexedMessage> {
+    let mut text = Vec::new();
+    let mut tools = Vec::new();
+    for content in &message.content {
+        match content {
+            MessageContent::Text(t) if !t.text.trim().is_empty() => text.push(t.text.as_str()),
+            MessageContent::ToolRequest(request) => {
+                if let Ok(call) = &request.tool_call {
+                    tools.push(call.name.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+    if text.is_empty() && tools.is_empty() {
+        return None;
+    }
+    Some(IndexedMessage {
+        index,
+        text: text.join("\n"),
+        tools,
+    })
+}
+
+/// Lowercased words, splitting on anything that is not a letter or digit
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+}
+
+/// A session that matched a search
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub session_id: String,
+    pub path: String,
+    pub description: String,
+    pub working_dir: String,
+    pub score: f64,
+    /// The message the snippet comes from, if the best match was in a message
+    pub message_index: Option<usize>,
+    pub snippet: String,
+    /// The command that resumes this session
+    pub resume_command: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SessionIndex {
+    version: u32,
+    sessions: HashMap<String, IndexedSession>,
+    #[serde(skip)]
+    paths: HashMap<String, PathBuf>,
+}
+
+impl SessionIndex {
+    /// Load the index stored at `path`, or start an empty one if it is missing or outdated
+    pub fn load(path: &Path) -> Self {
+        let index = fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<SessionIndex>(&content).ok())
+            .filter(|index| index.version == INDEX_VERSION);
+        index.unwrap_or_else(|| SessionIndex {
+            version: INDEX_VERSION,
+            ..Default::default()
+        })
+    }
+
+    /// Write the index to `path`, replacing the previous one atomically
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Bring the index up to date with `sessions`, returning whether anything changed
+    ///
+    /// Sessions that can no longer be read are left out of the index rather than failing the search.
+    pub fn refresh(&mut self, sessions: &[(String, PathBuf)]) -> bool {
+        let mut changed = false;
+        let ids: HashSet<&str> = sessions.iter().map(|(id, _)| id.as_str()).collect();
+        self.sessions.retain(|id, _| {
+            let keep = ids.contains(id.as_str());
+            changed |= !keep;
+            keep
+        });
+
+        for (id, path) in sessions {
+            self.paths.insert(id.clone(), path.clone());
+            let Ok(file) = path.metadata() else {
+                continue;
+            };
+            let modified = file
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|age| age.as_millis() as u64)
+                .unwrap_or_default();
+            let size = file.len();
+            if self
+                .sessions
+                .get(id)
+                .is_some_and(|indexed| indexed.modified == modified && indexed.size == size)
+            {
+                continue;
+            }
+            changed = true;
+            match IndexedSession::build(path, modified, size) {
+                Ok(indexed) => {
+                    self.sessions.insert(id.clone(), indexed);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to index session {}: {:?}", id, e);
+                    self.sessions.remove(id);
+                }
+            }
+        }
+        changed
+    }
+
+    /// The sessions matching `query`, best first
+    ///
+    /// Sessions are ranked with BM25 over their terms, scaled by the share of query terms they
+    /// contain. A query term also matches longer words it is a prefix of.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let mut terms: Vec<String> = tokenize(query).collect();
+        terms.sort();
+        terms.dedup();
+        if terms.is_empty() || self.sessions.is_empty() {
+            return Vec::new();
+        }
+
+        let count = self.sessions.len() as f64;
+        let average_length = self
+            .sessions
+            .values()
+            .map(|session| session.length as f64)
+            .sum::<f64>()
+            / count;
+        let frequencies: HashMap<&str, Vec<u32>> = terms
+            .iter()
+            .map(|term| {
+                (
+                    term.as_str(),
+                    self.sessions
+                        .values()
+                        .map(|session| session.frequency(term))
+                        .collect(),
+                )
+            })
+            .collect();
+
+        let mut hits: Vec<SearchHit> = self
+            .sessions
+            .iter()
+            .enumerate()
+            .filter_map(|(position, (id, session))| {
+                let mut score = 0.0;
+                let mut matched = 0;
+                for term in &terms {
+                    let frequencies = &frequencies[term.as_str()];
+                    let frequency = frequencies[position] as f64;
+                    if frequency == 0.0 {
+                        continue;
+                    }
+                    matched += 1;
+                    let containing = frequencies.iter().filter(|f| **f > 0).count() as f64;
+                    let idf = ((count - containing + 0.5) / (containing + 0.5) + 1.0).ln();
+                    let norm = 1.0 - B + B * session.length as f64 / average_length.max(1.0);
+                    score += idf * frequency * (K1 + 1.0) / (frequency + K1 * norm);
+                }
+                if matched == 0 {
+                    return None;
+                }
+                score *= matched as f64 / terms.len() as f64;
+                let (message_index, snippet) = snippet(session, &terms);
+                Some(SearchHit {
+                    session_id: id.clone(),
+                    path: self
+                        .paths
+                        .get(id)
+                        .map(|path| path.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    description: session.description.clone(),
+                    working_dir: session.working_dir.clone(),
+                    score,
+                    message_index,
+                    snippet,
+                    resume_command: format!("goose session --resume --name {}", id),
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.session_id.cmp(&b.session_id))
+        });
+        hits.truncate(limit);
+        hits
+    }
+}
+
+fn matches_term(token: &str, terms: &[String]) -> bool {
+    terms.iter().any(|term| token.starts_with(term.as_str()))
+}
+
+/// The message that matches the most query terms and an excerpt around its first match
+fn snippet(session: &IndexedSession, terms: &[String]) -> (Option<usize>, String) {
+    let best = session
+        .messages
+        .iter()
+        .map(|message| {
+            let tokens: HashSet<String> = tokenize(&message.text)
+                .chain(message.tools.iter().flat_map(|tool| tokenize(tool)))
+                .collect();
+            let matched = terms
+                .iter()
+                .filter(|term| tokens.iter().any(|token| token.starts_with(term.as_str())))
+                .count();
+            (matched, message)
+        })
+        .filter(|(matched, _)| *matched > 0)
+        .max_by_key(|(matched, message)| (*matched, std::cmp::Reverse(message.index)));
+
+    let Some((_, message)) = best else {
+        // Only the description or working directory matched
+        let summary = if session.description.is_empty() {
+            session.working_dir.clone()
+        } else {
+            session.description.clone()
+        };
+        return (None, summary);
+    };
+
+    let words: Vec<&str> = message.text.split_whitespace().collect();
+    let first = words
+        .iter()
+        .position(|word| tokenize(word).any(|token| matches_term(&token, terms)));
+    let Some(first) = first else {
+        return (
+            Some(message.index),
+            format!("Tool call: {}", message.tools.join(", ")),
+        );
+    };
+
+    // Grow a window of whole words around the first match
+    let (mut start, mut end) = (first, first + 1);
+    let mut length = words[first].chars().count();
+    while length < SNIPPET_CONTEXT * 2 && (start > 0 || end < words.len()) {
+        if start > 0 && (first - start) * 2 <= end - first {
+            start -= 1;
+            length += words[start].chars().count() + 1;
+        } else if end < words.len() {
+            length += words[end].chars().count() + 1;
+            end += 1;
+        } else {
+            start -= 1;
+            length += words[start].chars().count() + 1;
+        }
+    }
+    let mut snippet = words[start..end].join(" ");
+    if start > 0 {
+        snippet = format!("…{}", snippet);
+    }
+    if end < words.len() {
+        snippet.push('…');
+    }
+    (Some(message.index), snippet)
+}
+
+/// Where the search index of the session directory is stored
+pub fn index_path() -> Result<PathBuf> {
+    Ok(ensure_session_dir()?.join(INDEX_FILE))
+}
+
+/// Search the stored sessions, updating the index on disk first
+pub fn search_sessions(query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+    let path = index_path()?;
+    let mut index = SessionIndex::load(&path);
+    if index.refresh(&list_sessions()?) {
+        if let Err(e) = index.save(&path) {
+            tracing::warn!("Failed to save the session search index: {:?}", e);
+        }
+    }
+    Ok(index.search(query, limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::storage::save_messages_with_metadata;
+    use crate::session::SessionMetadata;
+    use mcp_core::tool::ToolCall;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn write_session(dir: &Path, id: &str, description: &str, messages: &[Message]) -> PathBuf {
+        let path = dir.join(format!("{}.jsonl", id));
+        let metadata = SessionMetadata {
+            description: description.to_string(),
+            ..SessionMetadata::new(PathBuf::from("/work/billing-service"))
+        };
+        save_messages_with_metadata(&path, &metadata, messages).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_search_ranks_and_snippets() {
+        let dir = tempdir().unwrap();
+        let sessions = vec![
+            (
+                "migration".to_string(),
+                write_session(
+                    dir.path(),
+                    "migration",
+                    "Database schema fixes",
+                    &[
+                        Message::user().with_text("The deploy failed again"),
+                        Message::assistant()
+                            .with_text("We fixed the migration by adding the missing index on the invoices table")
+                            .with_tool_request(
+                                "1",
+                                Ok(ToolCall::new("developer__shell", json!({"command": "make migrate"}))),
+                            ),
+                    ],
+                ),
+            ),
+            (
+                "notes".to_string(),
+                write_session(
+                    dir.path(),
+                    "notes",
+                    "Meeting notes",
+                    &[Message::user().with_text("Summarise the notes about the release")],
+                ),
+            ),
+        ];
+
+        let mut index = SessionIndex::load(&dir.path().join(INDEX_FILE));
+        assert!(index.refresh(&sessions));
+        assert!(!index.refresh(&sessions));
+
+        let hits = index.search("fix migration", 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].session_id, "migration");
+        assert_eq!(hits[0].message_index, Some(1));
+        assert!(hits[0].snippet.contains("fixed the migration"));
+        assert_eq!(
+            hits[0].resume_command,
+            "goose session --resume --name migration"
+        );
+
+        // Tool names and working directories are searchable too
+        assert_eq!(
+            index.search("developer__shell", 10)[0].session_id,
+            "migration"
+        );
+        assert_eq!(index.search("billing", 10).len(), 2);
+        assert!(index.search("kubernetes", 10).is_empty());
+    }
+
+    #[test]
+    fn test_refresh_is_incremental() {
+        let dir = tempdir().unwrap();
+        let index_file = dir.path().join(INDEX_FILE);
+        let path = write_session(
+            dir.path(),
+            "first",
+            "",
+            &[Message::user().with_text("rotate the api keys")],
+        );
+        let mut sessions = vec![("first".to_string(), path.clone())];
+
+        let mut index = SessionIndex::load(&index_file);
+        index.refresh(&sessions);
+        index.save(&index_file).unwrap();
+
+        // A reloaded index only picks up what changed
+        let mut index = SessionIndex::load(&index_file);
+        assert!(!index.refresh(&sessions));
+        assert_eq!(index.search("rotate", 10).len(), 1);
+
+        let second = write_session(
+            dir.path(),
+            "second",
+            "",
+            &[Message::user().with_text("rotate the certificates")],
+        );
+        sessions.push(("second".to_string(), second));
+        assert!(index.refresh(&sessions));
+        assert_eq!(index.search("rotate", 10).len(), 2);
+
+        sessions.remove(0);
+        assert!(index.refresh(&sessions));
+        let hits = index.search("rotate", 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].session_id, "second");
+    }
+}
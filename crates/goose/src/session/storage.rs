@@ -1,11 +1,12 @@
+use crate::config::Config;
 use crate::message::Message;
 use crate::providers::base::Provider;
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use chrono::Local;
 use etcetera::{choose_app_strategy, AppStrategy, AppStrategyArgs};
 use serde::{Deserialize, Serialize};
 use std::fs::{self, File};
-use std::io::{self, BufRead, Write};
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
@@ -16,6 +17,15 @@ fn get_home_dir() -> PathBuf {
         .to_path_buf()
 }
 
+/// Where a forked session came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionLineage {
+    /// The id of the session it was forked from
+    pub session_id: String,
+    /// The index of the first message of the parent that the fork does not include
+    pub message_index: usize,
+}
+
 /// Metadata for a session, stored as the first line in the session file
 #[derive(Debug, Clone, Serialize)]
 pub struct SessionMetadata {
@@ -27,6 +37,13 @@ pub struct SessionMetadata {
     pub message_count: usize,
     /// The total number of tokens used in the session. Retrieved from the provider's last usage.
     pub total_tokens: Option<i32>,
+    /// The input tokens of the provider's last usage
+    pub input_tokens: Option<i32>,
+    /// The output tokens of the provider's last usage
+    pub output_tokens: Option<i32>,
+    /// The session and message this session was forked from, if it is a fork
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forked_from: Option<SessionLineage>,
 }
 
 // Custom deserializer to handle old sessions without working_dir
@@ -40,7 +57,10 @@ impl<'de> Deserialize<'de> for SessionMetadata {
             description: String,
             message_count: usize,
             total_tokens: Option<i32>,
+            input_tokens: Option<i32>,
+            output_tokens: Option<i32>,
             working_dir: Option<PathBuf>,
+            forked_from: Option<SessionLineage>,
         }
 
         let helper = Helper::deserialize(deserializer)?;
@@ -49,7 +69,10 @@ impl<'de> Deserialize<'de> for SessionMetadata {
             description: helper.description,
             message_count: helper.message_count,
             total_tokens: helper.total_tokens,
+            input_tokens: helper.input_tokens,
+            output_tokens: helper.output_tokens,
             working_dir: helper.working_dir.unwrap_or_else(get_home_dir),
+            forked_from: helper.forked_from,
         })
     }
 }
@@ -61,6 +84,9 @@ impl SessionMetadata {
             description: String::new(),
             message_count: 0,
             total_tokens: None,
+            input_tokens: None,
+            output_tokens: None,
+            forked_from: None,
         }
     }
 }
@@ -74,6 +100,42 @@ impl Default for SessionMetadata {
 // The single app name used for all Goose applications
 const APP_NAME: &str = "goose";
 
+/// How much of the end of a session file to read when looking for its latest metadata
+const METADATA_TAIL_BYTES: u64 = 64 * 1024;
+
+/// When writes to session files are flushed to disk, set with `GOOSE_SESSION_FSYNC`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FsyncPolicy {
+    /// After every write
+    Always,
+    /// Only when a session file is compacted, where a lost write could lose the whole session.
+    /// Appends that a crash interrupts leave a torn record, which reading skips.
+    #[default]
+    Compaction,
+    /// Never, leaving it to the operating system
+    Never,
+}
+
+impl FsyncPolicy {
+    pub fn from_config() -> Self {
+        match Config::global().get_param::<String>("GOOSE_SESSION_FSYNC") {
+            Ok(policy) => match policy.to_lowercase().as_str() {
+                "always" => FsyncPolicy::Always,
+                "compaction" => FsyncPolicy::Compaction,
+                "never" => FsyncPolicy::Never,
+                _ => {
+                    tracing::warn!(
+                        "Unknown GOOSE_SESSION_FSYNC '{}', expected always, compaction or never",
+                        policy
+                    );
+                    FsyncPolicy::default()
+                }
+            },
+            Err(_) => FsyncPolicy::default(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Identifier {
     Name(String),
@@ -165,7 +227,9 @@ pub fn generate_session_id() -> String {
 /// Read messages from a session file
 ///
 /// Creates the file if it doesn't exist, reads and deserializes all messages if it does.
-/// The first line of the file is expected to be metadata, and the rest are messages.
+/// Metadata records are skipped: older sessions keep theirs on the first line, newer ones
+/// append one after each save. A torn record at the end of the file, left by a crash
+/// during a write, is ignored.
 pub fn read_messages(session_file: &Path) -> Result<Vec<Message>> {
     let file = fs::OpenOptions::new()
         .read(true)
@@ -174,25 +238,32 @@ pub fn read_messages(session_file: &Path) -> Result<Vec<Message>> {
         .truncate(false)
         .open(session_file)?;
 
-    let reader = io::BufReader::new(file);
-    let mut lines = reader.lines();
+    let mut reader = io::BufReader::new(file);
+    let mut line = Vec::new();
     let mut messages = Vec::new();
 
-    // Read the first line as metadata or create default if empty/missing
-    if let Some(line) = lines.next() {
-        let line = line?;
-        // Try to parse as metadata, but if it fails, treat it as a message
-        if let Ok(_metadata) = serde_json::from_str::<SessionMetadata>(&line) {
-            // Metadata successfully parsed, continue with the rest of the lines as messages
-        } else {
-            // This is not metadata, it's a message
-            messages.push(serde_json::from_str::<Message>(&line)?);
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let complete = line.ends_with(b"\n");
+        let record = line.trim_ascii();
+        if record.is_empty() {
+            continue;
+        }
+        match serde_json::from_slice::<Message>(record) {
+            Ok(message) => messages.push(message),
+            Err(_) if serde_json::from_slice::<SessionMetadata>(record).is_ok() => {}
+            Err(e) if !complete => {
+                tracing::warn!(
+                    "Ignoring a torn record at the end of {}: {}",
+                    session_file.display(),
+                    e
+                );
+            }
+            Err(e) => return Err(e.into()),
         }
-    }
-
-    // Read the rest of the lines as messages
-    for line in lines {
-        messages.push(serde_json::from_str::<Message>(&line?)?);
     }
 
     Ok(messages)
@@ -206,29 +277,54 @@ pub fn read_metadata(session_file: &Path) -> Result<SessionMetadata> {
         return Ok(SessionMetadata::default());
     }
 
-    let file = fs::File::open(session_file)?;
-    let mut reader = io::BufReader::new(file);
-    let mut first_line = String::new();
-
-    // Read just the first line
-    if reader.read_line(&mut first_line)? > 0 {
-        // Try to parse as metadata
-        match serde_json::from_str::<SessionMetadata>(&first_line) {
-            Ok(metadata) => Ok(metadata),
-            Err(_) => {
-                // If the first line isn't metadata, return default
-                Ok(SessionMetadata::default())
-            }
+    // Every save ends with a metadata record, so the last line is usually all we need
+    if let Some(metadata) = read_last_metadata(session_file)? {
+        return Ok(metadata);
+    }
+
+    // Older sessions have their metadata on the first line, and a crash can leave messages
+    // after the last record, so fall back to the last metadata record in the file
+    let mut reader = io::BufReader::new(File::open(session_file)?);
+    let mut line = Vec::new();
+    let mut metadata = SessionMetadata::default();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        if let Ok(record) = serde_json::from_slice::<SessionMetadata>(line.trim_ascii()) {
+            metadata = record;
         }
-    } else {
-        // Empty file, return default
-        Ok(SessionMetadata::default())
     }
+    Ok(metadata)
+}
+
+/// The metadata on the last complete line of a session file, if that line is metadata
+fn read_last_metadata(session_file: &Path) -> Result<Option<SessionMetadata>> {
+    let mut file = File::open(session_file)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(METADATA_TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+
+    // Drop a torn record after the last newline
+    let Some(end) = tail.iter().rposition(|b| *b == b'\n') else {
+        return Ok(None);
+    };
+    let tail = &tail[..end];
+    let line = match tail.iter().rposition(|b| *b == b'\n') {
+        Some(newline) => &tail[newline + 1..],
+        // The line may have started before the tail we read
+        None if start > 0 => return Ok(None),
+        None => tail,
+    };
+    Ok(serde_json::from_slice::<SessionMetadata>(line.trim_ascii()).ok())
 }
 
 /// Write messages to a session file with metadata
 ///
-/// Overwrites the file with metadata as the first line, followed by all messages in JSONL format.
+/// Appends the messages that are new since the last save, see [`save_messages_with_metadata`].
 /// If a provider is supplied, it will automatically generate a description when appropriate.
 pub async fn persist_messages(
     session_file: &Path,
@@ -288,29 +384,215 @@ pub async fn persist_messages(
 
 /// Write messages to a session file with the provided metadata
 ///
-/// Overwrites the file with metadata as the first line, followed by all messages in JSONL format.
+/// Session files are append-only: when the file already holds the start of `messages`, only
+/// the new messages are appended, followed by a metadata record. Otherwise, for example after
+/// the conversation was truncated or rewound, the file is compacted by writing a new file and
+/// renaming it over the old one, so a crash never leaves a half-written session.
 pub fn save_messages_with_metadata(
     session_file: &Path,
     metadata: &SessionMetadata,
     messages: &[Message],
 ) -> Result<()> {
-    let file = File::create(session_file).expect("The path specified does not exist");
-    let mut writer = io::BufWriter::new(file);
+    let policy = FsyncPolicy::from_config();
+    // The count in the last metadata record is how the next save knows what is stored
+    let metadata = SessionMetadata {
+        message_count: messages.len(),
+        ..metadata.clone()
+    };
+    let metadata_record = serde_json::to_string(&metadata)?;
+
+    match stored_messages(session_file, messages)? {
+        Some(Stored { count, metadata })
+            if count == messages.len() && metadata == metadata_record =>
+        {
+            Ok(())
+        }
+        Some(Stored { count, .. }) => {
+            append_records(session_file, &messages[count..], &metadata_record, policy)
+        }
+        None => compact(session_file, messages, &metadata_record, policy),
+    }
+    .with_context(|| format!("Failed to write session file {}", session_file.display()))
+}
+
+/// What the end of a session file says about its contents
+struct Stored {
+    /// How many messages the file holds
+    count: usize,
+    /// The last metadata record
+    metadata: String,
+}
+
+/// How many of `messages` the session file already holds, or None if it has to be rewritten
+///
+/// Only the end of the file is read: the last metadata record holds the number of stored
+/// messages, and the record before it must be the last of those messages. A file has to be
+/// rewritten when it ends in a torn record, was written before metadata was appended, or
+/// holds messages that are no longer the start of `messages`.
+fn stored_messages(session_file: &Path, messages: &[Message]) -> Result<Option<Stored>> {
+    let mut file = match File::open(session_file) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(Some(Stored {
+                count: 0,
+                metadata: String::new(),
+            }))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if file.metadata()?.len() == 0 {
+        return Ok(Some(Stored {
+            count: 0,
+            metadata: String::new(),
+        }));
+    }
+
+    let Some(records) = read_last_records(&mut file, 2)? else {
+        return Ok(None);
+    };
+    let Some(metadata) = records.last() else {
+        return Ok(None);
+    };
+    let Ok(stored) = serde_json::from_slice::<SessionMetadata>(metadata) else {
+        return Ok(None);
+    };
+    let count = stored.message_count;
+    if count > messages.len() {
+        return Ok(None);
+    }
+
+    let previous = records.len().checked_sub(2).map(|i| &records[i]);
+    let consistent = match (count.checked_sub(1), previous) {
+        // Anything before the first record can only be metadata
+        (None, None) => true,
+        (None, Some(previous)) => serde_json::from_slice::<SessionMetadata>(previous).is_ok(),
+        (Some(last), Some(previous)) => {
+            serde_json::to_vec(&messages[last]).is_ok_and(|expected| expected == *previous)
+        }
+        (Some(_), None) => false,
+    };
+    Ok(consistent.then(|| Stored {
+        count,
+        metadata: String::from_utf8_lossy(metadata).into_owned(),
+    }))
+}
+
+/// The last `count` records of a file, oldest first, reading backwards from the end
+///
+/// Returns None if the file does not end with a newline, which means the last record is torn.
+fn read_last_records(file: &mut File, count: usize) -> Result<Option<Vec<Vec<u8>>>> {
+    let len = file.metadata()?.len();
+    let mut tail: Vec<u8> = Vec::new();
+    let mut start = len;
+
+    loop {
+        let read_from = start.saturating_sub(METADATA_TAIL_BYTES);
+        let mut chunk = vec![0; (start - read_from) as usize];
+        file.seek(SeekFrom::Start(read_from))?;
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&tail);
+        tail = chunk;
+        start = read_from;
+
+        if !tail.ends_with(b"\n") {
+            return Ok(None);
+        }
+        let newlines = tail.iter().filter(|b| **b == b'\n').count();
+        // With count + 1 newlines the first of the records we want starts inside the tail
+        if newlines > count || start == 0 {
+            break;
+        }
+    }
 
-    // Write metadata as the first line
-    serde_json::to_writer(&mut writer, &metadata)?;
-    writeln!(writer)?;
+    let body = &tail[..tail.len() - 1];
+    let mut records: Vec<Vec<u8>> = body
+        .rsplit(|b| *b == b'\n')
+        .take(count)
+        .map(<[u8]>::to_vec)
+        .collect();
+    records.reverse();
+    Ok(Some(records))
+}
 
-    // Write all messages
+fn append_records(
+    session_file: &Path,
+    messages: &[Message],
+    metadata: &str,
+    policy: FsyncPolicy,
+) -> Result<()> {
+    let mut records = Vec::new();
     for message in messages {
-        serde_json::to_writer(&mut writer, &message)?;
-        writeln!(writer)?;
+        serde_json::to_writer(&mut records, message)?;
+        records.push(b'\n');
+    }
+    records.extend_from_slice(metadata.as_bytes());
+    records.push(b'\n');
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(session_file)?;
+    file.write_all(&records)?;
+    if policy == FsyncPolicy::Always {
+        file.sync_data()?;
+    }
+    Ok(())
+}
+
+/// Replace the session file with one holding just `messages` and a metadata record
+fn compact(
+    session_file: &Path,
+    messages: &[Message],
+    metadata: &str,
+    policy: FsyncPolicy,
+) -> Result<()> {
+    let file_name = session_file
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid session file {}", session_file.display()))?;
+    let tmp = session_file.with_file_name(format!("{}.tmp", file_name.to_string_lossy()));
+
+    let written = (|| -> Result<()> {
+        let mut writer = io::BufWriter::new(File::create(&tmp)?);
+        for message in messages {
+            serde_json::to_writer(&mut writer, message)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.write_all(metadata.as_bytes())?;
+        writer.write_all(b"\n")?;
+        let file = writer.into_inner().map_err(|e| e.into_error())?;
+        if policy != FsyncPolicy::Never {
+            file.sync_all()?;
+        }
+        fs::rename(&tmp, session_file)?;
+        Ok(())
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
     }
+    written?;
 
-    writer.flush()?;
+    if policy != FsyncPolicy::Never {
+        sync_parent_dir(session_file);
+    }
     Ok(())
 }
 
+/// Make a rename in the session's directory durable
+fn sync_parent_dir(session_file: &Path) {
+    #[cfg(unix)]
+    {
+        let dir = match session_file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        if let Err(e) = File::open(dir).and_then(|dir| dir.sync_all()) {
+            tracing::warn!("Failed to sync session directory {}: {}", dir.display(), e);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = session_file;
+}
+
 /// Generate a description for the session using the provider
 ///
 /// This function is called when appropriate to generate a short description
@@ -368,7 +650,7 @@ pub async fn update_metadata(session_file: &Path, metadata: &SessionMetadata) ->
     // Read all messages from the file
     let messages = read_messages(session_file)?;
 
-    // Rewrite the file with the new metadata and existing messages
+    // With the messages unchanged, this only appends a metadata record
     save_messages_with_metadata(session_file, metadata, &messages)
 }
 
@@ -426,6 +708,119 @@ mod tests {
         Ok(())
     }
 
+    fn line_count(path: &Path) -> usize {
+        fs::read_to_string(path).unwrap().lines().count()
+    }
+
+    #[test]
+    fn test_saves_append() -> Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("append.jsonl");
+        let mut metadata = SessionMetadata::new(dir.path().to_path_buf());
+        let mut messages = vec![
+            Message::user().with_text("Hello"),
+            Message::assistant().with_text("Hi there"),
+        ];
+
+        save_messages_with_metadata(&file_path, &metadata, &messages)?;
+        let before = fs::read(&file_path)?;
+        messages.push(Message::user().with_text("Fix the build"));
+        metadata.description = "Fix the build".to_string();
+        save_messages_with_metadata(&file_path, &metadata, &messages)?;
+
+        // The earlier records are untouched and the new message and metadata are appended
+        assert!(fs::read(&file_path)?.starts_with(&before));
+        assert_eq!(line_count(&file_path), 5);
+        assert_eq!(read_messages(&file_path)?, messages);
+        assert_eq!(read_metadata(&file_path)?.description, "Fix the build");
+
+        // Dropping messages compacts the file
+        messages.truncate(1);
+        save_messages_with_metadata(&file_path, &metadata, &messages)?;
+        assert_eq!(line_count(&file_path), 2);
+        assert_eq!(read_messages(&file_path)?, messages);
+        assert_eq!(read_metadata(&file_path)?.description, "Fix the build");
+        assert!(!dir.path().join("append.jsonl.tmp").exists());
+
+        // Saving the same session again writes nothing
+        let before = fs::read(&file_path)?;
+        save_messages_with_metadata(&file_path, &metadata, &messages)?;
+        assert_eq!(fs::read(&file_path)?, before);
+
+        // Changing the last stored message compacts the file
+        messages[0] = Message::user().with_text("Hello again");
+        messages.push(Message::assistant().with_text("Welcome back"));
+        save_messages_with_metadata(&file_path, &metadata, &messages)?;
+        assert_eq!(line_count(&file_path), 3);
+        assert_eq!(read_messages(&file_path)?, messages);
+        assert_eq!(read_metadata(&file_path)?.message_count, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reads_sessions_with_metadata_first() -> Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("legacy.jsonl");
+        let mut metadata = SessionMetadata::new(dir.path().to_path_buf());
+        metadata.description = "Legacy".to_string();
+        let messages = vec![
+            Message::user().with_text("Hello"),
+            Message::assistant().with_text("Hi there"),
+        ];
+        let mut content = serde_json::to_string(&metadata)? + "\n";
+        for message in &messages {
+            content += &(serde_json::to_string(message)? + "\n");
+        }
+        fs::write(&file_path, content)?;
+
+        assert_eq!(read_messages(&file_path)?, messages);
+        assert_eq!(read_metadata(&file_path)?.description, "Legacy");
+
+        let mut more = messages.clone();
+        more.push(Message::user().with_text("Next"));
+        save_messages_with_metadata(&file_path, &metadata, &more)?;
+        assert_eq!(read_messages(&file_path)?, more);
+        assert_eq!(read_metadata(&file_path)?.description, "Legacy");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recovers_from_torn_record() -> Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("torn.jsonl");
+        let mut metadata = SessionMetadata::new(dir.path().to_path_buf());
+        metadata.description = "Torn".to_string();
+        let messages = vec![Message::user().with_text("Hello")];
+        save_messages_with_metadata(&file_path, &metadata, &messages)?;
+
+        // A crash in the middle of appending the next message
+        let mut file = fs::OpenOptions::new().append(true).open(&file_path)?;
+        file.write_all(br#"{"role":"assistant","created":1,"content":[{"type":"te"#)?;
+        drop(file);
+
+        assert_eq!(read_messages(&file_path)?, messages);
+        assert_eq!(read_metadata(&file_path)?.description, "Torn");
+
+        // The next save drops the torn record
+        let mut more = messages.clone();
+        more.push(Message::assistant().with_text("Hi there"));
+        save_messages_with_metadata(&file_path, &metadata, &more)?;
+        assert!(fs::read(&file_path)?.ends_with(b"\n"));
+        assert_eq!(read_messages(&file_path)?, more);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_to_missing_directory_fails() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("missing").join("session.jsonl");
+        let metadata = SessionMetadata::default();
+        assert!(save_messages_with_metadata(&file_path, &metadata, &[]).is_err());
+    }
+
     #[test]
     fn test_generate_session_id() {
         let id = generate_session_id();